@@ -0,0 +1,333 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nodframe::*;
+
+fn numeric_frame(rows: usize) -> NodFrame<i64> {
+    NodFrameBuilder::new()
+        .add_numeric("a", (0..rows as i64).collect())
+        .add_numeric("b", (0..rows as i64).map(|x| x * 2).collect())
+        .add_numeric("c", (0..rows as i64).map(|x| x % 7).collect())
+        .build()
+        .unwrap()
+}
+
+fn numeric_rows_bench(c: &mut Criterion) {
+    let frame = numeric_frame(5_000_000);
+    let mut group = c.benchmark_group("numeric_rows_5m");
+    group.sample_size(10);
+    group.bench_function("eager", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            for row in frame.numeric_rows() {
+                total += row[0];
+            }
+            black_box(total)
+        })
+    });
+    group.bench_function("iter", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            for row in frame.iter_numeric_rows() {
+                total += row[0];
+            }
+            black_box(total)
+        })
+    });
+    group.bench_function("for_each", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            frame.for_each_numeric_row(|row| total += row[0]);
+            black_box(total)
+        })
+    });
+    group.finish();
+}
+
+fn wide_frame(rows: usize, cols: usize) -> NodFrame<i64> {
+    (0..cols)
+        .fold(NodFrameBuilder::new(), |b, c| {
+            b.add_numeric(
+                &format!("c{}", c),
+                (0..rows as i64)
+                    .map(|x| (x * 31 + c as i64) % 1000)
+                    .collect(),
+            )
+        })
+        .build()
+        .unwrap()
+}
+
+fn count_where_bench(c: &mut Criterion) {
+    let frame = wide_frame(1_000_000, 20);
+    let mut group = c.benchmark_group("count_where_1m_x20");
+    group.sample_size(10);
+    group.bench_function("filter", |b| {
+        b.iter(|| {
+            black_box(
+                frame
+                    .filter("c0", Comp::Les, FilterValue::Num(500))
+                    .unwrap()
+                    .shape()
+                    .0,
+            )
+        })
+    });
+    group.bench_function("count_where", |b| {
+        b.iter(|| black_box(frame.count_where("c0", Comp::Les, Some(500), None)))
+    });
+    group.finish();
+}
+
+// write_wide_csv generates a csv with cols integer columns and returns its path
+fn write_wide_csv(name: &str, rows: usize, cols: usize) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("nodframe_bench_{}.csv", name));
+    let mut text = (0..cols)
+        .map(|c| format!("c{}", c))
+        .collect::<Vec<String>>()
+        .join(",");
+    text.push('\n');
+    for r in 0..rows {
+        let row: Vec<String> = (0..cols).map(|c| (r * 7 + c).to_string()).collect();
+        text.push_str(&row.join(","));
+        text.push('\n');
+    }
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+fn csv_use_columns_bench(c: &mut Criterion) {
+    let path = write_wide_csv("use_columns", 200_000, 20);
+    let mut group = c.benchmark_group("csv_200k_x20");
+    group.sample_size(10);
+    group.bench_function("all_columns", |b| {
+        b.iter(|| black_box(frame_from_csv::<i64>(&path).unwrap().shape()))
+    });
+    let opts = CsvOptions {
+        use_columns: Some(vec![String::from("c3")]),
+        ..CsvOptions::default()
+    };
+    group.bench_function("one_column", |b| {
+        b.iter(|| {
+            black_box(
+                frame_from_csv_with_options::<i64>(&path, &opts)
+                    .unwrap()
+                    .shape(),
+            )
+        })
+    });
+    group.bench_function("read_csv_column", |b| {
+        b.iter(|| {
+            black_box(
+                read_csv_column::<i64>(&path, "c3", &CsvOptions::default())
+                    .unwrap()
+                    .len(),
+            )
+        })
+    });
+    group.finish();
+}
+
+// write_mixed_csv generates a csv with five integer and two string columns
+fn write_mixed_csv(name: &str, rows: usize) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("nodframe_bench_{}.csv", name));
+    let mut text = String::from("id,qty,price,store,day,city,item\n");
+    for r in 0..rows {
+        text.push_str(&format!(
+            "{},{},{},{},{},city{},item{}\n",
+            r,
+            r % 50,
+            (r * 37) % 10_000,
+            r % 300,
+            r % 365,
+            r % 40,
+            r % 1000
+        ));
+    }
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+fn csv_load_bench(c: &mut Criterion) {
+    let path = write_mixed_csv("mixed", 1_000_000);
+    let mut group = c.benchmark_group("csv_1m_mixed");
+    group.sample_size(10);
+    group.bench_function("frame_from_csv", |b| {
+        b.iter(|| black_box(frame_from_csv::<i64>(&path).unwrap().shape()))
+    });
+    #[cfg(feature = "rayon")]
+    group.bench_function("frame_from_csv_parallel", |b| {
+        b.iter(|| {
+            black_box(
+                frame_from_csv_parallel::<i64>(&path, &CsvOptions::default())
+                    .unwrap()
+                    .shape(),
+            )
+        })
+    });
+    group.finish();
+}
+
+fn sorted_filter_bench(c: &mut Criterion) {
+    let frame = numeric_frame(10_000_000).sort_by("c", true).unwrap();
+    let scanned = frame
+        .take(&(0..10_000_000).collect::<Vec<usize>>())
+        .unwrap();
+    let mut group = c.benchmark_group("filter_between_10m_sorted");
+    group.sample_size(10);
+    group.bench_function("scan", |b| {
+        b.iter(|| black_box(scanned.filter_between("c", 2, 3).unwrap().shape()))
+    });
+    group.bench_function("binary_search", |b| {
+        b.iter(|| black_box(frame.filter_between("c", 2, 3).unwrap().shape()))
+    });
+    group.finish();
+}
+
+fn to_csv_bench(c: &mut Criterion) {
+    let frame = NodFrameBuilder::new()
+        .add_numeric("a", (0..5_000_000_i64).collect())
+        .add_numeric("b", (0..5_000_000_i64).map(|x| x * 37 % 10_000).collect())
+        .add_discrete(
+            "s",
+            (0..5_000_000)
+                .map(|x| format!("item{}", x % 1000))
+                .collect(),
+        )
+        .build()
+        .unwrap();
+    let path = std::env::temp_dir().join("nodframe_bench_to_csv.csv");
+    let mut group = c.benchmark_group("to_csv_5m");
+    group.sample_size(10);
+    group.bench_function("to_csv", |b| {
+        b.iter(|| frame.to_csv(path.to_string_lossy().into_owned()).unwrap())
+    });
+    group.finish();
+}
+
+fn snapshot_load_bench(c: &mut Criterion) {
+    let csv = write_mixed_csv("snapshot", 1_000_000);
+    let snapshot = std::env::temp_dir().join("nodframe_bench_snapshot.bin");
+    frame_from_csv::<i64>(&csv)
+        .unwrap()
+        .save(&snapshot)
+        .unwrap();
+    let mut group = c.benchmark_group("load_1m_mixed");
+    group.sample_size(10);
+    group.bench_function("frame_from_csv", |b| {
+        b.iter(|| black_box(frame_from_csv::<i64>(&csv).unwrap().shape()))
+    });
+    group.bench_function("load", |b| {
+        b.iter(|| black_box(NodFrame::<i64>::load(&snapshot).unwrap().shape()))
+    });
+    group.finish();
+}
+
+fn stats_bench(c: &mut Criterion) {
+    let col = NumericColumn::new("v", (0..10_000_000_i64).map(|x| x * 37 % 10_007).collect());
+    let mut group = c.benchmark_group("stats_10m");
+    group.sample_size(10);
+    group.bench_function("five_passes", |b| {
+        b.iter(|| {
+            let n = col.len() as f64;
+            let sum = col.iter().map(|x| *x as f64).sum::<f64>();
+            let mean = col.iter().map(|x| *x as f64).sum::<f64>() / n;
+            let min = col.min();
+            let max = col.max();
+            let var = col.iter().map(|x| (*x as f64 - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            black_box((sum, mean, min, max, var))
+        })
+    });
+    group.bench_function("stats", |b| b.iter(|| black_box(col.stats())));
+    group.finish();
+}
+
+fn bounds_bench(c: &mut Criterion) {
+    let col = NumericColumn::new("v", (0..10_000_000_i64).map(|x| x * 37 % 10_007).collect());
+    col.min_cached();
+    let mut group = c.benchmark_group("bounds_10m");
+    group.sample_size(10);
+    group.bench_function("min_max", |b| b.iter(|| black_box((col.min(), col.max()))));
+    group.bench_function("cached", |b| {
+        b.iter(|| black_box((col.min_cached(), col.max_cached())))
+    });
+    group.finish();
+}
+
+fn merge_sorted_bench(c: &mut Criterion) {
+    // eight days of a million rows each, every day sorted by time
+    let days: Vec<NodFrame<i64>> = (0..8)
+        .map(|d| {
+            NodFrameBuilder::new()
+                .add_numeric("t", (0..1_000_000).map(|r| r * 8 + (r + d) % 8).collect())
+                .add_numeric("v", (0..1_000_000).map(|r| (r * 37 + d) % 1000).collect())
+                .build()
+                .unwrap()
+        })
+        .collect();
+    let mut group = c.benchmark_group("merge_8x1m");
+    group.sample_size(10);
+    group.bench_function("merge_sorted", |b| {
+        b.iter(|| black_box(NodFrame::merge_sorted(&days, "t", true).unwrap().shape()))
+    });
+    group.bench_function("stack_then_sort", |b| {
+        b.iter(|| {
+            // the rows stacked in input order, then a full sort
+            let t: Vec<i64> = days
+                .iter()
+                .flat_map(|d| d.numeric_column("t").unwrap().iter().cloned())
+                .collect();
+            let v: Vec<i64> = days
+                .iter()
+                .flat_map(|d| d.numeric_column("v").unwrap().iter().cloned())
+                .collect();
+            let stacked = NodFrameBuilder::new()
+                .add_numeric("t", t)
+                .add_numeric("v", v)
+                .build()
+                .unwrap();
+            black_box(stacked.sort_by("t", true).unwrap().shape())
+        })
+    });
+    group.finish();
+}
+
+fn group_by_bench(c: &mut Criterion) {
+    // a million distinct keys spread over the rows out of order
+    let rows = 5_000_000_i64;
+    let frame = NodFrameBuilder::new()
+        .add_numeric("k", (0..rows).map(|r| r * 7_919 % 1_000_003).collect())
+        .add_numeric("v", (0..rows).map(|r| r % 1000).collect())
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("group_by_5m_1m_keys");
+    group.sample_size(10);
+    group.bench_function("group_by_sum", |b| {
+        b.iter(|| {
+            let groups = frame.group_by("k").unwrap();
+            black_box(groups.agg("v", AggFn::Sum).unwrap().shape())
+        })
+    });
+    #[cfg(feature = "rayon")]
+    group.bench_function("par_group_by_sum", |b| {
+        b.iter(|| {
+            let groups = frame.par_group_by("k").unwrap();
+            black_box(groups.par_agg("v", AggFn::Sum).unwrap().shape())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    numeric_rows_bench,
+    count_where_bench,
+    csv_use_columns_bench,
+    csv_load_bench,
+    sorted_filter_bench,
+    to_csv_bench,
+    snapshot_load_bench,
+    stats_bench,
+    bounds_bench,
+    merge_sorted_bench,
+    group_by_bench
+);
+criterion_main!(benches);
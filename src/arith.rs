@@ -0,0 +1,268 @@
+// Element-wise arithmetic between two aligned frames, see
+// NodFrame::binary_op_with.
+//
+// Rows are matched by position, like copy_column_from, so the frames must
+// have the same number of rows in the same order. FrameOpOptions.align_key
+// checks the order with the same test as copy_column_checked.
+use crate::{ColKind, Column, NodFrame, NodFrameError, NodNum, NumericColumn};
+
+// Op is the element-wise operation of binary_op_with, self's value on the
+// left. Div fails on a zero divisor like NumericColumn::div
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    fn name(self) -> &'static str {
+        match self {
+            Op::Add => "add",
+            Op::Sub => "sub",
+            Op::Mul => "mul",
+            Op::Div => "div",
+        }
+    }
+
+    fn apply<T: NodNum>(
+        self,
+        a: &NumericColumn<T>,
+        b: &NumericColumn<T>,
+    ) -> Result<NumericColumn<T>, NodFrameError> {
+        match self {
+            Op::Add => a.add(b),
+            Op::Sub => a.sub(b),
+            Op::Mul => a.mul(b),
+            Op::Div => a.div(b),
+        }
+    }
+}
+
+// FrameOpOptions for binary_op_with_options: skip_unmatched leaves out a
+// numeric column found in only one frame instead of failing, align_key names
+// a column that must hold the same values, row for row, in both frames
+#[derive(Clone, Debug, Default)]
+pub struct FrameOpOptions {
+    pub skip_unmatched: bool,
+    pub align_key: Option<String>,
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // binary_op_with applies op between every numeric column of self and the
+    // column of the same key in other, e.g. Op::Sub of predictions and
+    // actuals for residuals. Results are keyed "<key>_<suffix>", discrete
+    // columns found in both frames are passed through once from self. A
+    // numeric column in only one frame is an error, see binary_op_with_options
+    pub fn binary_op_with(
+        &self,
+        other: &NodFrame<T>,
+        op: Op,
+        suffix: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.binary_op_with_options(other, op, suffix, &FrameOpOptions::default())
+    }
+
+    pub fn binary_op_with_options(
+        &self,
+        other: &NodFrame<T>,
+        op: Op,
+        suffix: &str,
+        opts: &FrameOpOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        if self.num_rows != other.num_rows {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "frames of {} and {} rows are not aligned",
+                self.num_rows, other.num_rows
+            )));
+        }
+        if let Some(align_key) = &opts.align_key {
+            self.check_aligned(other, align_key)?;
+        }
+        let only_in = |key: &str, frame: &str| {
+            NodFrameError::InvalidArgument(format!(
+                "numeric column '{}' is only in the {} frame",
+                key, frame
+            ))
+        };
+        if !opts.skip_unmatched {
+            if let Some(col) = other
+                .columns()
+                .iter()
+                .find(|c| c.kind() == ColKind::Numeric && self.column(c.get_key()).is_none())
+            {
+                return Err(only_in(col.get_key(), "second"));
+            }
+        }
+
+        let mut columns = Vec::new();
+        for col in self.columns() {
+            let key = col.get_key();
+            match (col, other.column(key)) {
+                (Column::Numeric(a), Some(Column::Numeric(b))) => {
+                    let out = op.apply(a, b)?;
+                    columns.push(Column::Numeric(out).with_key(&format!("{}_{}", key, suffix)));
+                }
+                (_, Some(theirs)) if theirs.kind() != col.kind() => {
+                    return Err(NodFrameError::KindMismatch {
+                        key: String::from(key),
+                        expected: col.kind(),
+                        found: theirs.kind(),
+                    })
+                }
+                (Column::Discrete(_), Some(_)) => columns.push(col.clone()),
+                (Column::Discrete(_), None) => {}
+                (Column::Numeric(_), _) if opts.skip_unmatched => {}
+                (Column::Numeric(_), _) => return Err(only_in(key, "first")),
+            }
+        }
+        let out = NodFrame::from_columns(columns)?;
+        Ok(self.traced(out, "binary_op_with", || {
+            vec![
+                ("op", String::from(op.name())),
+                ("suffix", String::from(suffix)),
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod arith_tests {
+    use crate::test_support::strings;
+    use crate::{FrameOpOptions, NodFrame, NodFrameBuilder, NodFrameError, Op, Schema};
+
+    fn frame(ids: &[&str], price: Vec<f64>, qty: Vec<f64>) -> NodFrame<f64> {
+        NodFrameBuilder::new()
+            .add_discrete("id", strings(ids))
+            .add_numeric("price", price)
+            .add_numeric("qty", qty)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn binary_op_with_test() {
+        let predicted = frame(&["a", "b", "c"], vec![10.0, 12.5, 8.0], vec![3.0, 4.0, 5.0]);
+        let actual = frame(&["a", "b", "c"], vec![9.0, 13.0, 8.0], vec![3.0, 2.0, 6.0]);
+        let residuals = predicted.binary_op_with(&actual, Op::Sub, "diff").unwrap();
+        assert_eq!(
+            residuals.schema(),
+            Schema::new()
+                .discrete("id")
+                .numeric("price_diff")
+                .numeric("qty_diff")
+        );
+        assert_eq!(residuals["id"].to_string(), "id: [a, b, c]");
+        assert_eq!(
+            residuals["price_diff"].to_string(),
+            "price_diff: [1, -0.5, 0]"
+        );
+        assert_eq!(residuals["qty_diff"].to_string(), "qty_diff: [0, 2, -1]");
+        let ratio = predicted.binary_op_with(&actual, Op::Div, "ratio").unwrap();
+        assert_eq!(
+            ratio["qty_ratio"].to_string(),
+            "qty_ratio: [1, 2, 0.8333333333333334]"
+        );
+
+        let short = frame(&["a", "b"], vec![1.0, 2.0], vec![1.0, 2.0]);
+        assert_eq!(
+            predicted
+                .binary_op_with(&short, Op::Sub, "diff")
+                .unwrap_err(),
+            NodFrameError::InvalidArgument(String::from("frames of 3 and 2 rows are not aligned"))
+        );
+        let zero = frame(&["a", "b", "c"], vec![1.0, 0.0, 1.0], vec![1.0, 1.0, 1.0]);
+        assert!(predicted.binary_op_with(&zero, Op::Div, "ratio").is_err());
+    }
+
+    #[test]
+    fn binary_op_unmatched_test() {
+        let predicted = NodFrameBuilder::new()
+            .add_discrete("id", strings(&["a", "b"]))
+            .add_numeric("price", vec![10.0, 12.0])
+            .build()
+            .unwrap();
+        let actual = frame(&["a", "b"], vec![9.0, 13.0], vec![3.0, 2.0]);
+        assert_eq!(
+            predicted
+                .binary_op_with(&actual, Op::Sub, "diff")
+                .unwrap_err(),
+            NodFrameError::InvalidArgument(String::from(
+                "numeric column 'qty' is only in the second frame"
+            ))
+        );
+        assert_eq!(
+            actual
+                .binary_op_with(&predicted, Op::Sub, "diff")
+                .unwrap_err(),
+            NodFrameError::InvalidArgument(String::from(
+                "numeric column 'qty' is only in the first frame"
+            ))
+        );
+        let skip = FrameOpOptions {
+            skip_unmatched: true,
+            ..FrameOpOptions::default()
+        };
+        for out in [
+            predicted.binary_op_with_options(&actual, Op::Sub, "diff", &skip),
+            actual.binary_op_with_options(&predicted, Op::Sub, "diff", &skip),
+        ] {
+            assert_eq!(out.unwrap().shape(), (2, 2));
+        }
+        let skipped = predicted
+            .binary_op_with_options(&actual, Op::Sub, "diff", &skip)
+            .unwrap();
+        assert_eq!(skipped["price_diff"].to_string(), "price_diff: [1, -1]");
+
+        // a key numeric in one frame and discrete in the other is never skipped
+        let text = actual.to_discrete("price").unwrap();
+        for (a, b) in [(&predicted, &text), (&text, &predicted)] {
+            assert!(matches!(
+                a.binary_op_with_options(b, Op::Sub, "diff", &skip)
+                    .unwrap_err(),
+                NodFrameError::KindMismatch { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn binary_op_align_test() {
+        let predicted = frame(&["a", "b", "c"], vec![1.0, 2.0, 3.0], vec![1.0, 1.0, 1.0]);
+        let shuffled = frame(&["a", "c", "b"], vec![1.0, 3.0, 2.0], vec![1.0, 1.0, 1.0]);
+        let aligned = FrameOpOptions {
+            align_key: Some(String::from("id")),
+            ..FrameOpOptions::default()
+        };
+        // without the check the shuffled rows give wrong residuals silently
+        let unchecked = predicted
+            .binary_op_with(&shuffled, Op::Sub, "diff")
+            .unwrap();
+        assert_eq!(
+            unchecked["price_diff"].to_string(),
+            "price_diff: [0, -1, 1]"
+        );
+        assert_eq!(
+            predicted
+                .binary_op_with_options(&shuffled, Op::Sub, "diff", &aligned)
+                .unwrap_err()
+                .to_string(),
+            "column 'id' row 1: 'b' != 'c', the frames are not aligned"
+        );
+        let sorted = shuffled.sort_by("id", true).unwrap();
+        let checked = predicted
+            .binary_op_with_options(&sorted, Op::Sub, "diff", &aligned)
+            .unwrap();
+        assert_eq!(checked["price_diff"].to_string(), "price_diff: [0, 0, 0]");
+        let missing = FrameOpOptions {
+            align_key: Some(String::from("row")),
+            ..FrameOpOptions::default()
+        };
+        assert_eq!(
+            predicted
+                .binary_op_with_options(&sorted, Op::Sub, "diff", &missing)
+                .unwrap_err(),
+            NodFrameError::MissingColumn(String::from("row"))
+        );
+    }
+}
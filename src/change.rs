@@ -0,0 +1,253 @@
+// Period-over-period changes of numeric columns.
+//
+// pct_change and ratio compare each value with the one periods rows earlier,
+// working in f64. Frames have no missing values, so the first periods rows
+// take a caller-supplied fill, and ZeroDivPolicy decides what a zero earlier
+// value gives. The NodFrame wrappers convert the results back to T and need a
+// float frame, an integer one would truncate every change to a whole number.
+use crate::{
+    require_float, to_f64, NodFrame, NodFrameBuilder, NodFrameError, NodNum, NumericColumn,
+};
+use num_traits::ToPrimitive;
+use std::ops::Mul;
+
+// ZeroDivPolicy for a change whose earlier value is zero: Error names the
+// row, Sentinel writes the given value (f64::NAN or f64::INFINITY are common
+// choices) and carries on
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ZeroDivPolicy {
+    #[default]
+    Error,
+    Sentinel(f64),
+}
+
+impl<T: Clone + ToPrimitive> NumericColumn<T> {
+    // against computes f(value, earlier) for every row with a value periods
+    // rows earlier, the first periods rows get fill
+    fn against(
+        &self,
+        periods: usize,
+        fill: f64,
+        zero: ZeroDivPolicy,
+        f: impl Fn(f64, f64) -> f64,
+    ) -> Result<NumericColumn<f64>, NodFrameError> {
+        let values: Vec<f64> = self.iter().map(to_f64).collect();
+        let mut out = Vec::with_capacity(values.len());
+        for (row, x) in values.iter().enumerate() {
+            if row < periods {
+                out.push(fill);
+                continue;
+            }
+            let earlier = values[row - periods];
+            out.push(match zero {
+                _ if earlier != 0.0 => f(*x, earlier),
+                ZeroDivPolicy::Sentinel(v) => v,
+                ZeroDivPolicy::Error => {
+                    return Err(NodFrameError::InvalidValue {
+                        key: self.key().to_string(),
+                        row,
+                        message: format!(
+                            "division by zero, the value {} rows earlier is 0",
+                            periods
+                        ),
+                    })
+                }
+            });
+        }
+        Ok(NumericColumn::new(self.key(), out))
+    }
+
+    // pct_change is (x[i] - x[i - periods]) / x[i - periods], see ZeroDivPolicy
+    pub fn pct_change(
+        &self,
+        periods: usize,
+        fill: f64,
+        zero: ZeroDivPolicy,
+    ) -> Result<NumericColumn<f64>, NodFrameError> {
+        self.against(periods, fill, zero, |x, earlier| (x - earlier) / earlier)
+    }
+
+    // ratio is x[i] / x[i - periods], see ZeroDivPolicy
+    pub fn ratio(
+        &self,
+        periods: usize,
+        fill: f64,
+        zero: ZeroDivPolicy,
+    ) -> Result<NumericColumn<f64>, NodFrameError> {
+        self.against(periods, fill, zero, |x, earlier| x / earlier)
+    }
+
+    // cumreturn compounds the one-period changes: row i holds the product of
+    // (1 + pct_change) up to i, minus one, and the first row is 0
+    pub fn cumreturn(&self, zero: ZeroDivPolicy) -> Result<NumericColumn<f64>, NodFrameError> {
+        let growth: Vec<f64> = self
+            .pct_change(1, 0.0, zero)?
+            .iter()
+            .map(|r| 1.0 + r)
+            .collect();
+        let compounded = NumericColumn::new(self.key(), growth).cumprod();
+        Ok(NumericColumn::new(
+            self.key(),
+            compounded.iter().map(|g| g - 1.0).collect(),
+        ))
+    }
+}
+
+impl<T: Clone + Mul<Output = T>> NumericColumn<T> {
+    // cumprod is the running product of the values
+    pub fn cumprod(&self) -> NumericColumn<T> {
+        let mut product: Option<T> = None;
+        NumericColumn::new(
+            self.key(),
+            self.iter()
+                .map(|x| {
+                    let next = match product.take() {
+                        Some(p) => p * x.clone(),
+                        None => x.clone(),
+                    };
+                    product = Some(next.clone());
+                    next
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // with_changes appends new_key holding values converted to T, traced as
+    // operation on key. T must be a float type, see require_float
+    fn with_changes(
+        &self,
+        operation: &'static str,
+        key: &str,
+        new_key: &str,
+        values: NumericColumn<f64>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        require_float::<T>(key, operation)?;
+        let mut converted = Vec::with_capacity(values.as_slice().len());
+        for (row, v) in values.iter().enumerate() {
+            converted.push(T::from(*v).ok_or_else(|| NodFrameError::ValueConversion {
+                key: String::from(new_key),
+                row,
+            })?);
+        }
+        let out = self
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, converted)
+            .build()?;
+        Ok(self.traced(out, operation, || {
+            vec![
+                ("key", String::from(key)),
+                ("new_key", String::from(new_key)),
+            ]
+        }))
+    }
+
+    // with_pct_change appends NumericColumn::pct_change of key as new_key
+    pub fn with_pct_change(
+        &self,
+        key: &str,
+        periods: usize,
+        fill: f64,
+        zero: ZeroDivPolicy,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let change = self.numeric_column(key)?.pct_change(periods, fill, zero)?;
+        self.with_changes("with_pct_change", key, new_key, change)
+    }
+
+    // with_ratio appends NumericColumn::ratio of key as new_key
+    pub fn with_ratio(
+        &self,
+        key: &str,
+        periods: usize,
+        fill: f64,
+        zero: ZeroDivPolicy,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let ratio = self.numeric_column(key)?.ratio(periods, fill, zero)?;
+        self.with_changes("with_ratio", key, new_key, ratio)
+    }
+
+    // with_cumreturn appends NumericColumn::cumreturn of key as new_key
+    pub fn with_cumreturn(
+        &self,
+        key: &str,
+        zero: ZeroDivPolicy,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let returns = self.numeric_column(key)?.cumreturn(zero)?;
+        self.with_changes("with_cumreturn", key, new_key, returns)
+    }
+}
+
+#[cfg(test)]
+mod change_tests {
+    use crate::{NodFrameBuilder, NodFrameError, NumericColumn, ZeroDivPolicy};
+
+    fn close(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(x, y)| (x.is_nan() && y.is_nan()) || (x - y).abs() < 1e-12)
+    }
+
+    #[test]
+    fn pct_change_test() {
+        let prices = NumericColumn::new("close", vec![100, 110, 99, 0, 50]);
+        let pct = prices
+            .pct_change(1, 0.0, ZeroDivPolicy::Sentinel(f64::NAN))
+            .unwrap();
+        // 110 / 100 - 1, 99 / 110 - 1, 0 / 99 - 1, then the 0 denominator
+        assert!(close(pct.as_slice(), &[0.0, 0.1, -0.1, -1.0, f64::NAN]));
+        let two = prices.ratio(2, -1.0, ZeroDivPolicy::Error).unwrap();
+        assert!(close(two.as_slice(), &[-1.0, -1.0, 0.99, 0.0, 50.0 / 99.0]));
+        assert_eq!(
+            prices
+                .pct_change(1, 0.0, ZeroDivPolicy::Error)
+                .unwrap_err()
+                .to_string(),
+            "column 'close' row 4: division by zero, the value 1 rows earlier is 0"
+        );
+
+        let growth = NumericColumn::new("close", vec![100, 110, 99, 121]);
+        let total = growth.cumreturn(ZeroDivPolicy::Error).unwrap();
+        assert!(close(total.as_slice(), &[0.0, 0.1, -0.01, 0.21]));
+        assert_eq!(
+            growth.cumprod().as_slice(),
+            &[100, 11_000, 1_089_000, 131_769_000]
+        );
+
+        // an integer frame would truncate 150 / 300 to 0
+        let integers = NodFrameBuilder::new()
+            .add_numeric("close", vec![100, 300, 150])
+            .build()
+            .unwrap();
+        assert_eq!(
+            integers
+                .with_ratio("close", 1, 1.0, ZeroDivPolicy::Error, "ratio")
+                .unwrap_err(),
+            NodFrameError::NeedsFloat {
+                key: String::from("close"),
+                operation: "with_ratio",
+            }
+        );
+        let frame = NodFrameBuilder::new()
+            .add_numeric("close", vec![100.0, 300.0, 150.0])
+            .build()
+            .unwrap();
+        let ratios = frame
+            .with_ratio("close", 1, 1.0, ZeroDivPolicy::Error, "ratio")
+            .unwrap();
+        assert_eq!(ratios["ratio"].to_string(), "ratio: [1, 3, 0.5]");
+        let filled = frame
+            .with_pct_change("close", 5, f64::NAN, ZeroDivPolicy::Error, "pct")
+            .unwrap();
+        assert_eq!(filled["pct"].to_string(), "pct: [NaN, NaN, NaN]");
+        assert!(frame
+            .with_cumreturn("missing", ZeroDivPolicy::Error, "r")
+            .is_err());
+    }
+}
@@ -0,0 +1,366 @@
+// Data quality checks on a frame, see NodFrame::check.
+//
+// Each Check looks at one column, or at the frame for RowCountAtLeast, and
+// counts the rows violating it. The CheckReport lists the failed checks with
+// their violation counts and the first few violating rows, so a large bad
+// column does not produce a huge report. Missing cells are the empty strings
+// of discrete columns and the NaN of numeric ones, as in fill.
+use crate::{is_nan, Column, NodFrame, NodFrameError, NodNum};
+use std::collections::HashSet;
+use std::fmt;
+
+// DEFAULT_EXAMPLES is the number of violating rows check keeps per failure
+const DEFAULT_EXAMPLES: usize = 5;
+
+// Check is one expectation on a frame:
+// NotEmpty: no missing cells in the column
+// InRange: numeric values within low..=high, NaN is out of range
+// OneOf: every value, as to_csv writes it, is one of the values
+// Unique: no value repeats an earlier row's value
+// RowCountAtLeast: the frame has at least n rows
+// MatchesRegex: every value, as to_csv writes it, contains a match of the
+// pattern, anchor it with ^ and $ to match whole values. The variants depend
+// on the enabled features, so matches outside the crate need a wildcard arm
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Check<T> {
+    NotEmpty(String),
+    InRange(String, T, T),
+    OneOf(String, Vec<String>),
+    Unique(String),
+    RowCountAtLeast(usize),
+    #[cfg(feature = "regex")]
+    MatchesRegex(String, String),
+}
+
+// Check is displayed like a call, e.g. "in_range(qty, 0, 10)"
+impl<T: fmt::Display> fmt::Display for Check<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::NotEmpty(key) => write!(f, "not_empty({})", key),
+            Check::InRange(key, low, high) => write!(f, "in_range({}, {}, {})", key, low, high),
+            Check::OneOf(key, values) => write!(f, "one_of({}, [{}])", key, values.join(", ")),
+            Check::Unique(key) => write!(f, "unique({})", key),
+            Check::RowCountAtLeast(n) => write!(f, "row_count_at_least({})", n),
+            #[cfg(feature = "regex")]
+            Check::MatchesRegex(key, pattern) => write!(f, "matches_regex({}, {})", key, pattern),
+        }
+    }
+}
+
+// CheckFailure is a failed check: the check as displayed, the number of
+// violating rows and the first of them. For RowCountAtLeast violations is the
+// number of rows missing and there are no example rows
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CheckFailure {
+    check: String,
+    violations: usize,
+    examples: Vec<usize>,
+}
+
+impl CheckFailure {
+    pub fn check(&self) -> &str {
+        &self.check
+    }
+
+    pub fn violations(&self) -> usize {
+        self.violations
+    }
+
+    pub fn examples(&self) -> &[usize] {
+        &self.examples
+    }
+}
+
+// CheckFailure is displayed as "in_range(qty, 0, 10): 3 violations (rows 1, 4, ...)"
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} violations", self.check, self.violations)?;
+        if !self.examples.is_empty() {
+            let rows: Vec<String> = self.examples.iter().map(|r| r.to_string()).collect();
+            let more = if self.violations > self.examples.len() {
+                ", ..."
+            } else {
+                ""
+            };
+            write!(f, " (rows {}{})", rows.join(", "), more)?;
+        }
+        Ok(())
+    }
+}
+
+// CheckReport is the outcome of NodFrame::check
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CheckReport {
+    checked: usize,
+    failures: Vec<CheckFailure>,
+}
+
+impl CheckReport {
+    // checked is the number of checks run
+    pub fn checked(&self) -> usize {
+        self.checked
+    }
+
+    // failures lists the failed checks in the order they were given
+    pub fn failures(&self) -> &[CheckFailure] {
+        &self.failures
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// CheckReport is displayed as a summary line and one line per failure
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed() {
+            return write!(f, "all {} checks passed", self.checked);
+        }
+        write!(
+            f,
+            "{} of {} checks failed",
+            self.failures.len(),
+            self.checked
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // check runs every check and reports the failed ones with up to five
+    // example rows each. A check naming a missing column, InRange on a
+    // discrete column or an invalid regex is an error rather than a failure
+    pub fn check(&self, checks: &[Check<T>]) -> Result<CheckReport, NodFrameError> {
+        self.check_with_examples(checks, DEFAULT_EXAMPLES)
+    }
+
+    // check_with_examples is check keeping up to max_examples violating rows
+    // per failure
+    pub fn check_with_examples(
+        &self,
+        checks: &[Check<T>],
+        max_examples: usize,
+    ) -> Result<CheckReport, NodFrameError> {
+        let mut failures = Vec::new();
+        for check in checks {
+            let (violations, examples) = match check {
+                Check::RowCountAtLeast(n) => (n.saturating_sub(self.num_rows), Vec::new()),
+                _ => {
+                    let rows = self.violating_rows(check)?;
+                    let examples = rows.iter().take(max_examples).copied().collect();
+                    (rows.len(), examples)
+                }
+            };
+            if violations > 0 {
+                failures.push(CheckFailure {
+                    check: check.to_string(),
+                    violations,
+                    examples,
+                });
+            }
+        }
+        Ok(CheckReport {
+            checked: checks.len(),
+            failures,
+        })
+    }
+
+    // check_strict is check for gating a pipeline: any failed check makes it
+    // an error holding the report
+    pub fn check_strict(&self, checks: &[Check<T>]) -> Result<(), NodFrameError> {
+        let report = self.check(checks)?;
+        if report.passed() {
+            Ok(())
+        } else {
+            Err(NodFrameError::ChecksFailed(report))
+        }
+    }
+
+    // violating_rows lists the rows failing a column check in row order
+    fn violating_rows(&self, check: &Check<T>) -> Result<Vec<usize>, NodFrameError> {
+        let column = |key: &str| {
+            self.column(key)
+                .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))
+        };
+        let rows = 0..self.num_rows;
+        Ok(match check {
+            Check::NotEmpty(key) => match column(key)? {
+                Column::Numeric(n) => rows.filter(|r| is_nan(n.get(*r))).collect(),
+                Column::Discrete(d) => rows.filter(|r| d.get(*r).is_empty()).collect(),
+            },
+            Check::InRange(key, low, high) => {
+                let values = self.numeric_column(key)?;
+                rows.filter(|r| {
+                    let x = values.get(*r);
+                    !(x >= low && x <= high)
+                })
+                .collect()
+            }
+            Check::OneOf(key, values) => {
+                let col = column(key)?;
+                let allowed: HashSet<&str> = values.iter().map(String::as_str).collect();
+                rows.filter(|r| !allowed.contains(col.cell_string(*r).as_str()))
+                    .collect()
+            }
+            Check::Unique(key) => {
+                let col = column(key)?;
+                let mut seen = HashSet::new();
+                rows.filter(|r| !seen.insert(col.cell_string(*r))).collect()
+            }
+            Check::RowCountAtLeast(_) => Vec::new(),
+            #[cfg(feature = "regex")]
+            Check::MatchesRegex(key, pattern) => {
+                let col = column(key)?;
+                let re = regex::Regex::new(pattern).map_err(|e| {
+                    NodFrameError::InvalidArgument(format!("invalid pattern '{}': {}", pattern, e))
+                })?;
+                rows.filter(|r| !re.is_match(&col.cell_string(*r)))
+                    .collect()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod check_tests {
+    use crate::test_support::strings;
+    use crate::{Check, NodFrame, NodFrameBuilder, NodFrameError};
+
+    fn orders() -> NodFrame<f64> {
+        NodFrameBuilder::new()
+            .add_discrete("id", strings(&["a1", "a2", "a2", "b7", ""]))
+            .add_discrete("status", strings(&["open", "done", "open", "lost", "done"]))
+            .add_numeric("qty", vec![1.0, 12.0, 5.0, f64::NAN, -3.0])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn checks_test() {
+        let frame = orders();
+        // row 3 has the NaN quantity, which no range contains
+        let clean = frame.take(&[0, 1, 2, 4]).unwrap();
+        let passing = vec![
+            Check::NotEmpty(String::from("status")),
+            Check::InRange(String::from("qty"), -5.0, 20.0),
+            Check::OneOf(String::from("status"), strings(&["open", "done", "lost"])),
+            Check::Unique(String::from("qty")),
+            Check::RowCountAtLeast(4),
+        ];
+        let report = clean.check(&passing).unwrap();
+        assert!(report.passed());
+        assert_eq!(report.checked(), 5);
+
+        let failing = vec![
+            Check::NotEmpty(String::from("id")),
+            Check::NotEmpty(String::from("qty")),
+            Check::InRange(String::from("qty"), 0.0, 10.0),
+            Check::OneOf(String::from("status"), strings(&["open", "done"])),
+            Check::Unique(String::from("id")),
+            Check::RowCountAtLeast(8),
+        ];
+        let report = frame.check(&failing).unwrap();
+        assert!(!report.passed());
+        let counts: Vec<(usize, Vec<usize>)> = report
+            .failures()
+            .iter()
+            .map(|f| (f.violations(), f.examples().to_vec()))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (1, vec![4]),
+                (1, vec![3]),
+                (3, vec![1, 3, 4]),
+                (1, vec![3]),
+                (1, vec![2]),
+                (3, vec![]),
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "6 of 6 checks failed\n  not_empty(id): 1 violations (rows 4)\n  \
+             not_empty(qty): 1 violations (rows 3)\n  \
+             in_range(qty, 0, 10): 3 violations (rows 1, 3, 4)\n  \
+             one_of(status, [open, done]): 1 violations (rows 3)\n  \
+             unique(id): 1 violations (rows 2)\n  \
+             row_count_at_least(8): 3 violations"
+        );
+
+        let ok = vec![Check::RowCountAtLeast(1)];
+        assert_eq!(frame.check(&ok).unwrap().to_string(), "all 1 checks passed");
+        assert!(frame.check_strict(&ok).is_ok());
+        match frame.check_strict(&failing) {
+            Err(NodFrameError::ChecksFailed(r)) => assert_eq!(r, report),
+            other => panic!("expected a failed check, got {:?}", other),
+        }
+
+        assert!(frame
+            .check(&[Check::NotEmpty(String::from("missing"))])
+            .is_err());
+        assert!(frame
+            .check(&[Check::InRange(String::from("id"), 0.0, 1.0)])
+            .is_err());
+    }
+
+    #[test]
+    fn check_examples_cap_test() {
+        let frame: NodFrame<i64> = NodFrameBuilder::new()
+            .add_numeric("x", (0..100).collect())
+            .build()
+            .unwrap();
+        let range = vec![Check::InRange(String::from("x"), 0, 9)];
+        let report = frame.check(&range).unwrap();
+        let failure = &report.failures()[0];
+        assert_eq!(failure.violations(), 90);
+        assert_eq!(failure.examples(), &[10, 11, 12, 13, 14]);
+        assert!(failure
+            .to_string()
+            .ends_with("(rows 10, 11, 12, 13, 14, ...)"));
+        let two = frame.check_with_examples(&range, 2).unwrap();
+        assert_eq!(two.failures()[0].examples(), &[10, 11]);
+        let none = frame.check_with_examples(&range, 0).unwrap();
+        assert_eq!(
+            none.failures()[0].to_string(),
+            "in_range(x, 0, 9): 90 violations"
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn matches_regex_test() {
+        let frame = orders();
+        let pattern = vec![Check::MatchesRegex(
+            String::from("id"),
+            String::from("^[a-z][0-9]$"),
+        )];
+        let report = frame.check(&pattern).unwrap();
+        assert_eq!(report.failures()[0].examples(), &[4]);
+        let loose = vec![Check::MatchesRegex(
+            String::from("status"),
+            String::from("o"),
+        )];
+        assert!(frame.check(&loose).unwrap().passed());
+        let bad = vec![Check::MatchesRegex(String::from("id"), String::from("("))];
+        assert!(frame.check(&bad).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_report_serialize_test() {
+        let report = orders()
+            .check(&[Check::Unique(String::from("id"))])
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"checked":1,"failures":[{"check":"unique(id)","violations":1,"examples":[2]}]}"#
+        );
+    }
+}
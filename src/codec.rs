@@ -0,0 +1,759 @@
+// Compressed column encodings and a frame that keeps them resident.
+//
+// Delta encoding stores an integer column as a checkpoint value every 128 rows
+// plus each row's difference to the previous one, in the narrowest integer
+// width that holds every difference. Run-length encoding stores each run of
+// equal values once along with the row where the run ends. Both answer get and
+// filters without decoding the whole column; binary_view on a delta column
+// decodes only the kept rows.
+use crate::{
+    compare, Cell, ColKind, Column, Comp, DiscreteColumn, FilterValue, Mask, MemoryReport,
+    NodFrame, NodFrameBuilder, NodFrameError, NodNum, NumericColumn,
+};
+use std::collections::HashMap;
+use std::mem::size_of;
+
+const CHECKPOINT: usize = 128;
+
+#[derive(Clone, Debug)]
+enum Deltas {
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+}
+
+impl Deltas {
+    // narrowest stores the deltas in the smallest width that fits them all
+    fn narrowest(deltas: Vec<i64>) -> Deltas {
+        let (lo, hi) = deltas.iter().fold((0, 0), |(lo, hi), d| {
+            (d.min(&lo).to_owned(), d.max(&hi).to_owned())
+        });
+        if lo >= i8::MIN as i64 && hi <= i8::MAX as i64 {
+            Deltas::I8(deltas.into_iter().map(|d| d as i8).collect())
+        } else if lo >= i16::MIN as i64 && hi <= i16::MAX as i64 {
+            Deltas::I16(deltas.into_iter().map(|d| d as i16).collect())
+        } else if lo >= i32::MIN as i64 && hi <= i32::MAX as i64 {
+            Deltas::I32(deltas.into_iter().map(|d| d as i32).collect())
+        } else {
+            Deltas::I64(deltas)
+        }
+    }
+
+    fn get(&self, i: usize) -> i64 {
+        match self {
+            Deltas::I8(d) => d[i] as i64,
+            Deltas::I16(d) => d[i] as i64,
+            Deltas::I32(d) => d[i] as i64,
+            Deltas::I64(d) => d[i],
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        size_of::<Deltas>()
+            + match self {
+                Deltas::I8(d) => d.len(),
+                Deltas::I16(d) => d.len() * 2,
+                Deltas::I32(d) => d.len() * 4,
+                Deltas::I64(d) => d.len() * 8,
+            }
+    }
+}
+
+// DeltaColumn is a delta encoded integer column, see NumericColumn::encode_delta
+#[derive(Clone, Debug)]
+pub struct DeltaColumn<T> {
+    key: String,
+    len: usize,
+    checkpoints: Vec<i64>,
+    deltas: Deltas,
+    kind: std::marker::PhantomData<T>,
+}
+
+impl<T: num_traits::NumCast> DeltaColumn<T> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn raw(&self, index: usize) -> i64 {
+        let start = index / CHECKPOINT * CHECKPOINT;
+        ((start + 1)..=index).fold(self.checkpoints[index / CHECKPOINT], |v, i| {
+            v + self.deltas.get(i)
+        })
+    }
+
+    // get decodes the value at index from the nearest checkpoint
+    pub fn get(&self, index: usize) -> T {
+        assert!(index < self.len, "index {} out of bounds", index);
+        // encode_delta checked that every value converts back
+        T::from(self.raw(index)).unwrap()
+    }
+
+    // iter decodes the values in order with a running sum
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        let mut value = 0;
+        (0..self.len).map(move |i| {
+            value = if i % CHECKPOINT == 0 {
+                self.checkpoints[i / CHECKPOINT]
+            } else {
+                value + self.deltas.get(i)
+            };
+            T::from(value).unwrap()
+        })
+    }
+
+    pub fn filter_array(&self, val: &T, comparison: Comp) -> Vec<bool>
+    where
+        T: PartialOrd,
+    {
+        self.iter().map(|x| compare(&x, &comparison, val)).collect()
+    }
+
+    // binary_view keeps the rows marked true, decoded
+    pub fn binary_view(&self, picker: &[bool]) -> NumericColumn<T> {
+        NumericColumn::new(
+            &self.key,
+            self.iter()
+                .zip(picker)
+                .filter(|(_, keep)| **keep)
+                .map(|(x, _)| x)
+                .collect(),
+        )
+    }
+
+    pub fn decode(&self) -> NumericColumn<T> {
+        NumericColumn::new(&self.key, self.iter().collect())
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Vec<i64>>() + self.checkpoints.len() * 8 + self.deltas.bytes()
+    }
+}
+
+impl<T> NumericColumn<T> {
+    // encode_delta delta encodes an integer column, erroring on values that
+    // are not integers or differences that overflow i64
+    pub fn encode_delta(&self) -> Result<DeltaColumn<T>, NodFrameError>
+    where
+        T: num_traits::ToPrimitive + num_traits::NumCast + PartialEq,
+    {
+        let invalid = |row: usize, message: &str| NodFrameError::InvalidValue {
+            key: self.key().to_string(),
+            row,
+            message: String::from(message),
+        };
+        let mut checkpoints = Vec::with_capacity(self.as_slice().len() / CHECKPOINT + 1);
+        let mut deltas = Vec::with_capacity(self.as_slice().len());
+        let mut prev = 0_i64;
+        for (row, x) in self.iter().enumerate() {
+            let v = x
+                .to_i64()
+                .filter(|v| T::from(*v).is_some_and(|back| back == *x))
+                .ok_or_else(|| invalid(row, "value is not an integer"))?;
+            if row % CHECKPOINT == 0 {
+                checkpoints.push(v);
+                deltas.push(0);
+            } else {
+                let d = v
+                    .checked_sub(prev)
+                    .ok_or_else(|| invalid(row, "difference to the previous row overflows"))?;
+                deltas.push(d);
+            }
+            prev = v;
+        }
+        Ok(DeltaColumn {
+            key: self.key().to_string(),
+            len: deltas.len(),
+            checkpoints,
+            deltas: Deltas::narrowest(deltas),
+            kind: std::marker::PhantomData,
+        })
+    }
+
+    pub fn encode_rle(&self) -> RleColumn<T>
+    where
+        T: Clone + PartialEq,
+    {
+        RleColumn::encode(self.key(), self.iter())
+    }
+}
+
+impl DiscreteColumn {
+    // encode_rle keeps the level order of an ordered column
+    pub fn encode_rle(&self) -> RleColumn<String> {
+        let mut col = RleColumn::encode(self.key(), self.iter());
+        col.levels = self.levels.clone();
+        col
+    }
+}
+
+// HeapSize is the memory a value owns outside its inline size
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+macro_rules! no_heap {
+    ($($t:ty),*) => {
+        $(impl HeapSize for $t {
+            fn heap_size(&self) -> usize {
+                0
+            }
+        })*
+    };
+}
+
+no_heap!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+// RleColumn is a run-length encoded column of numbers or strings
+#[derive(Clone, Debug)]
+pub struct RleColumn<V> {
+    key: String,
+    values: Vec<V>,
+    // ends[r] is the row after the last row of run r
+    ends: Vec<usize>,
+    // level order of an encoded ordered discrete column
+    levels: Option<Vec<String>>,
+}
+
+impl<V: Clone + PartialEq> RleColumn<V> {
+    // encode takes borrowed values, &T for numbers or &str for strings
+    fn encode<'a, Q>(key: &str, items: impl Iterator<Item = &'a Q>) -> RleColumn<V>
+    where
+        Q: ?Sized + PartialEq + ToOwned<Owned = V> + 'a,
+        V: std::borrow::Borrow<Q>,
+    {
+        let mut col: RleColumn<V> = RleColumn {
+            key: String::from(key),
+            values: Vec::new(),
+            ends: Vec::new(),
+            levels: None,
+        };
+        for (row, x) in items.enumerate() {
+            match col.values.last() {
+                Some(last) if last.borrow() == x => *col.ends.last_mut().unwrap() = row + 1,
+                _ => {
+                    col.values.push(x.to_owned());
+                    col.ends.push(row + 1);
+                }
+            }
+        }
+        col
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.last().copied().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    // runs is the number of runs of equal values
+    pub fn runs(&self) -> usize {
+        self.values.len()
+    }
+
+    // get finds the run holding index with a binary search
+    pub fn get(&self, index: usize) -> &V {
+        assert!(index < self.len(), "index {} out of bounds", index);
+        &self.values[self.ends.partition_point(|end| *end <= index)]
+    }
+
+    fn runs_with_len(&self) -> impl Iterator<Item = (&V, usize)> {
+        self.values
+            .iter()
+            .zip(self.ends.iter())
+            .scan(0, |start, (v, end)| {
+                let len = end - *start;
+                *start = *end;
+                Some((v, len))
+            })
+    }
+
+    // filter_array compares once per run
+    pub fn filter_array(&self, val: &V, comparison: Comp) -> Vec<bool>
+    where
+        V: PartialOrd,
+    {
+        let mut out = Vec::with_capacity(self.len());
+        for (v, len) in self.runs_with_len() {
+            out.extend(std::iter::repeat_n(compare(v, &comparison, val), len));
+        }
+        out
+    }
+
+    // binary_view keeps the rows marked true and stays encoded
+    pub fn binary_view(&self, picker: &[bool]) -> RleColumn<V> {
+        let mut col = RleColumn {
+            key: self.key.clone(),
+            values: Vec::new(),
+            ends: Vec::new(),
+            levels: self.levels.clone(),
+        };
+        let mut start = 0;
+        let mut kept = 0;
+        for (v, end) in self.values.iter().zip(self.ends.iter()) {
+            let n = picker[start..*end].iter().filter(|b| **b).count();
+            start = *end;
+            if n == 0 {
+                continue;
+            }
+            kept += n;
+            match col.values.last() {
+                Some(last) if last == v => *col.ends.last_mut().unwrap() = kept,
+                _ => {
+                    col.values.push(v.clone());
+                    col.ends.push(kept);
+                }
+            }
+        }
+        col
+    }
+
+    // decode expands the runs back into one value per row
+    pub fn decode(&self) -> Vec<V> {
+        let mut out = Vec::with_capacity(self.len());
+        for (v, len) in self.runs_with_len() {
+            out.extend(std::iter::repeat_n(v.clone(), len));
+        }
+        out
+    }
+
+    pub fn memory_usage(&self) -> usize
+    where
+        V: HeapSize,
+    {
+        2 * size_of::<Vec<V>>()
+            + self.values.len() * (size_of::<V>() + size_of::<usize>())
+            + self.values.iter().map(|v| v.heap_size()).sum::<usize>()
+    }
+}
+
+impl RleColumn<String> {
+    // ordering is DiscreteColumn::ordering of the encoded column
+    pub fn ordering(&self) -> Option<&[String]> {
+        self.levels.as_deref()
+    }
+
+    // filter_comp is DiscreteColumn::filter_comp, comparing once per run
+    pub fn filter_comp(&self, val: &str, comp: Comp) -> Result<Vec<bool>, NodFrameError> {
+        let mut runs = DiscreteColumn::new(&self.key, self.values.clone());
+        runs.levels = self.levels.clone();
+        let bits = runs.filter_comp(val, comp)?;
+        let mut out = Vec::with_capacity(self.len());
+        for (bit, (_, len)) in bits.into_iter().zip(self.runs_with_len()) {
+            out.extend(std::iter::repeat_n(bit, len));
+        }
+        Ok(out)
+    }
+
+    // to_discrete decodes into a DiscreteColumn with the same levels
+    pub fn to_discrete(&self) -> DiscreteColumn {
+        let mut col = DiscreteColumn::new(&self.key, self.decode());
+        col.levels = self.levels.clone();
+        col
+    }
+}
+
+// Codec picks how encode_columns stores a column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Codec {
+    Delta,
+    Rle,
+}
+
+#[derive(Clone, Debug)]
+pub enum EncodedColumn<T> {
+    Plain(Column<T>),
+    Delta(DeltaColumn<T>),
+    NumericRle(RleColumn<T>),
+    DiscreteRle(RleColumn<String>),
+}
+
+// EncodedFrame holds a frame with some columns encoded. It answers lookups
+// and filters in place and decodes to a NodFrame when needed
+#[derive(Clone, Debug)]
+pub struct EncodedFrame<T> {
+    columns: Vec<EncodedColumn<T>>,
+    column_idx: HashMap<String, usize>,
+    num_rows: usize,
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // encode_columns encodes the named columns with their codec, delta needs
+    // a numeric column of integers
+    pub fn encode_columns(
+        &self,
+        codecs: &[(&str, Codec)],
+    ) -> Result<EncodedFrame<T>, NodFrameError> {
+        let mut columns: Vec<EncodedColumn<T>> = self
+            .columns()
+            .iter()
+            .map(|c| EncodedColumn::Plain(c.clone()))
+            .collect();
+        for (key, codec) in codecs {
+            let idx = *self
+                .column_idx
+                .get(*key)
+                .ok_or_else(|| NodFrameError::MissingColumn(key.to_string()))?;
+            columns[idx] = match (&self.columns()[idx], codec) {
+                (Column::Numeric(n), Codec::Delta) => EncodedColumn::Delta(n.encode_delta()?),
+                (Column::Numeric(n), Codec::Rle) => EncodedColumn::NumericRle(n.encode_rle()),
+                (Column::Discrete(d), Codec::Rle) => EncodedColumn::DiscreteRle(d.encode_rle()),
+                (Column::Discrete(_), Codec::Delta) => {
+                    return Err(NodFrameError::KindMismatch {
+                        key: key.to_string(),
+                        expected: ColKind::Numeric,
+                        found: ColKind::Discrete,
+                    })
+                }
+            };
+        }
+        Ok(EncodedFrame {
+            columns,
+            column_idx: self.column_idx.clone(),
+            num_rows: self.shape().0,
+        })
+    }
+}
+
+impl<T: NodNum + HeapSize> EncodedColumn<T> {
+    pub fn key(&self) -> &str {
+        match self {
+            EncodedColumn::Plain(c) => c.get_key(),
+            EncodedColumn::Delta(d) => d.key(),
+            EncodedColumn::NumericRle(r) => r.key(),
+            EncodedColumn::DiscreteRle(r) => r.key(),
+        }
+    }
+
+    pub fn kind(&self) -> ColKind {
+        match self {
+            EncodedColumn::Plain(c) => c.kind(),
+            EncodedColumn::Delta(_) | EncodedColumn::NumericRle(_) => ColKind::Numeric,
+            EncodedColumn::DiscreteRle(_) => ColKind::Discrete,
+        }
+    }
+
+    pub fn is_encoded(&self) -> bool {
+        !matches!(self, EncodedColumn::Plain(_))
+    }
+
+    pub fn get_cell(&self, index: usize) -> Cell<T> {
+        match self {
+            EncodedColumn::Plain(c) => c.get_cell(index),
+            EncodedColumn::Delta(d) => Cell::Num(d.get(index)),
+            EncodedColumn::NumericRle(r) => Cell::Num(r.get(index).clone()),
+            EncodedColumn::DiscreteRle(r) => Cell::Str(r.get(index).clone()),
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        match self {
+            EncodedColumn::Plain(c) => c.memory_usage(),
+            EncodedColumn::Delta(d) => d.memory_usage(),
+            EncodedColumn::NumericRle(r) => r.memory_usage(),
+            EncodedColumn::DiscreteRle(r) => r.memory_usage(),
+        }
+    }
+
+    pub fn decode(&self) -> Column<T> {
+        match self {
+            EncodedColumn::Plain(c) => c.clone(),
+            EncodedColumn::Delta(d) => Column::Numeric(d.decode()),
+            EncodedColumn::NumericRle(r) => {
+                Column::Numeric(NumericColumn::new(r.key(), r.decode()))
+            }
+            EncodedColumn::DiscreteRle(r) => Column::Discrete(r.to_discrete()),
+        }
+    }
+
+    fn binary_view(&self, picker: &[bool]) -> Column<T> {
+        match self {
+            EncodedColumn::Plain(c) => c.binary_view(picker),
+            EncodedColumn::Delta(d) => Column::Numeric(d.binary_view(picker)),
+            EncodedColumn::NumericRle(r) => {
+                Column::Numeric(NumericColumn::new(r.key(), r.binary_view(picker).decode()))
+            }
+            EncodedColumn::DiscreteRle(r) => Column::Discrete(r.binary_view(picker).to_discrete()),
+        }
+    }
+}
+
+impl<T: NodNum + HeapSize> EncodedFrame<T> {
+    pub fn shape(&self) -> (usize, usize) {
+        (self.num_rows, self.columns.len())
+    }
+
+    pub fn columns(&self) -> &[EncodedColumn<T>] {
+        &self.columns
+    }
+
+    pub fn column(&self, key: &str) -> Option<&EncodedColumn<T>> {
+        self.column_idx.get(key).map(|i| &self.columns[*i])
+    }
+
+    // encoded_keys lists the encoded columns in frame order
+    pub fn encoded_keys(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .filter(|c| c.is_encoded())
+            .map(|c| c.key())
+            .collect()
+    }
+
+    pub fn at(&self, key: &str, row: usize) -> Option<Cell<T>> {
+        if row >= self.num_rows {
+            return None;
+        }
+        Some(self.column(key)?.get_cell(row))
+    }
+
+    pub fn memory_usage(&self) -> MemoryReport {
+        MemoryReport {
+            columns: self
+                .columns
+                .iter()
+                .map(|c| (c.key().to_string(), c.memory_usage()))
+                .collect(),
+        }
+    }
+
+    // filter_mask marks the rows NodFrame::filter would keep, discrete
+    // columns compare as in DiscreteColumn::filter_comp
+    pub fn filter_mask(
+        &self,
+        key: &str,
+        comp: Comp,
+        value: &FilterValue<T>,
+    ) -> Result<Mask, NodFrameError> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        let bits = match (col, value) {
+            (EncodedColumn::Plain(Column::Numeric(n)), FilterValue::Num(v)) => {
+                n.filter_array(v, comp)
+            }
+            (EncodedColumn::Plain(Column::Discrete(d)), FilterValue::Str(s)) => {
+                d.filter_comp(s, comp)?
+            }
+            (EncodedColumn::Delta(d), FilterValue::Num(v)) => d.filter_array(v, comp),
+            (EncodedColumn::NumericRle(r), FilterValue::Num(v)) => r.filter_array(v, comp),
+            (EncodedColumn::DiscreteRle(r), FilterValue::Str(s)) => r.filter_comp(s, comp)?,
+            (col, value) => {
+                return Err(NodFrameError::FilterKindMismatch {
+                    key: String::from(key),
+                    column: col.kind(),
+                    value: value.kind(),
+                })
+            }
+        };
+        Ok(Mask::new(bits))
+    }
+
+    // filter decodes the rows where the filter holds into a NodFrame
+    pub fn filter(
+        &self,
+        key: &str,
+        comp: Comp,
+        value: &FilterValue<T>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let mask = self.filter_mask(key, comp, value)?;
+        self.columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| {
+                b.add_column(c.binary_view(mask.as_slice()))
+            })
+            .build()
+    }
+
+    pub fn decode(&self) -> NodFrame<T> {
+        // the columns came from a valid frame, so the lengths and keys agree
+        self.columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.decode()))
+            .build()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod codec_tests {
+    use crate::test_support::strings;
+    use crate::{
+        assert_valid, Cell, Codec, Comp, DiscreteColumn, FilterValue, NodFrame, NodFrameBuilder,
+        NodFrameError, NumericColumn,
+    };
+
+    fn events(rows: usize) -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_numeric("id", (0..rows as i64).map(|i| 1_000_000 + i * 3).collect())
+            .add_numeric("code", (0..rows as i64).map(|i| i / 500).collect())
+            .add_discrete(
+                "status",
+                (0..rows)
+                    .map(|i| String::from(if i % 1000 < 990 { "ok" } else { "failed" }))
+                    .collect(),
+            )
+            .add_numeric("noise", (0..rows as i64).map(|i| i * 7919 % 1013).collect())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let col = NumericColumn::new("v", vec![5_i64, 7, 7, -300, 70_000, i64::MIN / 2, 3]);
+        let delta = col.encode_delta().unwrap();
+        assert_eq!(delta.decode().as_slice(), col.as_slice());
+        assert_eq!(delta.get(5), i64::MIN / 2);
+        let rle = col.encode_rle();
+        assert_eq!(rle.runs(), 6);
+        assert_eq!(rle.decode(), col.as_slice());
+        assert_eq!(*rle.get(2), 7);
+
+        let frame = events(1000);
+        let encoded = frame
+            .encode_columns(&[
+                ("id", Codec::Delta),
+                ("status", Codec::Rle),
+                ("code", Codec::Rle),
+            ])
+            .unwrap();
+        assert_eq!(encoded.encoded_keys(), vec!["id", "code", "status"]);
+        assert_eq!(encoded.decode().to_string(), frame.to_string());
+        assert_eq!(encoded.at("id", 999), frame.at("id", 999));
+        assert_eq!(
+            encoded.at("status", 995),
+            Some(Cell::Str(String::from("failed")))
+        );
+
+        let big = NumericColumn::new("v", vec![i64::MIN, i64::MAX]);
+        assert!(big.encode_delta().is_err());
+        assert!(frame.encode_columns(&[("status", Codec::Delta)]).is_err());
+        assert!(DiscreteColumn::new("s", vec![]).encode_rle().is_empty());
+    }
+
+    #[test]
+    fn encoded_filter_test() {
+        let frame = events(3000);
+        let encoded = frame
+            .encode_columns(&[
+                ("id", Codec::Delta),
+                ("status", Codec::Rle),
+                ("code", Codec::Rle),
+            ])
+            .unwrap();
+        let cases = [
+            ("id", Comp::Geq, FilterValue::Num(1_006_000)),
+            ("code", Comp::Eq, FilterValue::Num(3)),
+            ("code", Comp::Not, FilterValue::Num(3)),
+            ("status", Comp::Eq, FilterValue::Str(String::from("failed"))),
+        ];
+        for (key, comp, value) in cases {
+            let plain = frame.filter(key, comp, value.clone()).unwrap();
+            let filtered = encoded.filter(key, comp, &value).unwrap();
+            assert_valid(&filtered);
+            assert_eq!(
+                filtered.to_string(),
+                plain.to_string(),
+                "{} {:?}",
+                key,
+                comp
+            );
+        }
+        assert!(encoded
+            .filter("status", Comp::Eq, &FilterValue::Num(1))
+            .is_err());
+
+        let rle = frame.numeric_column("code").unwrap().encode_rle();
+        let picker: Vec<bool> = (0..3000).map(|i| i % 3 == 0).collect();
+        assert_eq!(
+            rle.binary_view(&picker).decode(),
+            frame
+                .numeric_column("code")
+                .unwrap()
+                .binary_view(&picker)
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn encoded_levels_test() {
+        let mut frame = NodFrameBuilder::<i64>::new()
+            .add_discrete("size", strings(&["S", "S", "L", "M", "M", "S"]))
+            .add_discrete("city", strings(&["a", "a", "b", "b", "c", "c"]))
+            .build()
+            .unwrap();
+        frame.set_order("size", &["S", "M", "L"]).unwrap();
+        let encoded = frame
+            .encode_columns(&[("size", Codec::Rle), ("city", Codec::Rle)])
+            .unwrap();
+        let above_s = FilterValue::Str(String::from("S"));
+        let filtered = encoded.filter("size", Comp::Gra, &above_s).unwrap();
+        assert_eq!(
+            filtered.to_string(),
+            frame
+                .filter("size", Comp::Gra, above_s.clone())
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(filtered["size"].to_string(), "size: [L, M, M]");
+        // decoding and filtering keep the levels
+        let levels = strings(&["S", "M", "L"]);
+        let ordering = |f: &NodFrame<i64>| {
+            f["size"]
+                .as_discrete()
+                .unwrap()
+                .ordering()
+                .map(|l| l.to_vec())
+        };
+        assert_eq!(ordering(&filtered), Some(levels.clone()));
+        assert_eq!(ordering(&encoded.decode()), Some(levels));
+        assert_eq!(
+            encoded.filter("city", Comp::Gra, &above_s).unwrap_err(),
+            NodFrameError::UnorderedComparison {
+                key: String::from("city"),
+                comp: Comp::Gra,
+            }
+        );
+    }
+
+    #[test]
+    fn encoded_memory_test() {
+        let frame = events(100_000);
+        let encoded = frame
+            .encode_columns(&[
+                ("id", Codec::Delta),
+                ("status", Codec::Rle),
+                ("code", Codec::Rle),
+            ])
+            .unwrap();
+        let plain = frame.memory_usage();
+        let packed = encoded.memory_usage();
+        // ids step by 3, so each delta fits in one byte instead of eight
+        assert!(packed.columns()[0].1 * 7 < plain.columns()[0].1);
+        assert!(packed.columns()[1].1 * 100 < plain.columns()[1].1);
+        assert!(packed.columns()[2].1 * 10 < plain.columns()[2].1);
+        // the unencoded column is unchanged
+        assert_eq!(packed.columns()[3], plain.columns()[3]);
+        assert!(packed.total() * 2 < plain.total());
+    }
+}
@@ -0,0 +1,378 @@
+// Row conditions and case/when column construction.
+//
+// A Cond compares one column against a value, numeric columns against a T and
+// discrete columns against a string, and conditions combine with and/or/not.
+// NodFrame::cond_mask turns a condition into a Mask, and case_when picks a
+// value per row from the first condition that holds.
+use crate::{
+    compare_natural, ColKind, Column, Comp, DiscreteColumn, Mask, NodFrame, NodFrameBuilder,
+    NodFrameError, NodNum, NumericColumn,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cond<T> {
+    Num {
+        key: String,
+        comp: Comp,
+        val: T,
+    },
+    Str {
+        key: String,
+        comp: Comp,
+        val: String,
+    },
+    Natural {
+        key: String,
+        comp: Comp,
+        val: String,
+    },
+    And(Box<Cond<T>>, Box<Cond<T>>),
+    Or(Box<Cond<T>>, Box<Cond<T>>),
+    Not(Box<Cond<T>>),
+}
+
+impl<T> Cond<T> {
+    // num compares the numeric column key against val
+    pub fn num(key: &str, comp: Comp, val: T) -> Cond<T> {
+        Cond::Num {
+            key: String::from(key),
+            comp,
+            val,
+        }
+    }
+
+    // str compares the discrete column key against val as filter does, by
+    // level on an ordered column, only Eq and Not on an unordered one
+    pub fn str(key: &str, comp: Comp, val: &str) -> Cond<T> {
+        Cond::Str {
+            key: String::from(key),
+            comp,
+            val: String::from(val),
+        }
+    }
+
+    // natural compares the discrete column key against val with natural_cmp,
+    // so Cond::natural("version", Comp::Geq, "1.10") holds for "1.12"
+    pub fn natural(key: &str, comp: Comp, val: &str) -> Cond<T> {
+        Cond::Natural {
+            key: String::from(key),
+            comp,
+            val: String::from(val),
+        }
+    }
+
+    pub fn and(self, other: Cond<T>) -> Cond<T> {
+        Cond::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Cond<T>) -> Cond<T> {
+        Cond::Or(Box::new(self), Box::new(other))
+    }
+}
+
+// !cond holds where cond does not
+impl<T> std::ops::Not for Cond<T> {
+    type Output = Cond<T>;
+
+    fn not(self) -> Cond<T> {
+        Cond::Not(Box::new(self))
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // cond_mask evaluates cond on every row
+    pub fn cond_mask(&self, cond: &Cond<T>) -> Result<Mask, NodFrameError> {
+        let column = |key: &str, expected: ColKind| {
+            let col = self
+                .column(key)
+                .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+            if col.kind() != expected {
+                return Err(NodFrameError::KindMismatch {
+                    key: String::from(key),
+                    expected,
+                    found: col.kind(),
+                });
+            }
+            Ok(col)
+        };
+        let combine = |a: &Cond<T>, b: &Cond<T>, f: fn(bool, bool) -> bool| {
+            let (a, b) = (self.cond_mask(a)?, self.cond_mask(b)?);
+            Ok(Mask::new(
+                a.as_slice()
+                    .iter()
+                    .zip(b.as_slice())
+                    .map(|(x, y)| f(*x, *y))
+                    .collect(),
+            ))
+        };
+        match cond {
+            Cond::Num { key, comp, val } => match column(key, ColKind::Numeric)? {
+                Column::Numeric(n) => Ok(Mask::new(n.filter_array(val, *comp))),
+                Column::Discrete(_) => unreachable!(),
+            },
+            Cond::Str { key, comp, val } => match column(key, ColKind::Discrete)? {
+                Column::Discrete(d) => Ok(Mask::new(d.filter_comp(val, *comp)?)),
+                Column::Numeric(_) => unreachable!(),
+            },
+            Cond::Natural { key, comp, val } => match column(key, ColKind::Discrete)? {
+                Column::Discrete(d) => Ok(Mask::new(
+                    d.iter().map(|s| compare_natural(s, comp, val)).collect(),
+                )),
+                Column::Numeric(_) => unreachable!(),
+            },
+            Cond::And(a, b) => combine(a, b, |x, y| x && y),
+            Cond::Or(a, b) => combine(a, b, |x, y| x || y),
+            Cond::Not(a) => Ok(Mask::new(
+                self.cond_mask(a)?.as_slice().iter().map(|b| !b).collect(),
+            )),
+        }
+    }
+
+    // case_when starts building the discrete column key, see CaseWhen
+    pub fn case_when(&self, key: &str) -> CaseWhen<'_, T, String> {
+        CaseWhen::new(self, key)
+    }
+
+    // case_when_num is case_when producing a numeric column
+    pub fn case_when_num(&self, key: &str) -> CaseWhen<'_, T, T> {
+        CaseWhen::new(self, key)
+    }
+}
+
+// CaseWhen builds a column from conditions checked in order, each row takes
+// the value of the first condition that holds, or the otherwise value. A row
+// matching nothing is an error, unless unmatched_empty is set on a discrete
+// column, which leaves it an empty string
+pub struct CaseWhen<'a, T, V> {
+    frame: &'a NodFrame<T>,
+    key: String,
+    branches: Vec<(Cond<T>, V)>,
+    otherwise: Option<V>,
+    unmatched_empty: bool,
+}
+
+impl<'a, T: NodNum, V: Clone> CaseWhen<'a, T, V> {
+    fn new(frame: &'a NodFrame<T>, key: &str) -> Self {
+        CaseWhen {
+            frame,
+            key: String::from(key),
+            branches: Vec::new(),
+            otherwise: None,
+            unmatched_empty: false,
+        }
+    }
+
+    pub fn when(mut self, cond: Cond<T>, value: impl Into<V>) -> Self {
+        self.branches.push((cond, value.into()));
+        self
+    }
+
+    pub fn otherwise(mut self, value: impl Into<V>) -> Self {
+        self.otherwise = Some(value.into());
+        self
+    }
+
+    // values picks each row's value, None for rows matching nothing
+    fn values(&self) -> Result<Vec<Option<V>>, NodFrameError> {
+        let masks = self
+            .branches
+            .iter()
+            .map(|(cond, _)| self.frame.cond_mask(cond))
+            .collect::<Result<Vec<Mask>, NodFrameError>>()?;
+        let (rows, _) = self.frame.shape();
+        Ok((0..rows)
+            .map(|row| {
+                masks
+                    .iter()
+                    .position(|m| m.as_slice()[row])
+                    .map(|b| self.branches[b].1.clone())
+                    .or_else(|| self.otherwise.clone())
+            })
+            .collect())
+    }
+
+    fn unmatched(&self, row: usize) -> NodFrameError {
+        NodFrameError::InvalidValue {
+            key: self.key.clone(),
+            row,
+            message: String::from("no case matches and there is no otherwise value"),
+        }
+    }
+
+    fn append(&self, col: Column<T>) -> Result<NodFrame<T>, NodFrameError> {
+        let out = self
+            .frame
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_column(col)
+            .build()?;
+        Ok(self.frame.traced(out, "case_when", || {
+            vec![
+                ("key", self.key.clone()),
+                ("branches", self.branches.len().to_string()),
+            ]
+        }))
+    }
+}
+
+impl<T: NodNum> CaseWhen<'_, T, String> {
+    pub fn unmatched_empty(mut self, empty: bool) -> Self {
+        self.unmatched_empty = empty;
+        self
+    }
+
+    // build appends the discrete column to a copy of the frame
+    pub fn build(self) -> Result<NodFrame<T>, NodFrameError> {
+        let mut values = Vec::new();
+        for (row, value) in self.values()?.into_iter().enumerate() {
+            match value {
+                Some(v) => values.push(v),
+                None if self.unmatched_empty => values.push(String::new()),
+                None => return Err(self.unmatched(row)),
+            }
+        }
+        self.append(Column::Discrete(DiscreteColumn::new(&self.key, values)))
+    }
+}
+
+impl<T: NodNum> CaseWhen<'_, T, T> {
+    // build_numeric appends the numeric column to a copy of the frame
+    pub fn build_numeric(self) -> Result<NodFrame<T>, NodFrameError> {
+        let mut values = Vec::new();
+        for (row, value) in self.values()?.into_iter().enumerate() {
+            values.push(value.ok_or_else(|| self.unmatched(row))?);
+        }
+        self.append(Column::Numeric(NumericColumn::new(&self.key, values)))
+    }
+}
+
+#[cfg(test)]
+mod cond_tests {
+    use crate::test_support::strings;
+    use crate::{assert_valid, Comp, Cond, FilterValue, NodFrame, NodFrameBuilder, NodFrameError};
+
+    fn parcels() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_numeric("size", vec![5, 50, 500, 10, 100])
+            .add_discrete(
+                "zone",
+                ["eu", "us", "eu", "us", "eu"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn cond_mask_test() {
+        let frame = parcels();
+        let big_eu = Cond::num("size", Comp::Geq, 100).and(Cond::str("zone", Comp::Eq, "eu"));
+        assert_eq!(
+            frame.cond_mask(&big_eu).unwrap().as_slice(),
+            &[false, false, true, false, true]
+        );
+        let either = Cond::num("size", Comp::Les, 10).or(Cond::str("zone", Comp::Eq, "us"));
+        assert_eq!(frame.cond_mask(&either).unwrap().indices(), vec![0, 1, 3]);
+        assert_eq!(frame.cond_mask(&!either).unwrap().indices(), vec![2, 4]);
+        let err = frame
+            .cond_mask(&Cond::num("zone", Comp::Eq, 1))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column 'zone' is discrete, expected numeric"
+        );
+        assert!(frame
+            .cond_mask(&Cond::str("missing", Comp::Eq, "x"))
+            .is_err());
+        // range comparisons go by level, as in filter
+        let err = frame
+            .cond_mask(&Cond::str("zone", Comp::Gra, "eu"))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            NodFrameError::UnorderedComparison {
+                key: String::from("zone"),
+                comp: Comp::Gra,
+            }
+        );
+        let mut sized = NodFrameBuilder::<i64>::new()
+            .add_discrete("size", strings(&["L", "S", "M", "S"]))
+            .build()
+            .unwrap();
+        sized.set_order("size", &["S", "M", "L"]).unwrap();
+        let above_s = sized.cond_mask(&Cond::str("size", Comp::Gra, "S")).unwrap();
+        assert_eq!(above_s.indices(), vec![0, 2]);
+        assert_eq!(
+            sized
+                .filter("size", Comp::Gra, FilterValue::Str(String::from("S")))
+                .unwrap()["size"]
+                .to_string(),
+            "size: [L, M]"
+        );
+    }
+
+    #[test]
+    fn case_when_test() {
+        let frame = parcels();
+        let sized = frame
+            .case_when("class")
+            .when(Cond::num("size", Comp::Les, 10), "small")
+            .when(Cond::num("size", Comp::Les, 100), "medium")
+            .otherwise("large")
+            .build()
+            .unwrap();
+        assert_valid(&sized);
+        assert_eq!(
+            sized["class"].to_string(),
+            "class: [small, medium, large, medium, large]"
+        );
+        // the first matching branch wins, so a broad first branch hides the rest
+        let shadowed = frame
+            .case_when("class")
+            .when(Cond::num("size", Comp::Les, 1000), "any")
+            .when(Cond::num("size", Comp::Les, 10), "small")
+            .build()
+            .unwrap();
+        assert_eq!(
+            shadowed["class"].to_string(),
+            "class: [any, any, any, any, any]"
+        );
+    }
+
+    #[test]
+    fn case_when_unmatched_test() {
+        let frame = parcels();
+        let mixed = || {
+            frame
+                .case_when("tag")
+                .when(
+                    Cond::str("zone", Comp::Eq, "us").and(Cond::num("size", Comp::Gra, 20)),
+                    "us-big",
+                )
+                .when(Cond::str("zone", Comp::Eq, "eu"), "eu")
+        };
+        let err = mixed().build().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column 'tag' row 3: no case matches and there is no otherwise value"
+        );
+        let tagged = mixed().unmatched_empty(true).build().unwrap();
+        assert_eq!(tagged["tag"].to_string(), "tag: [eu, us-big, eu, , eu]");
+
+        let fees = frame
+            .case_when_num("fee")
+            .when(Cond::num("size", Comp::Geq, 100), 9)
+            .otherwise(1)
+            .build_numeric()
+            .unwrap();
+        assert_eq!(fees["fee"].to_string(), "fee: [1, 1, 9, 1, 9]");
+        assert!(frame
+            .case_when_num("fee")
+            .when(Cond::num("size", Comp::Geq, 100), 9)
+            .build_numeric()
+            .is_err());
+    }
+}
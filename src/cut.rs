@@ -0,0 +1,265 @@
+// Binning numeric columns by explicit edges, see NodFrame::cut_with_edges.
+//
+// Edges come from NumericColumn::quantile_edges, which splits a column into
+// bins holding about the same number of rows, or from the caller. Keeping the
+// edges of one frame and cutting a later frame with them bins both the same
+// way, even though the later frame's own quantiles differ.
+use crate::{
+    cmp_nan_last, is_nan, Column, DiscreteColumn, NodFrame, NodFrameError, NodNum, NumericColumn,
+};
+
+// OutOfRange decides what cut_with_edges does with a value below the first
+// edge or above the last: Missing labels it with an empty string, Clamp puts
+// it in the first or last bin and Error fails the cut. NaN is always missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutOfRange {
+    Missing,
+    Clamp,
+    Error,
+}
+
+impl<T: NodNum> NumericColumn<T> {
+    // quantile_edges returns n_bins + 1 ascending edges at the quantiles
+    // 0, 1 / n_bins, ..., 1 of the values, NaN skipped, taken with the
+    // nearest-rank method of quantile. Ties can make neighbouring edges equal:
+    // that is an error, unless allow_fewer is set, which drops the repeated
+    // edges and so returns fewer bins. A column of one distinct value then
+    // gives the single bin [v, v]
+    pub fn quantile_edges(
+        &self,
+        n_bins: usize,
+        allow_fewer: bool,
+    ) -> Result<Vec<T>, NodFrameError> {
+        if n_bins == 0 {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "quantile_edges needs at least one bin",
+            )));
+        }
+        let mut sorted: Vec<T> = self.items.iter().filter(|x| !is_nan(*x)).cloned().collect();
+        if sorted.is_empty() {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "column '{}' has no values to take edges from",
+                self.key
+            )));
+        }
+        sorted.sort_by(cmp_nan_last);
+        let last = (sorted.len() - 1) as f64;
+        let mut edges: Vec<T> = (0..=n_bins)
+            .map(|i| {
+                let rank = (i as f64 / n_bins as f64 * last).round() as usize;
+                sorted[rank].clone()
+            })
+            .collect();
+        edges.dedup();
+        if edges.len() < n_bins + 1 {
+            if !allow_fewer {
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "column '{}' has too many ties for {} bins, only {} edges are distinct",
+                    self.key,
+                    n_bins,
+                    edges.len()
+                )));
+            }
+            if edges.len() == 1 {
+                edges.push(edges[0].clone());
+            }
+        }
+        Ok(edges)
+    }
+
+    // cut labels each value with its bin in edges, see NodFrame::cut_with_edges
+    pub fn cut(
+        &self,
+        edges: &[T],
+        labels: Option<&[String]>,
+        out_of_range: OutOfRange,
+    ) -> Result<DiscreteColumn, NodFrameError> {
+        if edges.len() < 2 || edges.iter().any(is_nan) || edges.windows(2).any(|w| w[0] > w[1]) {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "cut edges must be at least two ascending values",
+            )));
+        }
+        let last = edges.len() - 1;
+        let labels = match labels {
+            Some(labels) if labels.len() != last => {
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "{} edges make {} bins but {} labels were given",
+                    edges.len(),
+                    last,
+                    labels.len()
+                )))
+            }
+            Some(labels) => labels.to_vec(),
+            None => edges
+                .windows(2)
+                .enumerate()
+                .map(|(i, w)| {
+                    let close = if i == last - 1 { "]" } else { ")" };
+                    format!("[{}, {}{}", w[0], w[1], close)
+                })
+                .collect(),
+        };
+        let mut items = Vec::with_capacity(self.items.len());
+        for (row, x) in self.items.iter().enumerate() {
+            let bin = if is_nan(x) {
+                None
+            } else if x < &edges[0] || x > &edges[last] {
+                match out_of_range {
+                    OutOfRange::Missing => None,
+                    OutOfRange::Clamp if x < &edges[0] => Some(0),
+                    OutOfRange::Clamp => Some(last - 1),
+                    OutOfRange::Error => {
+                        return Err(NodFrameError::InvalidValue {
+                            key: self.key.clone(),
+                            row,
+                            message: format!(
+                                "{} is outside the edges {} to {}",
+                                x, edges[0], edges[last]
+                            ),
+                        })
+                    }
+                }
+            } else {
+                // bins are [edges[i], edges[i + 1]) with the last one closed
+                let below = edges.partition_point(|e| e <= x);
+                Some((below - 1).min(last - 1))
+            };
+            items.push(bin.map_or("", |b| labels[b].as_str()));
+        }
+        Ok(DiscreteColumn::interned(&self.key, items))
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // cut_with_edges replaces the numeric column key by the label of the bin
+    // each value falls in. Bins are [edges[i], edges[i + 1]), the last one also
+    // holding its upper edge. labels names the bins, one per pair of edges,
+    // and defaults to "[lo, hi)". Values outside the edges follow out_of_range
+    pub fn cut_with_edges(
+        &self,
+        key: &str,
+        edges: &[T],
+        labels: Option<&[String]>,
+        out_of_range: OutOfRange,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let binned = self.numeric_column(key)?.cut(edges, labels, out_of_range)?;
+        let mut out = self.clone();
+        out.set_column(key, Column::Discrete(binned))?;
+        Ok(self.traced(out, "cut_with_edges", || {
+            vec![
+                ("key", String::from(key)),
+                ("bins", edges.len().saturating_sub(1).to_string()),
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod cut_tests {
+    use crate::test_support::strings;
+    use crate::{NodFrame, NodFrameBuilder, NodFrameError, NumericColumn, OutOfRange};
+
+    fn scores(values: Vec<f64>) -> NodFrame<f64> {
+        let n = values.len();
+        NodFrameBuilder::new()
+            .add_numeric("score", values)
+            .add_numeric("row", (0..n).map(|i| i as f64).collect())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn quantile_edges_test() {
+        let uniform = NumericColumn::new("x", (0..=100).collect::<Vec<i64>>());
+        assert_eq!(
+            uniform.quantile_edges(4, false).unwrap(),
+            vec![0, 25, 50, 75, 100]
+        );
+        assert_eq!(uniform.quantile_edges(1, false).unwrap(), vec![0, 100]);
+        // NaN is skipped, so it neither shifts the ranks nor becomes an edge
+        let gaps = NumericColumn::new("x", vec![4.0, f64::NAN, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!(gaps.quantile_edges(2, false).unwrap(), vec![1.0, 3.0, 5.0]);
+        assert!(uniform.quantile_edges(0, false).is_err());
+        assert!(NumericColumn::new("x", vec![f64::NAN])
+            .quantile_edges(2, true)
+            .is_err());
+    }
+
+    #[test]
+    fn quantile_edges_ties_test() {
+        let mut values = vec![0; 60];
+        values.extend(1..=40);
+        let tied = NumericColumn::new("x", values);
+        assert_eq!(
+            tied.quantile_edges(4, false).unwrap_err(),
+            NodFrameError::InvalidArgument(String::from(
+                "column 'x' has too many ties for 4 bins, only 3 edges are distinct"
+            ))
+        );
+        assert_eq!(tied.quantile_edges(4, true).unwrap(), vec![0, 15, 40]);
+        let constant = NumericColumn::new("x", vec![7; 5]);
+        assert_eq!(constant.quantile_edges(3, true).unwrap(), vec![7, 7]);
+        let binned = constant
+            .cut(&[7, 7], None, OutOfRange::Error)
+            .unwrap()
+            .into_vec();
+        assert_eq!(binned, vec![String::from("[7, 7]"); 5]);
+    }
+
+    #[test]
+    fn cut_with_edges_test() {
+        let train = scores((1..=12).map(|x| x as f64).collect());
+        let edges = train
+            .numeric_column("score")
+            .unwrap()
+            .quantile_edges(3, false)
+            .unwrap();
+        assert_eq!(edges, vec![1.0, 5.0, 8.0, 12.0]);
+        let labels = strings(&["low", "mid", "high"]);
+        let binned = train
+            .cut_with_edges("score", &edges, Some(&labels), OutOfRange::Error)
+            .unwrap();
+        assert_eq!(
+            binned["score"].to_string(),
+            "score: [low, low, low, low, mid, mid, mid, high, high, high, high, high]"
+        );
+        assert_eq!(binned["row"].to_string(), train["row"].to_string());
+
+        // the test frame is binned by the train edges, not its own quantiles
+        let test = scores(vec![5.0, 4.9, 12.0, 8.0, f64::NAN, 0.5, 30.0]);
+        let same = test
+            .cut_with_edges("score", &edges, Some(&labels), OutOfRange::Missing)
+            .unwrap();
+        assert_eq!(
+            same["score"].to_string(),
+            "score: [mid, low, high, high, , , ]"
+        );
+        let clamped = test
+            .cut_with_edges("score", &edges, None, OutOfRange::Clamp)
+            .unwrap();
+        assert_eq!(
+            clamped["score"].to_string(),
+            "score: [\"[5, 8)\", \"[1, 5)\", \"[8, 12]\", \"[8, 12]\", , \"[1, 5)\", \"[8, 12]\"]"
+        );
+        assert_eq!(
+            test.cut_with_edges("score", &edges, None, OutOfRange::Error)
+                .unwrap_err(),
+            NodFrameError::InvalidValue {
+                key: String::from("score"),
+                row: 5,
+                message: String::from("0.5 is outside the edges 1 to 12"),
+            }
+        );
+
+        assert!(test
+            .cut_with_edges("score", &[3.0, 1.0], None, OutOfRange::Clamp)
+            .is_err());
+        assert!(test
+            .cut_with_edges("score", &edges, Some(&labels[..2]), OutOfRange::Clamp)
+            .is_err());
+        let text = binned
+            .cut_with_edges("score", &edges, None, OutOfRange::Clamp)
+            .unwrap_err();
+        assert!(matches!(text, NodFrameError::KindMismatch { .. }));
+    }
+}
@@ -0,0 +1,178 @@
+// Text encodings for csv input, enabled with the "encoding" feature.
+//
+// Plain UTF-8 files are streamed from disk as before. Any other encoding, or a
+// file starting with a UTF-16 byte order mark, is decoded to UTF-8 in memory
+// before parsing.
+use crate::NodFrameError;
+use encoding_rs::{DecoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use std::error::Error;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+// CsvEncoding is the text encoding of a csv file. A byte order mark at the
+// start of the file takes precedence over the chosen encoding
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CsvEncoding {
+    #[default]
+    Utf8,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl CsvEncoding {
+    fn encoding(self) -> &'static Encoding {
+        match self {
+            CsvEncoding::Utf8 => UTF_8,
+            CsvEncoding::Windows1252 => WINDOWS_1252,
+            CsvEncoding::Utf16Le => UTF_16LE,
+            CsvEncoding::Utf16Be => UTF_16BE,
+        }
+    }
+}
+
+// open_decoded opens file_path as a stream of UTF-8 bytes
+pub(crate) fn open_decoded(
+    file_path: impl AsRef<Path>,
+    encoding: CsvEncoding,
+) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let mut file = File::open(file_path)?;
+    let mut head = [0u8; 2];
+    let mut filled = 0;
+    while filled < head.len() {
+        match file.read(&mut head[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    let utf16_bom = matches!(
+        Encoding::for_bom(&head[..filled]),
+        Some((e, _)) if e == UTF_16LE || e == UTF_16BE
+    );
+    if encoding == CsvEncoding::Utf8 && !utf16_bom {
+        file.seek(SeekFrom::Start(0))?;
+        return Ok(Box::new(file));
+    }
+    file.seek(SeekFrom::Start(0))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(Box::new(Cursor::new(decode(&bytes, encoding)?)))
+}
+
+// detected names the encoding open_decoded reads file_path with, that of a
+// byte order mark or else encoding
+pub(crate) fn detected(
+    file_path: impl AsRef<Path>,
+    encoding: CsvEncoding,
+) -> Result<&'static str, Box<dyn Error>> {
+    let mut head = Vec::with_capacity(3);
+    File::open(file_path)?.take(3).read_to_end(&mut head)?;
+    Ok(Encoding::for_bom(&head)
+        .map_or(encoding.encoding(), |(e, _)| e)
+        .name())
+}
+
+// decode converts bytes to UTF-8, reporting the byte offset of the first
+// sequence that is not valid in the encoding
+fn decode(bytes: &[u8], encoding: CsvEncoding) -> Result<Vec<u8>, NodFrameError> {
+    let (encoding, bom_len) = Encoding::for_bom(bytes).unwrap_or((encoding.encoding(), 0));
+    let src = &bytes[bom_len..];
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut out = String::with_capacity(src.len());
+    let mut read = 0;
+    loop {
+        let (result, n) =
+            decoder.decode_to_string_without_replacement(&src[read..], &mut out, true);
+        read += n;
+        match result {
+            DecoderResult::InputEmpty => return Ok(out.into_bytes()),
+            DecoderResult::OutputFull => out.reserve(src.len() - read + 16),
+            DecoderResult::Malformed(bad, after) => {
+                return Err(NodFrameError::InvalidEncoding {
+                    encoding: encoding.name(),
+                    offset: bom_len + read - bad as usize - after as usize,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use crate::test_support::temp_csv;
+    use crate::{frame_from_csv_with_options, Cell, CsvEncoding, CsvOptions};
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        let mut bytes = if little_endian {
+            vec![0xFF, 0xFE]
+        } else {
+            vec![0xFE, 0xFF]
+        };
+        for unit in text.encode_utf16() {
+            if little_endian {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            } else {
+                bytes.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn with_encoding(encoding: CsvEncoding) -> CsvOptions {
+        CsvOptions {
+            encoding,
+            ..CsvOptions::default()
+        }
+    }
+
+    #[test]
+    fn windows_1252_test() {
+        // 0xE9 is é and 0x80 is the euro sign in windows-1252
+        let path = temp_csv("cp1252.csv", b"name,price\ncaf\xE9,3\n\x80uro,5\n");
+        let frame =
+            frame_from_csv_with_options::<i64>(&path, &with_encoding(CsvEncoding::Windows1252))
+                .unwrap();
+        assert_eq!(frame.at("name", 0), Some(Cell::Str(String::from("café"))));
+        assert_eq!(frame.at("name", 1), Some(Cell::Str(String::from("€uro"))));
+        assert_eq!(frame.at("price", 1), Some(Cell::Num(5)));
+    }
+
+    #[test]
+    fn utf16_bom_test() {
+        let text = "name,n\nÅsa,1\nJörg,2\n";
+        for little_endian in [true, false] {
+            let path = temp_csv("utf16.csv", utf16(text, little_endian));
+            // the byte order mark wins over the default utf-8 setting
+            let frame = frame_from_csv_with_options::<i64>(&path, &CsvOptions::default()).unwrap();
+            assert_eq!(frame.at("name", 1), Some(Cell::Str(String::from("Jörg"))));
+            assert_eq!(frame.at("n", 0), Some(Cell::Num(1)));
+        }
+    }
+
+    #[test]
+    fn utf16_without_bom_test() {
+        let bytes: Vec<u8> = "a\n7\n"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        let path = temp_csv("utf16be.csv", &bytes);
+        let frame = frame_from_csv_with_options::<i64>(&path, &with_encoding(CsvEncoding::Utf16Be))
+            .unwrap();
+        assert_eq!(frame.at("a", 0), Some(Cell::Num(7)));
+    }
+
+    #[test]
+    fn decode_error_offset_test() {
+        // an unpaired low surrogate at byte 6
+        let mut bytes: Vec<u8> = "a\n1"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        bytes.extend_from_slice(&[0x00, 0xDC]);
+        let path = temp_csv("bad_utf16.csv", &bytes);
+        let err = frame_from_csv_with_options::<i64>(&path, &with_encoding(CsvEncoding::Utf16Le))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "invalid UTF-16LE data at byte 6");
+    }
+}
@@ -0,0 +1,125 @@
+// Excel support, enabled with the "excel" feature. Sheets are read with
+// calamine and written with rust_xlsxwriter.
+//
+// Excel stores every number as a float. When reading, floats with no
+// fractional part are rendered without a decimal point ("3.0" becomes "3") so
+// they load into integer frames, other cells go through the same inference as
+// frame_from_csv.
+use crate::{frame_from_strings, Column, NodFrame, NodFrameError, NodNum};
+use calamine::{open_workbook_auto, Data, Reader};
+use rust_xlsxwriter::Workbook;
+use std::error::Error;
+
+// xlsx_sheet_names lists the sheets of a workbook in order
+pub fn xlsx_sheet_names(file_path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let workbook = open_workbook_auto(file_path)?;
+    Ok(workbook.sheet_names())
+}
+
+// frame_from_xlsx reads a sheet (the first one when sheet is None) treating the
+// first row as headers
+pub fn frame_from_xlsx<T: NodNum>(
+    file_path: &str,
+    sheet: Option<&str>,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let mut workbook = open_workbook_auto(file_path)?;
+    let names = workbook.sheet_names();
+    let name = match sheet {
+        Some(name) if names.iter().any(|n| n == name) => name.to_string(),
+        Some(name) => {
+            return Err(Box::new(NodFrameError::InvalidArgument(format!(
+                "no sheet named '{}', available sheets: {}",
+                name,
+                names.join(", ")
+            ))))
+        }
+        None => names.first().cloned().ok_or_else(|| {
+            NodFrameError::InvalidArgument(String::from("workbook has no sheets"))
+        })?,
+    };
+    let range = workbook.worksheet_range(&name)?;
+    let mut rows = range.rows();
+    let header: Vec<String> = match rows.next() {
+        Some(row) => row.iter().map(cell_text).collect(),
+        None => Vec::new(),
+    };
+    let mut data: Vec<Vec<String>> = vec![Vec::new(); header.len()];
+    for row in rows {
+        for (i, column) in data.iter_mut().enumerate() {
+            column.push(row.get(i).map(cell_text).unwrap_or_default());
+        }
+    }
+    Ok(frame_from_strings(&header, data)?)
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{}", *f as i64),
+        Data::Empty => String::new(),
+        other => other.to_string(),
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // to_xlsx writes the frame to a single sheet, numeric columns are stored as
+    // Excel numbers and discrete columns as text
+    pub fn to_xlsx(&self, file_path: &str, sheet_name: &str) -> Result<(), Box<dyn Error>> {
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(sheet_name)?;
+        for (c, col) in self.columns().iter().enumerate() {
+            let c = c as u16;
+            sheet.write_string(0, c, col.get_key())?;
+            match col {
+                Column::Numeric(n) => {
+                    for (r, v) in n.iter().enumerate() {
+                        sheet.write_number(r as u32 + 1, c, v.to_f64().unwrap_or(f64::NAN))?;
+                    }
+                }
+                Column::Discrete(d) => {
+                    for (r, v) in d.iter().enumerate() {
+                        sheet.write_string(r as u32 + 1, c, v)?;
+                    }
+                }
+            }
+        }
+        workbook.save(file_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod excel_tests {
+    use super::*;
+    use crate::test_support::temp_path;
+    use crate::{Cell, NodFrameBuilder};
+
+    #[test]
+    fn xlsx_round_trip_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("city", vec![String::from("Oslo"), String::from("Rome")])
+            .add_numeric("pop", vec![700_000_i64, 2_800_000])
+            .build()
+            .unwrap();
+        let path = temp_path("round.xlsx").display().to_string();
+        frame.to_xlsx(&path, "cities").unwrap();
+        assert_eq!(xlsx_sheet_names(&path).unwrap(), vec!["cities"]);
+        let back: NodFrame<i64> = frame_from_xlsx(&path, Some("cities")).unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
+        assert_eq!(back.at("pop", 1), Some(Cell::Num(2_800_000)));
+        let first: NodFrame<i64> = frame_from_xlsx(&path, None).unwrap();
+        assert_eq!(first.shape(), (2, 2));
+    }
+
+    #[test]
+    fn xlsx_missing_sheet_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1_i64])
+            .build()
+            .unwrap();
+        let path = temp_path("missing.xlsx").display().to_string();
+        frame.to_xlsx(&path, "data").unwrap();
+        let err = frame_from_xlsx::<i64>(&path, Some("nope")).unwrap_err();
+        assert!(err.to_string().contains("no sheet named 'nope'"));
+    }
+}
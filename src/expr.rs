@@ -0,0 +1,413 @@
+// Arithmetic expressions over numeric columns, used by NodFrame::eval.
+//
+// An expression is tokenized, parsed into a tree with the usual precedence
+// (* and / before + and -, unary minus tightest) and evaluated row by row in
+// f64. Column names are bare identifiers, or wrapped in backticks when they
+// hold other characters: `unit price` * qty
+use crate::{
+    is_float, to_f64, ColKind, Column, NodFrame, NodFrameBuilder, NodFrameError, NodNum,
+    NumericColumn,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Token {
+    Num(f64),
+    Name(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+// tokenize splits expr into tokens, each with its byte offset in expr
+pub(crate) fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>, NodFrameError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((at, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '+' | '-' | '*' | '/' => Token::Op(c),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '`' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '`')) => break,
+                        Some((_, c)) => name.push(c),
+                        None => return Err(syntax_error(expr, at, "unclosed '`'")),
+                    }
+                }
+                Token::Name(name)
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = at + c.len_utf8();
+                while let Some((i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || *c == '.') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let text = &expr[at..end];
+                let value = text
+                    .parse()
+                    .map_err(|_| syntax_error(expr, at, &format!("'{}' is not a number", text)))?;
+                Token::Num(value)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::from(c);
+                while let Some((_, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || *c == '_') {
+                        break;
+                    }
+                    name.push(*c);
+                    chars.next();
+                }
+                Token::Name(name)
+            }
+            c => return Err(syntax_error(expr, at, &format!("unexpected '{}'", c))),
+        };
+        tokens.push((at, token));
+    }
+    Ok(tokens)
+}
+
+fn syntax_error(expr: &str, at: usize, message: &str) -> NodFrameError {
+    NodFrameError::InvalidArgument(format!(
+        "invalid expression '{}': {} at position {}",
+        expr, message, at
+    ))
+}
+
+// MAX_DEPTH caps how deeply parentheses and unary minus nest and how tall
+// the tree grows, so parsing and evaluating cannot overflow the stack
+const MAX_DEPTH: usize = 256;
+
+// Neg and Binary hold the height of their subtree
+enum Expr<'a, T> {
+    Num(f64),
+    Column(&'a NumericColumn<T>),
+    Neg(usize, Box<Expr<'a, T>>),
+    Binary(char, usize, Box<Expr<'a, T>>, Box<Expr<'a, T>>),
+}
+
+impl<T> Expr<'_, T> {
+    fn height(&self) -> usize {
+        match self {
+            Expr::Num(_) | Expr::Column(_) => 1,
+            Expr::Neg(height, _) | Expr::Binary(_, height, _, _) => *height,
+        }
+    }
+}
+
+struct Parser<'a, T> {
+    text: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    // depth counts the open parentheses and unary minuses being parsed
+    depth: usize,
+    frame: &'a NodFrame<T>,
+}
+
+impl<'a, T: NodNum> Parser<'a, T> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn error(&self, message: &str) -> NodFrameError {
+        match self.tokens.get(self.pos) {
+            Some((at, _)) => syntax_error(self.text, *at, message),
+            None => syntax_error(self.text, self.text.len(), message),
+        }
+    }
+
+    fn too_deep(&self) -> NodFrameError {
+        self.error(&format!("nested more than {} levels deep", MAX_DEPTH))
+    }
+
+    // nested parses with f one level deeper, after the '(' or '-' that
+    // opened the level
+    fn nested(
+        &mut self,
+        f: fn(&mut Self) -> Result<Expr<'a, T>, NodFrameError>,
+    ) -> Result<Expr<'a, T>, NodFrameError> {
+        if self.depth == MAX_DEPTH {
+            self.pos -= 1;
+            return Err(self.too_deep());
+        }
+        self.depth += 1;
+        let expr = f(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn binary(
+        &self,
+        op: char,
+        left: Expr<'a, T>,
+        right: Expr<'a, T>,
+    ) -> Result<Expr<'a, T>, NodFrameError> {
+        let height = 1 + left.height().max(right.height());
+        if height > MAX_DEPTH {
+            return Err(self.too_deep());
+        }
+        Ok(Expr::Binary(op, height, Box::new(left), Box::new(right)))
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr<'a, T>, NodFrameError> {
+        let mut left = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let right = self.product()?;
+            left = self.binary(op, left, right)?;
+        }
+        Ok(left)
+    }
+
+    // product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expr<'a, T>, NodFrameError> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek() {
+            let op = *op;
+            self.pos += 1;
+            let right = self.unary()?;
+            left = self.binary(op, left, right)?;
+        }
+        Ok(left)
+    }
+
+    // unary := '-' unary | atom
+    fn unary(&mut self) -> Result<Expr<'a, T>, NodFrameError> {
+        if let Some(Token::Op('-')) = self.peek() {
+            self.pos += 1;
+            let inner = self.nested(Self::unary)?;
+            return Ok(Expr::Neg(inner.height() + 1, Box::new(inner)));
+        }
+        self.atom()
+    }
+
+    // atom := number | name | '(' sum ')'
+    fn atom(&mut self) -> Result<Expr<'a, T>, NodFrameError> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.error("unexpected end")),
+        };
+        let expr = match token {
+            Token::Num(value) => Expr::Num(value),
+            Token::Name(name) => match self.frame.column(&name) {
+                Some(Column::Numeric(n)) => Expr::Column(n),
+                Some(Column::Discrete(_)) => {
+                    return Err(NodFrameError::KindMismatch {
+                        key: name,
+                        expected: ColKind::Numeric,
+                        found: ColKind::Discrete,
+                    })
+                }
+                None => return Err(NodFrameError::MissingColumn(name)),
+            },
+            Token::Open => {
+                self.pos += 1;
+                let inner = self.nested(Self::sum)?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(self.error("expected ')'"));
+                }
+                inner
+            }
+            Token::Close => return Err(self.error("unexpected ')'")),
+            Token::Op(op) => return Err(self.error(&format!("unexpected '{}'", op))),
+        };
+        self.pos += 1;
+        Ok(expr)
+    }
+}
+
+impl<T: num_traits::ToPrimitive> Expr<'_, T> {
+    fn value(&self, row: usize) -> Result<f64, &'static str> {
+        Ok(match self {
+            Expr::Num(value) => *value,
+            Expr::Column(n) => to_f64(&n[row]),
+            Expr::Neg(_, inner) => -inner.value(row)?,
+            Expr::Binary(op, _, left, right) => {
+                let (a, b) = (left.value(row)?, right.value(row)?);
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    _ if b == 0.0 => return Err("division by zero"),
+                    _ => a / b,
+                }
+            }
+        })
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // eval appends new_key computed from an arithmetic expression over the
+    // numeric columns, e.g. "(price * qty) - discount". Values are computed in
+    // f64 and converted to T. On an integer frame a result with a fraction is
+    // an error naming the row, as is dividing by zero
+    pub fn eval(&self, new_key: &str, expr: &str) -> Result<NodFrame<T>, NodFrameError> {
+        let mut parser = Parser {
+            text: expr,
+            tokens: tokenize(expr)?,
+            pos: 0,
+            depth: 0,
+            frame: self,
+        };
+        let tree = parser.sum()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(parser.error("unexpected token"));
+        }
+        let (rows, _) = self.shape();
+        let integral = !is_float::<T>();
+        let mut values = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let invalid = |message: &str| NodFrameError::InvalidValue {
+                key: String::from(new_key),
+                row,
+                message: String::from(message),
+            };
+            let value = tree.value(row).map_err(invalid)?;
+            if integral && value.fract() != 0.0 && value.is_finite() {
+                return Err(invalid(&format!(
+                    "{} is not an integer, an integer frame would truncate it",
+                    value
+                )));
+            }
+            values.push(
+                T::from(value).ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from(new_key),
+                    row,
+                })?,
+            );
+        }
+        let out = self
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, values)
+            .build()?;
+        Ok(self.traced(out, "eval", || {
+            vec![
+                ("new_key", String::from(new_key)),
+                ("expr", String::from(expr)),
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod expr_tests {
+    use super::{tokenize, Token};
+    use crate::{NodFrame, NodFrameBuilder};
+
+    fn orders() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_numeric("price", vec![10, 4, 7])
+            .add_numeric("qty", vec![3, 5, 0])
+            .add_numeric("discount", vec![5, 0, 1])
+            .add_discrete("sku", vec![String::from("a"); 3])
+            .build()
+            .unwrap()
+    }
+
+    fn eval(expr: &str) -> String {
+        match orders().eval("out", expr) {
+            Ok(frame) => frame["out"].to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn tokenize_test() {
+        let tokens: Vec<Token> = tokenize("(a_1+ 2.5)*`unit price`")
+            .unwrap()
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Open,
+                Token::Name(String::from("a_1")),
+                Token::Op('+'),
+                Token::Num(2.5),
+                Token::Close,
+                Token::Op('*'),
+                Token::Name(String::from("unit price")),
+            ]
+        );
+    }
+
+    #[test]
+    fn precedence_test() {
+        assert_eq!(eval("(price * qty) - discount"), "out: [25, 20, -1]");
+        assert_eq!(eval("price * qty - discount"), "out: [25, 20, -1]");
+        assert_eq!(eval("price - qty * 2"), "out: [4, -6, 7]");
+        assert_eq!(eval("(price - qty) * 2"), "out: [14, -2, 14]");
+        assert_eq!(eval("price - qty - discount"), "out: [2, -1, 6]");
+        assert_eq!(eval("-price + 1"), "out: [-9, -3, -6]");
+        // evaluated in f64, so 7 / 2 * 2 is 7 again
+        assert_eq!(eval("price / 2 * 2"), "out: [10, 4, 7]");
+        assert_eq!(eval("price * 1.5 * 2"), "out: [30, 12, 21]");
+        let floats = NodFrameBuilder::new()
+            .add_numeric("price", vec![10.0, 4.0, 7.0])
+            .build()
+            .unwrap();
+        let halves = floats.eval("out", "price / 4").unwrap();
+        assert_eq!(halves["out"].to_string(), "out: [2.5, 1, 1.75]");
+    }
+
+    #[test]
+    fn eval_error_test() {
+        assert_eq!(
+            eval("price * qty / qty"),
+            "column 'out' row 2: division by zero"
+        );
+        assert_eq!(
+            eval("price * 1.5"),
+            "column 'out' row 2: 10.5 is not an integer, an integer frame would truncate it"
+        );
+        let deep = format!("{}1{}", "(".repeat(300), ")".repeat(300));
+        assert_eq!(
+            eval(&deep),
+            format!(
+                "invalid expression '{}': nested more than 256 levels deep at position 256",
+                deep
+            )
+        );
+        assert!(eval(&"-".repeat(100_000)).contains("nested more than 256 levels deep"));
+        let long = vec!["price"; 300].join(" + ");
+        assert!(eval(&long).contains("nested more than 256 levels deep"));
+        let fits = vec!["1"; 256].join(" + ");
+        assert_eq!(eval(&fits), "out: [256, 256, 256]");
+        assert_eq!(eval("price * cost"), "no column named 'cost'");
+        assert_eq!(
+            eval("sku + 1"),
+            "column 'sku' is discrete, expected numeric"
+        );
+        assert_eq!(
+            eval("(price + 1"),
+            "invalid expression '(price + 1': expected ')' at position 10"
+        );
+        assert_eq!(
+            eval("price + * qty"),
+            "invalid expression 'price + * qty': unexpected '*' at position 8"
+        );
+        assert_eq!(
+            eval("price qty"),
+            "invalid expression 'price qty': unexpected token at position 6"
+        );
+        assert_eq!(
+            eval("price % 2"),
+            "invalid expression 'price % 2': unexpected '%' at position 6"
+        );
+        assert_eq!(
+            eval("1.2.3"),
+            "invalid expression '1.2.3': '1.2.3' is not a number at position 0"
+        );
+        assert!(orders().eval("price", "qty").is_err());
+    }
+}
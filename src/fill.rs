@@ -0,0 +1,329 @@
+// Gap filling: carrying the last (or next) present value into missing cells.
+//
+// The column methods take a predicate deciding what is missing, so any
+// sentinel works. The NodFrame methods use the missing cells frame_from_csv
+// produces: empty strings in discrete columns and NaN in numeric ones.
+// Missing cells with nothing to carry, at the start for a forward fill or the
+// end for a backward fill, stay as they are unless an initial value is given.
+use crate::{
+    is_nan, Cell, Column, DiscreteColumn, NodFrame, NodFrameError, NodNum, NumericColumn,
+    ValueOrder,
+};
+use std::sync::Arc;
+
+// FillDirection for fill: Forward carries the last present value down,
+// Backward carries the next present value up
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FillDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+// carry fills the missing values among rows, visited in the given direction,
+// from the last present value seen or initial
+fn carry<V: Clone>(
+    values: &mut [V],
+    rows: &[usize],
+    direction: FillDirection,
+    is_missing: impl Fn(&V) -> bool,
+    initial: Option<&V>,
+) {
+    let mut last: Option<V> = initial.cloned();
+    let mut visit = |row: usize| {
+        if is_missing(&values[row]) {
+            if let Some(v) = &last {
+                values[row] = v.clone();
+            }
+        } else {
+            last = Some(values[row].clone());
+        }
+    };
+    match direction {
+        FillDirection::Forward => rows.iter().for_each(|r| visit(*r)),
+        FillDirection::Backward => rows.iter().rev().for_each(|r| visit(*r)),
+    }
+}
+
+impl DiscreteColumn {
+    // fill replaces every value is_missing accepts with the nearest present
+    // value in direction, or initial when there is none. An ordered column
+    // only takes an initial value that is one of its levels
+    pub fn fill(
+        &self,
+        direction: FillDirection,
+        is_missing: impl Fn(&str) -> bool,
+        initial: Option<&str>,
+    ) -> Result<DiscreteColumn, NodFrameError> {
+        self.check_initial(&is_missing, initial)?;
+        let rows: Vec<usize> = (0..self.len()).collect();
+        Ok(self.fill_rows(&[rows], direction, is_missing, initial))
+    }
+
+    // check_initial fails when some missing cell could be given initial and
+    // initial is not a level of the ordered column
+    fn check_initial(
+        &self,
+        is_missing: &impl Fn(&str) -> bool,
+        initial: Option<&str>,
+    ) -> Result<(), NodFrameError> {
+        match (initial, self.iter().position(is_missing)) {
+            (Some(value), Some(row)) => self.check_level(row, value),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn fill_forward(&self, is_missing: impl Fn(&str) -> bool) -> DiscreteColumn {
+        let rows: Vec<usize> = (0..self.len()).collect();
+        self.fill_rows(&[rows], FillDirection::Forward, is_missing, None)
+    }
+
+    pub fn fill_backward(&self, is_missing: impl Fn(&str) -> bool) -> DiscreteColumn {
+        let rows: Vec<usize> = (0..self.len()).collect();
+        self.fill_rows(&[rows], FillDirection::Backward, is_missing, None)
+    }
+
+    // fill_rows fills each run of rows on its own, nothing carries between runs
+    fn fill_rows(
+        &self,
+        runs: &[Vec<usize>],
+        direction: FillDirection,
+        is_missing: impl Fn(&str) -> bool,
+        initial: Option<&str>,
+    ) -> DiscreteColumn {
+        let mut items = self.as_slice().to_vec();
+        let initial = initial.map(Arc::from);
+        for rows in runs {
+            carry(
+                &mut items,
+                rows,
+                direction,
+                |s: &Arc<str>| is_missing(s),
+                initial.as_ref(),
+            );
+        }
+        // filling only copies values the column already holds, so an ordered
+        // column keeps its levels
+        DiscreteColumn {
+            key: self.key.clone(),
+            items,
+            levels: self.levels.clone(),
+        }
+    }
+}
+
+impl<T: NodNum> NumericColumn<T> {
+    // fill replaces every value is_missing accepts with the nearest present
+    // value in direction, or initial when there is none
+    pub fn fill(
+        &self,
+        direction: FillDirection,
+        is_missing: impl Fn(&T) -> bool,
+        initial: Option<&T>,
+    ) -> NumericColumn<T> {
+        let rows: Vec<usize> = (0..self.len()).collect();
+        self.fill_rows(&[rows], direction, is_missing, initial)
+    }
+
+    pub fn fill_forward(&self, is_missing: impl Fn(&T) -> bool) -> NumericColumn<T> {
+        self.fill(FillDirection::Forward, is_missing, None)
+    }
+
+    pub fn fill_backward(&self, is_missing: impl Fn(&T) -> bool) -> NumericColumn<T> {
+        self.fill(FillDirection::Backward, is_missing, None)
+    }
+
+    fn fill_rows(
+        &self,
+        runs: &[Vec<usize>],
+        direction: FillDirection,
+        is_missing: impl Fn(&T) -> bool,
+        initial: Option<&T>,
+    ) -> NumericColumn<T> {
+        let mut items = self.as_slice().to_vec();
+        for rows in runs {
+            carry(&mut items, rows, direction, &is_missing, initial);
+        }
+        NumericColumn::new(self.key(), items)
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // ffill carries the last present value of key into the empty or NaN
+    // cells after it
+    pub fn ffill(&self, key: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.fill_gaps(key, None, FillDirection::Forward, None)
+    }
+
+    // bfill carries the next present value of key into the cells before it
+    pub fn bfill(&self, key: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.fill_gaps(key, None, FillDirection::Backward, None)
+    }
+
+    // ffill_by is ffill within each group of group_col, restarting at every
+    // group so no value is carried from one group into another
+    pub fn ffill_by(&self, key: &str, group_col: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.fill_gaps(key, Some(group_col), FillDirection::Forward, None)
+    }
+
+    pub fn bfill_by(&self, key: &str, group_col: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.fill_gaps(key, Some(group_col), FillDirection::Backward, None)
+    }
+
+    // fill_gaps fills key in direction, within each group of group_col when
+    // given. initial fills the cells with nothing to carry and must match the
+    // kind of key
+    pub fn fill_gaps(
+        &self,
+        key: &str,
+        group_col: Option<&str>,
+        direction: FillDirection,
+        initial: Option<Cell<T>>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        if let Some(cell) = &initial {
+            if cell.kind() != col.kind() {
+                return Err(NodFrameError::KindMismatch {
+                    key: String::from(key),
+                    expected: col.kind(),
+                    found: cell.kind(),
+                });
+            }
+        }
+        let runs = match group_col {
+            Some(g) => self
+                .group_by_ordered(g, ValueOrder::FirstSeen)?
+                .groups()
+                .to_vec(),
+            None => vec![(0..self.shape().0).collect()],
+        };
+        let filled = match (col, &initial) {
+            (Column::Numeric(n), Some(Cell::Num(v))) => {
+                Column::Numeric(n.fill_rows(&runs, direction, is_nan, Some(v)))
+            }
+            (Column::Numeric(n), _) => Column::Numeric(n.fill_rows(&runs, direction, is_nan, None)),
+            (Column::Discrete(d), Some(Cell::Str(s))) => {
+                d.check_initial(&str::is_empty, Some(s))?;
+                Column::Discrete(d.fill_rows(&runs, direction, str::is_empty, Some(s)))
+            }
+            (Column::Discrete(d), _) => {
+                Column::Discrete(d.fill_rows(&runs, direction, str::is_empty, None))
+            }
+        };
+        let mut out = self.clone();
+        out.set_column(key, filled)?;
+        Ok(self.traced(out, "fill_gaps", || {
+            vec![
+                ("key", String::from(key)),
+                ("by", group_col.map_or_else(String::new, String::from)),
+                ("direction", format!("{:?}", direction)),
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod fill_tests {
+    use crate::test_support::strings;
+    use crate::{Cell, DiscreteColumn, FillDirection, NodFrame, NodFrameBuilder, NumericColumn};
+
+    #[test]
+    fn fill_column_test() {
+        // a leading gap, two consecutive gaps and a trailing gap
+        let col = DiscreteColumn::new("s", strings(&["", "a", "", "", "b", ""]));
+        let forward = col.fill_forward(str::is_empty);
+        assert_eq!(
+            forward.iter().collect::<Vec<_>>(),
+            strings(&["", "a", "a", "a", "b", "b"]).as_slice()
+        );
+        let backward = col.fill_backward(str::is_empty);
+        assert_eq!(
+            backward.iter().collect::<Vec<_>>(),
+            strings(&["a", "a", "b", "b", "b", ""]).as_slice()
+        );
+        let seeded = col
+            .fill(FillDirection::Forward, str::is_empty, Some("start"))
+            .unwrap();
+        assert_eq!(&seeded[0], "start");
+
+        let readings = NumericColumn::new("t", vec![-999, 20, -999, -999, 23, -999]);
+        let missing = |x: &i64| *x == -999;
+        assert_eq!(
+            readings.fill_forward(missing).as_slice(),
+            &[-999, 20, 20, 20, 23, 23]
+        );
+        assert_eq!(
+            readings.fill_backward(missing).as_slice(),
+            &[20, 20, 23, 23, 23, -999]
+        );
+        assert_eq!(
+            readings
+                .fill(FillDirection::Backward, missing, Some(&0))
+                .as_slice(),
+            &[20, 20, 23, 23, 23, 0]
+        );
+    }
+
+    #[test]
+    fn ffill_frame_test() {
+        let frame: NodFrame<f64> = NodFrameBuilder::new()
+            .add_discrete("sensor", strings(&["a", "a", "b", "a", "b", "b"]))
+            .add_numeric(
+                "temp",
+                vec![1.0, f64::NAN, f64::NAN, f64::NAN, 5.0, f64::NAN],
+            )
+            .add_discrete("note", strings(&["x", "", "", "y", "", ""]))
+            .build()
+            .unwrap();
+        let filled = frame.ffill("temp").unwrap();
+        assert_eq!(
+            filled.numeric_column("temp").unwrap().as_slice(),
+            &[1.0, 1.0, 1.0, 1.0, 5.0, 5.0]
+        );
+        let back = frame.bfill("note").unwrap();
+        assert_eq!(
+            back.discrete_column("note")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["x", "y", "y", "y", "", ""]).as_slice()
+        );
+
+        // groups restart: b never takes a's reading
+        let by = frame.ffill_by("temp", "sensor").unwrap();
+        let temps = by.numeric_column("temp").unwrap().as_slice();
+        assert_eq!(&temps[..2], &[1.0, 1.0]);
+        assert!(temps[2].is_nan());
+        assert_eq!(&temps[3..], &[1.0, 5.0, 5.0]);
+        let up = frame.bfill_by("note", "sensor").unwrap();
+        assert_eq!(
+            up.discrete_column("note")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["x", "y", "", "y", "", ""]).as_slice()
+        );
+
+        let seeded = frame
+            .fill_gaps(
+                "temp",
+                Some("sensor"),
+                FillDirection::Forward,
+                Some(Cell::Num(0.0)),
+            )
+            .unwrap();
+        assert_eq!(seeded.numeric_column("temp").unwrap().as_slice()[2], 0.0);
+        assert!(frame
+            .fill_gaps(
+                "temp",
+                None,
+                FillDirection::Forward,
+                Some(Cell::Str(String::new()))
+            )
+            .is_err());
+        assert!(frame.ffill("missing").is_err());
+        assert!(frame.ffill_by("temp", "missing").is_err());
+    }
+}
@@ -0,0 +1,1573 @@
+// Grouping rows by the values of a key column.
+//
+// A GroupBy borrows its frame and keeps the row indices of each group. Groups
+// are ordered by key value and rows within a group keep their frame order, so
+// every aggregation produces its rows in the same deterministic order.
+use crate::preview::{sample_rows, SplitMix64};
+use crate::{
+    cmp_nan_last, is_nan, require_float, to_f64, weighted_totals, Column, ColumnStats,
+    CsvWriteOptions, Mask, NodFrame, NodFrameBuilder, NodFrameError, NodNum, NumericColumn,
+    SortMode, ValueOrder, WeightOptions,
+};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// AggFn picks how the values of a group reduce to one value. Count is the
+// number of values and Std their sample standard deviation, see ColumnStats.
+// Mean and Std need a float frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggFn {
+    Sum,
+    Mean,
+    Min,
+    Max,
+    First,
+    Last,
+    Count,
+    Std,
+}
+
+impl AggFn {
+    // check fails for Mean and Std on an integer frame, which would truncate
+    // them, see require_float
+    pub(crate) fn check<T: NodNum>(self, key: &str) -> Result<(), NodFrameError> {
+        match self {
+            AggFn::Mean => require_float::<T>(key, "AggFn::Mean"),
+            AggFn::Std => require_float::<T>(key, "AggFn::Std"),
+            _ => Ok(()),
+        }
+    }
+}
+
+// aggregate reduces a non-empty run of values, means and deviations are
+// converted back to T, see AggFn::check. The deviation of a single value is
+// NaN
+pub(crate) fn aggregate<T: NodNum>(values: &[T], agg: AggFn) -> Option<T> {
+    let pick = |keep: fn(&T, &T) -> bool| {
+        values
+            .iter()
+            .fold(None, |best: Option<&T>, v| match best {
+                Some(b) if !keep(v, b) => Some(b),
+                _ => Some(v),
+            })
+            .cloned()
+    };
+    match agg {
+        AggFn::Sum => Some(values.iter().fold(T::zero(), |total, x| total + x.clone())),
+        AggFn::Mean => T::from(values.iter().map(to_f64).sum::<f64>() / values.len() as f64),
+        AggFn::Min => pick(|v, b| v < b),
+        AggFn::Max => pick(|v, b| v > b),
+        AggFn::First => values.first().cloned(),
+        AggFn::Last => values.last().cloned(),
+        AggFn::Count => T::from(values.len()),
+        AggFn::Std => T::from(ColumnStats::from_values(values).std().unwrap_or(f64::NAN)),
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // agg_where reduces the values of value_col where mask is true without
+    // copying them, see the NumericColumn *_where methods. Sum and Count give
+    // zero when the mask selects nothing, the others None, and Std is None
+    // below two values
+    pub fn agg_where(
+        &self,
+        value_col: &str,
+        mask: &Mask,
+        agg: AggFn,
+    ) -> Result<Option<T>, NodFrameError> {
+        let values = self.numeric_column(value_col)?;
+        agg.check::<T>(value_col)?;
+        Ok(match agg {
+            AggFn::Sum => Some(values.sum_where(mask)?),
+            AggFn::Mean => values.mean_where(mask)?.and_then(T::from),
+            AggFn::Min => values.min_where(mask)?,
+            AggFn::Max => values.max_where(mask)?,
+            AggFn::First => values.masked(mask)?.next().cloned(),
+            AggFn::Last => values.masked(mask)?.last().cloned(),
+            AggFn::Count => T::from(values.masked(mask)?.count()),
+            AggFn::Std => {
+                let mut stats = ColumnStats::new();
+                values.masked(mask)?.for_each(|x| stats.push(to_f64(x)));
+                stats.std().and_then(T::from)
+            }
+        })
+    }
+}
+
+// TransformFn is a built-in group_transform: the group's sum, mean, max or
+// min repeated on every row, or each value's z-score within its group. Mean
+// and ZScore need a float frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformFn {
+    Mean,
+    Sum,
+    Max,
+    Min,
+    ZScore,
+}
+
+// transform computes one value per entry of values. ZScore uses the sample
+// standard deviation, groups with one row or no spread get 0
+fn transform<T: NodNum>(values: &[T], f: TransformFn) -> Vec<Option<T>> {
+    let agg = match f {
+        TransformFn::Mean => AggFn::Mean,
+        TransformFn::Sum => AggFn::Sum,
+        TransformFn::Max => AggFn::Max,
+        TransformFn::Min => AggFn::Min,
+        TransformFn::ZScore => {
+            let n = values.len() as f64;
+            let mean = values.iter().map(to_f64).sum::<f64>() / n;
+            let var = values
+                .iter()
+                .map(|v| (to_f64(v) - mean).powi(2))
+                .sum::<f64>()
+                / (n - 1.0);
+            let std = var.sqrt();
+            return values
+                .iter()
+                .map(|v| {
+                    if std > 0.0 {
+                        T::from((to_f64(v) - mean) / std)
+                    } else {
+                        T::from(0.0)
+                    }
+                })
+                .collect();
+        }
+    };
+    vec![aggregate(values, agg); values.len()]
+}
+
+// sanitize_file_part makes a group value safe to use in a file name
+fn sanitize_file_part(value: &str) -> String {
+    let name: String = value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // empty names and names made only of dots would point at a directory
+    if name.chars().all(|c| c == '.') {
+        return "_".repeat(name.len().max(1));
+    }
+    name
+}
+
+// ResampleOptions for resample_rows: keep_partial keeps a final window with
+// fewer than window rows, discrete_last takes the last discrete value of each
+// window instead of the first
+#[derive(Clone, Copy, Debug)]
+pub struct ResampleOptions {
+    pub keep_partial: bool,
+    pub discrete_last: bool,
+}
+
+impl Default for ResampleOptions {
+    fn default() -> Self {
+        ResampleOptions {
+            keep_partial: true,
+            discrete_last: false,
+        }
+    }
+}
+
+pub struct GroupBy<'a, T> {
+    frame: &'a NodFrame<T>,
+    key: String,
+    groups: Vec<Vec<usize>>,
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // group_by splits the rows by the values of key, which may be a numeric or
+    // a discrete column
+    pub fn group_by(&self, key: &str) -> Result<GroupBy<'_, T>, NodFrameError> {
+        self.group_by_ordered(key, ValueOrder::Sorted)
+    }
+
+    // group_by_ordered is group_by with the groups in the given order of their
+    // key values
+    pub fn group_by_ordered(
+        &self,
+        key: &str,
+        order: ValueOrder,
+    ) -> Result<GroupBy<'_, T>, NodFrameError> {
+        self.group_by_with(key, order, |col| {
+            let mut index: HashMap<String, usize> = HashMap::new();
+            let mut groups: Vec<Vec<usize>> = Vec::new();
+            for row in 0..col.len() {
+                let next = groups.len();
+                let g = *index.entry(col.cell_string(row)).or_insert(next);
+                if g == next {
+                    groups.push(Vec::new());
+                }
+                groups[g].push(row);
+            }
+            groups
+        })
+    }
+
+    // group_by_with orders the groups split from the key column, which must
+    // list them in order of first occurrence. The serial and parallel
+    // group_by share it so both order the groups the same way
+    pub(crate) fn group_by_with(
+        &self,
+        key: &str,
+        order: ValueOrder,
+        split: impl FnOnce(&Column<T>) -> Vec<Vec<usize>>,
+    ) -> Result<GroupBy<'_, T>, NodFrameError> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        let mut groups = split(col);
+        match (col, order) {
+            (_, ValueOrder::FirstSeen) => {}
+            (Column::Numeric(n), ValueOrder::Sorted) => {
+                groups.sort_by(|a, b| cmp_nan_last(n.get(a[0]), n.get(b[0])))
+            }
+            (Column::Discrete(d), ValueOrder::Sorted) => {
+                groups.sort_by(|a, b| d.get(a[0]).cmp(d.get(b[0])))
+            }
+        }
+        Ok(GroupBy {
+            frame: self,
+            key: String::from(key),
+            groups,
+        })
+    }
+
+    // row_number_within appends new_key holding each row's 0-based position
+    // within its group of by, in frame order or ascending by order_by
+    pub fn row_number_within(
+        &self,
+        by: &str,
+        order_by: Option<&str>,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.row_number_within_ordered(by, order_by, true, new_key)
+    }
+
+    // row_number_within_ordered is row_number_within with the direction of
+    // order_by chosen, ties keep frame order either way
+    pub fn row_number_within_ordered(
+        &self,
+        by: &str,
+        order_by: Option<&str>,
+        ascending: bool,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let order = match order_by {
+            Some(key) => {
+                let col = self
+                    .column(key)
+                    .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+                col.check_levels()?;
+                Some(col)
+            }
+            None => None,
+        };
+        let (rows, _) = self.shape();
+        let mut numbers = vec![None; rows];
+        for group in self.group_by(by)?.groups {
+            let mut group = group;
+            if let Some(col) = order {
+                group.sort_by(|a, b| col.cmp_rows(*a, *b, ascending, SortMode::Lexical));
+            }
+            for (position, row) in group.into_iter().enumerate() {
+                numbers[row] = T::from(position);
+            }
+        }
+        let numbers = numbers
+            .into_iter()
+            .enumerate()
+            .map(|(row, n)| {
+                n.ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from(new_key),
+                    row,
+                })
+            })
+            .collect::<Result<Vec<T>, NodFrameError>>()?;
+        let out = self
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, numbers)
+            .build()?;
+        Ok(self.traced(out, "row_number_within", || {
+            vec![
+                ("by", String::from(by)),
+                ("order_by", order_by.map_or_else(String::new, String::from)),
+                ("ascending", ascending.to_string()),
+            ]
+        }))
+    }
+
+    // group_transform appends new_key where each row holds f computed over its
+    // group of by in value_col. Rows keep their frame order
+    pub fn group_transform(
+        &self,
+        by: &str,
+        value_col: &str,
+        f: TransformFn,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.numeric_column(value_col)?;
+        match f {
+            TransformFn::Mean => AggFn::Mean.check::<T>(value_col)?,
+            TransformFn::ZScore => require_float::<T>(value_col, "TransformFn::ZScore")?,
+            _ => {}
+        }
+        self.group_transform_fn(
+            by,
+            value_col,
+            |group| transform(group.as_slice(), f),
+            new_key,
+        )
+    }
+
+    // group_transform_fn is group_transform with a closure, which gets the
+    // group's values in frame order and must return one value per row
+    pub fn group_transform_fn<F, R>(
+        &self,
+        by: &str,
+        value_col: &str,
+        f: F,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError>
+    where
+        F: Fn(&NumericColumn<T>) -> Vec<R>,
+        R: Into<Option<T>>,
+    {
+        let values = self.numeric_column(value_col)?;
+        let (rows, _) = self.shape();
+        let mut out: Vec<Option<T>> = vec![None; rows];
+        for group in self.group_by(by)?.groups {
+            let result = f(&values.take(&group));
+            if result.len() != group.len() {
+                return Err(NodFrameError::LengthMismatch {
+                    key: String::from(new_key),
+                    expected: group.len(),
+                    found: result.len(),
+                });
+            }
+            for (row, value) in group.into_iter().zip(result) {
+                out[row] = value.into();
+            }
+        }
+        let out = out
+            .into_iter()
+            .enumerate()
+            .map(|(row, v)| {
+                v.ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from(new_key),
+                    row,
+                })
+            })
+            .collect::<Result<Vec<T>, NodFrameError>>()?;
+        let out = self
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, out)
+            .build()?;
+        Ok(self.traced(out, "group_transform", || {
+            vec![
+                ("by", String::from(by)),
+                ("value_col", String::from(value_col)),
+                ("new_key", String::from(new_key)),
+            ]
+        }))
+    }
+
+    // cumsum_by appends new_key holding the running total of value_col within
+    // each group of by. Groups may be interleaved, each total only counts the
+    // group's own rows up to and including the current one
+    pub fn cumsum_by(
+        &self,
+        by: &str,
+        value_col: &str,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.group_transform_fn(
+            by,
+            value_col,
+            |group| {
+                group
+                    .iter()
+                    .scan(None, |acc: &mut Option<T>, x| {
+                        let next = match acc.take() {
+                            Some(total) => total + x.clone(),
+                            None => x.clone(),
+                        };
+                        *acc = Some(next.clone());
+                        Some(next)
+                    })
+                    .collect::<Vec<T>>()
+            },
+            new_key,
+        )
+    }
+
+    // cummax_by is cumsum_by with the running maximum, NaN values are skipped
+    // once the group has a number
+    pub fn cummax_by(
+        &self,
+        by: &str,
+        value_col: &str,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.group_transform_fn(
+            by,
+            value_col,
+            |group| {
+                group
+                    .iter()
+                    .scan(None, |acc: &mut Option<T>, x| {
+                        let best = match acc.take() {
+                            Some(best) if is_nan(&best) || *x > best => x.clone(),
+                            Some(best) => best,
+                            None => x.clone(),
+                        };
+                        *acc = Some(best.clone());
+                        Some(best)
+                    })
+                    .collect::<Vec<T>>()
+            },
+            new_key,
+        )
+    }
+
+    // cumcount_by appends new_key counting the rows of each group of by seen
+    // so far, 1 on the group's first row
+    pub fn cumcount_by(&self, by: &str, new_key: &str) -> Result<NodFrame<T>, NodFrameError> {
+        let (rows, _) = self.shape();
+        let mut counts = vec![None; rows];
+        for group in self.group_by(by)?.groups {
+            for (position, row) in group.into_iter().enumerate() {
+                counts[row] = T::from(position + 1);
+            }
+        }
+        let counts = counts
+            .into_iter()
+            .enumerate()
+            .map(|(row, n)| {
+                n.ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from(new_key),
+                    row,
+                })
+            })
+            .collect::<Result<Vec<T>, NodFrameError>>()?;
+        let out = self
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, counts)
+            .build()?;
+        Ok(self.traced(out, "cumcount_by", || vec![("by", String::from(by))]))
+    }
+
+    // partition_by splits the frame into one sub-frame per distinct value of
+    // key, ordered by value, each labelled with the value as to_csv writes it
+    pub fn partition_by(&self, key: &str) -> Result<Vec<(String, NodFrame<T>)>, NodFrameError> {
+        let groups = self.group_by(key)?;
+        let col = &self[key];
+        groups
+            .groups
+            .iter()
+            .map(|rows| Ok((col.cell_string(rows[0]), self.take(rows)?)))
+            .collect()
+    }
+
+    // head_per_group keeps the first n rows of each value of by, groups
+    // ordered by value and rows in frame order within each group
+    pub fn head_per_group(&self, by: &str, n: usize) -> Result<NodFrame<T>, NodFrameError> {
+        let rows = self.rows_per_group(by, n, false, |g| g[..n.min(g.len())].to_vec())?;
+        let head = self.take(&rows)?;
+        Ok(self.traced(head, "head_per_group", || {
+            vec![("by", String::from(by)), ("n", n.to_string())]
+        }))
+    }
+
+    // sample_per_group keeps n randomly chosen rows of each value of by, all
+    // the rows of smaller groups, ordered as head_per_group. The same seed
+    // picks the same rows on every run, see NumericColumn::sample, None seeds
+    // from the clock
+    pub fn sample_per_group(
+        &self,
+        by: &str,
+        n: usize,
+        seed: Option<u64>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.sample_groups(by, n, seed, false)
+    }
+
+    // sample_per_group_strict is sample_per_group where a group with fewer
+    // than n rows is an error
+    pub fn sample_per_group_strict(
+        &self,
+        by: &str,
+        n: usize,
+        seed: Option<u64>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.sample_groups(by, n, seed, true)
+    }
+
+    fn sample_groups(
+        &self,
+        by: &str,
+        n: usize,
+        seed: Option<u64>,
+        strict: bool,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let seed = seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        // each group draws its own seed in group order, so a group's rows do
+        // not depend on the sizes of the groups before it
+        let mut seeds = SplitMix64::new(seed);
+        let rows = self.rows_per_group(by, n, strict, |g| {
+            sample_rows(g.len(), n, seeds.next_u64())
+                .into_iter()
+                .map(|i| g[i])
+                .collect()
+        })?;
+        let sample = self.take(&rows)?;
+        Ok(self.traced(sample, "sample_per_group", || {
+            vec![
+                ("by", String::from(by)),
+                ("n", n.to_string()),
+                ("seed", seed.to_string()),
+            ]
+        }))
+    }
+
+    // rows_per_group concatenates the rows pick keeps of each group of by, in
+    // group order. strict makes a group with fewer than n rows an error
+    fn rows_per_group(
+        &self,
+        by: &str,
+        n: usize,
+        strict: bool,
+        mut pick: impl FnMut(&[usize]) -> Vec<usize>,
+    ) -> Result<Vec<usize>, NodFrameError> {
+        let groups = self.group_by(by)?;
+        let col = &self[by];
+        let mut rows = Vec::new();
+        for group in groups.groups.iter() {
+            if strict && group.len() < n {
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "group '{}' of '{}' has {} rows, fewer than {}",
+                    col.cell_string(group[0]),
+                    by,
+                    group.len(),
+                    n
+                )));
+            }
+            rows.extend(pick(group));
+        }
+        Ok(rows)
+    }
+
+    // group_sizes counts the rows of each value of key, one row per value
+    // ordered by value, in the columns key and "count"
+    pub fn group_sizes(&self, key: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.group_sizes_multi(&[key])
+    }
+
+    // group_sizes_multi counts the rows of each distinct combination of keys,
+    // ordered by the key columns in turn
+    pub fn group_sizes_multi(&self, keys: &[&str]) -> Result<NodFrame<T>, NodFrameError> {
+        let groups = self.key_groups(keys)?;
+        let counts = groups
+            .iter()
+            .enumerate()
+            .map(|(row, rows)| {
+                T::from(rows.len()).ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from("count"),
+                    row,
+                })
+            })
+            .collect::<Result<Vec<T>, NodFrameError>>()?;
+        let out = self
+            .key_frame(keys, &groups)
+            .add_numeric("count", counts)
+            .build()?;
+        Ok(self.traced(out, "group_sizes", || vec![("keys", keys.join(","))]))
+    }
+
+    // group_keys lists the distinct combinations of keys, the rows of the key
+    // columns with duplicates dropped, in the order of group_sizes_multi
+    pub fn group_keys(&self, keys: &[&str]) -> Result<NodFrame<T>, NodFrameError> {
+        let groups = self.key_groups(keys)?;
+        let out = self.key_frame(keys, &groups).build()?;
+        Ok(self.traced(out, "group_keys", || vec![("keys", keys.join(","))]))
+    }
+
+    // key_groups splits the rows by their values in keys, groups sorted by the
+    // key columns in turn as sort_by orders them
+    fn key_groups(&self, keys: &[&str]) -> Result<Vec<Vec<usize>>, NodFrameError> {
+        if keys.is_empty() {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "at least one key column is required",
+            )));
+        }
+        let cols = keys
+            .iter()
+            .map(|k| {
+                self.column(k)
+                    .ok_or_else(|| NodFrameError::MissingColumn(String::from(*k)))
+            })
+            .collect::<Result<Vec<&Column<T>>, NodFrameError>>()?;
+        for col in cols.iter() {
+            col.check_levels()?;
+        }
+        let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for row in 0..self.num_rows {
+            let next = groups.len();
+            let values = cols.iter().map(|c| c.cell_string(row)).collect();
+            let g = *index.entry(values).or_insert(next);
+            if g == next {
+                groups.push(Vec::new());
+            }
+            groups[g].push(row);
+        }
+        groups.sort_by(|a, b| {
+            cols.iter()
+                .map(|c| c.cmp_rows(a[0], b[0], true, SortMode::Lexical))
+                .find(|o| o.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(groups)
+    }
+
+    // key_frame starts a builder with the key columns at the first row of
+    // each group
+    fn key_frame(&self, keys: &[&str], groups: &[Vec<usize>]) -> NodFrameBuilder<T> {
+        let rows: Vec<usize> = groups.iter().map(|g| g[0]).collect();
+        keys.iter().fold(NodFrameBuilder::new(), |b, k| {
+            b.add_column(self[*k].take(&rows))
+        })
+    }
+
+    // to_csv_partitioned writes each partition_by sub-frame to dir/pattern
+    // with {key} replaced by the group value. Characters other than letters,
+    // digits, '-', '_' and '.' become '_', and a name already used by an
+    // earlier group gets a "_1", "_2"... suffix. Existing files are never
+    // overwritten: if one of the names is taken nothing is written. Returns
+    // the written paths
+    pub fn to_csv_partitioned(
+        &self,
+        key: &str,
+        dir: &Path,
+        pattern: &str,
+    ) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        if !pattern.contains("{key}") {
+            return Err(Box::new(NodFrameError::InvalidArgument(format!(
+                "file pattern '{}' has no {{key}} placeholder",
+                pattern
+            ))));
+        }
+        let mut used = HashSet::new();
+        let mut parts = Vec::new();
+        for (value, part) in self.partition_by(key)? {
+            let name = sanitize_file_part(&value);
+            let file = (0..)
+                .map(|n| match n {
+                    0 => pattern.replace("{key}", &name),
+                    n => pattern.replace("{key}", &format!("{}_{}", name, n)),
+                })
+                .find(|file| !used.contains(file))
+                .unwrap();
+            parts.push((dir.join(&file), part));
+            used.insert(file);
+        }
+        let exists = |path: &Path| {
+            NodFrameError::InvalidArgument(format!("file '{}' already exists", path.display()))
+        };
+        if let Some((path, _)) = parts.iter().find(|(path, _)| path.exists()) {
+            return Err(Box::new(exists(path)));
+        }
+        let mut paths = Vec::with_capacity(parts.len());
+        for (path, part) in parts {
+            // create_new still refuses a file made since the check above
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map_err(|e| match e.kind() {
+                    ErrorKind::AlreadyExists => Box::new(exists(&path)) as Box<dyn Error>,
+                    _ => Box::new(e),
+                })?;
+            part.write_csv(file, &CsvWriteOptions::default(), |_| {})?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    // resample_rows reduces every run of window consecutive rows to one row,
+    // numeric columns with agg and discrete columns by taking a value
+    pub fn resample_rows(&self, window: usize, agg: AggFn) -> Result<NodFrame<T>, NodFrameError> {
+        self.resample_rows_with(window, agg, &ResampleOptions::default())
+    }
+
+    pub fn resample_rows_with(
+        &self,
+        window: usize,
+        agg: AggFn,
+        opts: &ResampleOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        if window == 0 {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "resample window must be at least one row",
+            )));
+        }
+        let (rows, _) = self.shape();
+        let windows: Vec<(usize, usize)> = (0..rows)
+            .step_by(window)
+            .map(|start| (start, (start + window).min(rows)))
+            .filter(|(start, end)| opts.keep_partial || end - start == window)
+            .collect();
+        let mut builder = NodFrameBuilder::new();
+        for col in self.columns() {
+            builder = match col {
+                Column::Numeric(n) => {
+                    agg.check::<T>(n.key())?;
+                    let mut values = Vec::with_capacity(windows.len());
+                    for (w, (start, end)) in windows.iter().enumerate() {
+                        let value =
+                            aggregate(&n.as_slice()[*start..*end], agg).ok_or_else(|| {
+                                NodFrameError::ValueConversion {
+                                    key: n.key().to_string(),
+                                    row: w,
+                                }
+                            })?;
+                        values.push(value);
+                    }
+                    builder.add_numeric(n.key(), values)
+                }
+                Column::Discrete(d) => builder.add_discrete(
+                    d.key(),
+                    windows
+                        .iter()
+                        .map(|(start, end)| {
+                            let row = if opts.discrete_last { end - 1 } else { *start };
+                            String::from(d.get(row))
+                        })
+                        .collect(),
+                ),
+            };
+        }
+        Ok(self.traced(builder.build()?, "resample_rows", || {
+            vec![
+                ("window", window.to_string()),
+                ("agg", format!("{:?}", agg)),
+            ]
+        }))
+    }
+}
+
+impl<T: NodNum> GroupBy<'_, T> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    // len is the number of groups
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // groups lists the row indices of each group
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+
+    // key_column holds the key value of each of the given groups
+    fn key_column(&self, groups: &[usize]) -> Column<T> {
+        let rows: Vec<usize> = groups.iter().map(|g| self.groups[*g][0]).collect();
+        self.frame[self.key.as_str()].take(&rows)
+    }
+
+    // first keeps the first row of each group, all columns included
+    pub fn first(&self) -> Result<NodFrame<T>, NodFrameError> {
+        self.nth(0)
+    }
+
+    // last keeps the last row of each group
+    pub fn last(&self) -> Result<NodFrame<T>, NodFrameError> {
+        let rows: Vec<usize> = self
+            .groups
+            .iter()
+            .filter_map(|g| g.last())
+            .copied()
+            .collect();
+        let last = self.frame.take(&rows)?;
+        Ok(self
+            .frame
+            .traced(last, "group_by.last", || vec![("key", self.key.clone())]))
+    }
+
+    // nth keeps the row at position n of each group, groups with n rows or
+    // fewer are left out
+    pub fn nth(&self, n: usize) -> Result<NodFrame<T>, NodFrameError> {
+        let rows: Vec<usize> = self
+            .groups
+            .iter()
+            .filter_map(|g| g.get(n))
+            .copied()
+            .collect();
+        let nth = self.frame.take(&rows)?;
+        Ok(self.frame.traced(nth, "group_by.nth", || {
+            vec![("key", self.key.clone()), ("n", n.to_string())]
+        }))
+    }
+
+    // agg reduces value_col within each group with agg. The result has the key
+    // column and value_col, one row per group
+    pub fn agg(&self, value_col: &str, agg: AggFn) -> Result<NodFrame<T>, NodFrameError> {
+        self.agg_with(value_col, agg, |values, groups| {
+            groups
+                .iter()
+                .map(|rows| aggregate(values.take(rows).as_slice(), agg))
+                .collect()
+        })
+    }
+
+    // agg_with builds the agg result from reduce, which gives the value of
+    // each group in group order. None fails on the first such group
+    pub(crate) fn agg_with(
+        &self,
+        value_col: &str,
+        agg: AggFn,
+        reduce: impl FnOnce(&NumericColumn<T>, &[Vec<usize>]) -> Vec<Option<T>>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let values = self.frame.numeric_column(value_col)?;
+        agg.check::<T>(value_col)?;
+        let out = reduce(values, &self.groups)
+            .into_iter()
+            .enumerate()
+            .map(|(g, v)| {
+                v.ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from(value_col),
+                    row: g,
+                })
+            })
+            .collect::<Result<Vec<T>, _>>()?;
+        let all: Vec<usize> = (0..self.groups.len()).collect();
+        let out = NodFrameBuilder::new()
+            .add_column(self.key_column(&all))
+            .add_numeric(value_col, out)
+            .build()?;
+        Ok(self.frame.traced(out, "group_by.agg", || {
+            vec![
+                ("key", self.key.clone()),
+                ("value_col", String::from(value_col)),
+                ("agg", format!("{:?}", agg)),
+            ]
+        }))
+    }
+
+    // weighted_mean computes the weighted mean of value_col within each group.
+    // The result has the key column and value_col, which needs a float frame
+    // as an integer one would truncate the means
+    pub fn weighted_mean(
+        &self,
+        value_col: &str,
+        weight_col: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.weighted_mean_with(value_col, weight_col, &WeightOptions::default())
+    }
+
+    pub fn weighted_mean_with(
+        &self,
+        value_col: &str,
+        weight_col: &str,
+        opts: &WeightOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let values = self.frame.numeric_column(value_col)?;
+        let weights = self.frame.numeric_column(weight_col)?;
+        require_float::<T>(value_col, "group_by.weighted_mean")?;
+        let mut kept = Vec::new();
+        let mut means = Vec::new();
+        for (g, rows) in self.groups.iter().enumerate() {
+            let (total, weight) = weighted_totals(values, weights, rows.iter().copied(), opts)?;
+            if weight == 0.0 {
+                if opts.skip_zero_weight {
+                    continue;
+                }
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "weights in '{}' sum to zero for group '{}'",
+                    weight_col,
+                    self.frame[self.key.as_str()].cell_string(rows[0])
+                )));
+            }
+            let mean = T::from(total / weight).ok_or_else(|| NodFrameError::ValueConversion {
+                key: String::from(value_col),
+                row: g,
+            })?;
+            kept.push(g);
+            means.push(mean);
+        }
+        let out = NodFrameBuilder::new()
+            .add_column(self.key_column(&kept))
+            .add_numeric(value_col, means)
+            .build()?;
+        Ok(self.frame.traced(out, "group_by.weighted_mean", || {
+            vec![
+                ("key", self.key.clone()),
+                ("value_col", String::from(value_col)),
+                ("weight_col", String::from(weight_col)),
+            ]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod group_tests {
+    use crate::{
+        assert_valid, AggFn, Comp, Cond, FilterValue, Mask, NodFrame, NodFrameBuilder,
+        NodFrameError, ResampleOptions, TransformFn, ValueOrder, WeightOptions,
+    };
+
+    fn survey() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_discrete(
+                "region",
+                ["b", "a", "b", "c", "a", "c"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .add_numeric("score", vec![10, 20, 40, 7, 50, 9])
+            .add_numeric("weight", vec![1, 3, 2, 0, 1, 0])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn group_agg_test() {
+        let frame = survey();
+        let groups = frame.group_by("region").unwrap();
+        let summed = groups.agg("score", AggFn::Sum).unwrap();
+        assert_valid(&summed);
+        assert_eq!(summed["region"].to_string(), "region: [a, b, c]");
+        assert_eq!(summed["score"].to_string(), "score: [70, 50, 16]");
+        let counted = groups.agg("weight", AggFn::Count).unwrap();
+        assert_eq!(counted["weight"].to_string(), "weight: [2, 2, 2]");
+        assert!(groups.agg("region", AggFn::Sum).is_err());
+        // a one-row group has no deviation, which an integer frame cannot hold
+        let short = frame.slice(0, 4).unwrap();
+        let single = short.group_by("region").unwrap();
+        assert!(single.agg("score", AggFn::Std).is_err());
+    }
+
+    #[test]
+    fn agg_where_test() {
+        let frame = survey();
+        let in_b = frame
+            .cond_mask(&Cond::str("region", Comp::Eq, "b"))
+            .unwrap();
+        let filtered = frame
+            .filter("region", Comp::Eq, FilterValue::Str(String::from("b")))
+            .unwrap();
+        let kept = filtered.numeric_column("score").unwrap();
+        let cases = [
+            (AggFn::Sum, kept.sum()),
+            (AggFn::Min, kept.min().unwrap()),
+            (AggFn::Max, kept.max().unwrap()),
+            (AggFn::First, *kept.get(0)),
+            (AggFn::Last, *kept.get(kept.len() - 1)),
+            (AggFn::Count, 2),
+        ];
+        for (agg, expected) in cases {
+            assert_eq!(
+                frame.agg_where("score", &in_b, agg).unwrap(),
+                Some(expected)
+            );
+        }
+        // the mean of 10 and 40 fits, but an integer frame truncates in general
+        assert_eq!(
+            frame.agg_where("score", &in_b, AggFn::Mean).unwrap_err(),
+            NodFrameError::NeedsFloat {
+                key: String::from("score"),
+                operation: "AggFn::Mean",
+            }
+        );
+        let floats = NodFrameBuilder::new()
+            .add_numeric("score", vec![10.0, 20.0, 40.0, 7.0, 50.0, 9.0])
+            .build()
+            .unwrap();
+        // the sample deviation of 10 and 40 is 21.2
+        let std = floats.agg_where("score", &in_b, AggFn::Std).unwrap();
+        assert!((std.unwrap() - 450f64.sqrt()).abs() < 1e-12);
+        let score = frame.numeric_column("score").unwrap();
+        assert_eq!(score.mean_where(&in_b).unwrap(), Some(25.0));
+        assert_eq!(score.count_where(&in_b).unwrap(), 2);
+
+        let none = Mask::new(vec![false; 6]);
+        assert_eq!(score.sum_where(&none).unwrap(), 0);
+        assert_eq!(score.mean_where(&none).unwrap(), None);
+        assert_eq!(score.count_where(&none).unwrap(), 0);
+        assert_eq!(frame.agg_where("score", &none, AggFn::Max).unwrap(), None);
+        assert_eq!(
+            frame.agg_where("score", &none, AggFn::Count).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            frame.agg_where("score", &none, AggFn::Sum).unwrap(),
+            Some(0)
+        );
+
+        let short = Mask::new(vec![true; 5]);
+        assert!(score.min_where(&short).is_err());
+        assert!(frame.agg_where("score", &short, AggFn::First).is_err());
+        assert!(frame.agg_where("region", &in_b, AggFn::Sum).is_err());
+    }
+
+    #[test]
+    fn group_by_test() {
+        let frame = survey();
+        let groups = frame.group_by("region").unwrap();
+        assert_eq!(groups.len(), 3);
+        // groups are ordered by key, rows keep frame order
+        assert_eq!(groups.groups(), &[vec![1, 4], vec![0, 2], vec![3, 5]]);
+        let seen = frame
+            .group_by_ordered("region", ValueOrder::FirstSeen)
+            .unwrap();
+        assert_eq!(seen.groups(), &[vec![0, 2], vec![1, 4], vec![3, 5]]);
+        let by_weight = frame.group_by("weight").unwrap();
+        assert_eq!(
+            by_weight.groups(),
+            &[vec![3, 5], vec![0, 4], vec![2], vec![1]]
+        );
+        assert!(frame.group_by("missing").is_err());
+    }
+
+    #[test]
+    fn weighted_test() {
+        let frame = survey();
+        // (10 + 60 + 80 + 0 + 50 + 0) / 7
+        assert_eq!(frame.weighted_sum("score", "weight").unwrap(), 200.0);
+        assert_eq!(frame.weighted_mean("score", "weight").unwrap(), 200.0 / 7.0);
+        assert!(frame.weighted_mean("region", "weight").is_err());
+
+        // an integer frame would truncate the means below
+        assert_eq!(
+            frame
+                .group_by("region")
+                .unwrap()
+                .weighted_mean("score", "weight")
+                .unwrap_err(),
+            NodFrameError::NeedsFloat {
+                key: String::from("score"),
+                operation: "group_by.weighted_mean",
+            }
+        );
+        let floats = NodFrameBuilder::new()
+            .add_discrete(
+                "region",
+                ["b", "a", "b", "c", "a", "c"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .add_numeric("score", vec![10.0, 20.0, 40.0, 7.0, 50.0, 9.0])
+            .add_numeric("weight", vec![1.0, 3.0, 2.0, 0.0, 1.0, 0.0])
+            .build()
+            .unwrap();
+        let groups = floats.group_by("region").unwrap();
+        // group c has weights 0 and 0
+        let err = groups.weighted_mean("score", "weight").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "weights in 'weight' sum to zero for group 'c'"
+        );
+        let opts = WeightOptions {
+            skip_zero_weight: true,
+            ..WeightOptions::default()
+        };
+        let means = groups.weighted_mean_with("score", "weight", &opts).unwrap();
+        assert_eq!(means.shape(), (2, 2));
+        // a: (60 + 50) / 4 = 27.5, b: (10 + 80) / 3 = 30
+        assert_eq!(
+            means.numeric_column("score").unwrap().as_slice(),
+            &[27.5, 30.0]
+        );
+        assert_eq!(
+            means
+                .discrete_column("region")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            &["a", "b"]
+        );
+    }
+
+    #[test]
+    fn negative_weight_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("v", vec![4, 2])
+            .add_numeric("w", vec![3, -1])
+            .build()
+            .unwrap();
+        let err = frame.weighted_mean("v", "w").unwrap_err();
+        assert_eq!(err.to_string(), "column 'w' row 1: negative weight");
+        let opts = WeightOptions {
+            allow_negative: true,
+            ..WeightOptions::default()
+        };
+        // (12 - 2) / 2
+        assert_eq!(frame.weighted_mean_with("v", "w", &opts).unwrap(), 5.0);
+        let frame = NodFrameBuilder::new()
+            .add_numeric("v", vec![4, 2])
+            .add_numeric("w", vec![1, -1])
+            .build()
+            .unwrap();
+        assert!(frame.weighted_mean_with("v", "w", &opts).is_err());
+    }
+
+    #[test]
+    fn positional_test() {
+        let frame = survey();
+        let groups = frame.group_by("region").unwrap();
+        let first = groups.first().unwrap();
+        assert_valid(&first);
+        assert_eq!(first.shape(), (3, 3));
+        assert_eq!(
+            first.numeric_column("score").unwrap().as_slice(),
+            &[20, 10, 7]
+        );
+        assert_eq!(
+            groups.last().unwrap()["score"].to_string(),
+            "score: [50, 40, 9]"
+        );
+        assert_eq!(
+            groups.nth(1).unwrap()["region"].to_string(),
+            "region: [a, b, c]"
+        );
+        assert_eq!(groups.nth(2).unwrap().shape(), (0, 3));
+
+        // a group with a single row is its own first, last and 0th row
+        let single = frame.group_by("weight").unwrap();
+        assert_eq!(
+            single.first().unwrap()["score"].to_string(),
+            "score: [7, 10, 40, 20]"
+        );
+        assert_eq!(
+            single.last().unwrap()["score"].to_string(),
+            "score: [9, 50, 40, 20]"
+        );
+    }
+
+    #[test]
+    fn row_number_within_test() {
+        let frame = survey();
+        let numbered = frame.row_number_within("region", None, "n").unwrap();
+        assert_eq!(numbered["n"].to_string(), "n: [0, 0, 1, 0, 1, 1]");
+
+        let by_score = frame
+            .row_number_within_ordered("region", Some("score"), false, "n")
+            .unwrap();
+        // the highest score of each region gets 0
+        assert_eq!(by_score["n"].to_string(), "n: [1, 1, 0, 1, 0, 0]");
+        let ascending = frame
+            .row_number_within("region", Some("score"), "n")
+            .unwrap();
+        assert_eq!(ascending["n"].to_string(), "n: [0, 0, 1, 0, 1, 1]");
+
+        // keeping row number 0 agrees with first()
+        let kept = numbered
+            .filter("n", Comp::Eq, FilterValue::Num(0))
+            .unwrap()
+            .sort_by("region", true)
+            .unwrap();
+        let first = frame.group_by("region").unwrap().first().unwrap();
+        assert_eq!(kept["score"].to_string(), first["score"].to_string());
+
+        assert!(frame.row_number_within("region", None, "score").is_err());
+        assert!(frame
+            .row_number_within("region", Some("missing"), "n")
+            .is_err());
+    }
+
+    #[test]
+    fn group_transform_test() {
+        let frame = survey();
+        // an integer frame would truncate the means and z-scores
+        for f in [TransformFn::Mean, TransformFn::ZScore] {
+            let err = frame
+                .group_transform("region", "score", f, "out")
+                .unwrap_err();
+            assert!(matches!(err, NodFrameError::NeedsFloat { .. }), "{:?}", f);
+        }
+        let floats = NodFrameBuilder::new()
+            .add_numeric("g", vec![1.0, 2.0, 1.0, 2.0, 2.0])
+            .add_numeric("v", vec![10.0, 1.0, 40.0, 2.0, 4.0])
+            .build()
+            .unwrap();
+        let means = floats
+            .group_transform("g", "v", TransformFn::Mean, "mean")
+            .unwrap();
+        // 1: (10 + 40) / 2, 2: (1 + 2 + 4) / 3, in frame order
+        assert_eq!(
+            means["mean"].to_string(),
+            "mean: [25, 2.3333333333333335, 25, 2.3333333333333335, 2.3333333333333335]"
+        );
+        let column =
+            |f| frame.group_transform("region", "score", f, "out").unwrap()["out"].to_string();
+        assert_eq!(column(TransformFn::Sum), "out: [50, 70, 50, 16, 70, 16]");
+        assert_eq!(column(TransformFn::Max), "out: [40, 50, 40, 9, 50, 9]");
+        assert_eq!(column(TransformFn::Min), "out: [10, 20, 10, 7, 20, 7]");
+
+        // each group is spread around its mean by one standard deviation, so
+        // the z-scores of a group sum to zero
+        let spread = NodFrameBuilder::new()
+            .add_numeric("g", vec![1.0, 2.0, 1.0, 2.0, 1.0, 2.0, 3.0])
+            .add_numeric("v", vec![10.0, 5.0, 20.0, 7.0, 30.0, 9.0, 4.0])
+            .build()
+            .unwrap();
+        let z = spread
+            .group_transform("g", "v", TransformFn::ZScore, "z")
+            .unwrap();
+        assert_eq!(z["z"].to_string(), "z: [-1, -1, 0, 0, 1, 1, 0]");
+        for g in 1..=3 {
+            let rows = z.filter("g", Comp::Eq, FilterValue::Num(g as f64)).unwrap();
+            assert_eq!(rows.numeric_column("z").unwrap().sum(), 0.0);
+        }
+        // [1, 2, 4] has mean 7 / 3, so its z-scores are not whole numbers
+        let skewed = NodFrameBuilder::new()
+            .add_numeric("g", vec![1.0_f64; 3])
+            .add_numeric("v", vec![1.0, 2.0, 4.0])
+            .build()
+            .unwrap();
+        let z = skewed
+            .group_transform("g", "v", TransformFn::ZScore, "z")
+            .unwrap();
+        let z = z.numeric_column("z").unwrap();
+        assert!((*z.get(0) + 0.8728715609439696).abs() < 1e-12);
+        assert!((*z.get(2) - 1.0910894511799618).abs() < 1e-12);
+    }
+
+    #[test]
+    fn group_transform_fn_test() {
+        let frame = survey();
+        // rank within the group by position
+        let ranked = frame
+            .group_transform_fn("region", "score", |g| (0..g.len() as i64).collect(), "pos")
+            .unwrap();
+        assert_eq!(ranked["pos"].to_string(), "pos: [0, 0, 1, 0, 1, 1]");
+        // deviation from the first value of each group
+        let diffs = frame
+            .group_transform_fn(
+                "region",
+                "score",
+                |g| g.iter().map(|v| v - g[0]).collect(),
+                "diff",
+            )
+            .unwrap();
+        assert_eq!(diffs["diff"].to_string(), "diff: [0, 0, 30, 0, 30, 2]");
+        let err = frame
+            .group_transform_fn("region", "score", |_| vec![1_i64], "bad")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "column 'bad' has 1 rows, expected 2");
+        assert!(frame
+            .group_transform("region", "missing", TransformFn::Sum, "x")
+            .is_err());
+    }
+
+    #[test]
+    fn cumulative_by_test() {
+        let frame = survey();
+        // regions are interleaved: b a b c a c
+        let sums = frame.cumsum_by("region", "score", "running").unwrap();
+        assert_eq!(
+            sums["running"].to_string(),
+            "running: [10, 20, 50, 7, 70, 16]"
+        );
+        let maxes = frame.cummax_by("region", "weight", "peak").unwrap();
+        assert_eq!(maxes["peak"].to_string(), "peak: [1, 3, 2, 0, 3, 0]");
+        let counts = frame.cumcount_by("region", "n").unwrap();
+        assert_eq!(counts["n"].to_string(), "n: [1, 1, 2, 1, 2, 2]");
+
+        // single-row groups keep their own value
+        let ids = NodFrameBuilder::new()
+            .add_numeric("id", vec![3, 1, 2])
+            .add_numeric("v", vec![5, 6, 7])
+            .build()
+            .unwrap();
+        let single = ids.cumsum_by("id", "v", "total").unwrap();
+        assert_eq!(single["total"].to_string(), "total: [5, 6, 7]");
+
+        // with one group the running total is the global one
+        let one = NodFrameBuilder::new()
+            .add_numeric("g", vec![0; 5])
+            .add_numeric("v", vec![4, -1, 3, 0, 9])
+            .build()
+            .unwrap()
+            .cumsum_by("g", "v", "total")
+            .unwrap();
+        let global: Vec<i64> = [4, -1, 3, 0, 9]
+            .iter()
+            .scan(0, |acc, x| {
+                *acc += x;
+                Some(*acc)
+            })
+            .collect();
+        assert_eq!(one.numeric_column("total").unwrap().as_slice(), &global[..]);
+        assert!(frame.cumsum_by("region", "missing", "x").is_err());
+    }
+
+    #[test]
+    fn partition_by_test() {
+        let frame = survey();
+        let parts = frame.partition_by("region").unwrap();
+        let labels: Vec<&str> = parts.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(labels, vec!["a", "b", "c"]);
+        assert_eq!(parts[1].1["score"].to_string(), "score: [10, 40]");
+        // stacking the partitions back gives every row exactly once
+        let mut rows: Vec<String> = parts
+            .iter()
+            .flat_map(|(_, p)| (0..p.shape().0).map(move |r| format!("{:?}", p.row(r))))
+            .collect();
+        let mut original: Vec<String> = (0..frame.shape().0)
+            .map(|r| format!("{:?}", frame.row(r)))
+            .collect();
+        rows.sort();
+        original.sort();
+        assert_eq!(rows, original);
+        assert_eq!(frame.partition_by("weight").unwrap()[0].0, "0");
+        assert!(frame.partition_by("missing").is_err());
+    }
+
+    fn labelled(rows: usize) -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_discrete(
+                "label",
+                (0..rows)
+                    .map(|i| ["cat", "dog", "dog", "eel"][i % 4].to_string())
+                    .collect(),
+            )
+            .add_numeric("id", (0..rows as i64).collect())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn head_per_group_test() {
+        let frame = survey();
+        let head = frame.head_per_group("region", 1).unwrap();
+        assert_eq!(head["region"].to_string(), "region: [a, b, c]");
+        assert_eq!(head["score"].to_string(), "score: [20, 10, 7]");
+        let all = frame.head_per_group("region", 5).unwrap();
+        assert_eq!(all["score"].to_string(), "score: [20, 50, 10, 40, 7, 9]");
+        assert_eq!(frame.head_per_group("region", 0).unwrap().shape(), (0, 3));
+        assert!(frame.head_per_group("missing", 1).is_err());
+    }
+
+    #[test]
+    fn sample_per_group_test() {
+        let frame = labelled(40);
+        let sample = frame.sample_per_group("label", 6, Some(7)).unwrap();
+        let sizes = sample.group_sizes("label").unwrap();
+        assert_eq!(sizes["count"].to_string(), "count: [6, 6, 6]");
+        // every sampled row is a row of the input with its own label
+        let ids = sample.numeric_column("id").unwrap();
+        let labels = sample.discrete_column("label").unwrap();
+        let mut seen = std::collections::HashSet::new();
+        for (id, label) in ids.iter().zip(labels.iter()) {
+            assert!(seen.insert(*id));
+            assert_eq!(
+                frame.discrete_column("label").unwrap().get(*id as usize),
+                label
+            );
+        }
+        assert_valid(&sample);
+
+        let again = frame.sample_per_group("label", 6, Some(7)).unwrap();
+        assert_eq!(again.to_string(), sample.to_string());
+        assert!((0..20).any(|seed| frame
+            .sample_per_group("label", 6, Some(seed))
+            .unwrap()
+            .to_string()
+            != sample.to_string()));
+        assert_eq!(
+            frame.sample_per_group("label", 6, None).unwrap().shape(),
+            (18, 2)
+        );
+    }
+
+    #[test]
+    fn sample_per_group_small_test() {
+        // cat and eel have 3 rows each, dog has 6
+        let frame = labelled(12);
+        let capped = frame.sample_per_group("label", 4, Some(1)).unwrap();
+        let sizes = capped.group_sizes("label").unwrap();
+        assert_eq!(sizes["count"].to_string(), "count: [3, 4, 3]");
+        assert_eq!(
+            frame
+                .sample_per_group_strict("label", 4, Some(1))
+                .unwrap_err()
+                .to_string(),
+            "group 'cat' of 'label' has 3 rows, fewer than 4"
+        );
+        let strict = frame.sample_per_group_strict("label", 3, Some(1)).unwrap();
+        assert_eq!(strict.shape(), (9, 2));
+        assert!(frame.sample_per_group("missing", 1, Some(1)).is_err());
+    }
+
+    #[test]
+    fn to_csv_partitioned_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete(
+                "city",
+                ["New York", "New/York", "..", "Oslo"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .add_numeric("n", vec![1, 2, 3, 4])
+            .build()
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("nodframe_{}_parts", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = frame
+            .to_csv_partitioned("city", &dir, "sales_{key}.csv")
+            .unwrap();
+        let names: Vec<String> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        // both New York spellings sanitize to New_York, the second gets a suffix
+        assert_eq!(
+            names,
+            vec![
+                "sales___.csv",
+                "sales_New_York.csv",
+                "sales_New_York_1.csv",
+                "sales_Oslo.csv"
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&paths[2]).unwrap(),
+            "city,n\nNew/York,2\n"
+        );
+        assert!(frame.to_csv_partitioned("city", &dir, "sales.csv").is_err());
+
+        // a second run would overwrite the files, so it writes none of them
+        std::fs::remove_file(&paths[3]).unwrap();
+        let err = frame
+            .to_csv_partitioned("city", &dir, "sales_{key}.csv")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("file '{}' already exists", paths[0].display())
+        );
+        assert!(!paths[3].exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resample_rows_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("t", (0..10).collect())
+            .add_numeric("v", vec![5, 1, 3, 2, 8, 4, 9, 6, 7, 10])
+            .add_discrete("s", (0..10).map(|i| format!("s{}", i)).collect())
+            .build()
+            .unwrap();
+        let sums = frame.resample_rows(3, AggFn::Sum).unwrap();
+        assert_eq!(sums.shape(), (4, 3));
+        assert_eq!(
+            sums.numeric_column("v").unwrap().as_slice(),
+            &[9, 14, 22, 10]
+        );
+        assert_eq!(
+            sums.discrete_column("s")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            &["s0", "s3", "s6", "s9"]
+        );
+
+        let opts = ResampleOptions {
+            keep_partial: false,
+            discrete_last: true,
+        };
+        // 14 / 3 and 22 / 3 would truncate for an integer frame
+        assert!(frame.resample_rows_with(3, AggFn::Mean, &opts).is_err());
+        let floats = NodFrameBuilder::new()
+            .add_numeric("v", vec![5.0, 1.0, 3.0, 2.0, 8.0, 4.0, 9.0, 6.0, 7.0, 10.0])
+            .add_discrete("s", (0..10).map(|i| format!("s{}", i)).collect())
+            .build()
+            .unwrap();
+        let means = floats.resample_rows_with(3, AggFn::Mean, &opts).unwrap();
+        assert_eq!(means.shape(), (3, 2));
+        assert_eq!(
+            means["v"].to_string(),
+            "v: [3, 4.666666666666667, 7.333333333333333]"
+        );
+        assert_eq!(
+            means
+                .discrete_column("s")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            &["s2", "s5", "s8"]
+        );
+
+        let column = |agg| frame.resample_rows(3, agg).unwrap()["v"].to_string();
+        assert_eq!(column(AggFn::Min), "v: [1, 2, 6, 10]");
+        assert_eq!(column(AggFn::Max), "v: [5, 8, 9, 10]");
+        assert_eq!(column(AggFn::First), "v: [5, 2, 9, 10]");
+        assert_eq!(column(AggFn::Last), "v: [3, 4, 7, 10]");
+        assert!(frame.resample_rows(0, AggFn::Sum).is_err());
+    }
+
+    #[test]
+    fn group_sizes_test() {
+        let frame = survey();
+        let sizes = frame.group_sizes("region").unwrap();
+        assert_eq!(sizes["region"].to_string(), "region: [a, b, c]");
+        assert_eq!(sizes["count"].to_string(), "count: [2, 2, 2]");
+
+        let multi = frame.group_sizes_multi(&["weight", "region"]).unwrap();
+        assert_eq!(multi["weight"].to_string(), "weight: [0, 1, 1, 2, 3]");
+        assert_eq!(multi["region"].to_string(), "region: [c, a, b, b, a]");
+        assert_eq!(multi["count"].to_string(), "count: [2, 1, 1, 1, 1]");
+
+        // group_keys holds each distinct key row once
+        let keys = frame.group_keys(&["weight", "region"]).unwrap();
+        assert_eq!(keys.shape(), (5, 2));
+        let mut seen: Vec<(String, String)> = (0..frame.shape().0)
+            .map(|r| {
+                (
+                    frame["weight"].cell_string(r),
+                    frame["region"].cell_string(r),
+                )
+            })
+            .collect();
+        seen.sort();
+        seen.dedup();
+        let mut listed: Vec<(String, String)> = (0..keys.shape().0)
+            .map(|r| (keys["weight"].cell_string(r), keys["region"].cell_string(r)))
+            .collect();
+        listed.sort();
+        assert_eq!(listed, seen);
+
+        assert!(frame.group_keys(&[]).is_err());
+        assert!(frame.group_sizes("missing").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+// A one-screen overview of a frame, like pandas' DataFrame.info.
+//
+// info lists every column with its kind, how many values are not missing
+// (NaN and empty strings are missing, as in ColumnProfile) and the bytes
+// estimated by memory_usage, then the shape and totals. The text only depends
+// on the frame, so it can be compared in tests.
+use crate::{format_bytes, is_nan, ColKind, Column, NodFrame, NodNum};
+
+impl<T: NodNum> NodFrame<T> {
+    // info renders the overview as aligned text, one line per column in frame
+    // order between a shape line and the totals
+    pub fn info(&self) -> String {
+        let header = ["#", "column", "kind", "non-missing", "memory"];
+        let rows: Vec<[String; 5]> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, col)| {
+                let present = match col {
+                    Column::Numeric(n) => n.iter().filter(|x| !is_nan(*x)).count(),
+                    Column::Discrete(d) => d.iter().filter(|s| !s.is_empty()).count(),
+                };
+                [
+                    i.to_string(),
+                    col.get_key().clone(),
+                    col.kind().to_string(),
+                    present.to_string(),
+                    format_bytes(col.memory_usage()),
+                ]
+            })
+            .collect();
+        let widths: Vec<usize> = (0..header.len())
+            .map(|c| {
+                rows.iter()
+                    .map(|r| r[c].chars().count())
+                    .chain([header[c].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let line = |cells: [&str; 5]| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+
+        let numeric = self
+            .columns
+            .iter()
+            .filter(|c| c.kind() == ColKind::Numeric)
+            .count();
+        let mut out = format!("{} rows x {} columns\n", self.num_rows, self.num_cols);
+        out.push_str(&line(header));
+        out.push('\n');
+        for row in rows.iter() {
+            out.push_str(&line(row.each_ref().map(|s| s.as_str())));
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "kinds: numeric {}, discrete {}\n",
+            numeric,
+            self.num_cols - numeric
+        ));
+        out.push_str(&format!(
+            "memory: {}",
+            format_bytes(self.memory_usage().total())
+        ));
+        out
+    }
+
+    // print_info prints info to stdout
+    pub fn print_info(&self) {
+        println!("{}", self.info());
+    }
+}
+
+#[cfg(test)]
+mod info_tests {
+    use crate::{NodFrame, NodFrameBuilder};
+
+    #[test]
+    fn info_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("price", vec![9.5, f64::NAN, 3.0, 12.25])
+            .add_discrete(
+                "city",
+                ["Oslo", "", "Bergen", "Oslo"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .add_numeric("quantity_sold", vec![1.0, 2.0, 3.0, 4.0])
+            .build()
+            .unwrap();
+        assert_eq!(
+            frame.info(),
+            "4 rows x 3 columns\n\
+             #  column         kind      non-missing  memory\n\
+             0  price          numeric   3            56 B\n\
+             1  city           discrete  3            166 B\n\
+             2  quantity_sold  numeric   4            56 B\n\
+             kinds: numeric 2, discrete 1\n\
+             memory: 278 B"
+        );
+    }
+
+    #[test]
+    fn info_degenerate_test() {
+        let empty: NodFrame<i64> = NodFrameBuilder::new().build().unwrap();
+        assert_eq!(
+            empty.info(),
+            "0 rows x 0 columns\n\
+             #  column  kind  non-missing  memory\n\
+             kinds: numeric 0, discrete 0\n\
+             memory: 0 B"
+        );
+        let no_rows: NodFrame<i64> = NodFrameBuilder::new()
+            .add_numeric("id", Vec::new())
+            .add_discrete("name", Vec::new())
+            .build()
+            .unwrap();
+        assert_eq!(
+            no_rows.info(),
+            "0 rows x 2 columns\n\
+             #  column  kind      non-missing  memory\n\
+             0  id      numeric   0            24 B\n\
+             1  name    discrete  0            24 B\n\
+             kinds: numeric 1, discrete 1\n\
+             memory: 48 B"
+        );
+    }
+}
@@ -0,0 +1,344 @@
+// Dry-run inspection of a csv file, see inspect_csv.
+//
+// Only the header and the first sample_rows rows are read, so a report is
+// cheap even for files too large to load. Everything in it describes the
+// sample: a column reported numeric may still hold text further down, and the
+// row count is extrapolated from the file size and the average sampled row.
+use crate::{loses_text, name_empty_headers, ColKind, HyperLogLog, Schema};
+use csv::{ByteRecord, ReaderBuilder};
+use num_traits::ToPrimitive;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+// sniff_delimiter picks the candidate appearing most often in line outside
+// quotes, a comma when none appears
+fn sniff_delimiter(line: &[u8]) -> u8 {
+    let mut counts = [0; DELIMITERS.len()];
+    let mut quoted = false;
+    for b in line {
+        if *b == b'"' {
+            quoted = !quoted;
+        } else if !quoted {
+            if let Some(i) = DELIMITERS.iter().position(|d| d == b) {
+                counts[i] += 1;
+            }
+        }
+    }
+    // max_by_key keeps the last maximum, so ties go to the earlier candidate
+    let best = (0..DELIMITERS.len())
+        .rev()
+        .max_by_key(|i| counts[*i])
+        .unwrap();
+    if counts[best] == 0 {
+        b','
+    } else {
+        DELIMITERS[best]
+    }
+}
+
+// ColumnReport describes one column of the sample. distinct is estimated for
+// discrete columns only, min and max cover the cells that parsed as numbers
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnReport {
+    key: String,
+    kind: ColKind,
+    numeric_fraction: f64,
+    distinct: Option<usize>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl ColumnReport {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    // kind is the kind frame_from_csv would infer from the sample alone
+    pub fn kind(&self) -> ColKind {
+        self.kind
+    }
+
+    // numeric_fraction is the share of sampled cells that parsed as numbers,
+    // 1 when nothing was sampled
+    pub fn numeric_fraction(&self) -> f64 {
+        self.numeric_fraction
+    }
+
+    pub fn distinct(&self) -> Option<usize> {
+        self.distinct
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+}
+
+// CsvReport is the result of inspect_csv
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvReport {
+    delimiter: u8,
+    columns: Vec<ColumnReport>,
+    sampled_rows: usize,
+    estimated_rows: usize,
+    exact: bool,
+}
+
+impl CsvReport {
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn columns(&self) -> &[ColumnReport] {
+        &self.columns
+    }
+
+    pub fn column(&self, key: &str) -> Option<&ColumnReport> {
+        self.columns.iter().find(|c| c.key == key)
+    }
+
+    pub fn sampled_rows(&self) -> usize {
+        self.sampled_rows
+    }
+
+    // estimated_rows is the row count extrapolated from the sample, exact
+    // when the sample reached the end of the file
+    pub fn estimated_rows(&self) -> usize {
+        self.estimated_rows
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    // schema turns the inferred kinds into a strict Schema, which can be
+    // adjusted and passed to frame_from_csv_with_schema
+    pub fn schema(&self) -> Schema {
+        self.columns
+            .iter()
+            .fold(Schema::new(), |s, c| s.column(&c.key, c.kind))
+    }
+}
+
+impl fmt::Display for CsvReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let delimiter = match self.delimiter {
+            b'\t' => String::from("\\t"),
+            d => (d as char).to_string(),
+        };
+        writeln!(
+            f,
+            "delimiter '{}', {} rows sampled, {}{} rows",
+            delimiter,
+            self.sampled_rows,
+            if self.exact { "" } else { "~" },
+            self.estimated_rows
+        )?;
+        let optional = |x: Option<f64>| x.map_or(String::from("-"), |v| v.to_string());
+        let mut rows =
+            vec![["column", "kind", "numeric", "distinct", "min", "max"].map(String::from)];
+        for c in self.columns.iter() {
+            rows.push([
+                c.key.clone(),
+                c.kind.to_string(),
+                format!("{:.1}%", c.numeric_fraction * 100.0),
+                c.distinct.map_or(String::from("-"), |d| d.to_string()),
+                optional(c.min),
+                optional(c.max),
+            ]);
+        }
+        let widths: Vec<usize> = (0..6)
+            .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap())
+            .collect();
+        let lines: Vec<String> = rows
+            .iter()
+            .map(|r| {
+                r.iter()
+                    .zip(widths.iter())
+                    .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+// ColumnSample accumulates one column while the sample is read
+struct ColumnSample {
+    numeric: usize,
+    // a number that would lose its text keeps the column discrete, see
+    // NodFrame::demoted_columns
+    loses_text: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    sketch: HyperLogLog,
+}
+
+// inspect_csv reads the header and up to sample_rows rows of a csv file and
+// reports what loading it as a NodFrame<T> would likely give, see CsvReport.
+// The delimiter is sniffed from the header line among , ; tab and |
+pub fn inspect_csv<T: FromStr + ToPrimitive>(
+    file_path: impl AsRef<Path>,
+    sample_rows: usize,
+) -> Result<CsvReport, Box<dyn Error>> {
+    let file_len = std::fs::metadata(&file_path)?.len();
+    let mut input = BufReader::new(File::open(&file_path)?);
+    let delimiter = sniff_delimiter(input.fill_buf()?.split(|b| *b == b'\n').next().unwrap());
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(input);
+    let mut header: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+    name_empty_headers(&mut header);
+    let data_start = reader.position().byte();
+    let mut samples: Vec<ColumnSample> = header
+        .iter()
+        .map(|_| ColumnSample {
+            numeric: 0,
+            loses_text: false,
+            min: None,
+            max: None,
+            sketch: HyperLogLog::new(),
+        })
+        .collect();
+    let mut record = ByteRecord::new();
+    let mut sampled = 0;
+    let mut exact = false;
+    while sampled < sample_rows {
+        if !reader.read_byte_record(&mut record)? {
+            exact = true;
+            break;
+        }
+        sampled += 1;
+        for (sample, cell) in samples.iter_mut().zip(record.iter()) {
+            let cell = String::from_utf8_lossy(cell);
+            sample.sketch.add(&cell);
+            if let Some(x) = cell.parse::<T>().ok().and_then(|n| n.to_f64()) {
+                sample.numeric += 1;
+                sample.loses_text = sample.loses_text || loses_text(&cell);
+                sample.min = Some(sample.min.map_or(x, |m| m.min(x)));
+                sample.max = Some(sample.max.map_or(x, |m| m.max(x)));
+            }
+        }
+    }
+    // a full sample may have stopped right at the end of the file
+    exact = exact || !reader.read_byte_record(&mut record)?;
+    let estimated_rows = if exact || sampled == 0 {
+        sampled
+    } else {
+        let average = (reader.position().byte() - data_start) as f64 / (sampled + 1) as f64;
+        ((file_len - data_start) as f64 / average).round() as usize
+    };
+    let columns = header
+        .into_iter()
+        .zip(samples)
+        .map(|(key, s)| {
+            let numeric = s.numeric == sampled && !s.loses_text;
+            ColumnReport {
+                key,
+                kind: if numeric {
+                    ColKind::Numeric
+                } else {
+                    ColKind::Discrete
+                },
+                numeric_fraction: if sampled == 0 {
+                    1.0
+                } else {
+                    s.numeric as f64 / sampled as f64
+                },
+                distinct: (!numeric).then(|| s.sketch.estimate()),
+                min: s.min,
+                max: s.max,
+            }
+        })
+        .collect();
+    Ok(CsvReport {
+        delimiter,
+        columns,
+        sampled_rows: sampled,
+        estimated_rows,
+        exact,
+    })
+}
+
+#[cfg(test)]
+mod inspect_tests {
+    use crate::test_support::temp_path;
+    use crate::{frame_from_csv_with_schema, inspect_csv, ColKind, NodFrame};
+
+    #[test]
+    fn inspect_csv_test() {
+        let path = temp_path("mixed.csv");
+        let mut text = String::from("id;zip;\"note;s\"\n");
+        for i in 0..1000 {
+            let zip = if i % 4 == 0 {
+                String::from("n/a")
+            } else {
+                (10_000 + i % 9).to_string()
+            };
+            text.push_str(&format!("{};{};n{}\n", i, zip, i % 3));
+        }
+        std::fs::write(&path, &text).unwrap();
+
+        let report = inspect_csv::<i64>(&path, 100).unwrap();
+        assert_eq!(report.delimiter(), b';');
+        assert_eq!(report.sampled_rows(), 100);
+        assert!(!report.is_exact());
+        let estimate = report.estimated_rows() as f64;
+        assert!((estimate - 1000.0).abs() < 100.0, "{}", estimate);
+
+        let id = report.column("id").unwrap();
+        assert_eq!(id.kind(), ColKind::Numeric);
+        assert_eq!((id.min(), id.max()), (Some(0.0), Some(99.0)));
+        assert_eq!(id.distinct(), None);
+        let zip = report.column("zip").unwrap();
+        assert_eq!(zip.kind(), ColKind::Discrete);
+        assert_eq!(zip.numeric_fraction(), 0.75);
+        assert_eq!((zip.min(), zip.max()), (Some(10_000.0), Some(10_008.0)));
+        assert_eq!(zip.distinct(), Some(10));
+        assert_eq!(report.column("note;s").unwrap().distinct(), Some(3));
+
+        let table = report.to_string();
+        assert!(table.starts_with("delimiter ';', 100 rows sampled, ~"));
+        assert!(table.contains("\nzip     discrete  75.0%    10        10000  10008"));
+
+        let schema = report.schema().numeric("zip");
+        assert_eq!(schema.kind_of("note;s"), Some(ColKind::Discrete));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn inspect_delimiters_test() {
+        for (name, delimiter) in [("comma", b','), ("tab", b'\t'), ("pipe", b'|')] {
+            let path = temp_path(&format!("{}.csv", name));
+            let d = delimiter as char;
+            let text = format!("a{d}b\n1{d}x\n2{d}y\n3{d}x\n", d = d);
+            std::fs::write(&path, text).unwrap();
+            let report = inspect_csv::<i64>(&path, 10).unwrap();
+            assert_eq!(report.delimiter(), delimiter, "{}", name);
+            assert!(report.is_exact());
+            assert_eq!(report.estimated_rows(), 3);
+            assert_eq!(report.column("b").unwrap().distinct(), Some(2));
+
+            if delimiter == b',' {
+                // inspect, then load with the inferred schema
+                let frame: NodFrame<i64> =
+                    frame_from_csv_with_schema(&path, &report.schema()).unwrap();
+                assert_eq!(frame.schema(), report.schema());
+            }
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,626 @@
+// Joins between frames on a shared key column.
+//
+// Keys are matched on their text form (see Column::cell_string), so the key
+// column must have the same kind on both sides.
+use crate::{
+    Column, DiscreteColumn, NodFrame, NodFrameBuilder, NodFrameError, NodNum, NumericColumn,
+};
+use std::collections::{HashMap, HashSet};
+
+// join_keys looks up the key column on both sides and checks their kinds
+fn join_keys<'a, T>(
+    left: &'a NodFrame<T>,
+    right: &'a NodFrame<T>,
+    on: &str,
+) -> Result<(&'a Column<T>, &'a Column<T>), NodFrameError>
+where
+    T: NodNum,
+{
+    let missing = || NodFrameError::MissingColumn(String::from(on));
+    let l = left.column(on).ok_or_else(missing)?;
+    let r = right.column(on).ok_or_else(missing)?;
+    if l.kind() != r.kind() {
+        return Err(NodFrameError::KindMismatch {
+            key: String::from(on),
+            expected: l.kind(),
+            found: r.kind(),
+        });
+    }
+    Ok((l, r))
+}
+
+// JoinFill holds the values written into the other side's columns for rows
+// without a match, as frames have no missing values
+#[derive(Clone, Debug, PartialEq)]
+pub struct JoinFill<T> {
+    pub numeric: T,
+    pub discrete: String,
+}
+
+impl<T> JoinFill<T> {
+    pub fn new(numeric: T, discrete: &str) -> JoinFill<T> {
+        JoinFill {
+            numeric,
+            discrete: String::from(discrete),
+        }
+    }
+}
+
+// JoinKind picks which unmatched rows join keeps. Every kind that keeps
+// unmatched rows carries the fill for them
+#[derive(Clone, Debug, PartialEq)]
+pub enum JoinKind<T> {
+    Inner,
+    Left(JoinFill<T>),
+    Right(JoinFill<T>),
+    Outer(JoinFill<T>),
+}
+
+impl<T> JoinKind<T> {
+    fn keeps_left(&self) -> bool {
+        matches!(self, JoinKind::Left(_) | JoinKind::Outer(_))
+    }
+
+    fn keeps_right(&self) -> bool {
+        matches!(self, JoinKind::Right(_) | JoinKind::Outer(_))
+    }
+
+    fn fill(&self) -> Option<&JoinFill<T>> {
+        match self {
+            JoinKind::Inner => None,
+            JoinKind::Left(f) | JoinKind::Right(f) | JoinKind::Outer(f) => Some(f),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "inner",
+            JoinKind::Left(_) => "left",
+            JoinKind::Right(_) => "right",
+            JoinKind::Outer(_) => "outer",
+        }
+    }
+}
+
+// take_or_fill takes the rows at indices, writing the fill for None
+fn take_or_fill<T: Clone>(
+    col: &Column<T>,
+    indices: &[Option<usize>],
+    fill: Option<&JoinFill<T>>,
+) -> Column<T> {
+    // join only produces None when the kind has a fill, align_to always has one
+    let fill = || fill.unwrap();
+    match col {
+        Column::Numeric(n) => Column::Numeric(NumericColumn::new(
+            n.key(),
+            indices
+                .iter()
+                .map(|i| match i {
+                    Some(i) => n.as_slice()[*i].clone(),
+                    None => fill().numeric.clone(),
+                })
+                .collect(),
+        )),
+        Column::Discrete(d) => Column::Discrete(DiscreteColumn::new(
+            d.key(),
+            indices
+                .iter()
+                .map(|i| match i {
+                    Some(i) => String::from(d.get(*i)),
+                    None => fill().discrete.clone(),
+                })
+                .collect(),
+        )),
+    }
+}
+
+// take_key builds the on column of a join, each row takes its key from the
+// left when it has a left row and from the right otherwise
+fn take_key<T: Clone>(
+    l: &Column<T>,
+    r: &Column<T>,
+    pairs: &[(Option<usize>, Option<usize>)],
+) -> Column<T> {
+    fn pick<V: Clone>(a: &[V], b: &[V], pair: &(Option<usize>, Option<usize>)) -> V {
+        match pair {
+            (Some(i), _) => a[*i].clone(),
+            (None, Some(j)) => b[*j].clone(),
+            (None, None) => unreachable!(),
+        }
+    }
+    match (l, r) {
+        (Column::Numeric(a), Column::Numeric(b)) => Column::Numeric(NumericColumn::new(
+            a.key(),
+            pairs
+                .iter()
+                .map(|p| pick(a.as_slice(), b.as_slice(), p))
+                .collect(),
+        )),
+        (Column::Discrete(a), Column::Discrete(b)) => {
+            Column::Discrete(DiscreteColumn::from_shared(
+                a.key(),
+                pairs
+                    .iter()
+                    .map(|p| pick(a.as_slice(), b.as_slice(), p))
+                    .collect(),
+            ))
+        }
+        // join_keys checked both sides have the same kind
+        _ => unreachable!(),
+    }
+}
+
+// CrossJoinOptions guards cross_join against huge results: a product of more
+// than max_rows rows is an error, None lifts the limit
+#[derive(Clone, Copy, Debug)]
+pub struct CrossJoinOptions {
+    pub max_rows: Option<usize>,
+}
+
+impl Default for CrossJoinOptions {
+    fn default() -> Self {
+        CrossJoinOptions {
+            max_rows: Some(10_000_000),
+        }
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // semi_join keeps the rows whose on value appears in other. Only the
+    // columns of self are kept and no row is repeated, however often its key
+    // appears in other
+    pub fn semi_join(&self, other: &NodFrame<T>, on: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.filter_keys(other, on, true)
+    }
+
+    // anti_join keeps the rows whose on value does not appear in other
+    pub fn anti_join(&self, other: &NodFrame<T>, on: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.filter_keys(other, on, false)
+    }
+
+    // join pairs the rows of self and other with equal on values, each pair
+    // is one row and a key matching several rows on both sides gives every
+    // combination. Pairs come in left order followed by the unmatched right
+    // rows when kind keeps them. The output has the left columns, with the on
+    // column taken from whichever side has the row, then the other right
+    // columns, suffixed "_right" when the key is already used on the left
+    pub fn join(
+        &self,
+        other: &NodFrame<T>,
+        on: &str,
+        kind: JoinKind<T>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.join_rows(other, on, &kind, None)
+    }
+
+    // join_with_source is join with an extra discrete column source_key
+    // telling where each row came from: "both", "left_only" or "right_only"
+    pub fn join_with_source(
+        &self,
+        other: &NodFrame<T>,
+        on: &str,
+        kind: JoinKind<T>,
+        source_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        self.join_rows(other, on, &kind, Some(source_key))
+    }
+
+    // align_to returns one row per entry of keys, in that order, taking each
+    // row from self where the discrete column on holds the key and writing
+    // fill into the other columns where it does not. Rows of self whose key
+    // is not listed are dropped. A key on more than one row of self is an
+    // error unless keep_first is set, which uses its first row
+    pub fn align_to(
+        &self,
+        keys: &[String],
+        on: &str,
+        fill: &JoinFill<T>,
+        keep_first: bool,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let col = self.discrete_column(on)?;
+        let mut index: HashMap<&str, usize> = HashMap::with_capacity(col.len());
+        for (row, key) in col.iter().enumerate() {
+            if index.contains_key(key) {
+                if keep_first {
+                    continue;
+                }
+                return Err(NodFrameError::InvalidValue {
+                    key: String::from(on),
+                    row,
+                    message: format!("key '{}' appears on more than one row", key),
+                });
+            }
+            index.insert(key, row);
+        }
+        let rows: Vec<Option<usize>> = keys
+            .iter()
+            .map(|k| index.get(k.as_str()).copied())
+            .collect();
+        let out = NodFrame::from_columns(
+            self.columns()
+                .iter()
+                .map(|c| match c {
+                    Column::Discrete(d) if d.key() == on => {
+                        Column::Discrete(DiscreteColumn::new(on, keys.to_vec()))
+                    }
+                    c => take_or_fill(c, &rows, Some(fill)),
+                })
+                .collect(),
+        )?;
+        Ok(self.traced(out, "align_to", || {
+            vec![("on", String::from(on)), ("keys", keys.len().to_string())]
+        }))
+    }
+
+    fn join_rows(
+        &self,
+        other: &NodFrame<T>,
+        on: &str,
+        kind: &JoinKind<T>,
+        source_key: Option<&str>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let (l, r) = join_keys(self, other, on)?;
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for j in 0..r.len() {
+            index.entry(r.cell_string(j)).or_default().push(j);
+        }
+        let mut pairs: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+        let mut matched = vec![false; r.len()];
+        for i in 0..l.len() {
+            match index.get(&l.cell_string(i)) {
+                Some(rows) => {
+                    for j in rows {
+                        matched[*j] = true;
+                        pairs.push((Some(i), Some(*j)));
+                    }
+                }
+                None if kind.keeps_left() => pairs.push((Some(i), None)),
+                None => {}
+            }
+        }
+        if kind.keeps_right() {
+            pairs.extend(
+                (0..r.len())
+                    .filter(|j| !matched[*j])
+                    .map(|j| (None, Some(j))),
+            );
+        }
+        let left: Vec<Option<usize>> = pairs.iter().map(|(i, _)| *i).collect();
+        let right: Vec<Option<usize>> = pairs.iter().map(|(_, j)| *j).collect();
+
+        let mut builder = NodFrameBuilder::new();
+        for col in self.columns() {
+            let taken = if col.get_key() == on {
+                take_key(col, r, &pairs)
+            } else {
+                take_or_fill(col, &left, kind.fill())
+            };
+            builder = builder.add_column(taken);
+        }
+        for col in other.columns().iter().filter(|c| c.get_key() != on) {
+            let taken = take_or_fill(col, &right, kind.fill());
+            builder = match self.column(col.get_key()) {
+                Some(_) => builder.add_column(taken.with_key(&format!("{}_right", col.get_key()))),
+                None => builder.add_column(taken),
+            };
+        }
+        if let Some(source_key) = source_key {
+            let sources = pairs
+                .iter()
+                .map(|pair| {
+                    String::from(match pair {
+                        (Some(_), Some(_)) => "both",
+                        (Some(_), None) => "left_only",
+                        _ => "right_only",
+                    })
+                })
+                .collect();
+            builder = builder.add_discrete(source_key, sources);
+        }
+        Ok(self.traced(builder.build()?, "join", || {
+            vec![
+                ("on", String::from(on)),
+                ("kind", String::from(kind.name())),
+                ("right_rows", other.shape().0.to_string()),
+            ]
+        }))
+    }
+
+    // cross_join pairs every row of self with every row of other, left-major:
+    // all pairings of the first left row come first. Right columns whose key is
+    // already used on the left get a "_right" suffix
+    pub fn cross_join(&self, other: &NodFrame<T>) -> Result<NodFrame<T>, NodFrameError> {
+        self.cross_join_with(other, &CrossJoinOptions::default())
+    }
+
+    pub fn cross_join_with(
+        &self,
+        other: &NodFrame<T>,
+        opts: &CrossJoinOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let (left_rows, right_rows) = (self.shape().0, other.shape().0);
+        let rows = left_rows.checked_mul(right_rows);
+        if let Some(limit) = opts.max_rows {
+            if rows.is_none_or(|r| r > limit) {
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "cross join of {} x {} rows exceeds the limit of {} rows",
+                    left_rows, right_rows, limit
+                )));
+            }
+        }
+        let rows = rows.ok_or_else(|| {
+            NodFrameError::InvalidArgument(String::from("cross join size overflows usize"))
+        })?;
+        let left: Vec<usize> = (0..rows).map(|i| i / right_rows).collect();
+        let right: Vec<usize> = (0..rows).map(|i| i % right_rows).collect();
+        let mut builder = NodFrameBuilder::new();
+        for col in self.columns() {
+            builder = builder.add_column(col.take(&left));
+        }
+        for col in other.columns() {
+            let taken = col.take(&right);
+            builder = match self.column(col.get_key()) {
+                Some(_) => builder.add_column(taken.with_key(&format!("{}_right", col.get_key()))),
+                None => builder.add_column(taken),
+            };
+        }
+        Ok(self.traced(builder.build()?, "cross_join", || {
+            vec![("right_rows", right_rows.to_string())]
+        }))
+    }
+
+    fn filter_keys(
+        &self,
+        other: &NodFrame<T>,
+        on: &str,
+        present: bool,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let (l, r) = join_keys(self, other, on)?;
+        let keys: HashSet<String> = (0..r.len()).map(|i| r.cell_string(i)).collect();
+        let picker: Vec<bool> = (0..l.len())
+            .map(|i| keys.contains(&l.cell_string(i)) == present)
+            .collect();
+        let operation = if present { "semi_join" } else { "anti_join" };
+        Ok(self.traced(self.pick_rows(&picker), operation, || {
+            vec![("on", String::from(on))]
+        }))
+    }
+}
+
+#[cfg(test)]
+mod join_tests {
+    use crate::test_support::strings;
+    use crate::{assert_valid, CrossJoinOptions, JoinFill, JoinKind, NodFrame, NodFrameBuilder};
+
+    fn transactions() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_discrete("user", strings(&["ann", "bob", "cat", "bob", "dan"]))
+            .add_numeric("amount", vec![10, 20, 30, 40, 50])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn semi_anti_join_test() {
+        let left = transactions();
+        // bob is listed twice and eve never appears on the left
+        let blocked = NodFrameBuilder::new()
+            .add_discrete("user", strings(&["bob", "eve", "bob", "dan"]))
+            .add_numeric("level", vec![1, 2, 3, 4])
+            .build()
+            .unwrap();
+        let semi = left.semi_join(&blocked, "user").unwrap();
+        assert_eq!(semi.shape(), (3, 2));
+        assert_eq!(
+            semi.numeric_column("amount").unwrap().as_slice(),
+            &[20, 40, 50]
+        );
+        let anti = left.anti_join(&blocked, "user").unwrap();
+        assert_eq!(
+            anti.discrete_column("user")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            &["ann", "cat"]
+        );
+        assert_eq!(semi.shape().0 + anti.shape().0, left.shape().0);
+    }
+
+    #[test]
+    fn join_key_errors_test() {
+        let left = transactions();
+        let numeric_user = NodFrameBuilder::new()
+            .add_numeric("user", vec![1])
+            .build()
+            .unwrap();
+        assert_eq!(
+            left.semi_join(&numeric_user, "user")
+                .unwrap_err()
+                .to_string(),
+            "column 'user' is numeric, expected discrete"
+        );
+        assert!(left.anti_join(&left, "missing").is_err());
+    }
+
+    #[test]
+    fn cross_join_test() {
+        let sizes = NodFrameBuilder::new()
+            .add_discrete("size", strings(&["s", "m", "l"]))
+            .add_numeric("id", vec![1, 2, 3])
+            .build()
+            .unwrap();
+        let colors = NodFrameBuilder::new()
+            .add_discrete("color", strings(&["red", "green", "blue", "black"]))
+            .add_numeric("id", vec![10, 20, 30, 40])
+            .build()
+            .unwrap();
+        let grid = sizes.cross_join(&colors).unwrap();
+        assert_eq!(grid.shape(), (12, 4));
+        assert_eq!(
+            grid.schema()
+                .columns()
+                .iter()
+                .map(|(k, _)| k.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["size", "id", "color", "id_right"]
+        );
+        assert_eq!(
+            grid.discrete_column("size")
+                .unwrap()
+                .iter()
+                .take(5)
+                .collect::<Vec<_>>(),
+            strings(&["s", "s", "s", "s", "m"])
+        );
+        assert_eq!(
+            grid.numeric_column("id_right").unwrap().as_slice()[..5],
+            [10, 20, 30, 40, 10]
+        );
+
+        let small = CrossJoinOptions { max_rows: Some(11) };
+        assert_eq!(
+            sizes
+                .cross_join_with(&colors, &small)
+                .unwrap_err()
+                .to_string(),
+            "cross join of 3 x 4 rows exceeds the limit of 11 rows"
+        );
+        let unlimited = CrossJoinOptions { max_rows: None };
+        assert_eq!(
+            sizes.cross_join_with(&colors, &unlimited).unwrap().shape(),
+            (12, 4)
+        );
+
+        let empty = colors.filter_between("id", 0, 0).unwrap();
+        let none = sizes.cross_join(&empty).unwrap();
+        assert_eq!(none.shape(), (0, 4));
+        assert_eq!(none.schema(), grid.schema());
+    }
+
+    fn exports() -> (NodFrame<i64>, NodFrame<i64>) {
+        let ours = NodFrameBuilder::new()
+            .add_discrete("invoice", strings(&["a", "b", "b", "c"]))
+            .add_numeric("amount", vec![10, 20, 21, 30])
+            .add_discrete("status", strings(&["paid", "paid", "open", "open"]))
+            .build()
+            .unwrap();
+        let theirs = NodFrameBuilder::new()
+            .add_discrete("invoice", strings(&["d", "c", "b", "c"]))
+            .add_numeric("amount", vec![40, 30, 20, 31])
+            .add_numeric("fee", vec![4, 3, 2, 1])
+            .build()
+            .unwrap();
+        (ours, theirs)
+    }
+
+    fn count(frame: &NodFrame<i64>, source: &str) -> usize {
+        frame
+            .discrete_column("source")
+            .unwrap()
+            .iter()
+            .filter(|s| *s == source)
+            .count()
+    }
+
+    #[test]
+    fn outer_join_test() {
+        let (ours, theirs) = exports();
+        let fill = JoinFill::new(-1, "missing");
+        let outer = ours
+            .join_with_source(&theirs, "invoice", JoinKind::Outer(fill.clone()), "source")
+            .unwrap();
+        assert_valid(&outer);
+        // b matches once per left row and c matches twice
+        assert_eq!(outer.shape(), (6, 6));
+        assert_eq!((count(&outer, "both"), count(&outer, "left_only")), (4, 1));
+        assert_eq!(count(&outer, "right_only"), 1);
+        assert_eq!(outer["invoice"].to_string(), "invoice: [a, b, b, c, c, d]");
+        assert_eq!(
+            outer["amount"].to_string(),
+            "amount: [10, 20, 21, 30, 30, -1]"
+        );
+        assert_eq!(
+            outer["status"].to_string(),
+            "status: [paid, paid, open, open, open, missing]"
+        );
+        assert_eq!(
+            outer["amount_right"].to_string(),
+            "amount_right: [-1, 20, 20, 30, 31, 40]"
+        );
+        assert_eq!(outer["fee"].to_string(), "fee: [-1, 2, 2, 3, 1, 4]");
+
+        let right = ours
+            .join_with_source(&theirs, "invoice", JoinKind::Right(fill.clone()), "source")
+            .unwrap();
+        assert_eq!(right.shape().0, 5);
+        assert_eq!(count(&right, "left_only"), 0);
+        let left = ours.join(&theirs, "invoice", JoinKind::Left(fill)).unwrap();
+        assert_eq!(left["invoice"].to_string(), "invoice: [a, b, b, c, c]");
+        let inner = ours.join(&theirs, "invoice", JoinKind::Inner).unwrap();
+        assert_eq!(inner.shape(), (4, 5));
+        assert!(ours
+            .join_with_source(&theirs, "invoice", JoinKind::Inner, "amount")
+            .is_err());
+    }
+
+    #[test]
+    fn align_to_test() {
+        let monday = NodFrameBuilder::new()
+            .add_numeric("visits", vec![5, 7, 9])
+            .add_discrete("entity", strings(&["a", "b", "c"]))
+            .add_discrete("tier", strings(&["gold", "free", "free"]))
+            .build()
+            .unwrap();
+        let tuesday = NodFrameBuilder::new()
+            .add_numeric("visits", vec![8, 6, 1])
+            .add_discrete("entity", strings(&["d", "c", "a"]))
+            .add_discrete("tier", strings(&["free", "free", "gold"]))
+            .build()
+            .unwrap();
+        let keys = strings(&["c", "a", "d"]);
+        let fill = JoinFill::new(0, "none");
+        let before = monday.align_to(&keys, "entity", &fill, false).unwrap();
+        let after = tuesday.align_to(&keys, "entity", &fill, false).unwrap();
+        assert_valid(&before);
+        assert_eq!(before["entity"].to_string(), "entity: [c, a, d]");
+        assert_eq!(before["visits"].to_string(), "visits: [9, 5, 0]");
+        assert_eq!(before["tier"].to_string(), "tier: [free, gold, none]");
+        assert_eq!(after["visits"].to_string(), "visits: [6, 1, 8]");
+        // aligned columns can be combined row by row
+        let change: Vec<i64> = after
+            .numeric_column("visits")
+            .unwrap()
+            .iter()
+            .zip(before.numeric_column("visits").unwrap().iter())
+            .map(|(a, b)| a - b)
+            .collect();
+        assert_eq!(change, vec![-3, -4, 8]);
+
+        let doubled = NodFrameBuilder::new()
+            .add_numeric("visits", vec![5, 7, 9, 8, 6, 1])
+            .add_discrete("entity", strings(&["a", "b", "c", "d", "c", "a"]))
+            .add_discrete(
+                "tier",
+                strings(&["gold", "free", "free", "free", "free", "gold"]),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            doubled
+                .align_to(&keys, "entity", &fill, false)
+                .unwrap_err()
+                .to_string(),
+            "column 'entity' row 4: key 'c' appears on more than one row"
+        );
+        let first = doubled.align_to(&keys, "entity", &fill, true).unwrap();
+        assert_eq!(first["visits"].to_string(), "visits: [9, 5, 8]");
+        assert!(monday.align_to(&keys, "visits", &fill, false).is_err());
+        assert_eq!(
+            monday
+                .align_to(&[], "entity", &fill, false)
+                .unwrap()
+                .shape(),
+            (0, 3)
+        );
+    }
+}
@@ -1,11 +1,366 @@
-use csv::{Reader, StringRecord, Writer};
+use csv::{ByteRecord, Position, ReaderBuilder, Writer};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
+use std::fmt::Write as _;
 use std::fs::File;
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::Index;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "encoding")]
+pub use encoding::CsvEncoding;
+#[cfg(feature = "excel")]
+mod excel;
+#[cfg(feature = "excel")]
+pub use excel::{frame_from_xlsx, xlsx_sheet_names};
+mod arith;
+mod change;
+mod check;
+mod codec;
+mod cond;
+mod cut;
+mod expr;
+mod fill;
+mod group;
+mod info;
+mod inspect;
+mod join;
+mod load;
+mod merge;
+mod parse;
+mod preview;
+mod profile;
+mod report;
+mod sidecar;
+mod sketch;
+mod snapshot;
+mod spill;
+mod stats;
+mod stream;
+mod trace;
+pub use arith::{FrameOpOptions, Op};
+pub use change::ZeroDivPolicy;
+pub use check::{Check, CheckFailure, CheckReport};
+pub use codec::{Codec, DeltaColumn, EncodedColumn, EncodedFrame, HeapSize, RleColumn};
+pub use cond::{CaseWhen, Cond};
+pub use cut::OutOfRange;
+pub use fill::FillDirection;
+pub use group::{AggFn, GroupBy, ResampleOptions, TransformFn};
+pub use inspect::{inspect_csv, ColumnReport, CsvReport};
+pub use join::{CrossJoinOptions, JoinFill, JoinKind};
+use load::ColumnWatch;
+pub use load::{frame_from_csv_with_report, LoadEvent, LoadReport};
+pub use merge::InterleaveOptions;
+use parse::ParseFn;
+pub use parse::{ColumnParsers, Parsers};
+pub use profile::{ColumnProfile, Drift, DriftReport, DriftThresholds, FrameProfile};
+pub use report::{read_csv_report, CsvReportWriter, ReportSection};
+pub use sidecar::frame_from_csv_enforcing_schema;
+pub use sketch::{HyperLogLog, TopKSketch};
+pub use spill::{frame_from_csv_spilled, SpillColumn, SpilledColumn, SpilledFrame};
+pub use stats::{ColumnStats, OutlierMethod};
+pub use stream::{aggregate_csv, aggregate_csv_total};
+pub use trace::OpRecord;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "rayon")]
+pub use parallel::frame_from_csv_parallel;
+#[cfg(feature = "polars")]
+mod polars;
+#[cfg(feature = "polars")]
+pub use self::polars::frame_from_polars;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{frame_from_sql, IfExists};
+
+// NodNum is the value type of numeric columns. Arithmetic on it gives T back,
+// conversions to and from f64 go through NumCast, and text goes through
+// Display and FromStr. Every primitive number implements it, f32 and f64
+// included. It is 'static so csv parsers can be stored apart from their type
+pub trait NodNum:
+    num_traits::Num + num_traits::NumCast + PartialOrd + Clone + fmt::Display + FromStr + 'static
+{
+}
+
+impl<T> NodNum for T where
+    T: num_traits::Num
+        + num_traits::NumCast
+        + PartialOrd
+        + Clone
+        + fmt::Display
+        + FromStr
+        + 'static
+{
+}
+
+// NodFrameError describes why a frame operation could not be completed
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodFrameError {
+    LengthMismatch {
+        key: String,
+        expected: usize,
+        found: usize,
+    },
+    DuplicateKey(String),
+    InvalidKey {
+        key: String,
+        reason: &'static str,
+    },
+    KeyCountMismatch {
+        keys: usize,
+        columns: usize,
+    },
+    // row of frame_from_rows gave found cells for expected keys
+    CellCountMismatch {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    MissingColumn(String),
+    KindMismatch {
+        key: String,
+        expected: ColKind,
+        found: ColKind,
+    },
+    RowConversion(usize),
+    ValueConversion {
+        key: String,
+        row: usize,
+    },
+    InvalidValue {
+        key: String,
+        row: usize,
+        message: String,
+    },
+    InvalidArgument(String),
+    OutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    InvalidRange {
+        start: usize,
+        end: usize,
+        len: usize,
+    },
+    UnexpectedColumn(String),
+    ParseError {
+        key: String,
+        line: u64,
+        value: String,
+    },
+    RaggedRow {
+        line: u64,
+        expected: usize,
+        found: usize,
+    },
+    InvalidEncoding {
+        encoding: &'static str,
+        offset: usize,
+    },
+    UnsupportedType {
+        key: String,
+        dtype: String,
+    },
+    WriteFailed {
+        row: usize,
+        message: String,
+    },
+    InvalidSnapshot(String),
+    InvalidSchema(String),
+    ColumnOrder {
+        key: String,
+        expected: usize,
+        found: usize,
+    },
+    ChecksFailed(CheckReport),
+    ParserFailed {
+        key: String,
+        line: u64,
+        value: String,
+        message: String,
+    },
+    FilterKindMismatch {
+        key: String,
+        column: ColKind,
+        value: ColKind,
+    },
+    // comp is a range comparison on a discrete column without a level order
+    UnorderedComparison {
+        key: String,
+        comp: Comp,
+    },
+    // repeated is the number of values occurring more than once, examples
+    // holds the first few of them with all their rows
+    NotUnique {
+        key: String,
+        repeated: usize,
+        examples: Vec<(String, Vec<usize>)>,
+    },
+    // operation on key gives fractions that an integer frame would truncate
+    NeedsFloat {
+        key: String,
+        operation: &'static str,
+    },
+}
+
+impl fmt::Display for NodFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodFrameError::LengthMismatch {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column '{}' has {} rows, expected {}",
+                key, found, expected
+            ),
+            NodFrameError::DuplicateKey(key) => write!(f, "duplicate column key '{}'", key),
+            NodFrameError::InvalidKey { key, reason } => {
+                write!(f, "invalid column key {:?}: {}", key, reason)
+            }
+            NodFrameError::KeyCountMismatch { keys, columns } => write!(
+                f,
+                "{} keys were given for {} columns of data",
+                keys, columns
+            ),
+            NodFrameError::CellCountMismatch {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} has {} cells where {} keys were given",
+                row, found, expected
+            ),
+            NodFrameError::MissingColumn(key) => write!(f, "no column named '{}'", key),
+            NodFrameError::KindMismatch {
+                key,
+                expected,
+                found,
+            } => write!(f, "column '{}' is {}, expected {}", key, found, expected),
+            NodFrameError::RowConversion(row) => {
+                write!(f, "row {} could not be converted from its cells", row)
+            }
+            NodFrameError::ValueConversion { key, row } => write!(
+                f,
+                "value in column '{}' at row {} cannot be represented in the target type",
+                key, row
+            ),
+            NodFrameError::InvalidValue { key, row, message } => {
+                write!(f, "column '{}' row {}: {}", key, row, message)
+            }
+            NodFrameError::InvalidArgument(message) => write!(f, "{}", message),
+            NodFrameError::OutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+            NodFrameError::InvalidRange { start, end, len } if start > end => write!(
+                f,
+                "range {}..{} starts after it ends (length {})",
+                start, end, len
+            ),
+            NodFrameError::InvalidRange { start, end, len } => write!(
+                f,
+                "range {}..{} is out of bounds for length {}",
+                start, end, len
+            ),
+            NodFrameError::UnexpectedColumn(key) => {
+                write!(f, "column '{}' is not part of the schema", key)
+            }
+            NodFrameError::ParseError { key, line, value } => write!(
+                f,
+                "line {}: value '{}' in column '{}' is not numeric",
+                line, value, key
+            ),
+            NodFrameError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} fields, found {}",
+                line, expected, found
+            ),
+            NodFrameError::InvalidEncoding { encoding, offset } => {
+                write!(f, "invalid {} data at byte {}", encoding, offset)
+            }
+            NodFrameError::UnsupportedType { key, dtype } => {
+                write!(f, "column '{}' has unsupported type {}", key, dtype)
+            }
+            NodFrameError::WriteFailed { row, message } => {
+                write!(f, "writing row {} failed: {}", row, message)
+            }
+            NodFrameError::InvalidSnapshot(message) => write!(f, "invalid snapshot: {}", message),
+            NodFrameError::InvalidSchema(message) => write!(f, "invalid schema: {}", message),
+            NodFrameError::ColumnOrder {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "column '{}' is at position {}, expected {}",
+                key, found, expected
+            ),
+            NodFrameError::ChecksFailed(report) => write!(f, "{}", report),
+            NodFrameError::ParserFailed {
+                key,
+                line,
+                value,
+                message,
+            } => write!(
+                f,
+                "line {}: value '{}' in column '{}' failed to parse: {}",
+                line, value, key, message
+            ),
+            NodFrameError::FilterKindMismatch { key, column, value } => write!(
+                f,
+                "column '{}' is {} and cannot be filtered by a {} value",
+                key, column, value
+            ),
+            NodFrameError::UnorderedComparison { key, comp } => write!(
+                f,
+                "column '{}' has no level order for {:?}, see set_order",
+                key, comp
+            ),
+            NodFrameError::NotUnique {
+                key,
+                repeated,
+                examples,
+            } => {
+                let examples: Vec<String> = examples
+                    .iter()
+                    .map(|(value, rows)| {
+                        let rows: Vec<String> = rows.iter().map(|r| r.to_string()).collect();
+                        format!("'{}' at rows {}", value, rows.join(", "))
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "column '{}' is not unique, {} values repeat: {}",
+                    key,
+                    repeated,
+                    examples.join("; ")
+                )
+            }
+            NodFrameError::NeedsFloat { key, operation } => write!(
+                f,
+                "{} of column '{}' needs a float frame, an integer frame would truncate it",
+                operation, key
+            ),
+        }
+    }
+}
+
+impl Error for NodFrameError {}
 
 // Comp enum for filtering
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Comp {
     Eq,
     Geq,
@@ -15,6 +370,33 @@ pub enum Comp {
     Not,
 }
 
+// FilterValue is what filter compares a column against: Num for numeric
+// columns and Str for discrete ones
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterValue<T> {
+    Num(T),
+    Str(String),
+}
+
+impl<T> FilterValue<T> {
+    // kind is the column kind the value can filter
+    pub fn kind(&self) -> ColKind {
+        match self {
+            FilterValue::Num(_) => ColKind::Numeric,
+            FilterValue::Str(_) => ColKind::Discrete,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for FilterValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterValue::Num(v) => write!(f, "{}", v),
+            FilterValue::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
 pub fn compare<T: PartialOrd>(a: T, op: &Comp, b: T) -> bool {
     match op {
         Comp::Eq => a.eq(&b),
@@ -26,43 +408,271 @@ pub fn compare<T: PartialOrd>(a: T, op: &Comp, b: T) -> bool {
     }
 }
 
+// natural_cmp orders strings the way people read them: runs of ASCII digits
+// compare by numeric value, so "item2" < "item10" and "1.2.9" < "1.2.10", and
+// everything else compares byte by byte. A digit run of any width compares
+// without overflow. Strings that are equal this way, like "01" and "1", fall
+// back to plain byte order, which puts the run with more leading zeros first
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (x, y) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < x.len() && j < y.len() {
+        if x[i].is_ascii_digit() && y[j].is_ascii_digit() {
+            let ((a_digits, a_end), (b_digits, b_end)) = (digit_run(x, i), digit_run(y, j));
+            let order = a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(b_digits));
+            if order.is_ne() {
+                return order;
+            }
+            (i, j) = (a_end, b_end);
+        } else {
+            if x[i] != y[j] {
+                return x[i].cmp(&y[j]);
+            }
+            (i, j) = (i + 1, j + 1);
+        }
+    }
+    (x.len() - i).cmp(&(y.len() - j)).then_with(|| x.cmp(y))
+}
+
+// digit_run returns the digits starting at start without leading zeros, and
+// the position after the run
+fn digit_run(s: &[u8], start: usize) -> (&[u8], usize) {
+    let end = start + s[start..].iter().take_while(|c| c.is_ascii_digit()).count();
+    let zeros = s[start..end].iter().take_while(|c| **c == b'0').count();
+    (&s[start + zeros..end], end)
+}
+
+// compare_natural is compare for strings under natural_cmp
+pub fn compare_natural(a: &str, op: &Comp, b: &str) -> bool {
+    compare(natural_cmp(a, b), op, std::cmp::Ordering::Equal)
+}
+
+// SortMode picks how sort_by_mode orders discrete columns, numeric columns
+// always sort by value
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortMode {
+    #[default]
+    Lexical,
+    // Natural orders with natural_cmp
+    Natural,
+}
+
+// ValueOrder picks the order of distinct values: FirstSeen in order of first
+// occurrence, Sorted ascending (NaN last for numeric values)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ValueOrder {
+    #[default]
+    FirstSeen,
+    Sorted,
+}
+
+// Mask marks rows to keep (true) or drop (false)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mask {
+    bits: Vec<bool>,
+}
+
+impl Mask {
+    pub fn new(bits: Vec<bool>) -> Mask {
+        Mask { bits }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[bool] {
+        &self.bits
+    }
+
+    pub fn count_true(&self) -> usize {
+        self.bits.iter().filter(|b| **b).count()
+    }
+
+    // indices returns the positions of true values in ascending order
+    pub fn indices(&self) -> Vec<usize> {
+        self.bits
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl From<Vec<bool>> for Mask {
+    fn from(bits: Vec<bool>) -> Mask {
+        Mask { bits }
+    }
+}
+
+// Fnv64 is the 64-bit FNV-1a hash. Strings are written with their length
+// first so neighbouring values cannot run together
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Fnv64 {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write(&(s.len() as u64).to_le_bytes());
+        self.write(s.as_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 // Column trait for general columns
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Column<T> {
     Numeric(NumericColumn<T>),
     Discrete(DiscreteColumn),
 }
 
-impl<
-        T: Clone
-            + Eq
-            + std::hash::Hash
-            + Add
-            + Div
-            + Mul
-            + Sub
-            + core::cmp::PartialOrd
-            + std::string::ToString,
-    > Column<T>
-{
-    fn get_key(&self) -> &String {
+impl<T: NodNum> Column<T> {
+    pub fn get_key(&self) -> &String {
         match self {
             Self::Discrete(x) => &x.key,
             Self::Numeric(x) => &x.key,
         }
     }
 
-    fn get_num(&self, index: usize) -> Option<&T> {
+    pub fn kind(&self) -> ColKind {
+        match self {
+            Self::Numeric(_) => ColKind::Numeric,
+            Self::Discrete(_) => ColKind::Discrete,
+        }
+    }
+
+    // content_hash is a 64-bit FNV-1a hash of the key, kind and values in
+    // order. Numeric values are hashed through their to_string form, so the
+    // hash does not need T: Hash and is the same on every run and platform
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv64::new();
+        hasher.write_str(self.get_key());
+        hasher.write_str(&self.kind().to_string());
+        hasher.write(&(self.len() as u64).to_le_bytes());
+        for i in 0..self.len() {
+            match self {
+                Self::Numeric(n) => hasher.write_str(&n.get(i).to_string()),
+                Self::Discrete(d) => hasher.write_str(d.get(i)),
+            }
+        }
+        hasher.finish()
+    }
+
+    // memory_usage estimates the heap and Vec bytes held by the column
+    pub fn memory_usage(&self) -> usize {
         match self {
+            Self::Numeric(n) => n.memory_usage(),
+            Self::Discrete(d) => d.memory_usage(),
+        }
+    }
+
+    // get_cell returns a copy of the value at index wrapped in a Cell
+    pub fn get_cell(&self, index: usize) -> Cell<T> {
+        match self {
+            Self::Numeric(n) => Cell::Num(n.get(index).clone()),
+            Self::Discrete(d) => Cell::Str(String::from(d.get(index))),
+        }
+    }
+
+    // as_numeric returns the inner column if it is numeric
+    pub fn as_numeric(&self) -> Option<&NumericColumn<T>> {
+        match self {
+            Self::Numeric(n) => Some(n),
             Self::Discrete(_) => None,
-            Self::Numeric(n) => Some(n.get(index)),
         }
     }
 
-    fn filter_array(&self, comp: Comp, val: Option<T>, str_val: Option<String>) -> Vec<bool> {
+    // as_discrete returns the inner column if it is discrete
+    pub fn as_discrete(&self) -> Option<&DiscreteColumn> {
+        match self {
+            Self::Discrete(d) => Some(d),
+            Self::Numeric(_) => None,
+        }
+    }
+
+    // get_cell_checked returns None when index is past the end of the column
+    pub fn get_cell_checked(&self, index: usize) -> Option<Cell<T>> {
+        if index < self.len() {
+            Some(self.get_cell(index))
+        } else {
+            None
+        }
+    }
+
+    fn with_key(mut self, key: &str) -> Column<T> {
+        match &mut self {
+            Self::Numeric(n) => n.key = String::from(key),
+            Self::Discrete(d) => d.key = String::from(key),
+        }
+        self
+    }
+
+    // cell_string renders a value the same way to_csv writes it
+    fn cell_string(&self, index: usize) -> String {
+        self.cell_string_with(index, FloatFormat::Shortest)
+    }
+
+    // cell_string_with is cell_string with numbers written by format
+    fn cell_string_with(&self, index: usize, format: FloatFormat) -> String {
+        match self {
+            Self::Numeric(n) => format.format(n.get(index)),
+            Self::Discrete(d) => String::from(d.get(index)),
+        }
+    }
+
+    // check_levels is DiscreteColumn::check_levels, numeric columns pass
+    pub(crate) fn check_levels(&self) -> Result<(), NodFrameError> {
+        match self {
+            Self::Numeric(_) => Ok(()),
+            Self::Discrete(d) => d.check_levels(),
+        }
+    }
+
+    // cmp_rows orders rows a and b by their values, NaN last in either
+    // direction. Ordered discrete columns compare by level whatever the mode,
+    // see check_levels
+    fn cmp_rows(&self, a: usize, b: usize, ascending: bool, mode: SortMode) -> std::cmp::Ordering {
+        let directed = |o: std::cmp::Ordering| if ascending { o } else { o.reverse() };
+        match self {
+            Self::Numeric(n) => {
+                let (a, b) = (&n.items[a], &n.items[b]);
+                match (is_nan(a), is_nan(b)) {
+                    (false, false) => directed(cmp_nan_last(a, b)),
+                    _ => cmp_nan_last(a, b),
+                }
+            }
+            Self::Discrete(d) => directed(match mode {
+                _ if d.levels.is_some() => d.cmp_values(&d.items[a], &d.items[b]),
+                SortMode::Lexical => d.items[a].cmp(&d.items[b]),
+                SortMode::Natural => natural_cmp(&d.items[a], &d.items[b]),
+            }),
+        }
+    }
+
+    fn get_num(&self, index: usize) -> Option<&T> {
         match self {
-            Column::Discrete(d) => d.filter_array(&str_val.unwrap()),
-            Column::Numeric(n) => n.filter_array(&val.unwrap(), comp),
+            Self::Discrete(_) => None,
+            Self::Numeric(n) => Some(n.get(index)),
         }
     }
 
@@ -73,445 +683,7851 @@ impl<
         }
     }
 
+    fn take(&self, indices: &[usize]) -> Column<T> {
+        match self {
+            Column::Numeric(n) => Column::Numeric(n.take(indices)),
+            Column::Discrete(d) => Column::Discrete(d.take(indices)),
+        }
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Column<T> {
+        match self {
+            Column::Numeric(n) => Column::Numeric(n.slice(start, end)),
+            Column::Discrete(d) => Column::Discrete(d.slice(start, end)),
+        }
+    }
+
     fn len(&self) -> usize {
         match self {
             Column::Numeric(n) => n.len(),
             Column::Discrete(d) => d.len(),
         }
     }
+}
+
+impl<T: std::string::ToString> fmt::Display for Column<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Numeric(n) => n.fmt(f),
+            Column::Discrete(d) => d.fmt(f),
+        }
+    }
+}
+
+// ColKind names the two kinds of column a frame can hold
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColKind {
+    Numeric,
+    Discrete,
+}
 
-    fn to_string(&self) -> String {
+impl fmt::Display for ColKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Column::Numeric(n) => n.to_string(),
-            Column::Discrete(d) => d.to_string(),
+            ColKind::Numeric => write!(f, "numeric"),
+            ColKind::Discrete => write!(f, "discrete"),
         }
     }
 }
 
-// DiscreteColumn struct contains only string values
-#[derive(Clone)]
+// Cell holds a single value taken from (or destined for) a column
+#[derive(Clone, Debug, PartialEq)]
+pub enum Cell<T> {
+    Num(T),
+    Str(String),
+}
+
+impl<T> Cell<T> {
+    pub fn kind(&self) -> ColKind {
+        match self {
+            Cell::Num(_) => ColKind::Numeric,
+            Cell::Str(_) => ColKind::Discrete,
+        }
+    }
+}
+
+// DiscreteColumn struct contains only string values. Values are shared
+// strings, so equal values can share one allocation, see intern
+#[derive(Clone, Debug)]
 pub struct DiscreteColumn {
     key: String,
-    items: Vec<String>,
+    items: Vec<Arc<str>>,
+    // level order set by set_order, lowest first
+    levels: Option<Vec<String>>,
 }
 
 impl DiscreteColumn {
-    // Take a binary view of the numeric column, true values are preserved, false values are ignored
-    pub fn binary_view(&self, picker: &[bool]) -> DiscreteColumn {
+    pub fn new(key: &str, items: Vec<String>) -> DiscreteColumn {
+        DiscreteColumn::from_shared(key, items.into_iter().map(Arc::from).collect())
+    }
+
+    // from_shared builds a column from values that may already share
+    // allocations, such as those of another column
+    pub fn from_shared(key: &str, items: Vec<Arc<str>>) -> DiscreteColumn {
         DiscreteColumn {
-            key: self.key.clone(),
-            items: self
-                .items
-                .iter()
-                .zip(picker.iter())
-                .filter(|(_, b)| **b)
-                .map(|(a, _)| a.clone())
+            key: String::from(key),
+            items,
+            levels: None,
+        }
+    }
+
+    // interned builds a column storing each distinct value once, every row
+    // holding it points to the same allocation
+    pub fn interned<S: AsRef<str>>(
+        key: &str,
+        items: impl IntoIterator<Item = S>,
+    ) -> DiscreteColumn {
+        let mut interner = Interner::default();
+        DiscreteColumn::from_shared(
+            key,
+            items
+                .into_iter()
+                .map(|s| interner.intern(s.as_ref()))
                 .collect(),
+        )
+    }
+
+    // intern is the column with equal values sharing one allocation
+    pub fn intern(&self) -> DiscreteColumn {
+        let mut column = DiscreteColumn::interned(&self.key, self.iter());
+        column.levels = self.levels.clone();
+        column
+    }
+
+    // set_order makes the column ordered categorical: levels lists every
+    // value the column may hold, lowest first. Ordered columns compare by
+    // level in filter_ordered, min, max and sort_by. A value missing from
+    // levels or a level given twice is an error and leaves the column as it was
+    pub fn set_order(&mut self, levels: &[&str]) -> Result<(), NodFrameError> {
+        let mut seen = HashSet::new();
+        if let Some(level) = levels.iter().find(|l| !seen.insert(**l)) {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "level '{}' is listed more than once",
+                level
+            )));
+        }
+        if let Some(row) = self.items.iter().position(|s| !seen.contains(&**s)) {
+            return Err(self.not_a_level(row, &self.items[row]));
         }
+        self.levels = Some(levels.iter().map(|l| l.to_string()).collect());
+        Ok(())
     }
 
-    pub fn slice(&self, start: usize, end: usize) -> DiscreteColumn {
-        DiscreteColumn {
+    fn not_a_level(&self, row: usize, value: &str) -> NodFrameError {
+        NodFrameError::InvalidValue {
             key: self.key.clone(),
-            items: self.items[start..end].to_vec(),
+            row,
+            message: format!("'{}' is not one of the levels", value),
         }
     }
 
-    pub fn values(&self) -> HashSet<String> {
-        self.items.iter().cloned().collect()
+    // check_level fails when the column is ordered and value, to be written
+    // at row, is not one of its levels
+    pub(crate) fn check_level(&self, row: usize, value: &str) -> Result<(), NodFrameError> {
+        match &self.levels {
+            Some(levels) if !levels.iter().any(|l| l == value) => Err(self.not_a_level(row, value)),
+            _ => Ok(()),
+        }
     }
 
-    pub fn len(&self) -> usize {
-        self.items.len()
+    // check_levels fails on the first value of an ordered column that is not
+    // one of its levels. Every writer checks its values, so this only finds
+    // columns whose values were changed behind the frame's back
+    pub(crate) fn check_levels(&self) -> Result<(), NodFrameError> {
+        let Some(levels) = &self.levels else {
+            return Ok(());
+        };
+        let levels: HashSet<&str> = levels.iter().map(|l| l.as_str()).collect();
+        match self.items.iter().position(|s| !levels.contains(&**s)) {
+            Some(row) => Err(self.not_a_level(row, &self.items[row])),
+            None => Ok(()),
+        }
     }
 
-    pub fn get(&self, index: usize) -> &String {
-        &self.items[index]
+    // clear_order makes the column unordered again
+    pub fn clear_order(&mut self) {
+        self.levels = None;
     }
 
-    pub fn filter_array(&self, val: &String) -> Vec<bool> {
-        let mut filter = Vec::new();
-        for n in self.items.iter() {
-            if val.eq(n) {
-                filter.push(true)
-            } else {
-                filter.push(false)
-            }
-        }
-        filter
+    // ordering returns the levels set by set_order, lowest first
+    pub fn ordering(&self) -> Option<&[String]> {
+        self.levels.as_deref()
     }
 
-    pub fn to_string(&self) -> String {
-        let mut result = self.key.clone();
-        let data = self.items.join(", ");
-        result.push_str(": [");
-        result.push_str(&data);
-        result.push_str("]");
-        result
+    // rank is the position of value among the levels, None when the column
+    // is unordered or value is not a level
+    pub fn rank(&self, value: &str) -> Option<usize> {
+        self.levels.as_ref()?.iter().position(|l| l == value)
     }
-}
 
-// NumericColumn struct is roughly equivalent to pandas Series
-#[derive(Clone)]
-pub struct NumericColumn<T> {
-    key: String,
-    items: Vec<T>,
-}
+    // filter_ordered compares every value with val by level, the column must
+    // be ordered and val one of its levels
+    pub fn filter_ordered(&self, val: &str, comp: Comp) -> Result<Vec<bool>, NodFrameError> {
+        if self.levels.is_none() {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "column '{}' has no level order, see set_order",
+                self.key
+            )));
+        }
+        let target = self.rank(val).ok_or_else(|| {
+            NodFrameError::InvalidArgument(format!(
+                "'{}' is not a level of column '{}'",
+                val, self.key
+            ))
+        })?;
+        let ranks: HashMap<&str, usize> = self
+            .levels
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, l)| (l.as_str(), i))
+            .collect();
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(row, s)| match ranks.get(&**s) {
+                Some(rank) => Ok(compare(*rank, &comp, target)),
+                None => Err(self.not_a_level(row, s)),
+            })
+            .collect()
+    }
 
-impl<
-        T: Clone
-            + Eq
-            + std::hash::Hash
-            + Add
-            + Div
-            + Mul
-            + Sub
-            + core::cmp::PartialOrd
-            + std::string::ToString,
-    > NumericColumn<T>
-{
-    // Take a binary view of the numeric column, true values are preserved, false values are ignored
-    pub fn binary_view(&self, picker: &[bool]) -> NumericColumn<T> {
-        NumericColumn {
-            key: self.key.clone(),
-            items: self
-                .items
+    // filter_comp marks the rows comparing to val with comp: by level when
+    // the column is ordered and val is a level, otherwise Eq and Not test
+    // equality and the range comparisons are an error
+    pub fn filter_comp(&self, val: &str, comp: Comp) -> Result<Vec<bool>, NodFrameError> {
+        match comp {
+            Comp::Eq | Comp::Not if self.rank(val).is_none() => Ok(self
                 .iter()
-                .zip(picker.iter())
-                .filter(|(_, b)| **b)
-                .map(|(a, _)| a.clone())
-                .collect(),
+                .map(|s| (s == val) == (comp == Comp::Eq))
+                .collect()),
+            _ if self.levels.is_some() => self.filter_ordered(val, comp),
+            _ => Err(NodFrameError::UnorderedComparison {
+                key: self.key.clone(),
+                comp,
+            }),
         }
     }
 
-    pub fn slice(&self, start: usize, end: usize) -> NumericColumn<T> {
-        NumericColumn {
-            key: self.key.clone(),
-            items: self.items[start..end].to_vec(),
+    // min is the lowest value, by level when the column is ordered and
+    // lexically otherwise. None for an empty column
+    pub fn min(&self) -> Option<&str> {
+        self.iter().min_by(|a, b| self.cmp_values(a, b))
+    }
+
+    // max is the highest value, see min
+    pub fn max(&self) -> Option<&str> {
+        self.iter().max_by(|a, b| self.cmp_values(a, b))
+    }
+
+    // cmp_values orders two values of the column by level when it is
+    // ordered, lexically otherwise. A value outside the levels has no rank,
+    // callers that sort run check_levels first so it never goes unreported
+    fn cmp_values(&self, a: &str, b: &str) -> std::cmp::Ordering {
+        match &self.levels {
+            Some(_) => self.rank(a).cmp(&self.rank(b)),
+            None => a.cmp(b),
         }
     }
 
-    pub fn values(&self) -> HashSet<T> {
-        self.items.iter().cloned().collect()
+    pub fn key(&self) -> &str {
+        &self.key
     }
 
-    pub fn len(&self) -> usize {
-        self.items.len()
+    pub fn iter(&self) -> DiscreteIter<'_> {
+        DiscreteIter {
+            inner: self.items.iter(),
+        }
     }
 
-    pub fn get(&self, index: usize) -> &T {
-        &self.items[index]
+    // as_slice gives the shared values, use iter or get for plain &str
+    pub fn as_slice(&self) -> &[Arc<str>] {
+        &self.items
     }
 
-    pub fn filter_array(&self, val: &T, comparison: Comp) -> Vec<bool> {
+    // parse_as parses every value as E, failing on the first value that does
+    // not parse
+    pub fn parse_as<E: FromStr>(&self) -> Result<Vec<E>, NodFrameError> {
         self.items
             .iter()
-            .map(|x| compare(x, &comparison, val))
+            .enumerate()
+            .map(|(row, s)| {
+                s.parse::<E>().map_err(|_| NodFrameError::InvalidValue {
+                    key: self.key.clone(),
+                    row,
+                    message: format!("'{}' does not parse", s),
+                })
+            })
             .collect()
     }
 
-    pub fn to_string(&self) -> String {
-        let mut result = self.key.clone();
-        let str_form: Vec<String> = self.items.iter().map(|x| x.to_string()).collect();
-        result.push_str(": [");
-        result.push_str(&str_form.join(", "));
-        result.push_str("]");
+    // parse_as_lenient is parse_as with None for values that do not parse
+    pub fn parse_as_lenient<E: FromStr>(&self) -> Vec<Option<E>> {
+        self.items.iter().map(|s| s.parse::<E>().ok()).collect()
+    }
 
-        result
+    // argsort_natural returns the row order that sorts the column under
+    // natural_cmp, ties keep their order
+    pub fn argsort_natural(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.items.len()).collect();
+        order.sort_by(|a, b| natural_cmp(&self.items[*a], &self.items[*b]));
+        order
     }
-}
 
-// Build function for building a numeric column
-pub fn build_column_numeric<T>(key: &str, data: Vec<T>) -> Column<T> {
-    Column::Numeric(NumericColumn {
-        key: String::from(key),
-        items: data,
-    })
-}
+    pub fn into_vec(self) -> Vec<String> {
+        self.iter().map(String::from).collect()
+    }
 
-// Build function for building a discrete (String) column
-pub fn build_column_discrete<T>(key: &str, data: Vec<String>) -> Column<T> {
-    Column::Discrete(DiscreteColumn {
-        key: String::from(key),
-        items: data,
-    })
+    // map_indexed builds a column from f applied to each row index and value
+    pub fn map_indexed(&self, f: impl Fn(usize, &str) -> String) -> DiscreteColumn {
+        DiscreteColumn {
+            key: self.key.clone(),
+            items: self
+                .items
+                .iter()
+                .enumerate()
+                .map(|(i, s)| Arc::from(f(i, s)))
+                .collect(),
+            levels: None,
+        }
+    }
+
+    // Take a binary view of the numeric column, true values are preserved, false values are ignored
+    pub fn binary_view(&self, picker: &[bool]) -> DiscreteColumn {
+        DiscreteColumn {
+            key: self.key.clone(),
+            items: self
+                .items
+                .iter()
+                .zip(picker.iter())
+                .filter(|(_, b)| **b)
+                .map(|(a, _)| a.clone())
+                .collect(),
+            levels: self.levels.clone(),
+        }
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> DiscreteColumn {
+        DiscreteColumn {
+            key: self.key.clone(),
+            items: self.items[start..end].to_vec(),
+            levels: self.levels.clone(),
+        }
+    }
+
+    // slice_checked is slice returning an error instead of panicking
+    pub fn slice_checked(&self, start: usize, end: usize) -> Result<DiscreteColumn, NodFrameError> {
+        check_range(start, end, self.items.len())?;
+        Ok(self.slice(start, end))
+    }
+
+    // take builds a column from the values at the given indices, in that order
+    pub fn take(&self, indices: &[usize]) -> DiscreteColumn {
+        DiscreteColumn {
+            key: self.key.clone(),
+            items: indices.iter().map(|i| self.items[*i].clone()).collect(),
+            levels: self.levels.clone(),
+        }
+    }
+
+    pub fn values(&self) -> HashSet<String> {
+        self.iter().map(String::from).collect()
+    }
+
+    // unique returns the distinct values in order of first occurrence, unlike
+    // values the order is the same on every run
+    pub fn unique(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.iter()
+            .filter(|s| seen.insert(*s))
+            .map(String::from)
+            .collect()
+    }
+
+    pub fn is_unique(&self) -> bool {
+        let mut seen = HashSet::new();
+        self.iter().all(|s| seen.insert(s))
+    }
+
+    // duplicate_values lists the values occurring more than once with their
+    // count, most frequent first and ties in order of first occurrence
+    pub fn duplicate_values(&self) -> Vec<(String, usize)> {
+        repeated_rows(self.iter())
+            .into_iter()
+            .map(|rows| (String::from(self.get(rows[0])), rows.len()))
+            .collect()
+    }
+
+    // unique_ordered is unique in the given order
+    pub fn unique_ordered(&self, order: ValueOrder) -> Vec<String> {
+        let mut unique = self.unique();
+        if order == ValueOrder::Sorted {
+            unique.sort();
+        }
+        unique
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &str {
+        &self.items[index]
+    }
+
+    pub fn get_checked(&self, index: usize) -> Option<&str> {
+        self.items.get(index).map(|s| &**s)
+    }
+
+    // memory_usage estimates the bytes held by the values: the Vec of shared
+    // pointers plus, once per allocation however many rows share it, the
+    // reference counts and the text
+    pub fn memory_usage(&self) -> usize {
+        let mut buffers = HashSet::new();
+        let shared: usize = self
+            .items
+            .iter()
+            .filter(|s| buffers.insert(Arc::as_ptr(s) as *const u8))
+            .map(|s| buffer_bytes(s))
+            .sum();
+        std::mem::size_of::<Vec<Arc<str>>>()
+            + self.items.len() * std::mem::size_of::<Arc<str>>()
+            + shared
+    }
+
+    // estimated_dictionary_savings is how many bytes storing the column as a
+    // dictionary of distinct strings plus one u32 code per row would save
+    pub fn estimated_dictionary_savings(&self) -> usize {
+        let distinct: HashSet<&str> = self.iter().collect();
+        let dictionary = std::mem::size_of::<Vec<String>>()
+            + distinct
+                .iter()
+                .map(|s| std::mem::size_of::<String>() + s.len())
+                .sum::<usize>()
+            + std::mem::size_of::<Vec<u32>>()
+            + self.items.len() * std::mem::size_of::<u32>();
+        self.memory_usage().saturating_sub(dictionary)
+    }
+
+    pub fn filter_array(&self, val: &str) -> Vec<bool> {
+        let mut filter = Vec::new();
+        for n in self.iter() {
+            if val == n {
+                filter.push(true)
+            } else {
+                filter.push(false)
+            }
+        }
+        filter
+    }
+
+    // value_counts counts each distinct value, most frequent first and ties
+    // in value order
+    pub fn value_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for s in self.iter() {
+            *counts.entry(s).or_default() += 1;
+        }
+        ranked_counts(counts.into_iter().map(|(s, n)| (String::from(s), n)))
+    }
+}
+
+// repeated_rows groups the rows of the values occurring more than once, the
+// largest group first and ties in order of first occurrence
+fn repeated_rows<K: Eq + std::hash::Hash>(values: impl Iterator<Item = K>) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<K, Vec<usize>> = HashMap::new();
+    for (row, value) in values.enumerate() {
+        groups.entry(value).or_default().push(row);
+    }
+    let mut repeated: Vec<Vec<usize>> = groups.into_values().filter(|r| r.len() > 1).collect();
+    repeated.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+    repeated
+}
+
+// ranked_counts orders value counts for value_counts
+pub(crate) fn ranked_counts(
+    counts: impl IntoIterator<Item = (String, usize)>,
+) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+impl IntoIterator for DiscreteColumn {
+    type Item = String;
+    type IntoIter = std::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a DiscreteColumn {
+    type Item = &'a str;
+    type IntoIter = DiscreteIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// DiscreteIter yields the values of a DiscreteColumn as &str
+#[derive(Clone, Debug)]
+pub struct DiscreteIter<'a> {
+    inner: std::slice::Iter<'a, Arc<str>>,
+}
+
+impl<'a> Iterator for DiscreteIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.inner.next().map(|s| &**s)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for DiscreteIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|s| &**s)
+    }
+}
+
+impl ExactSizeIterator for DiscreteIter<'_> {}
+
+// buffer_bytes is the heap allocation of an Arc<str> holding s: the two
+// reference counts and the text
+pub(crate) fn buffer_bytes(s: &str) -> usize {
+    2 * std::mem::size_of::<usize>() + s.len()
+}
+
+// Interner hands out one shared allocation per distinct string
+#[derive(Default)]
+pub(crate) struct Interner {
+    seen: HashSet<Arc<str>>,
+    bytes: usize,
+}
+
+impl Interner {
+    pub(crate) fn intern(&mut self, s: &str) -> Arc<str> {
+        match self.seen.get(s) {
+            Some(shared) => shared.clone(),
+            None => {
+                let shared: Arc<str> = Arc::from(s);
+                self.bytes += buffer_bytes(s);
+                self.seen.insert(shared.clone());
+                shared
+            }
+        }
+    }
+
+    // bytes is the buffer_bytes of every string handed out, each counted once
+    // as DiscreteColumn::memory_usage counts shared values
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+// collecting into a column leaves the key empty, use collect_column to name it
+impl FromIterator<String> for DiscreteColumn {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        DiscreteColumn::new("", iter.into_iter().collect())
+    }
+}
+
+impl Index<usize> for DiscreteColumn {
+    type Output = str;
+
+    fn index(&self, index: usize) -> &str {
+        &self.items[index]
+    }
+}
+
+impl fmt::Display for DiscreteColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shown: Vec<Cow<str>> = self.items.iter().map(|s| display_value(s)).collect();
+        write!(f, "{}: [{}]", self.key, shown.join(", "))
+    }
+}
+
+// values longer than this are cut by display_value
+const DISPLAY_MAX_CHARS: usize = 80;
+
+// display_value makes a value safe to print in a ", " separated list. Values
+// over DISPLAY_MAX_CHARS keep their first characters followed by an ellipsis
+// and the true length, e.g. "aaa…[10000 chars]". Values holding a comma, a
+// double quote or a line break are then quoted like csv, quotes doubled
+fn display_value(value: &str) -> Cow<'_, str> {
+    let mut out = Cow::Borrowed(value);
+    if let Some((cut, _)) = value.char_indices().nth(DISPLAY_MAX_CHARS) {
+        let len = value.chars().count();
+        out = Cow::Owned(format!("{}…[{} chars]", &value[..cut], len));
+    }
+    if out.contains([',', '"', '\n', '\r']) {
+        out = Cow::Owned(format!("\"{}\"", out.replace('"', "\"\"")));
+    }
+    out
+}
+
+// NumericColumn struct is roughly equivalent to pandas Series
+#[derive(Clone)]
+pub struct NumericColumn<T> {
+    key: String,
+    items: Vec<T>,
+    // computed on first use, see min_cached, and dropped by items_mut
+    summary: OnceLock<Summary<T>>,
+}
+
+// the cache is left out, two columns with the same values debug the same
+impl<T: fmt::Debug> fmt::Debug for NumericColumn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NumericColumn")
+            .field("key", &self.key)
+            .field("items", &self.items)
+            .finish()
+    }
+}
+
+// Summary is what a NumericColumn caches about its values: min and max skip
+// NaN like min and max, count is the number of values that are not NaN
+#[derive(Clone, Debug)]
+struct Summary<T> {
+    min: Option<T>,
+    max: Option<T>,
+    sum: T,
+    count: usize,
 }
 
-#[derive(Clone)]
-pub struct NodFrame<T> {
-    columns: Vec<Column<T>>,
-    column_idx: HashMap<String, usize>,
-    num_rows: usize,
-    num_cols: usize,
-}
+impl<T: NodNum> NumericColumn<T> {
+    // Take a binary view of the numeric column, true values are preserved, false values are ignored
+    pub fn binary_view(&self, picker: &[bool]) -> NumericColumn<T> {
+        NumericColumn {
+            key: self.key.clone(),
+            items: self
+                .items
+                .iter()
+                .zip(picker.iter())
+                .filter(|(_, b)| **b)
+                .map(|(a, _)| a.clone())
+                .collect(),
+            summary: OnceLock::new(),
+        }
+    }
+
+    pub fn slice(&self, start: usize, end: usize) -> NumericColumn<T> {
+        NumericColumn {
+            key: self.key.clone(),
+            items: self.items[start..end].to_vec(),
+            summary: OnceLock::new(),
+        }
+    }
+
+    // slice_checked is slice returning an error instead of panicking
+    pub fn slice_checked(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<NumericColumn<T>, NodFrameError> {
+        check_range(start, end, self.items.len())?;
+        Ok(self.slice(start, end))
+    }
+
+    // take builds a column from the values at the given indices, in that order
+    pub fn take(&self, indices: &[usize]) -> NumericColumn<T> {
+        NumericColumn {
+            key: self.key.clone(),
+            items: indices.iter().map(|i| self.items[*i].clone()).collect(),
+            summary: OnceLock::new(),
+        }
+    }
+
+    pub fn values(&self) -> HashSet<T>
+    where
+        T: Eq + std::hash::Hash,
+    {
+        self.items.iter().cloned().collect()
+    }
+
+    // unique returns the distinct values in order of first occurrence. Values
+    // are told apart by their text form, so it works for floats too
+    pub fn unique(&self) -> Vec<T> {
+        let mut seen = HashSet::new();
+        self.items
+            .iter()
+            .filter(|x| seen.insert(x.to_string()))
+            .cloned()
+            .collect()
+    }
+
+    // is_unique tells values apart by their text form like unique, so for
+    // floats NaN repeats NaN while 0 and -0 differ
+    pub fn is_unique(&self) -> bool {
+        let mut seen = HashSet::new();
+        self.items.iter().all(|x| seen.insert(x.to_string()))
+    }
+
+    // duplicate_values is DiscreteColumn::duplicate_values, values told apart
+    // as by is_unique
+    pub fn duplicate_values(&self) -> Vec<(T, usize)> {
+        repeated_rows(self.items.iter().map(|x| x.to_string()))
+            .into_iter()
+            .map(|rows| (self.items[rows[0]].clone(), rows.len()))
+            .collect()
+    }
+
+    // unique_ordered is unique in the given order
+    pub fn unique_ordered(&self, order: ValueOrder) -> Vec<T> {
+        let mut unique = self.unique();
+        if order == ValueOrder::Sorted {
+            unique.sort_by(cmp_nan_last);
+        }
+        unique
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &T {
+        &self.items[index]
+    }
+
+    pub fn get_checked(&self, index: usize) -> Option<&T> {
+        self.items.get(index)
+    }
+
+    pub fn sum(&self) -> T {
+        self.items
+            .iter()
+            .fold(T::zero(), |total, x| total + x.clone())
+    }
+
+    // items_mut is the only way to change the values in place, it drops the
+    // cached summary
+    fn items_mut(&mut self) -> &mut Vec<T> {
+        self.summary.take();
+        &mut self.items
+    }
+
+    // summary scans the column once and keeps the result for later calls.
+    // Columns are only shared read-only, so the cache can never go stale
+    fn summary(&self) -> &Summary<T> {
+        self.summary.get_or_init(|| {
+            let mut summary = Summary {
+                min: None,
+                max: None,
+                sum: T::zero(),
+                count: 0,
+            };
+            for x in self.items.iter() {
+                summary.sum = summary.sum + x.clone();
+                if is_nan(x) {
+                    continue;
+                }
+                summary.count += 1;
+                if summary.min.as_ref().is_none_or(|m| x < m) {
+                    summary.min = Some(x.clone());
+                }
+                if summary.max.as_ref().is_none_or(|m| x > m) {
+                    summary.max = Some(x.clone());
+                }
+            }
+            summary
+        })
+    }
+
+    // min_cached is min, scanning the column only on the first call. Slices
+    // and other derived columns start without a cache
+    pub fn min_cached(&self) -> Option<T> {
+        self.summary().min.clone()
+    }
+
+    // max_cached is max, see min_cached
+    pub fn max_cached(&self) -> Option<T> {
+        self.summary().max.clone()
+    }
+
+    // sum_cached is sum, see min_cached
+    pub fn sum_cached(&self) -> T {
+        self.summary().sum.clone()
+    }
+
+    // count_cached is the number of values that are not NaN, see min_cached
+    pub fn count_cached(&self) -> usize {
+        self.summary().count
+    }
+
+    // zip_with combines the values of self and other row by row, the result
+    // keeps self's key
+    fn zip_with(
+        &self,
+        other: &NumericColumn<T>,
+        f: impl Fn(usize, T, T) -> Result<T, NodFrameError>,
+    ) -> Result<NumericColumn<T>, NodFrameError> {
+        if other.len() != self.len() {
+            return Err(NodFrameError::LengthMismatch {
+                key: other.key.clone(),
+                expected: self.len(),
+                found: other.len(),
+            });
+        }
+        let items = self
+            .items
+            .iter()
+            .zip(other.items.iter())
+            .enumerate()
+            .map(|(row, (a, b))| f(row, a.clone(), b.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(NumericColumn {
+            key: self.key.clone(),
+            items,
+            summary: OnceLock::new(),
+        })
+    }
+
+    // add, sub, mul and div are element-wise arithmetic in T, other must have
+    // the same length
+    pub fn add(&self, other: &NumericColumn<T>) -> Result<NumericColumn<T>, NodFrameError> {
+        self.zip_with(other, |_, a, b| Ok(a + b))
+    }
+
+    pub fn sub(&self, other: &NumericColumn<T>) -> Result<NumericColumn<T>, NodFrameError> {
+        self.zip_with(other, |_, a, b| Ok(a - b))
+    }
+
+    pub fn mul(&self, other: &NumericColumn<T>) -> Result<NumericColumn<T>, NodFrameError> {
+        self.zip_with(other, |_, a, b| Ok(a * b))
+    }
+
+    // div truncates for integer columns, a zero divisor is an error naming its
+    // row rather than a panic or an infinity
+    pub fn div(&self, other: &NumericColumn<T>) -> Result<NumericColumn<T>, NodFrameError> {
+        self.zip_with(other, |row, a, b| {
+            if b.is_zero() {
+                return Err(NodFrameError::InvalidValue {
+                    key: other.key.clone(),
+                    row,
+                    message: String::from("division by zero"),
+                });
+            }
+            Ok(a / b)
+        })
+    }
+
+    // sorted_values returns a sorted copy of the values without NaN
+    fn sorted_values(&self) -> Vec<T> {
+        let mut sorted: Vec<T> = self.items.iter().filter(|x| !is_nan(*x)).cloned().collect();
+        sorted.sort_by(cmp_nan_last);
+        sorted
+    }
+
+    // quantile uses the nearest-rank method over the values other than NaN,
+    // so the result is always a value from the column. None for a column
+    // without such values or q outside [0, 1]
+    pub fn quantile(&self, q: f64) -> Option<T> {
+        if !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        let sorted = self.sorted_values();
+        let rank = (q * sorted.len().checked_sub(1)? as f64).round() as usize;
+        Some(sorted[rank].clone())
+    }
+
+    // clip bounds every value into [low, high], either bound may be omitted
+    pub fn clip(
+        &self,
+        low: Option<&T>,
+        high: Option<&T>,
+    ) -> Result<NumericColumn<T>, NodFrameError> {
+        if let (Some(l), Some(h)) = (low, high) {
+            if l > h {
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "clip lower bound {} is above the upper bound {}",
+                    l, h
+                )));
+            }
+        }
+        let items = self
+            .items
+            .iter()
+            .map(|x| match (low, high) {
+                (Some(l), _) if x < l => l.clone(),
+                (_, Some(h)) if x > h => h.clone(),
+                _ => x.clone(),
+            })
+            .collect();
+        Ok(NumericColumn {
+            key: self.key.clone(),
+            items,
+            summary: OnceLock::new(),
+        })
+    }
+
+    // winsorize clips the lowest lower_q and highest upper_q fraction of values
+    // to the quantile at each cutoff, both fractions must be in (0, 0.5]
+    pub fn winsorize(&self, lower_q: f64, upper_q: f64) -> Result<NumericColumn<T>, NodFrameError> {
+        for q in [lower_q, upper_q] {
+            if !(q > 0.0 && q <= 0.5) {
+                return Err(NodFrameError::InvalidArgument(format!(
+                    "winsorize fraction {} is outside (0, 0.5]",
+                    q
+                )));
+            }
+        }
+        let low = self.quantile(lower_q);
+        let high = self.quantile(1.0 - upper_q);
+        self.clip(low.as_ref(), high.as_ref())
+    }
+
+    // histogram counts values in bins of equal width between min and max, the
+    // edges are converted to T (truncated for integer columns) and values are
+    // assigned using the converted edges. Edges that truncate to the same
+    // value are merged, so an integer column may get fewer bins than asked
+    // for. A column holding a single distinct value produces one bin, NaN
+    // values are not counted
+    pub fn histogram(&self, bins: usize) -> Result<Vec<(T, T, usize)>, NodFrameError> {
+        if bins == 0 {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "histogram needs at least one bin",
+            )));
+        }
+        let (min, max) = match (self.min(), self.max()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Ok(Vec::new()),
+        };
+        if min == max {
+            let count = self.items.iter().filter(|x| !is_nan(*x)).count();
+            return Ok(vec![(min, max, count)]);
+        }
+        let (lo, hi) = (to_f64(&min), to_f64(&max));
+        let width = (hi - lo) / bins as f64;
+        let mut edges = vec![min];
+        for i in 1..bins {
+            let edge = T::from(lo + width * i as f64).ok_or_else(|| {
+                NodFrameError::InvalidArgument(String::from("histogram edge is not representable"))
+            })?;
+            edges.push(edge);
+        }
+        edges.push(max);
+        // a repeated edge would make a bin no value can fall into
+        edges.dedup();
+        self.histogram_with_edges(&edges)
+    }
+
+    // histogram_with_edges counts values in [edges[i], edges[i + 1]), the last
+    // bin also includes its upper edge. Values outside the edges and NaN values
+    // are ignored, NaN edges are rejected
+    pub fn histogram_with_edges(&self, edges: &[T]) -> Result<Vec<(T, T, usize)>, NodFrameError> {
+        if edges.len() < 2 || edges.iter().any(is_nan) || edges.windows(2).any(|w| w[0] > w[1]) {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "histogram edges must be at least two ascending values",
+            )));
+        }
+        let last = edges.len() - 1;
+        let mut counts = vec![0; last];
+        for x in self.items.iter() {
+            if is_nan(x) || x < &edges[0] || x > &edges[last] {
+                continue;
+            }
+            let below = edges.partition_point(|e| e <= x);
+            counts[(below - 1).min(last - 1)] += 1;
+        }
+        Ok(edges
+            .windows(2)
+            .zip(counts)
+            .map(|(w, c)| (w[0].clone(), w[1].clone(), c))
+            .collect())
+    }
+
+    pub fn filter_array(&self, val: &T, comparison: Comp) -> Vec<bool> {
+        self.items
+            .iter()
+            .map(|x| compare(x, &comparison, val))
+            .collect()
+    }
+
+    // is_sorted checks that the values are in ascending order
+    pub fn is_sorted(&self) -> bool {
+        self.items.is_sorted()
+    }
+
+    // sorted_range finds the rows matching comparison with binary searches,
+    // the values must be ascending with any NaN at the end. Comp::Not has no single range
+    fn sorted_range(&self, val: &T, comparison: Comp) -> Option<std::ops::Range<usize>> {
+        let below = || self.items.partition_point(|x| x < val);
+        let upto = || self.items.partition_point(|x| x <= val);
+        // NaN sorts last and matches no comparison
+        let end = || self.items.partition_point(|x| !is_nan(x));
+        match comparison {
+            Comp::Eq => Some(below()..upto()),
+            Comp::Geq => Some(below()..end()),
+            Comp::Gra => Some(upto()..end()),
+            Comp::Les => Some(0..below()),
+            Comp::Leq => Some(0..upto()),
+            Comp::Not => None,
+        }
+    }
+}
+
+impl<T> NumericColumn<T> {
+    pub fn new(key: &str, items: Vec<T>) -> NumericColumn<T> {
+        NumericColumn {
+            key: String::from(key),
+            items,
+            summary: OnceLock::new(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.items
+    }
+
+    // map_indexed builds a column from f applied to each row index and value
+    pub fn map_indexed(&self, f: impl Fn(usize, &T) -> T) -> NumericColumn<T> {
+        NumericColumn {
+            key: self.key.clone(),
+            items: self
+                .items
+                .iter()
+                .enumerate()
+                .map(|(i, x)| f(i, x))
+                .collect(),
+            summary: OnceLock::new(),
+        }
+    }
+
+    // memory_usage is the size of the Vec plus len values of T
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Vec<T>>() + self.items.len() * std::mem::size_of::<T>()
+    }
+
+    // min skips NaN values, None when the column is empty or all NaN
+    pub fn min(&self) -> Option<T>
+    where
+        T: PartialOrd + Clone,
+    {
+        Self::extreme(self.items.iter(), true, |m, x| m <= x)
+    }
+
+    // max skips NaN values, None when the column is empty or all NaN
+    pub fn max(&self) -> Option<T>
+    where
+        T: PartialOrd + Clone,
+    {
+        Self::extreme(self.items.iter(), true, |m, x| m >= x)
+    }
+
+    // min_with_nan is min without skipping NaN: any NaN makes the result NaN
+    pub fn min_with_nan(&self) -> Option<T>
+    where
+        T: PartialOrd + Clone,
+    {
+        Self::extreme(self.items.iter(), false, |m, x| m <= x)
+    }
+
+    // max_with_nan is max without skipping NaN: any NaN makes the result NaN
+    pub fn max_with_nan(&self) -> Option<T>
+    where
+        T: PartialOrd + Clone,
+    {
+        Self::extreme(self.items.iter(), false, |m, x| m >= x)
+    }
+
+    // masked iterates the values at the true positions of mask, which must be
+    // as long as the column
+    fn masked<'a>(&'a self, mask: &'a Mask) -> Result<impl Iterator<Item = &'a T>, NodFrameError> {
+        if mask.len() != self.items.len() {
+            return Err(NodFrameError::LengthMismatch {
+                key: String::from("mask"),
+                expected: self.items.len(),
+                found: mask.len(),
+            });
+        }
+        Ok(self
+            .items
+            .iter()
+            .zip(mask.as_slice())
+            .filter(|(_, keep)| **keep)
+            .map(|(x, _)| x))
+    }
+
+    // sum_where sums the values where mask is true in one pass, zero when
+    // none are
+    pub fn sum_where(&self, mask: &Mask) -> Result<T, NodFrameError>
+    where
+        T: NodNum,
+    {
+        Ok(self
+            .masked(mask)?
+            .fold(T::zero(), |total, x| total + x.clone()))
+    }
+
+    // mean_where is the mean of the values where mask is true, None when none are
+    pub fn mean_where(&self, mask: &Mask) -> Result<Option<f64>, NodFrameError>
+    where
+        T: num_traits::ToPrimitive,
+    {
+        let (count, sum) = self
+            .masked(mask)?
+            .fold((0, 0.0), |(n, s), x| (n + 1, s + to_f64(x)));
+        Ok((count > 0).then(|| sum / count as f64))
+    }
+
+    // min_where is min over the values where mask is true
+    pub fn min_where(&self, mask: &Mask) -> Result<Option<T>, NodFrameError>
+    where
+        T: PartialOrd + Clone,
+    {
+        Ok(Self::extreme(self.masked(mask)?, true, |m, x| m <= x))
+    }
+
+    // max_where is max over the values where mask is true
+    pub fn max_where(&self, mask: &Mask) -> Result<Option<T>, NodFrameError>
+    where
+        T: PartialOrd + Clone,
+    {
+        Ok(Self::extreme(self.masked(mask)?, true, |m, x| m >= x))
+    }
+
+    pub fn count_where(&self, mask: &Mask) -> Result<usize, NodFrameError> {
+        Ok(self.masked(mask)?.count())
+    }
+
+    // extreme keeps the running value while keep(current, next) holds
+    fn extreme<'a>(
+        values: impl Iterator<Item = &'a T>,
+        skip_nan: bool,
+        keep: impl Fn(&T, &T) -> bool,
+    ) -> Option<T>
+    where
+        T: 'a + PartialOrd + Clone,
+    {
+        let mut best: Option<&T> = None;
+        for x in values {
+            if is_nan(x) {
+                if skip_nan {
+                    continue;
+                }
+                return Some(x.clone());
+            }
+            best = match best {
+                Some(m) if keep(m, x) => Some(m),
+                _ => Some(x),
+            };
+        }
+        best.cloned()
+    }
+
+    // has_non_finite reports a NaN or infinite value, integer columns never have one
+    pub fn has_non_finite(&self) -> bool
+    where
+        T: num_traits::ToPrimitive,
+    {
+        self.items.iter().any(|x| !to_f64(x).is_finite())
+    }
+
+    pub fn count_non_finite(&self) -> usize
+    where
+        T: num_traits::ToPrimitive,
+    {
+        self.items
+            .iter()
+            .filter(|x| !to_f64(*x).is_finite())
+            .count()
+    }
+
+    // replace_non_finite swaps NaN, inf and -inf for value
+    pub fn replace_non_finite(&self, value: T) -> NumericColumn<T>
+    where
+        T: num_traits::ToPrimitive + Clone,
+    {
+        NumericColumn {
+            key: self.key.clone(),
+            items: self
+                .items
+                .iter()
+                .map(|x| {
+                    if to_f64(x).is_finite() {
+                        x.clone()
+                    } else {
+                        value.clone()
+                    }
+                })
+                .collect(),
+            summary: OnceLock::new(),
+        }
+    }
+
+    // finite_mask is true for the rows holding a finite value
+    pub fn finite_mask(&self) -> Mask
+    where
+        T: num_traits::ToPrimitive,
+    {
+        Mask::new(self.items.iter().map(|x| to_f64(x).is_finite()).collect())
+    }
+}
+
+impl<T> IntoIterator for NumericColumn<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a NumericColumn<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+// collecting into a column leaves the key empty, use collect_column to name it
+impl<T> FromIterator<T> for NumericColumn<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        NumericColumn {
+            key: String::new(),
+            items: iter.into_iter().collect(),
+            summary: OnceLock::new(),
+        }
+    }
+}
+
+// CollectColumn collects an iterator straight into a named column:
+// let col: NumericColumn<i64> = data.iter().map(|x| x.value).collect_column("value");
+pub trait CollectColumn<C>: Iterator {
+    fn collect_column(self, key: &str) -> C;
+}
+
+impl<T, I: Iterator<Item = T>> CollectColumn<NumericColumn<T>> for I {
+    fn collect_column(self, key: &str) -> NumericColumn<T> {
+        NumericColumn::new(key, self.collect())
+    }
+}
+
+impl<I: Iterator<Item = String>> CollectColumn<DiscreteColumn> for I {
+    fn collect_column(self, key: &str) -> DiscreteColumn {
+        DiscreteColumn::new(key, self.collect())
+    }
+}
+
+impl<T> Index<usize> for NumericColumn<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.items[index]
+    }
+}
+
+impl<T: std::string::ToString> fmt::Display for NumericColumn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str_form: Vec<String> = self.items.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}: [{}]", self.key, str_form.join(", "))
+    }
+}
+
+// check_range accepts start..end when start <= end <= len
+pub(crate) fn check_range(start: usize, end: usize, len: usize) -> Result<(), NodFrameError> {
+    if start > end || end > len {
+        return Err(NodFrameError::InvalidRange { start, end, len });
+    }
+    Ok(())
+}
+
+// is_nan is true for values that do not compare equal to themselves
+pub(crate) fn is_nan<T: PartialOrd>(x: &T) -> bool {
+    x.partial_cmp(x).is_none()
+}
+
+// cmp_nan_last is a total order for sorting: NaN sorts after every other
+// value and equal to another NaN
+pub(crate) fn cmp_nan_last<T: PartialOrd>(a: &T, b: &T) -> std::cmp::Ordering {
+    match (is_nan(a), is_nan(b)) {
+        (false, false) => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+// to_f64 converts a numeric value to f64, NaN when it has no representation
+pub(crate) fn to_f64<T: num_traits::ToPrimitive>(x: &T) -> f64 {
+    x.to_f64().unwrap_or(f64::NAN)
+}
+
+// WeightOptions controls weighted aggregations: negative weights are an error
+// unless allow_negative is set, and with skip_zero_weight a group whose
+// weights sum to zero is left out instead of failing the whole call
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WeightOptions {
+    pub allow_negative: bool,
+    pub skip_zero_weight: bool,
+}
+
+// weighted_totals returns (sum of value * weight, sum of weight) over rows
+pub(crate) fn weighted_totals<T: num_traits::ToPrimitive>(
+    values: &NumericColumn<T>,
+    weights: &NumericColumn<T>,
+    rows: impl Iterator<Item = usize>,
+    opts: &WeightOptions,
+) -> Result<(f64, f64), NodFrameError> {
+    let mut total = 0.0;
+    let mut weight = 0.0;
+    for row in rows {
+        let w = to_f64(&weights.items[row]);
+        if w < 0.0 && !opts.allow_negative {
+            return Err(NodFrameError::InvalidValue {
+                key: weights.key.clone(),
+                row,
+                message: String::from("negative weight"),
+            });
+        }
+        total += to_f64(&values.items[row]) * w;
+        weight += w;
+    }
+    Ok((total, weight))
+}
+
+// Build function for building a numeric column
+#[deprecated(note = "use build_numeric_column, which checks the key")]
+pub fn build_column_numeric<T>(key: &str, data: Vec<T>) -> Column<T> {
+    Column::Numeric(NumericColumn {
+        key: String::from(key),
+        items: data,
+        summary: OnceLock::new(),
+    })
+}
+
+// Build function for building a discrete (String) column
+#[deprecated(note = "use build_discrete_column, which checks the key")]
+pub fn build_column_discrete<T>(key: &str, data: Vec<String>) -> Column<T> {
+    Column::Discrete(DiscreteColumn::new(key, data))
+}
+
+// build_numeric_column builds a numeric column after checking its key, see
+// check_key. Column::from wraps it for insert_column and friends
+pub fn build_numeric_column<T>(key: &str, data: Vec<T>) -> Result<NumericColumn<T>, NodFrameError> {
+    check_key(key)?;
+    Ok(NumericColumn {
+        key: String::from(key),
+        items: data,
+        summary: OnceLock::new(),
+    })
+}
+
+// build_discrete_column builds a discrete column after checking its key
+pub fn build_discrete_column(
+    key: &str,
+    data: Vec<String>,
+) -> Result<DiscreteColumn, NodFrameError> {
+    check_key(key)?;
+    Ok(DiscreteColumn::new(key, data))
+}
+
+impl<T> From<NumericColumn<T>> for Column<T> {
+    fn from(col: NumericColumn<T>) -> Self {
+        Column::Numeric(col)
+    }
+}
+
+impl<T> From<DiscreteColumn> for Column<T> {
+    fn from(col: DiscreteColumn) -> Self {
+        Column::Discrete(col)
+    }
+}
+
+// KeyPolicy decides what NodFrameBuilder does with a key that has leading or
+// trailing whitespace: Reject fails the build and Trim strips it. Empty keys
+// and keys holding control characters are rejected either way
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyPolicy {
+    #[default]
+    Reject,
+    Trim,
+}
+
+impl KeyPolicy {
+    // apply returns the key to use for key under the policy
+    pub fn apply(&self, key: &str) -> Result<String, NodFrameError> {
+        let key = match self {
+            KeyPolicy::Reject => key,
+            KeyPolicy::Trim => key.trim(),
+        };
+        check_key(key)?;
+        Ok(String::from(key))
+    }
+}
+
+// check_key fails for keys no frame holds: empty keys, keys with leading or
+// trailing whitespace, which read like another key, and keys holding control
+// characters, which do not survive a csv header
+pub fn check_key(key: &str) -> Result<(), NodFrameError> {
+    let reason = if key.is_empty() {
+        "it is empty"
+    } else if key.trim() != key {
+        "it has leading or trailing whitespace"
+    } else if key.chars().any(char::is_control) {
+        "it contains a control character"
+    } else {
+        return Ok(());
+    };
+    Err(NodFrameError::InvalidKey {
+        key: String::from(key),
+        reason,
+    })
+}
+
+// column_index checks that columns have equal lengths and unique keys and
+// returns the row count and the key index
+fn column_index<T: NodNum>(
+    columns: &[Column<T>],
+) -> Result<(usize, HashMap<String, usize>), NodFrameError> {
+    let rows = columns.first().map(|c| c.len()).unwrap_or(0);
+    let mut index = HashMap::new();
+    for (i, col) in columns.iter().enumerate() {
+        if col.len() != rows {
+            return Err(NodFrameError::LengthMismatch {
+                key: col.get_key().clone(),
+                expected: rows,
+                found: col.len(),
+            });
+        }
+        check_key(col.get_key())?;
+        if index.insert(col.get_key().clone(), i).is_some() {
+            return Err(NodFrameError::DuplicateKey(col.get_key().clone()));
+        }
+    }
+    Ok((rows, index))
+}
+
+#[derive(Clone, Debug)]
+pub struct NodFrame<T> {
+    columns: Vec<Column<T>>,
+    column_idx: HashMap<String, usize>,
+    num_rows: usize,
+    num_cols: usize,
+    // numeric columns known to be ascending, so range filters can binary search
+    sorted: HashSet<String>,
+    // traced operations, None while tracing is off, see enable_tracing
+    history: Option<Vec<OpRecord>>,
+    // columns csv inference kept discrete, see demoted_columns
+    demoted: Vec<String>,
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // schema describes the frame's columns as a strict Schema, useful for
+    // checking that a later file has the same layout
+    pub fn schema(&self) -> Schema {
+        self.columns
+            .iter()
+            .fold(Schema::new(), |s, c| s.column(c.get_key(), c.kind()))
+    }
+
+    // demoted_columns lists the columns csv inference kept discrete although
+    // every cell parses, because some numbers would not be written back as
+    // they were read, such as zip codes with leading zeros, see loses_text.
+    // Only a frame just loaded from csv has any. A schema or a parser makes
+    // such a column numeric anyway
+    pub fn demoted_columns(&self) -> &[String] {
+        &self.demoted
+    }
+
+    // content_hash combines the content_hash of every column in frame order,
+    // see Column::content_hash for what is hashed
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv64::new();
+        hasher.write(&(self.num_cols as u64).to_le_bytes());
+        for col in self.columns.iter() {
+            hasher.write(&col.content_hash().to_le_bytes());
+        }
+        hasher.finish()
+    }
+
+    // compare lists how other differs from self: columns present on one side
+    // only, kind changes, unmatched rows and mismatching cells. Rows are paired
+    // by position, or by the values of opts.key when it is set
+    pub fn compare(
+        &self,
+        other: &NodFrame<T>,
+        opts: &CompareOptions,
+    ) -> Result<FrameDiff, NodFrameError> {
+        let mut diff = FrameDiff::default();
+        let keys = |f: &NodFrame<T>| -> Vec<String> {
+            f.columns.iter().map(|c| c.get_key().clone()).collect()
+        };
+        let (left_keys, right_keys) = (keys(self), keys(other));
+        diff.columns_only_in_left = left_keys
+            .iter()
+            .filter(|k| !other.column_idx.contains_key(*k))
+            .cloned()
+            .collect();
+        diff.columns_only_in_right = right_keys
+            .iter()
+            .filter(|k| !self.column_idx.contains_key(*k))
+            .cloned()
+            .collect();
+        let common: Vec<&String> = left_keys
+            .iter()
+            .filter(|k| other.column_idx.contains_key(*k))
+            .collect();
+        if !opts.ignore_column_order {
+            let right_common: Vec<&String> = right_keys
+                .iter()
+                .filter(|k| self.column_idx.contains_key(*k))
+                .collect();
+            diff.column_order_differs = common != right_common;
+        }
+        for key in common.iter() {
+            let (l, r) = (self[key.as_str()].kind(), other[key.as_str()].kind());
+            if l != r {
+                diff.kind_mismatches.push((key.to_string(), l, r));
+            }
+        }
+
+        // pairs holds (label, left row, right row) for the rows to compare
+        let mut pairs = Vec::new();
+        match &opts.key {
+            None => {
+                for i in 0..self.num_rows.min(other.num_rows) {
+                    pairs.push((i.to_string(), i, i));
+                }
+                diff.rows_only_in_left = (other.num_rows..self.num_rows)
+                    .map(|i| i.to_string())
+                    .collect();
+                diff.rows_only_in_right = (self.num_rows..other.num_rows)
+                    .map(|i| i.to_string())
+                    .collect();
+            }
+            Some(key) => {
+                let index = |f: &NodFrame<T>| -> Result<HashMap<String, usize>, NodFrameError> {
+                    let col = f
+                        .column(key)
+                        .ok_or_else(|| NodFrameError::MissingColumn(key.clone()))?;
+                    let mut index = HashMap::new();
+                    for i in 0..col.len() {
+                        let value = col.cell_string(i);
+                        if index.insert(value.clone(), i).is_some() {
+                            return Err(NodFrameError::InvalidArgument(format!(
+                                "key column '{}' has duplicate value '{}'",
+                                key, value
+                            )));
+                        }
+                    }
+                    Ok(index)
+                };
+                let (left_index, right_index) = (index(self)?, index(other)?);
+                for i in 0..self.num_rows {
+                    let value = self[key.as_str()].cell_string(i);
+                    match right_index.get(&value) {
+                        Some(j) => pairs.push((value, i, *j)),
+                        None => diff.rows_only_in_left.push(value),
+                    }
+                }
+                diff.rows_only_in_right = (0..other.num_rows)
+                    .map(|j| other[key.as_str()].cell_string(j))
+                    .filter(|v| !left_index.contains_key(v))
+                    .collect();
+            }
+        }
+
+        for key in common.iter() {
+            let (l, r) = (&self[key.as_str()], &other[key.as_str()]);
+            if l.kind() != r.kind() || opts.key.as_ref() == Some(*key) {
+                continue;
+            }
+            for (label, i, j) in pairs.iter() {
+                let equal = match (l, r) {
+                    (Column::Numeric(a), Column::Numeric(b)) => {
+                        let (x, y) = (to_f64(a.get(*i)), to_f64(b.get(*j)));
+                        x == y || (x - y).abs() <= opts.tolerance || (x.is_nan() && y.is_nan())
+                    }
+                    _ => l.cell_string(*i) == r.cell_string(*j),
+                };
+                if !equal {
+                    diff.mismatch_count += 1;
+                    if diff.mismatches.len() < opts.max_mismatches {
+                        diff.mismatches.push(CellMismatch {
+                            row: label.clone(),
+                            column: key.to_string(),
+                            left: l.cell_string(*i),
+                            right: r.cell_string(*j),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(diff)
+    }
+
+    // weighted_sum is the sum of value * weight over all rows
+    pub fn weighted_sum(&self, value_col: &str, weight_col: &str) -> Result<f64, NodFrameError> {
+        self.weighted_sum_with(value_col, weight_col, &WeightOptions::default())
+    }
+
+    pub fn weighted_sum_with(
+        &self,
+        value_col: &str,
+        weight_col: &str,
+        opts: &WeightOptions,
+    ) -> Result<f64, NodFrameError> {
+        let (values, weights) = (
+            self.numeric_column(value_col)?,
+            self.numeric_column(weight_col)?,
+        );
+        Ok(weighted_totals(values, weights, 0..self.num_rows, opts)?.0)
+    }
+
+    // weighted_mean is sum(value * weight) / sum(weight), weights that sum to
+    // zero are an error
+    pub fn weighted_mean(&self, value_col: &str, weight_col: &str) -> Result<f64, NodFrameError> {
+        self.weighted_mean_with(value_col, weight_col, &WeightOptions::default())
+    }
+
+    pub fn weighted_mean_with(
+        &self,
+        value_col: &str,
+        weight_col: &str,
+        opts: &WeightOptions,
+    ) -> Result<f64, NodFrameError> {
+        let (values, weights) = (
+            self.numeric_column(value_col)?,
+            self.numeric_column(weight_col)?,
+        );
+        let (total, weight) = weighted_totals(values, weights, 0..self.num_rows, opts)?;
+        if weight == 0.0 {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "weights in '{}' sum to zero",
+                weight_col
+            )));
+        }
+        Ok(total / weight)
+    }
+
+    // from_columns is the one place frames are put together: it derives the
+    // key index and the counts from columns, which must have equal lengths
+    // and unique keys. Every operation building a frame goes through it
+    pub(crate) fn from_columns(columns: Vec<Column<T>>) -> Result<NodFrame<T>, NodFrameError> {
+        let (num_rows, column_idx) = column_index(&columns)?;
+        Ok(NodFrame {
+            num_cols: columns.len(),
+            columns,
+            column_idx,
+            num_rows,
+            sorted: HashSet::new(),
+            history: None,
+            demoted: Vec::new(),
+        })
+    }
+
+    // with_rows_of builds a frame from columns holding the same keys in the
+    // same order as self, with rows picked alike from every column so their
+    // order is kept, which keeps sorted columns sorted
+    fn with_rows_of(&self, columns: Vec<Column<T>>) -> NodFrame<T> {
+        // every column took the same rows, so the lengths agree
+        let mut frame = NodFrame::from_columns(columns).unwrap();
+        frame.sorted = self.sorted.clone();
+        frame.history = self.history.clone();
+        frame
+    }
+
+    // validate checks the bookkeeping of the frame against its columns: row
+    // and column counts, the key index, key uniqueness, the values of ordered
+    // columns and sorted flags
+    pub fn validate(&self) -> Result<(), Vec<InvariantViolation>> {
+        let mut violations = Vec::new();
+        if self.num_cols != self.columns.len() {
+            violations.push(InvariantViolation::ColumnCount {
+                recorded: self.num_cols,
+                actual: self.columns.len(),
+            });
+        }
+        let mut seen = HashSet::new();
+        for (i, col) in self.columns.iter().enumerate() {
+            let key = col.get_key();
+            if col.len() != self.num_rows {
+                violations.push(InvariantViolation::ColumnLength {
+                    key: key.clone(),
+                    num_rows: self.num_rows,
+                    found: col.len(),
+                });
+            }
+            if !seen.insert(key) {
+                violations.push(InvariantViolation::DuplicateKey(key.clone()));
+                continue;
+            }
+            match self.column_idx.get(key) {
+                None => violations.push(InvariantViolation::MissingIndex(key.clone())),
+                Some(j) if *j != i => violations.push(InvariantViolation::WrongIndex {
+                    key: key.clone(),
+                    index: *j,
+                    position: i,
+                }),
+                _ => {}
+            }
+        }
+        let mut stale: Vec<&String> = self
+            .column_idx
+            .keys()
+            .filter(|k| !seen.contains(k))
+            .collect();
+        stale.sort();
+        for key in stale {
+            violations.push(InvariantViolation::StaleIndex(key.clone()));
+        }
+        for col in self.columns.iter() {
+            if let Column::Discrete(d) = col {
+                if let Err(NodFrameError::InvalidValue { row, .. }) = d.check_levels() {
+                    violations.push(InvariantViolation::UnknownLevel {
+                        key: col.get_key().clone(),
+                        row,
+                        value: String::from(d.get(row)),
+                    });
+                }
+            }
+        }
+        let mut sorted: Vec<&String> = self.sorted.iter().collect();
+        sorted.sort();
+        for key in sorted {
+            // looked up without column_idx, which may itself be broken
+            let col = self.columns.iter().find(|c| c.get_key() == key);
+            let ok = matches!(col, Some(Column::Numeric(n)) if n.is_sorted());
+            if !ok {
+                violations.push(InvariantViolation::SortedFlag(key.clone()));
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    // debug_assert_valid runs validate in debug builds, mutating methods call
+    // it before returning
+    fn debug_assert_valid(&self) {
+        #[cfg(debug_assertions)]
+        if let Err(violations) = self.validate() {
+            panic!("frame invariants broken: {:?}", violations);
+        }
+    }
+
+    // debug_assert_pushed is debug_assert_valid for a frame that just gained
+    // a row: only the lengths and the order of the last two values of sorted
+    // columns are checked, so a run of pushes stays linear in debug builds
+    fn debug_assert_pushed(&self) {
+        #[cfg(debug_assertions)]
+        for col in self.columns.iter() {
+            assert_eq!(
+                col.len(),
+                self.num_rows,
+                "column '{}' has a wrong length after push_row",
+                col.get_key()
+            );
+            if let Column::Numeric(n) = col {
+                let tail = &n.items[n.items.len().saturating_sub(2)..];
+                assert!(
+                    !self.sorted.contains(&n.key) || tail.is_sorted(),
+                    "column '{}' is flagged sorted after an unordered push_row",
+                    n.key
+                );
+            }
+        }
+    }
+
+    // repair rebuilds the key index, the counts and the sorted flags from the
+    // columns. Columns of different lengths or with duplicate keys cannot be
+    // repaired
+    pub fn repair(&mut self) -> Result<(), NodFrameError> {
+        let (rows, index) = column_index(&self.columns)?;
+        self.column_idx = index;
+        self.num_rows = rows;
+        self.num_cols = self.columns.len();
+        let columns = &self.columns;
+        let column_idx = &self.column_idx;
+        self.sorted.retain(|key| {
+            matches!(column_idx.get(key).map(|i| &columns[*i]), Some(Column::Numeric(n)) if n.is_sorted())
+        });
+        Ok(())
+    }
+
+    // memory_usage estimates the bytes held by each column
+    pub fn memory_usage(&self) -> MemoryReport {
+        MemoryReport {
+            columns: self
+                .columns
+                .iter()
+                .map(|c| (c.get_key().clone(), c.memory_usage()))
+                .collect(),
+        }
+    }
+
+    // columns returns the columns in frame order
+    pub fn columns(&self) -> &[Column<T>] {
+        &self.columns
+    }
+
+    // shape returns (rows, columns)
+    pub fn shape(&self) -> (usize, usize) {
+        (self.num_rows, self.num_cols)
+    }
+
+    // column looks up a column by key
+    pub fn column(&self, key: &str) -> Option<&Column<T>> {
+        self.column_idx.get(key).map(|i| &self.columns[*i])
+    }
+
+    // bounds is the lowest and highest value of the numeric column key, None
+    // when it holds no values but NaN. Repeated calls reuse the column's
+    // cached summary, see NumericColumn::min_cached
+    pub fn bounds(&self, key: &str) -> Result<Option<(T, T)>, NodFrameError> {
+        let col = self.numeric_column(key)?;
+        Ok(col.min_cached().zip(col.max_cached()))
+    }
+
+    // numeric_column looks up a column by key and checks that it is numeric
+    pub fn numeric_column(&self, key: &str) -> Result<&NumericColumn<T>, NodFrameError> {
+        match self.column(key) {
+            Some(Column::Numeric(n)) => Ok(n),
+            Some(Column::Discrete(_)) => Err(NodFrameError::KindMismatch {
+                key: String::from(key),
+                expected: ColKind::Numeric,
+                found: ColKind::Discrete,
+            }),
+            None => Err(NodFrameError::MissingColumn(String::from(key))),
+        }
+    }
+
+    // column_as parses the values of key as E from their text form, see
+    // DiscreteColumn::parse_as. Numeric columns go through to_string, so any
+    // E that parses that text works, T included
+    pub fn column_as<E: FromStr>(&self, key: &str) -> Result<Vec<E>, NodFrameError> {
+        match self.column(key) {
+            Some(Column::Discrete(d)) => d.parse_as(),
+            Some(col) => (0..self.num_rows)
+                .map(|row| {
+                    let text = col.cell_string(row);
+                    text.parse::<E>().map_err(|_| NodFrameError::InvalidValue {
+                        key: String::from(key),
+                        row,
+                        message: format!("'{}' does not parse", text),
+                    })
+                })
+                .collect(),
+            None => Err(NodFrameError::MissingColumn(String::from(key))),
+        }
+    }
+
+    // column_as_lenient is column_as with None for values that do not parse
+    pub fn column_as_lenient<E: FromStr>(
+        &self,
+        key: &str,
+    ) -> Result<Vec<Option<E>>, NodFrameError> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        Ok((0..self.num_rows)
+            .map(|row| col.cell_string(row).parse::<E>().ok())
+            .collect())
+    }
+
+    // discrete_column looks up a column by key and checks that it is discrete
+    pub fn discrete_column(&self, key: &str) -> Result<&DiscreteColumn, NodFrameError> {
+        match self.column(key) {
+            Some(Column::Discrete(d)) => Ok(d),
+            Some(Column::Numeric(_)) => Err(NodFrameError::KindMismatch {
+                key: String::from(key),
+                expected: ColKind::Discrete,
+                found: ColKind::Numeric,
+            }),
+            None => Err(NodFrameError::MissingColumn(String::from(key))),
+        }
+    }
+
+    // at reads a single value, None if the key or row does not exist
+    pub fn at(&self, key: &str, row: usize) -> Option<Cell<T>> {
+        self.column(key)?.get_cell_checked(row)
+    }
+
+    // row returns the cells of a single row in column order. It panics when
+    // index is past the last row, use row_checked to check
+    pub fn row(&self, index: usize) -> Vec<Cell<T>> {
+        self.columns.iter().map(|c| c.get_cell(index)).collect()
+    }
+
+    // row_checked is row returning None for an index past the last row
+    pub fn row_checked(&self, index: usize) -> Option<Vec<Cell<T>>> {
+        if index >= self.num_rows {
+            return None;
+        }
+        Some(self.row(index))
+    }
+
+    // slice keeps rows start..end, erroring when the range does not fit
+    pub fn slice(&self, start: usize, end: usize) -> Result<NodFrame<T>, NodFrameError> {
+        check_range(start, end, self.num_rows)?;
+        Ok(self.row_range(start..end))
+    }
+
+    // to_rows converts every row into R, the frame must contain each of R's
+    // keys with the matching kind but may hold additional columns
+    pub fn to_rows<R: FrameRow<T>>(&self) -> Result<Vec<R>, NodFrameError> {
+        let mut indices = Vec::new();
+        for (key, kind) in R::keys() {
+            let idx = *self
+                .column_idx
+                .get(&key)
+                .ok_or_else(|| NodFrameError::MissingColumn(key.clone()))?;
+            if self.columns[idx].kind() != kind {
+                return Err(NodFrameError::KindMismatch {
+                    key,
+                    expected: kind,
+                    found: self.columns[idx].kind(),
+                });
+            }
+            indices.push(idx);
+        }
+        (0..self.num_rows)
+            .map(|i| {
+                let cells = indices
+                    .iter()
+                    .map(|c| self.columns[*c].get_cell(i))
+                    .collect();
+                R::from_cells(cells).ok_or(NodFrameError::RowConversion(i))
+            })
+            .collect()
+    }
+
+    // numeric_cols returns the column names of numeric columns
+    pub fn numeric_cols(&self) -> Vec<&String> {
+        let mut num_col = Vec::new();
+        for column in self.columns.iter() {
+            if let Column::Numeric(a) = column {
+                num_col.push(&a.key)
+            }
+        }
+        num_col
+    }
+
+    // numeric_index returns the column indices of number columns
+    pub fn numeric_index(&self) -> Vec<usize> {
+        let cols = self.numeric_cols();
+        let mut indices = Vec::new();
+        for key in cols {
+            indices.push(*self.column_idx.get(key).unwrap())
+        }
+        indices
+    }
+
+    // numeric_rows returns the rows of numeric columns as a 2d matrix
+    pub fn numeric_rows(&self) -> Vec<Vec<T>> {
+        let numeric_index = self.numeric_index();
+        let mut data = Vec::new();
+        for i in 0..self.num_rows {
+            let mut row = Vec::new();
+            for col in numeric_index.iter() {
+                row.push(self.columns[*col].get_num(i).unwrap().clone())
+            }
+            data.push(row)
+        }
+        data
+    }
+
+    // iter_numeric_rows yields the same rows as numeric_rows one at a time
+    pub fn iter_numeric_rows(&self) -> impl Iterator<Item = Vec<T>> + '_ {
+        let cols = self.numeric_columns();
+        (0..self.num_rows).map(move |i| cols.iter().map(|c| c.get(i).clone()).collect())
+    }
+
+    // for_each_numeric_row calls f with every numeric row, reusing a single row
+    // buffer so no allocation happens per row
+    pub fn for_each_numeric_row<F: FnMut(&[T])>(&self, mut f: F) {
+        let cols = self.numeric_columns();
+        let mut row: Vec<T> = Vec::with_capacity(cols.len());
+        for i in 0..self.num_rows {
+            row.clear();
+            row.extend(cols.iter().map(|c| c.get(i).clone()));
+            f(&row);
+        }
+    }
+
+    fn numeric_columns(&self) -> Vec<&NumericColumn<T>> {
+        self.numeric_index()
+            .iter()
+            .filter_map(|i| self.columns[*i].as_numeric())
+            .collect()
+    }
+
+    // to_matrix returns the numeric columns as a flat row-major buffer together
+    // with its (rows, columns) shape, columns follow numeric_index order
+    pub fn to_matrix(&self) -> (Vec<T>, usize, usize) {
+        let cols = self.numeric_columns();
+        (self.row_major(&cols), self.num_rows, cols.len())
+    }
+
+    // to_matrix_cols is to_matrix restricted to the given numeric columns
+    pub fn to_matrix_cols(&self, keys: &[&str]) -> Result<(Vec<T>, usize, usize), NodFrameError> {
+        let cols = keys
+            .iter()
+            .map(|k| self.numeric_column(k))
+            .collect::<Result<Vec<&NumericColumn<T>>, NodFrameError>>()?;
+        Ok((self.row_major(&cols), self.num_rows, cols.len()))
+    }
+
+    fn row_major(&self, cols: &[&NumericColumn<T>]) -> Vec<T> {
+        let mut data = Vec::with_capacity(self.num_rows * cols.len());
+        for i in 0..self.num_rows {
+            for col in cols.iter() {
+                data.push(col.get(i).clone());
+            }
+        }
+        data
+    }
+
+    // to_ndarray converts the numeric columns to an f64 matrix, values that
+    // have no f64 representation become NaN
+    #[cfg(feature = "ndarray")]
+    pub fn to_ndarray(&self) -> ndarray::Array2<f64> {
+        let (data, rows, cols) = self.to_matrix();
+        let data = data
+            .iter()
+            .map(|x| x.to_f64().unwrap_or(f64::NAN))
+            .collect();
+        ndarray::Array2::from_shape_vec((rows, cols), data)
+            .expect("to_matrix returns rows * cols values")
+    }
+
+    // explode splits the discrete column key on sep and repeats the rest of the
+    // row once per piece, pieces are trimmed of surrounding whitespace
+    pub fn explode(&self, key: &str, sep: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.explode_with(key, sep, &ExplodeOptions::default())
+    }
+
+    pub fn explode_with(
+        &self,
+        key: &str,
+        sep: &str,
+        opts: &ExplodeOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let target = self.discrete_column(key)?;
+        let mut sources = Vec::new();
+        let mut pieces = Vec::new();
+        for (i, cell) in target.iter().enumerate() {
+            if cell.trim().is_empty() {
+                if opts.keep_empty_cells {
+                    sources.push(i);
+                    pieces.push(String::new());
+                }
+                continue;
+            }
+            for piece in cell.split(sep).map(|p| p.trim()) {
+                if piece.is_empty() && opts.drop_empty_pieces {
+                    continue;
+                }
+                sources.push(i);
+                pieces.push(piece.to_string());
+            }
+        }
+        let mut pieces = Some(pieces);
+        let out = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, col| {
+                if col.get_key() == key {
+                    b.add_discrete(key, pieces.take().unwrap_or_default())
+                } else {
+                    b.add_column(col.take(&sources))
+                }
+            })
+            .build()?;
+        Ok(self.traced(out, "explode", || {
+            vec![("key", String::from(key)), ("sep", String::from(sep))]
+        }))
+    }
+
+    // split_column splits every value of a discrete column on sep into one new
+    // discrete column per entry of new_keys, missing trailing pieces are empty
+    // strings and surplus pieces are handled per opts.extra_pieces
+    pub fn split_column(
+        &self,
+        key: &str,
+        sep: &str,
+        new_keys: &[&str],
+        opts: &SplitOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        if new_keys.is_empty() {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "split_column needs at least one new key",
+            )));
+        }
+        let target = self.discrete_column(key)?;
+        let mut parts: Vec<Vec<String>> = vec![Vec::with_capacity(self.num_rows); new_keys.len()];
+        for (row, cell) in target.iter().enumerate() {
+            let mut pieces: Vec<&str> = match opts.extra_pieces {
+                ExtraPieces::Merge => cell.splitn(new_keys.len(), sep).collect(),
+                _ => cell.split(sep).collect(),
+            };
+            if pieces.len() > new_keys.len() {
+                if opts.extra_pieces == ExtraPieces::Error {
+                    return Err(NodFrameError::InvalidValue {
+                        key: String::from(key),
+                        row,
+                        message: format!(
+                            "'{}' splits into {} pieces, expected at most {}",
+                            cell,
+                            pieces.len(),
+                            new_keys.len()
+                        ),
+                    });
+                }
+                pieces.truncate(new_keys.len());
+            }
+            for (i, part) in parts.iter_mut().enumerate() {
+                part.push(pieces.get(i).unwrap_or(&"").to_string());
+            }
+        }
+
+        let mut builder = NodFrameBuilder::new();
+        let mut parts = parts.into_iter();
+        for col in self.columns.iter() {
+            if col.get_key() != key {
+                builder = builder.add_column(col.clone());
+                continue;
+            }
+            if opts.keep_original {
+                builder = builder.add_column(col.clone());
+            }
+            for (new_key, part) in new_keys.iter().zip(parts.by_ref()) {
+                builder = builder.add_discrete(new_key, part);
+            }
+        }
+        Ok(self.traced(builder.build()?, "split_column", || {
+            vec![
+                ("key", String::from(key)),
+                ("sep", String::from(sep)),
+                ("new_keys", new_keys.join(",")),
+            ]
+        }))
+    }
+
+    // concat_columns joins the string form of the listed columns with sep into
+    // a new discrete column appended to the frame
+    pub fn concat_columns(
+        &self,
+        keys: &[&str],
+        sep: &str,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        if keys.is_empty() {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "concat_columns needs at least one key",
+            )));
+        }
+        let cols = keys
+            .iter()
+            .map(|k| {
+                self.column(k)
+                    .ok_or_else(|| NodFrameError::MissingColumn(k.to_string()))
+            })
+            .collect::<Result<Vec<&Column<T>>, NodFrameError>>()?;
+        let joined = (0..self.num_rows)
+            .map(|i| {
+                cols.iter()
+                    .map(|c| c.cell_string(i))
+                    .collect::<Vec<String>>()
+                    .join(sep)
+            })
+            .collect();
+        let out = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_discrete(new_key, joined)
+            .build()?;
+        Ok(self.traced(out, "concat_columns", || {
+            vec![
+                ("keys", keys.join(",")),
+                ("sep", String::from(sep)),
+                ("new_key", String::from(new_key)),
+            ]
+        }))
+    }
+
+    // rename gives the column from the key to, which must not already exist
+    pub fn rename(&self, from: &str, to: &str) -> Result<NodFrame<T>, NodFrameError> {
+        let mut mapping = HashMap::new();
+        mapping.insert(String::from(from), String::from(to));
+        self.rename_map(&mapping)
+    }
+
+    // rename_map renames every column in mapping at once, so swapping two names
+    // works, but a new name that collides with a remaining column is an error
+    pub fn rename_map(
+        &self,
+        mapping: &HashMap<String, String>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let mut missing: Vec<&String> = mapping
+            .keys()
+            .filter(|k| !self.column_idx.contains_key(*k))
+            .collect();
+        missing.sort();
+        if let Some(key) = missing.first() {
+            return Err(NodFrameError::MissingColumn(key.to_string()));
+        }
+        let out = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| {
+                match mapping.get(c.get_key()) {
+                    Some(to) => b.add_column(c.clone().with_key(to)),
+                    None => b.add_column(c.clone()),
+                }
+            })
+            .build()?;
+        Ok(self.traced(out, "rename", || {
+            let mut pairs: Vec<String> = mapping
+                .iter()
+                .map(|(from, to)| format!("{}->{}", from, to))
+                .collect();
+            pairs.sort();
+            vec![("mapping", pairs.join(","))]
+        }))
+    }
+
+    // set_column_names renames all columns by position
+    pub fn set_column_names(&self, names: &[&str]) -> Result<NodFrame<T>, NodFrameError> {
+        if names.len() != self.num_cols {
+            return Err(NodFrameError::KeyCountMismatch {
+                keys: names.len(),
+                columns: self.num_cols,
+            });
+        }
+        let out = self
+            .columns
+            .iter()
+            .zip(names)
+            .fold(NodFrameBuilder::new(), |b, (c, name)| {
+                b.add_column(c.clone().with_key(name))
+            })
+            .build()?;
+        Ok(self.traced(out, "set_column_names", || vec![("names", names.join(","))]))
+    }
+
+    // reorder moves the listed columns to the front in the given order, the
+    // remaining columns follow in their current order
+    pub fn reorder(&self, keys: &[&str]) -> Result<NodFrame<T>, NodFrameError> {
+        let mut picked = HashSet::new();
+        let mut builder = NodFrameBuilder::new();
+        for key in keys {
+            let col = self
+                .column(key)
+                .ok_or_else(|| NodFrameError::MissingColumn(key.to_string()))?;
+            if !picked.insert(*key) {
+                return Err(NodFrameError::DuplicateKey(key.to_string()));
+            }
+            builder = builder.add_column(col.clone());
+        }
+        let out = self
+            .columns
+            .iter()
+            .filter(|c| !picked.contains(c.get_key().as_str()))
+            .fold(builder, |b, c| b.add_column(c.clone()))
+            .build()?;
+        Ok(self.traced(out, "reorder", || vec![("keys", keys.join(","))]))
+    }
+
+    // insert_column places col at index, shifting later columns to the right
+    pub fn insert_column(&mut self, index: usize, col: Column<T>) -> Result<(), NodFrameError> {
+        if index > self.num_cols {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "cannot insert at position {} in a frame with {} columns",
+                index, self.num_cols
+            )));
+        }
+        if self.num_cols > 0 && col.len() != self.num_rows {
+            return Err(NodFrameError::LengthMismatch {
+                key: col.get_key().clone(),
+                expected: self.num_rows,
+                found: col.len(),
+            });
+        }
+        check_key(col.get_key())?;
+        if self.column_idx.contains_key(col.get_key()) {
+            return Err(NodFrameError::DuplicateKey(col.get_key().clone()));
+        }
+        self.num_rows = col.len();
+        self.columns.insert(index, col);
+        self.num_cols = self.columns.len();
+        self.column_idx = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.get_key().clone(), i))
+            .collect();
+        self.debug_assert_valid();
+        Ok(())
+    }
+
+    // set_column replaces the data of an existing column in place, keeping its
+    // key and position, the new column may be of either kind
+    pub fn set_column(&mut self, key: &str, col: Column<T>) -> Result<(), NodFrameError> {
+        let idx = *self
+            .column_idx
+            .get(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        if col.len() != self.num_rows {
+            return Err(NodFrameError::LengthMismatch {
+                key: String::from(key),
+                expected: self.num_rows,
+                found: col.len(),
+            });
+        }
+        self.columns[idx] = col.with_key(key);
+        self.sorted.remove(key);
+        self.debug_assert_valid();
+        Ok(())
+    }
+
+    // set_order gives the discrete column key a level order, see
+    // DiscreteColumn::set_order. filter, sort_by and the other filters
+    // then compare its values by level
+    pub fn set_order(&mut self, key: &str, levels: &[&str]) -> Result<(), NodFrameError> {
+        let idx = *self
+            .column_idx
+            .get(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        match &mut self.columns[idx] {
+            Column::Discrete(d) => d.set_order(levels),
+            Column::Numeric(_) => Err(NodFrameError::KindMismatch {
+                key: String::from(key),
+                expected: ColKind::Discrete,
+                found: ColKind::Numeric,
+            }),
+        }
+    }
+
+    // to_discrete turns the numeric column key into a discrete column of the
+    // values' text, in the same position. NaN becomes the empty string, the
+    // missing value of discrete columns
+    pub fn to_discrete(&self, key: &str) -> Result<NodFrame<T>, NodFrameError> {
+        self.to_discrete_with(key, |x| x.to_string())
+    }
+
+    // to_discrete_with is to_discrete with format writing each value, e.g.
+    // |x| format!("{:05}", x) to zero-pad ids
+    pub fn to_discrete_with(
+        &self,
+        key: &str,
+        format: impl Fn(&T) -> String,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let values = self.numeric_column(key)?;
+        let items = values
+            .iter()
+            .map(|x| if is_nan(x) { String::new() } else { format(x) })
+            .collect();
+        let mut out = self.clone();
+        out.set_column(key, Column::Discrete(DiscreteColumn::new(key, items)))?;
+        Ok(self.traced(out, "to_discrete", || vec![("key", String::from(key))]))
+    }
+
+    // copy_column_from copies column key of other into self, replacing the
+    // column of that key or appending it. Rows are matched by position, so
+    // both frames must have the same number of rows, see copy_column_checked
+    pub fn copy_column_from(
+        &mut self,
+        other: &NodFrame<T>,
+        key: &str,
+    ) -> Result<(), NodFrameError> {
+        self.copy_column_as(other, key, key)
+    }
+
+    // copy_column_as is copy_column_from storing the column as dst_key
+    pub fn copy_column_as(
+        &mut self,
+        other: &NodFrame<T>,
+        src_key: &str,
+        dst_key: &str,
+    ) -> Result<(), NodFrameError> {
+        let col = other
+            .column(src_key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(src_key)))?;
+        if col.len() != self.num_rows {
+            return Err(NodFrameError::LengthMismatch {
+                key: String::from(src_key),
+                expected: self.num_rows,
+                found: col.len(),
+            });
+        }
+        if self.column_idx.contains_key(dst_key) {
+            self.set_column(dst_key, col.clone())
+        } else {
+            self.insert_column(self.num_cols, col.clone().with_key(dst_key))
+        }
+    }
+
+    // copy_column_checked is copy_column_from after checking that the column
+    // align_key holds the same values, row for row, in both frames
+    pub fn copy_column_checked(
+        &mut self,
+        other: &NodFrame<T>,
+        key: &str,
+        align_key: &str,
+    ) -> Result<(), NodFrameError> {
+        self.check_aligned(other, align_key)?;
+        self.copy_column_from(other, key)
+    }
+
+    // check_aligned fails unless the column align_key has the same kind and
+    // the same values, row for row, in self and other
+    pub(crate) fn check_aligned(
+        &self,
+        other: &NodFrame<T>,
+        align_key: &str,
+    ) -> Result<(), NodFrameError> {
+        let missing = || NodFrameError::MissingColumn(String::from(align_key));
+        let ours = self.column(align_key).ok_or_else(missing)?;
+        let theirs = other.column(align_key).ok_or_else(missing)?;
+        if ours.kind() != theirs.kind() {
+            return Err(NodFrameError::KindMismatch {
+                key: String::from(align_key),
+                expected: ours.kind(),
+                found: theirs.kind(),
+            });
+        }
+        if ours.len() != theirs.len() {
+            return Err(NodFrameError::LengthMismatch {
+                key: String::from(align_key),
+                expected: ours.len(),
+                found: theirs.len(),
+            });
+        }
+        if let Some(row) = (0..ours.len()).find(|r| ours.cell_string(*r) != theirs.cell_string(*r))
+        {
+            return Err(NodFrameError::InvalidValue {
+                key: String::from(align_key),
+                row,
+                message: format!(
+                    "'{}' != '{}', the frames are not aligned",
+                    ours.cell_string(row),
+                    theirs.cell_string(row)
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    // assert_unique fails with NotUnique when a value of column key occurs
+    // more than once, naming up to five repeated values and their rows.
+    // Numbers are told apart by their text form, see NumericColumn::is_unique
+    pub fn assert_unique(&self, key: &str) -> Result<(), NodFrameError> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        let repeated = repeated_rows((0..col.len()).map(|r| col.cell_string(r)));
+        if repeated.is_empty() {
+            return Ok(());
+        }
+        Err(NodFrameError::NotUnique {
+            key: String::from(key),
+            repeated: repeated.len(),
+            examples: repeated
+                .iter()
+                .take(5)
+                .map(|rows| (col.cell_string(rows[0]), rows.clone()))
+                .collect(),
+        })
+    }
+
+    // set_value overwrites a single cell, the cell must match the column kind
+    pub fn set_value(&mut self, key: &str, row: usize, cell: Cell<T>) -> Result<(), NodFrameError> {
+        let idx = *self
+            .column_idx
+            .get(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        if row >= self.num_rows {
+            return Err(NodFrameError::OutOfBounds {
+                index: row,
+                len: self.num_rows,
+            });
+        }
+        match (&mut self.columns[idx], cell) {
+            (Column::Numeric(n), Cell::Num(v)) => {
+                n.items_mut()[row] = v;
+                self.sorted.remove(key);
+            }
+            (Column::Discrete(d), Cell::Str(v)) => {
+                d.check_level(row, &v)?;
+                d.items[row] = Arc::from(v)
+            }
+            (col, cell) => {
+                return Err(NodFrameError::KindMismatch {
+                    key: String::from(key),
+                    expected: col.kind(),
+                    found: cell.kind(),
+                })
+            }
+        }
+        self.debug_assert_valid();
+        Ok(())
+    }
+
+    // update_where sets target_col to new_value in every row matching the
+    // condition, given as for mask_where, and returns the number of rows set.
+    // A new_value of the wrong kind is an error and changes nothing
+    pub fn update_where(
+        &mut self,
+        cond_col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+        target_col: &str,
+        new_value: Cell<T>,
+    ) -> Result<usize, NodFrameError> {
+        self.update_where_with(cond_col, comp, val, str_val, target_col, |_| {
+            new_value.clone()
+        })
+    }
+
+    // update_where_with is update_where with the new value computed by f from
+    // the current cell. Every new value is computed and checked against the
+    // column kind before any row changes, so an error leaves the frame as it was
+    pub fn update_where_with(
+        &mut self,
+        cond_col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+        target_col: &str,
+        f: impl Fn(&Cell<T>) -> Cell<T>,
+    ) -> Result<usize, NodFrameError> {
+        let mask = self.mask_where(cond_col, comp, val, str_val)?;
+        let idx = *self
+            .column_idx
+            .get(target_col)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(target_col)))?;
+        let target = &self.columns[idx];
+        let updates = (0..self.num_rows)
+            .filter(|row| mask.as_slice()[*row])
+            .map(|row| {
+                let cell = f(&target.get_cell(row));
+                match (target, &cell) {
+                    (Column::Discrete(d), Cell::Str(v)) => d.check_level(row, v)?,
+                    (Column::Numeric(_), Cell::Num(_)) => {}
+                    _ => {
+                        return Err(NodFrameError::KindMismatch {
+                            key: String::from(target_col),
+                            expected: target.kind(),
+                            found: cell.kind(),
+                        })
+                    }
+                }
+                Ok((row, cell))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let changed = updates.len();
+        for (row, cell) in updates {
+            match (&mut self.columns[idx], cell) {
+                (Column::Numeric(n), Cell::Num(v)) => n.items_mut()[row] = v,
+                (Column::Discrete(d), Cell::Str(v)) => d.items[row] = Arc::from(v),
+                _ => unreachable!("cell kinds were checked above"),
+            }
+        }
+        if changed > 0 {
+            self.sorted.remove(target_col);
+        }
+        self.debug_assert_valid();
+        Ok(changed)
+    }
+
+    // push_row appends one cell to each column, in column order. Every cell is
+    // checked before any column changes, so a failed push leaves the frame as
+    // it was
+    pub fn push_row(&mut self, cells: &[Cell<T>]) -> Result<(), NodFrameError> {
+        if self.num_cols == 0 || cells.len() != self.num_cols {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "row has {} cells for {} columns",
+                cells.len(),
+                self.num_cols
+            )));
+        }
+        for (col, cell) in self.columns.iter().zip(cells) {
+            match (col, cell) {
+                (Column::Discrete(d), Cell::Str(v)) => d.check_level(self.num_rows, v)?,
+                (Column::Numeric(_), Cell::Num(_)) => {}
+                _ => {
+                    return Err(NodFrameError::KindMismatch {
+                        key: col.get_key().clone(),
+                        expected: col.kind(),
+                        found: cell.kind(),
+                    })
+                }
+            }
+        }
+        for (col, cell) in self.columns.iter_mut().zip(cells) {
+            match (col, cell) {
+                (Column::Numeric(n), Cell::Num(v)) => {
+                    // a sorted column stays sorted while values arrive in order
+                    if n.items.last().is_some_and(|last| {
+                        matches!(
+                            last.partial_cmp(v),
+                            Some(std::cmp::Ordering::Greater) | None
+                        )
+                    }) {
+                        self.sorted.remove(&n.key);
+                    }
+                    n.items_mut().push(v.clone());
+                }
+                (Column::Discrete(d), Cell::Str(v)) => d.items.push(Arc::from(v.as_str())),
+                _ => unreachable!("cell kinds were checked above"),
+            }
+        }
+        self.num_rows += 1;
+        self.debug_assert_pushed();
+        Ok(())
+    }
+
+    // push_row_map is push_row with the cells given by column key, every
+    // column needs exactly one cell
+    pub fn push_row_map(&mut self, cells: &HashMap<&str, Cell<T>>) -> Result<(), NodFrameError> {
+        if let Some(key) = cells.keys().find(|k| !self.column_idx.contains_key(**k)) {
+            return Err(NodFrameError::UnexpectedColumn(key.to_string()));
+        }
+        let row = self
+            .columns
+            .iter()
+            .map(|c| {
+                cells
+                    .get(c.get_key().as_str())
+                    .cloned()
+                    .ok_or_else(|| NodFrameError::MissingColumn(c.get_key().clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.push_row(&row)
+    }
+
+    // reserve_rows makes room for additional more rows in every column, so
+    // a run of push_row calls does not reallocate
+    pub fn reserve_rows(&mut self, additional: usize) {
+        for col in self.columns.iter_mut() {
+            match col {
+                Column::Numeric(n) => n.items.reserve(additional),
+                Column::Discrete(d) => d.items.reserve(additional),
+            }
+        }
+    }
+
+    // add_column_from_index appends new_key built from f called with each row
+    // index, the first cell decides the column kind and the others must match
+    pub fn add_column_from_index(
+        &self,
+        new_key: &str,
+        f: impl Fn(usize) -> Cell<T>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let mut numbers = Vec::new();
+        let mut strings = Vec::new();
+        let mut kind = ColKind::Numeric;
+        for row in 0..self.num_rows {
+            let cell = f(row);
+            if row == 0 {
+                kind = cell.kind();
+            }
+            match cell {
+                Cell::Num(v) if kind == ColKind::Numeric => numbers.push(v),
+                Cell::Str(s) if kind == ColKind::Discrete => strings.push(s),
+                cell => {
+                    return Err(NodFrameError::InvalidValue {
+                        key: String::from(new_key),
+                        row,
+                        message: format!("cell is {}, expected {}", cell.kind(), kind),
+                    })
+                }
+            }
+        }
+        let col = match kind {
+            ColKind::Numeric => Column::Numeric(NumericColumn::new(new_key, numbers)),
+            ColKind::Discrete => Column::Discrete(DiscreteColumn::new(new_key, strings)),
+        };
+        let out = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_column(col)
+            .build()?;
+        Ok(self.traced(out, "add_column_from_index", || {
+            vec![("new_key", String::from(new_key))]
+        }))
+    }
+
+    // zip_numeric yields the values of the numeric columns keys row by row,
+    // in the order of keys and borrowed from the frame, one item per row
+    pub fn zip_numeric<'a>(
+        &'a self,
+        keys: &[&str],
+    ) -> Result<impl Iterator<Item = Vec<&'a T>> + 'a, NodFrameError> {
+        let cols = self.zip_columns(keys, |k| self.numeric_column(k))?;
+        Ok((0..self.num_rows).map(move |row| cols.iter().map(|c| c.get(row)).collect()))
+    }
+
+    // zip_discrete is zip_numeric for discrete columns
+    pub fn zip_discrete<'a>(
+        &'a self,
+        keys: &[&str],
+    ) -> Result<impl Iterator<Item = Vec<&'a str>> + 'a, NodFrameError> {
+        let cols = self.zip_columns(keys, |k| self.discrete_column(k))?;
+        Ok((0..self.num_rows).map(move |row| cols.iter().map(|c| c.get(row)).collect()))
+    }
+
+    // zip_columns looks up every key with lookup, there must be at least one
+    fn zip_columns<'a, C>(
+        &'a self,
+        keys: &[&str],
+        lookup: impl Fn(&str) -> Result<&'a C, NodFrameError>,
+    ) -> Result<Vec<&'a C>, NodFrameError> {
+        if keys.is_empty() {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "zipping columns needs at least one key",
+            )));
+        }
+        keys.iter().map(|k| lookup(k)).collect()
+    }
+
+    // compute_from appends new_key holding f of the values of the numeric
+    // columns keys in each row, passed in the order of keys
+    pub fn compute_from(
+        &self,
+        keys: &[&str],
+        new_key: &str,
+        f: impl Fn(&[&T]) -> T,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let cols = self.zip_columns(keys, |k| self.numeric_column(k))?;
+        let mut row_values: Vec<&T> = Vec::with_capacity(cols.len());
+        let mut values = Vec::with_capacity(self.num_rows);
+        for row in 0..self.num_rows {
+            row_values.clear();
+            row_values.extend(cols.iter().map(|c| c.get(row)));
+            values.push(f(&row_values));
+        }
+        let out = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, values)
+            .build()?;
+        Ok(self.traced(out, "compute_from", || {
+            vec![("keys", keys.join(",")), ("new_key", String::from(new_key))]
+        }))
+    }
+
+    // with_row_ids appends new_key holding each row's index, which stays with
+    // the row through filters and sorts to trace it back to its position here
+    pub fn with_row_ids(&self, new_key: &str) -> Result<NodFrame<T>, NodFrameError> {
+        let mut ids = Vec::with_capacity(self.num_rows);
+        for row in 0..self.num_rows {
+            ids.push(T::from(row).ok_or_else(|| NodFrameError::ValueConversion {
+                key: String::from(new_key),
+                row,
+            })?);
+        }
+        let out = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(new_key, ids)
+            .build()?;
+        Ok(self.traced(out, "with_row_ids", || {
+            vec![("new_key", String::from(new_key))]
+        }))
+    }
+
+    // clip bounds the numeric column key into [low, high], replacing it in place
+    // when new_key is None or appending the result as new_key otherwise
+    pub fn clip(
+        &self,
+        key: &str,
+        low: Option<&T>,
+        high: Option<&T>,
+        new_key: Option<&str>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let clipped = self.numeric_column(key)?.clip(low, high)?;
+        let out = self.with_numeric_result(key, clipped, new_key)?;
+        Ok(self.traced(out, "clip", || {
+            let bound = |b: Option<&T>| b.map_or_else(String::new, |b| b.to_string());
+            vec![
+                ("key", String::from(key)),
+                ("low", bound(low)),
+                ("high", bound(high)),
+            ]
+        }))
+    }
+
+    // drop_non_finite keeps the rows where every numeric column is finite
+    pub fn drop_non_finite(&self) -> NodFrame<T> {
+        let mut keep = vec![true; self.num_rows];
+        for col in &self.columns {
+            if let Column::Numeric(n) = col {
+                for (k, finite) in keep.iter_mut().zip(n.finite_mask().as_slice()) {
+                    *k &= *finite;
+                }
+            }
+        }
+        self.traced(self.pick_rows(&keep), "drop_non_finite", Vec::new)
+    }
+
+    // replace_non_finite swaps NaN, inf and -inf for value in every numeric
+    // column. A replaced column is no longer known to be sorted
+    pub fn replace_non_finite(&self, value: T) -> NodFrame<T> {
+        let mut frame = self.clone();
+        for col in frame.columns.iter_mut() {
+            if let Column::Numeric(n) = col {
+                if n.has_non_finite() {
+                    frame.sorted.remove(&n.key);
+                    *n = n.replace_non_finite(value.clone());
+                }
+            }
+        }
+        self.traced(frame, "replace_non_finite", || {
+            vec![("value", value.to_string())]
+        })
+    }
+
+    // histogram returns a frame with a discrete "bin" label column and a
+    // numeric "count" column for the numeric column key
+    pub fn histogram(&self, key: &str, bins: usize) -> Result<NodFrame<T>, NodFrameError> {
+        let hist = self.numeric_column(key)?.histogram(bins)?;
+        let last = hist.len().saturating_sub(1);
+        let labels = hist
+            .iter()
+            .enumerate()
+            .map(|(i, (lo, hi, _))| {
+                let close = if i == last { "]" } else { ")" };
+                format!("[{}, {}{}", lo, hi, close)
+            })
+            .collect();
+        let counts = hist
+            .iter()
+            .map(|(_, _, c)| {
+                T::from(*c).ok_or_else(|| {
+                    NodFrameError::InvalidArgument(format!(
+                        "count {} does not fit the frame type",
+                        c
+                    ))
+                })
+            })
+            .collect::<Result<Vec<T>, NodFrameError>>()?;
+        let out = NodFrameBuilder::new()
+            .add_discrete("bin", labels)
+            .add_numeric("count", counts)
+            .build()?;
+        Ok(self.traced(out, "histogram", || {
+            vec![("key", String::from(key)), ("bins", bins.to_string())]
+        }))
+    }
+
+    // to_bar_chart renders one line per row as "label | ████ value", bars are
+    // scaled so the largest value spans width characters, values at or below
+    // zero get no bar and labels longer than 24 characters are shortened
+    pub fn to_bar_chart(
+        &self,
+        label_col: &str,
+        value_col: &str,
+        width: usize,
+    ) -> Result<String, NodFrameError> {
+        const MAX_LABEL: usize = 24;
+        let labels = self
+            .column(label_col)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(label_col)))?;
+        let values = self.numeric_column(value_col)?;
+        if self.num_rows == 0 {
+            return Ok(String::from("no data"));
+        }
+        let labels: Vec<String> = (0..self.num_rows)
+            .map(|i| {
+                let label = labels.cell_string(i);
+                if label.chars().count() > MAX_LABEL {
+                    let mut short: String = label.chars().take(MAX_LABEL - 1).collect();
+                    short.push('…');
+                    short
+                } else {
+                    label
+                }
+            })
+            .collect();
+        let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let max = values.iter().map(to_f64).fold(0.0, f64::max);
+
+        let mut chart = String::new();
+        for (label, value) in labels.iter().zip(values.iter()) {
+            let v = to_f64(value);
+            let len = if max > 0.0 && v > 0.0 {
+                (v / max * width as f64).round() as usize
+            } else {
+                0
+            };
+            let bar = "█".repeat(len);
+            let pad = " ".repeat(label_width - label.chars().count());
+            if bar.is_empty() {
+                chart.push_str(&format!("{}{} | {}\n", label, pad, value));
+            } else {
+                chart.push_str(&format!("{}{} | {} {}\n", label, pad, bar, value));
+            }
+        }
+        chart.pop();
+        Ok(chart)
+    }
+
+    // display_rows picks the rows to show when at most max_rows may be printed:
+    // the first and last halves, with None marking the elided middle
+    fn display_rows(&self, max_rows: Option<usize>) -> Vec<Option<usize>> {
+        match max_rows {
+            Some(max) if self.num_rows > max => {
+                let head = max.div_ceil(2);
+                let tail = max / 2;
+                (0..head)
+                    .map(Some)
+                    .chain(std::iter::once(None))
+                    .chain((self.num_rows - tail..self.num_rows).map(Some))
+                    .collect()
+            }
+            _ => (0..self.num_rows).map(Some).collect(),
+        }
+    }
+
+    // display_with formats like Display with the rows and numbers of opts,
+    // e.g. frame.display_with(&opts).to_string()
+    pub fn display_with<'a>(&'a self, opts: &'a DisplayOptions) -> FrameDisplay<'a, T> {
+        FrameDisplay { frame: self, opts }
+    }
+
+    fn shape_line(&self) -> String {
+        format!("{} rows × {} columns", self.num_rows, self.num_cols)
+    }
+
+    // to_markdown renders a GitHub-flavored table, numeric columns are right
+    // aligned and pipes in values are escaped
+    pub fn to_markdown(&self, max_rows: Option<usize>) -> String {
+        self.to_markdown_with(&DisplayOptions {
+            max_rows,
+            ..Default::default()
+        })
+    }
+
+    // to_markdown_with is to_markdown with the rows and numbers of opts
+    pub fn to_markdown_with(&self, opts: &DisplayOptions) -> String {
+        fn escape(v: &str) -> String {
+            v.replace('|', "\\|").replace('\n', "<br>")
+        }
+        let mut out = String::new();
+        let header: Vec<String> = self.columns.iter().map(|c| escape(c.get_key())).collect();
+        out.push_str(&format!("| {} |\n", header.join(" | ")));
+        let align: Vec<&str> = self
+            .columns
+            .iter()
+            .map(|c| match c.kind() {
+                ColKind::Numeric => "---:",
+                ColKind::Discrete => "---",
+            })
+            .collect();
+        out.push_str(&format!("| {} |\n", align.join(" | ")));
+        for row in self.display_rows(opts.max_rows) {
+            let cells: Vec<String> = match row {
+                Some(i) => self
+                    .columns
+                    .iter()
+                    .map(|c| escape(&c.cell_string_with(i, opts.float_format)))
+                    .collect(),
+                None => vec![String::from("…"); self.num_cols],
+            };
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        out.push_str(&format!("\n{}\n", self.shape_line()));
+        out
+    }
+
+    // to_html renders a plain <table> with the shape as its caption
+    pub fn to_html(&self, max_rows: Option<usize>) -> String {
+        self.to_html_with(&DisplayOptions {
+            max_rows,
+            ..Default::default()
+        })
+    }
+
+    // to_html_with is to_html with the rows and numbers of opts
+    pub fn to_html_with(&self, opts: &DisplayOptions) -> String {
+        fn escape(v: &str) -> String {
+            v.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        }
+        let mut out = String::from("<table>\n");
+        out.push_str(&format!("<caption>{}</caption>\n", self.shape_line()));
+        out.push_str("<thead><tr>");
+        for col in self.columns.iter() {
+            out.push_str(&format!("<th>{}</th>", escape(col.get_key())));
+        }
+        out.push_str("</tr></thead>\n<tbody>\n");
+        for row in self.display_rows(opts.max_rows) {
+            out.push_str("<tr>");
+            for col in self.columns.iter() {
+                let cell = match row {
+                    Some(i) => escape(&col.cell_string_with(i, opts.float_format)),
+                    None => String::from("…"),
+                };
+                out.push_str(&format!("<td>{}</td>", cell));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</tbody>\n</table>\n");
+        out
+    }
+
+    // with_numeric_result either replaces the column key with col or appends
+    // col under new_key
+    fn with_numeric_result(
+        &self,
+        key: &str,
+        col: NumericColumn<T>,
+        new_key: Option<&str>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let builder = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()));
+        match new_key {
+            Some(new_key) => builder.add_numeric(new_key, col.items).build(),
+            None => {
+                let mut frame = builder.build()?;
+                frame.set_column(key, Column::Numeric(col))?;
+                Ok(frame)
+            }
+        }
+    }
+
+    // count_where counts the rows filter would keep, scanning only the
+    // filtered column instead of building the filtered frame
+    pub fn count_where(
+        &self,
+        col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> Result<usize, NodFrameError> {
+        let matches = self.row_predicate(col, comp, val, str_val)?;
+        Ok((0..self.num_rows).filter(|i| matches(*i)).count())
+    }
+
+    // exists_where stops at the first matching row
+    pub fn exists_where(
+        &self,
+        col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> Result<bool, NodFrameError> {
+        let matches = self.row_predicate(col, comp, val, str_val)?;
+        Ok((0..self.num_rows).any(matches))
+    }
+
+    // which returns the ascending row indices that filter would keep
+    pub fn which(
+        &self,
+        col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> Result<Vec<usize>, NodFrameError> {
+        let matches = self.row_predicate(col, comp, val, str_val)?;
+        Ok((0..self.num_rows).filter(|i| matches(*i)).collect())
+    }
+
+    // take builds a frame from the rows at the given indices, in that order
+    pub fn take(&self, indices: &[usize]) -> Result<NodFrame<T>, NodFrameError> {
+        if let Some(bad) = indices.iter().find(|i| **i >= self.num_rows) {
+            return Err(NodFrameError::OutOfBounds {
+                index: *bad,
+                len: self.num_rows,
+            });
+        }
+        let taken = self
+            .columns
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.take(indices)))
+            .build()?;
+        Ok(self.traced(taken, "take", || vec![("rows", indices.len().to_string())]))
+    }
+
+    // count_where_mask counts the rows a mask keeps after checking its length
+    pub fn count_where_mask(&self, mask: &Mask) -> Result<usize, NodFrameError> {
+        self.check_mask(mask)?;
+        Ok(mask.count_true())
+    }
+
+    // mask_where computes the rows filter would keep as a Mask, which
+    // apply_mask can then use on this frame or any other with as many rows
+    pub fn mask_where(
+        &self,
+        col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> Result<Mask, NodFrameError> {
+        let matches = self.row_predicate(col, comp, val, str_val)?;
+        Ok(Mask::new((0..self.num_rows).map(matches).collect()))
+    }
+
+    // apply_mask keeps the rows where mask is true, in their order
+    pub fn apply_mask(&self, mask: &Mask) -> Result<NodFrame<T>, NodFrameError> {
+        self.check_mask(mask)?;
+        Ok(
+            self.traced(self.pick_rows(mask.as_slice()), "apply_mask", || {
+                vec![("kept", mask.count_true().to_string())]
+            }),
+        )
+    }
+
+    // filter_by_external filters this frame by a condition on other, which
+    // holds the same rows in the same order. Both frames are filtered with
+    // the same mask and returned as (self, other), so they stay row-aligned
+    pub fn filter_by_external(
+        &self,
+        other: &NodFrame<T>,
+        col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> Result<(NodFrame<T>, NodFrame<T>), NodFrameError> {
+        if other.num_rows != self.num_rows {
+            return Err(NodFrameError::LengthMismatch {
+                key: String::from(col),
+                expected: self.num_rows,
+                found: other.num_rows,
+            });
+        }
+        let mask = other.mask_where(col, comp, val, str_val)?;
+        Ok((self.apply_mask(&mask)?, other.apply_mask(&mask)?))
+    }
+
+    fn check_mask(&self, mask: &Mask) -> Result<(), NodFrameError> {
+        if mask.len() != self.num_rows {
+            return Err(NodFrameError::LengthMismatch {
+                key: String::from("mask"),
+                expected: self.num_rows,
+                found: mask.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // row_predicate validates filter arguments the way filter uses them:
+    // numeric columns compare against val, discrete ones str_val as in
+    // DiscreteColumn::filter_comp
+    fn row_predicate(
+        &self,
+        col: &str,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> Result<Box<dyn Fn(usize) -> bool + '_>, NodFrameError> {
+        let column = self
+            .column(col)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(col)))?;
+        match (column, val, str_val) {
+            (Column::Numeric(n), Some(v), _) => Ok(Box::new(move |i| compare(n.get(i), &comp, &v))),
+            (Column::Discrete(d), _, Some(v)) => {
+                let bits = d.filter_comp(&v, comp)?;
+                Ok(Box::new(move |i| bits[i]))
+            }
+            (column, _, _) => Err(NodFrameError::InvalidArgument(format!(
+                "column '{}' is {} and needs a {} filter value",
+                col,
+                column.kind(),
+                column.kind()
+            ))),
+        }
+    }
+
+    // filter keeps the rows where column col compares to value with comp. A
+    // numeric column needs a FilterValue::Num, a discrete one a
+    // FilterValue::Str, compared as in DiscreteColumn::filter_comp
+    pub fn filter(
+        &self,
+        col: &str,
+        comp: Comp,
+        value: FilterValue<T>,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let column = self
+            .column(col)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(col)))?;
+        let params = || {
+            vec![
+                ("col", String::from(col)),
+                ("comp", format!("{:?}", comp)),
+                ("val", value.to_string()),
+            ]
+        };
+        let picker = match (column, &value) {
+            (Column::Numeric(n), FilterValue::Num(v)) => {
+                if self.sorted.contains(col) {
+                    if let Some(range) = n.sorted_range(v, comp) {
+                        return Ok(self.traced(self.row_range(range), "filter", params));
+                    }
+                }
+                n.filter_array(v, comp)
+            }
+            (Column::Discrete(d), FilterValue::Str(s)) => d.filter_comp(s, comp)?,
+            (column, value) => {
+                return Err(NodFrameError::FilterKindMismatch {
+                    key: String::from(col),
+                    column: column.kind(),
+                    value: value.kind(),
+                })
+            }
+        };
+        Ok(self.traced(self.pick_rows(&picker), "filter", params))
+    }
+
+    // filter_frame is filter with the value split by kind: val for numeric
+    // columns, str_val for discrete ones. It panics on a missing column or a
+    // value of the wrong kind
+    #[deprecated(note = "use filter, which returns an error for a value of the wrong kind")]
+    pub fn filter_frame(
+        &self,
+        col: String,
+        comp: Comp,
+        val: Option<T>,
+        str_val: Option<String>,
+    ) -> NodFrame<T> {
+        let discrete = self
+            .column(&col)
+            .is_some_and(|c| c.kind() == ColKind::Discrete);
+        let value = match (val, str_val) {
+            (_, Some(s)) if discrete => FilterValue::Str(s),
+            (Some(v), _) => FilterValue::Num(v),
+            (None, Some(s)) => FilterValue::Str(s),
+            (None, None) => panic!("filter_frame on '{}' needs val or str_val", col),
+        };
+        self.filter(&col, comp, value)
+            .unwrap_or_else(|e| panic!("filter_frame: {}", e))
+    }
+
+    // sort_by reorders the rows by the values of key, ties keep their order.
+    // NaN values go to the end whichever the direction
+    pub fn sort_by(&self, key: &str, ascending: bool) -> Result<NodFrame<T>, NodFrameError> {
+        self.sort_by_mode(key, ascending, SortMode::Lexical)
+    }
+
+    // sort_by_mode is sort_by with a choice of order for discrete columns
+    pub fn sort_by_mode(
+        &self,
+        key: &str,
+        ascending: bool,
+        mode: SortMode,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        col.check_levels()?;
+        let mut order: Vec<usize> = (0..self.num_rows).collect();
+        order.sort_by(|a, b| col.cmp_rows(*a, *b, ascending, mode));
+        let mut sorted = self.take(&order)?;
+        if ascending
+            && self
+                .column(key)
+                .is_some_and(|c| c.kind() == ColKind::Numeric)
+        {
+            sorted.sorted.insert(String::from(key));
+        }
+        Ok(self.traced(sorted, "sort_by", || {
+            vec![
+                ("key", String::from(key)),
+                ("ascending", ascending.to_string()),
+                ("mode", format!("{:?}", mode)),
+            ]
+        }))
+    }
+
+    // is_known_sorted reports whether key is a numeric column the frame knows
+    // to be ascending, as left by sort_by. Use NumericColumn::is_sorted to check
+    pub fn is_known_sorted(&self, key: &str) -> bool {
+        self.sorted.contains(key)
+    }
+
+    // filter_between keeps the rows where low <= key <= high. On a column left
+    // ascending by sort_by the rows are found by binary search
+    pub fn filter_between(&self, key: &str, low: T, high: T) -> Result<NodFrame<T>, NodFrameError> {
+        let col = self.numeric_column(key)?;
+        let out = if self.sorted.contains(key) {
+            let start = col.items.partition_point(|x| *x < low);
+            let end = col.items.partition_point(|x| *x <= high).max(start);
+            self.row_range(start..end)
+        } else {
+            let picker: Vec<bool> = col.items.iter().map(|x| *x >= low && *x <= high).collect();
+            self.pick_rows(&picker)
+        };
+        Ok(self.traced(out, "filter_between", || {
+            vec![
+                ("key", String::from(key)),
+                ("low", low.to_string()),
+                ("high", high.to_string()),
+            ]
+        }))
+    }
+
+    // pick_rows keeps the rows marked true, in order, so sorted columns stay
+    // sorted
+    pub(crate) fn pick_rows(&self, picker: &[bool]) -> NodFrame<T> {
+        self.with_rows_of(self.columns.iter().map(|c| c.binary_view(picker)).collect())
+    }
+
+    // row_range keeps a contiguous run of rows, which keeps sorted columns sorted
+    fn row_range(&self, range: std::ops::Range<usize>) -> NodFrame<T> {
+        self.with_rows_of(
+            self.columns
+                .iter()
+                .map(|c| c.slice(range.start, range.end))
+                .collect(),
+        )
+    }
+
+    pub fn to_csv(&self, file_path: String) -> Result<(), Box<dyn Error>> {
+        self.to_csv_with_progress(file_path, &CsvWriteOptions::default(), |_| {})
+    }
+
+    // to_csv_with_progress writes the frame like to_csv, calling progress with
+    // the number of rows written every opts.progress_every rows and once at the
+    // end. A failed write reports the row being written when it surfaced, or
+    // the row count for the final flush. With opts.write_schema the schema
+    // sidecar is written next to the file, data.csv getting data.schema.json
+    pub fn to_csv_with_progress(
+        &self,
+        file_path: impl AsRef<Path>,
+        opts: &CsvWriteOptions,
+        progress: impl FnMut(usize),
+    ) -> Result<(), Box<dyn Error>> {
+        let file_path = file_path.as_ref();
+        self.write_csv(File::create(file_path)?, opts, progress)?;
+        if opts.write_schema {
+            self.write_schema_json(sidecar::sidecar_path(file_path))?;
+        }
+        Ok(())
+    }
+
+    // write_csv writes the frame as csv to sink, see to_csv_with_progress
+    pub(crate) fn write_csv(
+        &self,
+        sink: impl std::io::Write,
+        opts: &CsvWriteOptions,
+        mut progress: impl FnMut(usize),
+    ) -> Result<(), Box<dyn Error>> {
+        // a frame without columns is written as nothing at all
+        if self.columns.is_empty() {
+            progress(0);
+            return Ok(());
+        }
+        let mut writer = Writer::from_writer(sink);
+        writer.write_record(self.columns.iter().map(|x| x.get_key()))?;
+        let failed = |row: usize, e: &dyn Error| NodFrameError::WriteFailed {
+            row,
+            message: e.to_string(),
+        };
+        let mut buf = String::new();
+        let mut reported = 0;
+        for i in 0..self.num_rows {
+            for col in self.columns.iter() {
+                match col {
+                    Column::Numeric(n) => {
+                        buf.clear();
+                        match opts.float_format {
+                            FloatFormat::Shortest => write!(buf, "{}", n.items[i])?,
+                            format => buf.push_str(&format.format(&n.items[i])),
+                        }
+                        if opts.na_value.as_ref() == Some(&buf) {
+                            writer.write_field(&opts.na_rep)
+                        } else {
+                            writer.write_field(&buf)
+                        }
+                    }
+                    Column::Discrete(d) => writer.write_field(d.get(i)),
+                }
+                .map_err(|e| failed(i, &e))?;
+            }
+            writer
+                .write_record(None::<&[u8]>)
+                .map_err(|e| failed(i, &e))?;
+            if opts.progress_every > 0 && (i + 1).is_multiple_of(opts.progress_every) {
+                progress(i + 1);
+                reported = i + 1;
+            }
+        }
+        writer.flush().map_err(|e| failed(self.num_rows, &e))?;
+        if reported != self.num_rows || self.num_rows == 0 {
+            progress(self.num_rows);
+        }
+        Ok(())
+    }
+}
+
+// FrameRow describes how a struct maps onto the columns of a frame. keys lists
+// the columns in order, cells must return one cell per key in the same order
+// and from_cells rebuilds the struct from those cells.
+//
+// impl FrameRow<i64> for Measurement {
+//     fn keys() -> Vec<(String, ColKind)> {
+//         vec![(String::from("sensor"), ColKind::Discrete), (String::from("reading"), ColKind::Numeric)]
+//     }
+//     fn cells(&self) -> Vec<Cell<i64>> {
+//         vec![Cell::Str(self.sensor.clone()), Cell::Num(self.reading)]
+//     }
+//     fn from_cells(cells: Vec<Cell<i64>>) -> Option<Self> {
+//         match &cells[..] {
+//             [Cell::Str(sensor), Cell::Num(reading)] => Some(Measurement { sensor: sensor.clone(), reading: *reading }),
+//             _ => None,
+//         }
+//     }
+// }
+pub trait FrameRow<T>: Sized {
+    fn keys() -> Vec<(String, ColKind)>;
+    fn cells(&self) -> Vec<Cell<T>>;
+    fn from_cells(cells: Vec<Cell<T>>) -> Option<Self>;
+}
+
+// frame_from_rows builds a frame from structs in a single pass over the rows
+pub fn frame_from_rows<T: NodNum, R: FrameRow<T>>(
+    rows: impl IntoIterator<Item = R>,
+) -> Result<NodFrame<T>, NodFrameError> {
+    let keys = R::keys();
+    let mut num_data: Vec<Vec<T>> = vec![Vec::new(); keys.len()];
+    let mut str_data: Vec<Vec<String>> = vec![Vec::new(); keys.len()];
+
+    for (r, row) in rows.into_iter().enumerate() {
+        let cells = row.cells();
+        if cells.len() != keys.len() {
+            return Err(NodFrameError::CellCountMismatch {
+                row: r,
+                expected: keys.len(),
+                found: cells.len(),
+            });
+        }
+        for (i, cell) in cells.into_iter().enumerate() {
+            match (cell, keys[i].1) {
+                (Cell::Num(n), ColKind::Numeric) => num_data[i].push(n),
+                (Cell::Str(s), ColKind::Discrete) => str_data[i].push(s),
+                (cell, kind) => {
+                    return Err(NodFrameError::KindMismatch {
+                        key: keys[i].0.clone(),
+                        expected: kind,
+                        found: cell.kind(),
+                    })
+                }
+            }
+        }
+    }
+
+    let mut builder = NodFrameBuilder::new();
+    for ((key, kind), (num, disc)) in keys.iter().zip(num_data.into_iter().zip(str_data)) {
+        builder = match kind {
+            ColKind::Numeric => builder.add_numeric(key, num),
+            ColKind::Discrete => builder.add_discrete(key, disc),
+        };
+    }
+    builder.build()
+}
+
+// frame["key"] panics when the column does not exist, use column() to check
+impl<T> Index<&str> for NodFrame<T> {
+    type Output = Column<T>;
+
+    fn index(&self, key: &str) -> &Column<T> {
+        match self.column_idx.get(key) {
+            Some(i) => &self.columns[*i],
+            None => panic!("no column named '{}'", key),
+        }
+    }
+}
+
+impl<T: std::string::ToString> fmt::Display for NodFrame<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "nodframe:")?;
+        for col in self.columns.iter() {
+            writeln!(f, "{}", col)?;
+        }
+        write!(f, "Num Rows: {}", self.num_rows)
+    }
+}
+
+// FrameDisplay is a frame formatted with DisplayOptions, see display_with
+pub struct FrameDisplay<'a, T> {
+    frame: &'a NodFrame<T>,
+    opts: &'a DisplayOptions,
+}
+
+impl<T: NodNum> fmt::Display for FrameDisplay<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self.frame.display_rows(self.opts.max_rows);
+        writeln!(f, "nodframe:")?;
+        for col in self.frame.columns.iter() {
+            let cells: Vec<String> = rows
+                .iter()
+                .map(|row| match row {
+                    Some(i) => display_value(&col.cell_string_with(*i, self.opts.float_format))
+                        .into_owned(),
+                    None => String::from("…"),
+                })
+                .collect();
+            writeln!(f, "{}: [{}]", col.get_key(), cells.join(", "))?;
+        }
+        write!(f, "Num Rows: {}", self.frame.num_rows)
+    }
+}
+
+// MemoryReport lists the estimated bytes of each column in frame order
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryReport {
+    columns: Vec<(String, usize)>,
+}
+
+impl MemoryReport {
+    pub fn columns(&self) -> &[(String, usize)] {
+        &self.columns
+    }
+
+    pub fn total(&self) -> usize {
+        self.columns.iter().map(|(_, b)| b).sum()
+    }
+}
+
+// format_bytes renders a byte count with a binary unit, e.g. "1.5 KiB"
+fn format_bytes(bytes: usize) -> String {
+    let units = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < units.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .columns
+            .iter()
+            .map(|(k, _)| k.chars().count())
+            .chain(["column".len(), "total".len()])
+            .max()
+            .unwrap_or(0);
+        writeln!(f, "{:<width$}  bytes", "column", width = width)?;
+        for (key, bytes) in self.columns.iter() {
+            writeln!(
+                f,
+                "{:<width$}  {}",
+                key,
+                format_bytes(*bytes),
+                width = width
+            )?;
+        }
+        write!(
+            f,
+            "{:<width$}  {}",
+            "total",
+            format_bytes(self.total()),
+            width = width
+        )
+    }
+}
+
+// InvariantViolation is one inconsistency found by NodFrame::validate
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvariantViolation {
+    ColumnLength {
+        key: String,
+        num_rows: usize,
+        found: usize,
+    },
+    ColumnCount {
+        recorded: usize,
+        actual: usize,
+    },
+    DuplicateKey(String),
+    MissingIndex(String),
+    WrongIndex {
+        key: String,
+        index: usize,
+        position: usize,
+    },
+    StaleIndex(String),
+    SortedFlag(String),
+    UnknownLevel {
+        key: String,
+        row: usize,
+        value: String,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::ColumnLength {
+                key,
+                num_rows,
+                found,
+            } => write!(
+                f,
+                "column '{}' has {} rows, frame has {}",
+                key, found, num_rows
+            ),
+            InvariantViolation::ColumnCount { recorded, actual } => {
+                write!(f, "frame records {} columns but holds {}", recorded, actual)
+            }
+            InvariantViolation::DuplicateKey(key) => write!(f, "duplicate column key '{}'", key),
+            InvariantViolation::MissingIndex(key) => {
+                write!(f, "column '{}' is missing from the key index", key)
+            }
+            InvariantViolation::WrongIndex {
+                key,
+                index,
+                position,
+            } => write!(
+                f,
+                "key index maps '{}' to {} but the column is at {}",
+                key, index, position
+            ),
+            InvariantViolation::StaleIndex(key) => {
+                write!(f, "key index lists '{}' which is not a column", key)
+            }
+            InvariantViolation::SortedFlag(key) => {
+                write!(f, "column '{}' is flagged sorted but is not ascending", key)
+            }
+            InvariantViolation::UnknownLevel { key, row, value } => write!(
+                f,
+                "column '{}' row {}: '{}' is not one of the levels",
+                key, row, value
+            ),
+        }
+    }
+}
+
+// CompareOptions controls NodFrame::compare. key pairs rows by the values of
+// that column instead of by position, tolerance is the largest absolute
+// difference at which numeric cells still count as equal, and at most
+// max_mismatches cells are listed (all of them are counted)
+#[derive(Clone, Debug)]
+pub struct CompareOptions {
+    pub key: Option<String>,
+    pub tolerance: f64,
+    pub ignore_column_order: bool,
+    pub max_mismatches: usize,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            key: None,
+            tolerance: 0.0,
+            ignore_column_order: false,
+            max_mismatches: 20,
+        }
+    }
+}
+
+// CellMismatch is one differing cell, row is the row index or the key value
+// when rows are paired by key
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellMismatch {
+    pub row: String,
+    pub column: String,
+    pub left: String,
+    pub right: String,
+}
+
+// FrameDiff is the result of NodFrame::compare
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameDiff {
+    columns_only_in_left: Vec<String>,
+    columns_only_in_right: Vec<String>,
+    column_order_differs: bool,
+    kind_mismatches: Vec<(String, ColKind, ColKind)>,
+    rows_only_in_left: Vec<String>,
+    rows_only_in_right: Vec<String>,
+    mismatches: Vec<CellMismatch>,
+    mismatch_count: usize,
+}
+
+impl FrameDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == FrameDiff::default()
+    }
+
+    pub fn columns_only_in_left(&self) -> &[String] {
+        &self.columns_only_in_left
+    }
+
+    pub fn columns_only_in_right(&self) -> &[String] {
+        &self.columns_only_in_right
+    }
+
+    pub fn column_order_differs(&self) -> bool {
+        self.column_order_differs
+    }
+
+    // kind_mismatches lists (key, left kind, right kind), such columns are not
+    // compared cell by cell
+    pub fn kind_mismatches(&self) -> &[(String, ColKind, ColKind)] {
+        &self.kind_mismatches
+    }
+
+    // rows_only_in_left lists row indices, or key values when paired by key
+    pub fn rows_only_in_left(&self) -> &[String] {
+        &self.rows_only_in_left
+    }
+
+    pub fn rows_only_in_right(&self) -> &[String] {
+        &self.rows_only_in_right
+    }
+
+    pub fn mismatches(&self) -> &[CellMismatch] {
+        &self.mismatches
+    }
+
+    pub fn mismatch_count(&self) -> usize {
+        self.mismatch_count
+    }
+}
+
+impl fmt::Display for FrameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "frames are equal");
+        }
+        let mut lines = Vec::new();
+        if !self.columns_only_in_left.is_empty() {
+            lines.push(format!(
+                "columns only in left: {}",
+                self.columns_only_in_left.join(", ")
+            ));
+        }
+        if !self.columns_only_in_right.is_empty() {
+            lines.push(format!(
+                "columns only in right: {}",
+                self.columns_only_in_right.join(", ")
+            ));
+        }
+        if self.column_order_differs {
+            lines.push(String::from("column order differs"));
+        }
+        for (key, l, r) in self.kind_mismatches.iter() {
+            lines.push(format!(
+                "column '{}' is {} in left and {} in right",
+                key, l, r
+            ));
+        }
+        if !self.rows_only_in_left.is_empty() {
+            lines.push(format!(
+                "rows only in left: {}",
+                self.rows_only_in_left.join(", ")
+            ));
+        }
+        if !self.rows_only_in_right.is_empty() {
+            lines.push(format!(
+                "rows only in right: {}",
+                self.rows_only_in_right.join(", ")
+            ));
+        }
+        if self.mismatch_count > 0 {
+            lines.push(format!(
+                "cell mismatches: {} (showing {})",
+                self.mismatch_count,
+                self.mismatches.len()
+            ));
+            for m in self.mismatches.iter() {
+                lines.push(format!(
+                    "  row {}, column '{}': {} != {}",
+                    m.row, m.column, m.left, m.right
+                ));
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+// frame_from_csv reads in a csv and automatically converts it into a
+pub fn frame_from_csv<T: NodNum>(
+    file_path: impl AsRef<Path>,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    frame_from_csv_with_options(file_path, &CsvOptions::default())
+}
+
+// frame_from_csv_with_options is frame_from_csv with control over which rows
+// and columns are read
+pub fn frame_from_csv_with_options<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    read_csv(file_path, opts, |header| Ok(vec![None; header.len()]))
+}
+
+// read_csv_columns is frame_from_csv_with_options keeping only columns, in
+// file order, whatever use_columns says
+pub fn read_csv_columns<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    columns: &[&str],
+    opts: &CsvOptions,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let opts = CsvOptions {
+        use_columns: Some(columns.iter().map(|c| c.to_string()).collect()),
+        ..opts.clone()
+    };
+    frame_from_csv_with_options(file_path, &opts)
+}
+
+// read_csv_column streams one column of a csv file into a Vec without
+// building a frame, other fields are never copied or parsed. Rows are chosen
+// like frame_from_csv_with_options does. A cell in na_strings takes na_value,
+// and without an na_value it fails to parse like any other bad value
+pub fn read_csv_column<T: FromStr + Clone>(
+    file_path: impl AsRef<Path>,
+    column: &str,
+    opts: &CsvOptions,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    let na_value = match &opts.na_value {
+        Some(text) => Some(text.parse::<T>().map_err(|_| {
+            NodFrameError::InvalidArgument(format!("na_value '{}' is not numeric", text))
+        })?),
+        None => None,
+    };
+    let mut values = Vec::new();
+    for_each_csv_cell(file_path, column, opts, |cell, missing, line| {
+        let value = match (missing, &na_value) {
+            (true, Some(v)) => v.clone(),
+            _ => cell.parse::<T>().map_err(|_| NodFrameError::ParseError {
+                key: String::from(column),
+                line,
+                value: String::from(cell),
+            })?,
+        };
+        values.push(value);
+        Ok(())
+    })?;
+    Ok(values)
+}
+
+// read_csv_column_str is read_csv_column keeping the text, cells in
+// na_strings become empty strings as in a discrete column
+pub fn read_csv_column_str(
+    file_path: impl AsRef<Path>,
+    column: &str,
+    opts: &CsvOptions,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut values = Vec::new();
+    for_each_csv_cell(file_path, column, opts, |cell, missing, _| {
+        values.push(if missing {
+            String::new()
+        } else {
+            String::from(cell)
+        });
+        Ok(())
+    })?;
+    Ok(values)
+}
+
+// for_each_csv_cell calls f with the cell of column in every row read under
+// opts, whether the cell is in na_strings and its line
+fn for_each_csv_cell(
+    file_path: impl AsRef<Path>,
+    column: &str,
+    opts: &CsvOptions,
+    mut f: impl FnMut(&str, bool, u64) -> Result<(), NodFrameError>,
+) -> Result<(), Box<dyn Error>> {
+    for_each_csv_cells(file_path, [column], opts, |[(cell, missing)], line| {
+        f(cell, missing, line)
+    })
+}
+
+// for_each_csv_cells is for_each_csv_cell for several columns, f gets their
+// cells in the order of columns. A column may be named more than once
+pub(crate) fn for_each_csv_cells<const N: usize>(
+    file_path: impl AsRef<Path>,
+    columns: [&str; N],
+    opts: &CsvOptions,
+    mut f: impl FnMut([(&str, bool); N], u64) -> Result<(), NodFrameError>,
+) -> Result<(), Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(open_csv(file_path, opts)?);
+    let only = CsvOptions {
+        use_columns: Some(columns.iter().map(|c| c.to_string()).collect()),
+        ..opts.clone()
+    };
+    let layout = csv_layout(&mut reader, &only)?;
+    let index = columns.map(|c| layout.keep[layout.header.iter().position(|k| k == c).unwrap()]);
+    for_each_record(&mut reader, layout.width, opts, None, |record, position| {
+        let mut cells = [("", false); N];
+        for (cell, i) in cells.iter_mut().zip(index) {
+            // a short row kept by PadTruncate has an empty cell
+            let bytes = record.get(i).unwrap_or_default();
+            let text = std::str::from_utf8(bytes).map_err(|_| NodFrameError::InvalidEncoding {
+                encoding: "UTF-8",
+                offset: position.byte() as usize,
+            })?;
+            *cell = (text, layout.na_strings.iter().any(|na| na == text));
+        }
+        f(cells, position.line())
+    })
+}
+
+// CsvOptions controls how a csv file is read. skip_rows data rows after the
+// header are skipped, reading stops after max_rows data rows and only the
+// columns named in use_columns are kept (in file order). ragged decides what
+// happens to rows whose field count differs from the header and encoding
+// names the text encoding of the file. A header name appearing twice is an
+// error unless dedup_headers is set, which renames the later copies "value_1",
+// "value_2" and so on. An empty header is named "column_<field>" after its
+// position; use_columns refers to the renamed headers.
+// Cells equal to one of na_strings are missing: discrete columns store them
+// as empty strings and numeric columns as na_value parsed as T. Without an
+// na_value a missing cell cannot be numeric, so an inferred column holding one
+// turns discrete and a schema numeric column fails to parse. parsers holds
+// the per-column parsers registered with parse_column. spill_discrete_over is
+// read by frame_from_csv_spilled only, see spill_discrete_over
+#[derive(Clone, Debug, Default)]
+pub struct CsvOptions {
+    pub skip_rows: usize,
+    pub max_rows: Option<usize>,
+    pub use_columns: Option<Vec<String>>,
+    pub ragged: RaggedPolicy,
+    pub dedup_headers: bool,
+    pub na_strings: Vec<String>,
+    pub na_value: Option<String>,
+    #[cfg(feature = "encoding")]
+    pub encoding: CsvEncoding,
+    pub parsers: ColumnParsers,
+    pub spill_discrete_over: Option<usize>,
+}
+
+// CsvWriteOptions for to_csv_with_progress, progress_every is the number of
+// rows between progress calls (0 reports only the end). Numeric cells equal
+// to na_value are written as na_rep, the counterpart of na_strings and
+// na_value in CsvOptions. Discrete columns hold missing cells as empty
+// strings and are written as they are. write_schema also writes the schema
+// sidecar read by frame_from_csv_enforcing_schema
+#[derive(Clone, Debug)]
+pub struct CsvWriteOptions {
+    pub progress_every: usize,
+    pub na_rep: String,
+    pub na_value: Option<String>,
+    pub float_format: FloatFormat,
+    pub write_schema: bool,
+}
+
+impl Default for CsvWriteOptions {
+    fn default() -> Self {
+        CsvWriteOptions {
+            progress_every: 100_000,
+            na_rep: String::new(),
+            na_value: None,
+            float_format: FloatFormat::default(),
+            write_schema: false,
+        }
+    }
+}
+
+// FloatFormat controls how values of a float frame are written. Shortest is
+// the shortest text that parses back to the same value, so the default never
+// loses data. Decimals and Significant round and are opt-in, Significant(0)
+// keeps one digit like Significant(1). Integer frames, NaN and infinities are
+// always written as they are
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FloatFormat {
+    #[default]
+    Shortest,
+    Decimals(usize),
+    Significant(usize),
+}
+
+impl FloatFormat {
+    // format writes x according to the format
+    pub fn format<T: NodNum>(&self, x: &T) -> String {
+        let v = match (self, x.to_f64()) {
+            (FloatFormat::Shortest, _) | (_, None) => return x.to_string(),
+            (_, Some(v)) if !v.is_finite() || !is_float::<T>() => return x.to_string(),
+            (_, Some(v)) => v,
+        };
+        match *self {
+            FloatFormat::Decimals(places) => format!("{:.*}", places, v),
+            FloatFormat::Significant(digits) => {
+                // no digits at all would write every value as 0
+                let digits = digits.max(1);
+                if v == 0.0 {
+                    return format!("{:.*}", digits - 1, 0.0);
+                }
+                let mut magnitude = v.abs().log10().floor() as i64;
+                // rounding may carry into the next power of ten, 9.996 -> 10.0
+                let step = 10f64.powi((magnitude - digits as i64 + 1) as i32);
+                if (v.abs() / step).round() * step >= 10f64.powi(magnitude as i32 + 1) {
+                    magnitude += 1;
+                }
+                let places = digits as i64 - 1 - magnitude;
+                if places >= 0 {
+                    format!("{:.*}", places as usize, v)
+                } else {
+                    let scale = 10f64.powi(-places as i32);
+                    format!("{}", (v / scale).round() * scale)
+                }
+            }
+            FloatFormat::Shortest => unreachable!(),
+        }
+    }
+}
+
+// loses_text is true for a cell that parses as a number but would not be
+// written back as it was read in a way that matters: leading zeros as in the
+// zip code "02139", a leading "+" or whitespace around the number. Cells
+// such as "1.0" or "-0" may come back as "1" or "0" but hold the same number,
+// so they load as numbers
+pub(crate) fn loses_text(cell: &str) -> bool {
+    if cell.trim() != cell || cell.starts_with('+') {
+        return true;
+    }
+    let digits = cell.strip_prefix('-').unwrap_or(cell).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+// is_float tells float value types from integer ones
+pub(crate) fn is_float<T: NodNum>() -> bool {
+    T::from(0.5).and_then(|x: T| x.to_f64()) == Some(0.5)
+}
+
+// require_float fails with NeedsFloat when T is an integer type
+pub(crate) fn require_float<T: NodNum>(
+    key: &str,
+    operation: &'static str,
+) -> Result<(), NodFrameError> {
+    if is_float::<T>() {
+        return Ok(());
+    }
+    Err(NodFrameError::NeedsFloat {
+        key: String::from(key),
+        operation,
+    })
+}
+
+// DisplayOptions for display_with, to_markdown_with and to_html_with.
+// max_rows keeps the first and last rows around a "…" row
+#[derive(Clone, Debug, Default)]
+pub struct DisplayOptions {
+    pub max_rows: Option<usize>,
+    pub float_format: FloatFormat,
+}
+
+// RaggedPolicy for rows with too few or too many fields: Error reports the
+// line, SkipRow drops the row and PadTruncate pads short rows with empty
+// strings and cuts long rows down to the header length. Padded cells are
+// ordinary values, so a padded numeric column is inferred as discrete
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RaggedPolicy {
+    #[default]
+    Error,
+    SkipRow,
+    PadTruncate,
+}
+
+// frame_from_csv_with_schema loads columns with the kinds given by schema.
+// Numeric columns must parse on every row. Columns in the file that the schema
+// does not list are an error for a strict schema and are inferred otherwise,
+// schema columns missing from the file are always an error
+pub fn frame_from_csv_with_schema<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    schema: &Schema,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    read_csv(file_path, &CsvOptions::default(), |header| {
+        if let Some((key, _)) = schema.columns().iter().find(|(k, _)| !header.contains(k)) {
+            return Err(NodFrameError::MissingColumn(key.clone()));
+        }
+        header
+            .iter()
+            .map(|key| match schema.kind_of(key) {
+                None if schema.is_strict() => Err(NodFrameError::UnexpectedColumn(key.clone())),
+                kind => Ok(kind),
+            })
+            .collect()
+    })
+}
+
+// CsvColumn accumulates one column while a csv file is read. Inferred columns
+// stay numeric until a cell fails to parse, their text is kept in one shared
+// buffer so they can fall back to discrete without a String per cell.
+// Discrete columns intern their cells, repeated values share one allocation.
+// An inferred column holding a number that loses_text turns discrete with
+// demoted set, which stays set while every cell still parses
+pub(crate) enum CsvColumn<T> {
+    Inferred {
+        values: Vec<T>,
+        text: String,
+        ends: Vec<usize>,
+    },
+    Numeric(Vec<T>),
+    Discrete {
+        values: Vec<Arc<str>>,
+        interner: Interner,
+        demoted: bool,
+    },
+    Parsed {
+        values: Vec<T>,
+        parser: Arc<ParseFn<T>>,
+    },
+}
+
+// inferred_strings splits the text buffer of an inferred column into cells
+fn inferred_strings(text: &str, ends: &[usize]) -> Vec<String> {
+    let mut strings = Vec::with_capacity(ends.len());
+    let mut start = 0;
+    for end in ends.iter() {
+        strings.push(text[start..*end].to_string());
+        start = *end;
+    }
+    strings
+}
+
+impl<T: NodNum> CsvColumn<T> {
+    // discrete interns strings into a new discrete column
+    fn discrete<S: AsRef<str>>(strings: impl IntoIterator<Item = S>) -> Self {
+        let mut interner = Interner::default();
+        let values = strings
+            .into_iter()
+            .map(|s| interner.intern(s.as_ref()))
+            .collect();
+        CsvColumn::Discrete {
+            values,
+            interner,
+            demoted: false,
+        }
+    }
+
+    // demoted is whether the column is discrete only because of numbers that
+    // lose their text, see NodFrame::demoted_columns
+    fn is_demoted(&self) -> bool {
+        matches!(self, CsvColumn::Discrete { demoted: true, .. })
+    }
+
+    fn with_demoted(mut self, demote: bool) -> Self {
+        if let CsvColumn::Discrete { demoted, .. } = &mut self {
+            *demoted = demote;
+        }
+        self
+    }
+
+    pub(crate) fn new(kind: Option<ColKind>) -> Self {
+        match kind {
+            None => CsvColumn::Inferred {
+                values: Vec::new(),
+                text: String::new(),
+                ends: Vec::new(),
+            },
+            Some(ColKind::Numeric) => CsvColumn::Numeric(Vec::new()),
+            Some(ColKind::Discrete) => CsvColumn::discrete(Vec::<String>::new()),
+        }
+    }
+
+    // push adds one cell, line reports numeric cells of a schema column that
+    // do not parse
+    fn push(&mut self, key: &str, cell: &str, line: u64) -> Result<(), NodFrameError> {
+        match self {
+            CsvColumn::Inferred { values, text, ends } => match cell.parse::<T>() {
+                Ok(n) if !loses_text(cell) => {
+                    values.push(n);
+                    text.push_str(cell);
+                    ends.push(text.len());
+                }
+                parsed => {
+                    let mut strings = inferred_strings(text, ends);
+                    strings.push(cell.to_string());
+                    *self = CsvColumn::discrete(strings).with_demoted(parsed.is_ok());
+                }
+            },
+            CsvColumn::Numeric(values) => {
+                let n = cell.parse::<T>().map_err(|_| NodFrameError::ParseError {
+                    key: key.to_string(),
+                    line,
+                    value: cell.to_string(),
+                })?;
+                values.push(n);
+            }
+            CsvColumn::Discrete {
+                values,
+                interner,
+                demoted,
+            } => {
+                if *demoted && cell.parse::<T>().is_err() {
+                    *demoted = false;
+                }
+                values.push(interner.intern(cell))
+            }
+            CsvColumn::Parsed { values, parser } => {
+                let n = parser(cell).map_err(|message| NodFrameError::ParserFailed {
+                    key: key.to_string(),
+                    line,
+                    value: cell.to_string(),
+                    message,
+                })?;
+                values.push(n);
+            }
+        }
+        Ok(())
+    }
+
+    // push_missing adds a cell matching one of the na strings, see CsvOptions
+    fn push_missing(
+        &mut self,
+        key: &str,
+        cell: &str,
+        na_value: Option<&str>,
+        line: u64,
+    ) -> Result<(), NodFrameError> {
+        let value = match na_value {
+            Some(text) => Some(text.parse::<T>().map_err(|_| {
+                NodFrameError::InvalidArgument(format!("na_value '{}' is not numeric", text))
+            })?),
+            None => None,
+        };
+        match self {
+            CsvColumn::Inferred { values, text, ends } => match value {
+                Some(v) => {
+                    values.push(v);
+                    ends.push(text.len());
+                }
+                None => {
+                    let mut strings = inferred_strings(text, ends);
+                    strings.push(String::new());
+                    *self = CsvColumn::discrete(strings);
+                }
+            },
+            CsvColumn::Numeric(values) | CsvColumn::Parsed { values, .. } => {
+                values.push(value.ok_or_else(|| NodFrameError::ParseError {
+                    key: key.to_string(),
+                    line,
+                    value: cell.to_string(),
+                })?);
+            }
+            CsvColumn::Discrete {
+                values,
+                interner,
+                demoted,
+            } => {
+                // without na_value an inferred column would turn discrete anyway
+                *demoted = *demoted && value.is_some();
+                values.push(interner.intern(""))
+            }
+        }
+        Ok(())
+    }
+
+    // append adds the rows of other, which must come from the same column of
+    // the file. An inferred part turns discrete if the other part already is
+    #[cfg(feature = "rayon")]
+    pub(crate) fn append(self, other: CsvColumn<T>) -> CsvColumn<T> {
+        match (self, other) {
+            (
+                CsvColumn::Inferred {
+                    mut values,
+                    mut text,
+                    mut ends,
+                },
+                CsvColumn::Inferred {
+                    values: more,
+                    text: more_text,
+                    ends: more_ends,
+                },
+            ) => {
+                let offset = text.len();
+                values.extend(more);
+                text.push_str(&more_text);
+                ends.extend(more_ends.iter().map(|e| e + offset));
+                CsvColumn::Inferred { values, text, ends }
+            }
+            (CsvColumn::Numeric(mut values), CsvColumn::Numeric(more)) => {
+                values.extend(more);
+                CsvColumn::Numeric(values)
+            }
+            (first, second) => {
+                // demoted parts only stay demoted next to parts still inferred
+                let demoted = [&first, &second]
+                    .iter()
+                    .all(|c| c.is_demoted() || matches!(c, CsvColumn::Inferred { .. }));
+                let mut values = first.into_strings();
+                values.extend(second.into_strings());
+                CsvColumn::discrete(values).with_demoted(demoted)
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    fn into_strings(self) -> Vec<String> {
+        match self {
+            CsvColumn::Inferred { text, ends, .. } => inferred_strings(&text, &ends),
+            CsvColumn::Numeric(values) | CsvColumn::Parsed { values, .. } => {
+                values.iter().map(|v| v.to_string()).collect()
+            }
+            CsvColumn::Discrete { values, .. } => values.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    // add_to finishes the column, see into_column
+    pub(crate) fn add_to(self, builder: NodFrameBuilder<T>, key: &str) -> NodFrameBuilder<T> {
+        builder.add_column(self.into_column(key))
+    }
+
+    // into_column finishes the column, inferred columns without any rows have
+    // nothing to infer from and stay discrete
+    pub(crate) fn into_column(self, key: &str) -> Column<T> {
+        match self {
+            CsvColumn::Inferred { values, .. } if values.is_empty() => {
+                Column::Discrete(DiscreteColumn::new(key, Vec::new()))
+            }
+            CsvColumn::Inferred { values, .. }
+            | CsvColumn::Numeric(values)
+            | CsvColumn::Parsed { values, .. } => Column::Numeric(NumericColumn::new(key, values)),
+            CsvColumn::Discrete { values, .. } => {
+                Column::Discrete(DiscreteColumn::from_shared(key, values))
+            }
+        }
+    }
+}
+
+// CsvLayout is the header of a csv file: width is the number of fields in the
+// file, header the kept keys and keep the field index of each kept key
+pub(crate) struct CsvLayout {
+    pub(crate) width: usize,
+    pub(crate) header: Vec<String>,
+    keep: Vec<usize>,
+    na_strings: Vec<String>,
+    na_value: Option<String>,
+    // (field, header, new name) of each header renamed, see
+    // name_empty_headers and dedup_headers
+    renamed: Vec<(usize, String, String)>,
+}
+
+// csv_layout reads the header and applies use_columns
+pub(crate) fn csv_layout<R: std::io::Read>(
+    reader: &mut csv::Reader<R>,
+    opts: &CsvOptions,
+) -> Result<CsvLayout, Box<dyn Error>> {
+    let mut all_headers: Vec<String> = reader.headers()?.iter().map(|s| s.to_string()).collect();
+    // a UTF-8 byte order mark would otherwise stick to the first key
+    if let Some(first) = all_headers.first_mut() {
+        if let Some(rest) = first.strip_prefix('\u{feff}') {
+            *first = rest.to_string();
+        }
+    }
+    // padding around a header is layout, not part of the key, see check_key
+    for header in all_headers.iter_mut() {
+        if header.trim() != header {
+            *header = header.trim().to_string();
+        }
+    }
+    let mut renamed = name_empty_headers(&mut all_headers);
+    let mut seen: HashSet<String> = HashSet::new();
+    for i in 0..all_headers.len() {
+        if seen.contains(&all_headers[i]) {
+            if !opts.dedup_headers {
+                return Err(Box::new(NodFrameError::DuplicateKey(
+                    all_headers[i].clone(),
+                )));
+            }
+            let base = all_headers[i].clone();
+            all_headers[i] = (1..)
+                .map(|n| format!("{}_{}", base, n))
+                .find(|name| !seen.contains(name) && !all_headers.contains(name))
+                .unwrap();
+            renamed.push((i, base, all_headers[i].clone()));
+        }
+        seen.insert(all_headers[i].clone());
+    }
+    renamed.sort_by_key(|r| r.0);
+    let keep: Vec<usize> = match &opts.use_columns {
+        None => (0..all_headers.len()).collect(),
+        Some(names) => {
+            if let Some(name) = names.iter().find(|n| !all_headers.contains(n)) {
+                return Err(Box::new(NodFrameError::InvalidArgument(format!(
+                    "unknown column '{}', available columns: {}",
+                    name,
+                    all_headers.join(", ")
+                ))));
+            }
+            (0..all_headers.len())
+                .filter(|i| names.contains(&all_headers[*i]))
+                .collect()
+        }
+    };
+    Ok(CsvLayout {
+        width: all_headers.len(),
+        header: keep.iter().map(|i| all_headers[*i].clone()).collect(),
+        keep,
+        na_strings: opts.na_strings.clone(),
+        na_value: opts.na_value.clone(),
+        renamed,
+    })
+}
+
+// name_empty_headers names the empty headers "column_<field>", as the index
+// column of a pandas export, and returns (field, "", new name) for each.
+// A name already in the header gets "_1", "_2" and so on
+pub(crate) fn name_empty_headers(headers: &mut [String]) -> Vec<(usize, String, String)> {
+    let mut renamed = Vec::new();
+    for i in 0..headers.len() {
+        if headers[i].is_empty() {
+            let base = format!("column_{}", i);
+            headers[i] = std::iter::once(base.clone())
+                .chain((1..).map(|n| format!("{}_{}", base, n)))
+                .find(|name| !headers.contains(name))
+                .unwrap();
+            renamed.push((i, String::new(), headers[i].clone()));
+        }
+    }
+    renamed
+}
+
+// keep_record applies the ragged policy, returning false for a record that
+// is skipped
+pub(crate) fn keep_record(
+    record: &ByteRecord,
+    width: usize,
+    ragged: RaggedPolicy,
+    line: u64,
+) -> Result<bool, NodFrameError> {
+    if record.len() == width {
+        return Ok(true);
+    }
+    match ragged {
+        RaggedPolicy::Error => Err(NodFrameError::RaggedRow {
+            line,
+            expected: width,
+            found: record.len(),
+        }),
+        RaggedPolicy::SkipRow => Ok(false),
+        RaggedPolicy::PadTruncate => Ok(true),
+    }
+}
+
+// push_record adds the kept fields of record to columns, line and byte are
+// the position of the record in the file
+pub(crate) fn push_record<T: NodNum>(
+    columns: &mut [CsvColumn<T>],
+    layout: &CsvLayout,
+    record: &ByteRecord,
+    line: u64,
+    byte: u64,
+) -> Result<(), NodFrameError> {
+    for ((column, key), i) in columns
+        .iter_mut()
+        .zip(layout.header.iter())
+        .zip(layout.keep.iter())
+    {
+        let bytes = record.get(*i).unwrap_or_default();
+        // the offset is that of the record holding the bad field
+        let cell = std::str::from_utf8(bytes).map_err(|_| NodFrameError::InvalidEncoding {
+            encoding: "UTF-8",
+            offset: byte as usize,
+        })?;
+        if layout.na_strings.iter().any(|na| na == cell) {
+            column.push_missing(key, cell, layout.na_value.as_deref(), line)?;
+        } else {
+            column.push(key, cell, line)?;
+        }
+    }
+    Ok(())
+}
+
+// open_csv opens file_path as UTF-8 text
+pub(crate) fn open_csv(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+) -> Result<Box<dyn std::io::Read>, Box<dyn Error>> {
+    #[cfg(feature = "encoding")]
+    return encoding::open_decoded(file_path, opts.encoding);
+    #[cfg(not(feature = "encoding"))]
+    {
+        let _ = opts;
+        Ok(Box::new(File::open(file_path)?))
+    }
+}
+
+// read_csv loads a csv file, kinds maps the kept header to the kind of each
+// column, None meaning inferred. Cells are read as bytes and only copied into
+// Strings for discrete columns
+pub(crate) fn read_csv<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+    kinds: impl FnOnce(&[String]) -> Result<Vec<Option<ColKind>>, NodFrameError>,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    read_csv_from(open_csv(file_path, opts)?, opts, kinds)
+}
+
+// for_each_record calls f with every record that passes the ragged policy,
+// skip_rows and max_rows, together with its position in the file. Rows the
+// policy skips or reshapes are added to ragged when it is given
+fn for_each_record<R: std::io::Read>(
+    reader: &mut csv::Reader<R>,
+    width: usize,
+    opts: &CsvOptions,
+    mut ragged: Option<&mut Vec<LoadEvent>>,
+    mut f: impl FnMut(&ByteRecord, &Position) -> Result<(), NodFrameError>,
+) -> Result<(), Box<dyn Error>> {
+    let mut record = ByteRecord::new();
+    let mut seen = 0;
+    let mut kept = 0;
+    while opts.max_rows.is_none_or(|m| kept < m) {
+        if !reader.read_byte_record(&mut record)? {
+            break;
+        }
+        let position = record.position().cloned().unwrap_or_else(Position::new);
+        let keep = keep_record(&record, width, opts.ragged, position.line())?;
+        if let Some(events) = ragged.as_deref_mut().filter(|_| record.len() != width) {
+            events.push(LoadEvent::ragged(
+                position.line(),
+                width,
+                record.len(),
+                !keep,
+            ));
+        }
+        if !keep {
+            continue;
+        }
+        seen += 1;
+        if seen <= opts.skip_rows {
+            continue;
+        }
+        kept += 1;
+        f(&record, &position)?;
+    }
+    Ok(())
+}
+
+// csv_columns starts the columns of layout, kinds maps the kept header to
+// the kind of each column as for read_csv
+pub(crate) fn csv_columns<T: NodNum>(
+    layout: &CsvLayout,
+    opts: &CsvOptions,
+    kinds: impl FnOnce(&[String]) -> Result<Vec<Option<ColKind>>, NodFrameError>,
+) -> Result<Vec<CsvColumn<T>>, NodFrameError> {
+    let mut columns: Vec<CsvColumn<T>> = kinds(&layout.header)?
+        .into_iter()
+        .map(CsvColumn::new)
+        .collect();
+    // a parser decides the kind of its column, see CsvOptions::parse_column
+    for key in opts.parsers.keys() {
+        let i = layout
+            .header
+            .iter()
+            .position(|k| k == key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        if let Some(parser) = opts.parsers.get::<T>(key)? {
+            columns[i] = CsvColumn::Parsed {
+                values: Vec::new(),
+                parser,
+            };
+        }
+    }
+    Ok(columns)
+}
+
+// read_csv_from is read_csv over any reader of already decoded text
+pub(crate) fn read_csv_from<T: NodNum>(
+    input: impl std::io::Read,
+    opts: &CsvOptions,
+    kinds: impl FnOnce(&[String]) -> Result<Vec<Option<ColKind>>, NodFrameError>,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    read_csv_with_report(input, opts, kinds, None)
+}
+
+// read_csv_with_report is read_csv_from adding the events of the load to
+// report when it is given, see frame_from_csv_with_report
+pub(crate) fn read_csv_with_report<T: NodNum>(
+    input: impl std::io::Read,
+    opts: &CsvOptions,
+    kinds: impl FnOnce(&[String]) -> Result<Vec<Option<ColKind>>, NodFrameError>,
+    report: Option<&mut LoadReport>,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(input);
+    let layout = csv_layout(&mut reader, opts)?;
+    let mut columns = csv_columns(&layout, opts, kinds)?;
+    let mut ragged = report.as_ref().map(|_| Vec::new());
+    let mut watch = report.as_ref().map(|_| ColumnWatch::new(columns.len()));
+    for_each_record(
+        &mut reader,
+        layout.width,
+        opts,
+        ragged.as_mut(),
+        |record, position| {
+            if let Some(watch) = watch.as_mut() {
+                watch.before(&columns);
+            }
+            push_record(
+                &mut columns,
+                &layout,
+                record,
+                position.line(),
+                position.byte(),
+            )?;
+            if let Some(watch) = watch.as_mut() {
+                watch.after(&columns, &layout, record, position.line());
+            }
+            Ok(())
+        },
+    )?;
+    if let (Some(report), Some(ragged), Some(watch)) = (report, ragged, watch) {
+        report.add_events(ragged.into_iter().chain(watch.finish(&columns, &layout)));
+    }
+    Ok(frame_from_csv_columns(&layout.header, columns)?)
+}
+
+// frame_from_csv_columns builds the frame of the columns read for header and
+// records the demoted ones, see NodFrame::demoted_columns
+pub(crate) fn frame_from_csv_columns<T: NodNum>(
+    header: &[String],
+    columns: Vec<CsvColumn<T>>,
+) -> Result<NodFrame<T>, NodFrameError> {
+    let demoted = header
+        .iter()
+        .zip(columns.iter())
+        .filter(|(_, c)| c.is_demoted())
+        .map(|(key, _)| key.clone())
+        .collect();
+    let mut frame = header
+        .iter()
+        .zip(columns)
+        .fold(NodFrameBuilder::new(), |b, (key, column)| {
+            column.add_to(b, key)
+        })
+        .build()?;
+    frame.demoted = demoted;
+    Ok(frame)
+}
+
+// frame_from_strings infers the kind of each column and builds the frame,
+// columns keep the order of header
+#[cfg(feature = "excel")]
+pub(crate) fn frame_from_strings<T: NodNum, K: AsRef<str>>(
+    header: &[K],
+    data: Vec<Vec<String>>,
+) -> Result<NodFrame<T>, NodFrameError> {
+    header
+        .iter()
+        .zip(data)
+        .fold(NodFrameBuilder::new(), |b, (key, column)| {
+            add_inferred(b, key.as_ref(), column)
+        })
+        .build()
+}
+
+#[cfg(feature = "excel")]
+fn add_inferred<T: NodNum>(
+    builder: NodFrameBuilder<T>,
+    key: &str,
+    column: Vec<String>,
+) -> NodFrameBuilder<T> {
+    // a column is numeric when every value parses, columns without any rows
+    // have nothing to infer from and stay discrete
+    if column.is_empty() {
+        return builder.add_discrete(key, column);
+    }
+    let mut col = Vec::with_capacity(column.len());
+    for element in column.iter() {
+        match element.parse::<T>() {
+            Ok(n) => col.push(n),
+            Err(_) => return builder.add_discrete(key, column),
+        }
+    }
+    builder.add_numeric(key, col)
+}
+
+// Schema lists the expected kind of each column for frame_from_csv_with_schema:
+// Schema::new().numeric("price").discrete("zip").numeric("qty")
+// A strict schema rejects columns it does not list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schema {
+    columns: Vec<(String, ColKind)>,
+    strict: bool,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema {
+            columns: Vec::new(),
+            strict: true,
+        }
+    }
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    pub fn numeric(self, key: &str) -> Schema {
+        self.column(key, ColKind::Numeric)
+    }
+
+    pub fn discrete(self, key: &str) -> Schema {
+        self.column(key, ColKind::Discrete)
+    }
+
+    // column sets the kind of key, replacing an earlier entry for the same key
+    pub fn column(mut self, key: &str, kind: ColKind) -> Schema {
+        match self.columns.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = kind,
+            None => self.columns.push((String::from(key), kind)),
+        }
+        self
+    }
+
+    pub fn strict(mut self, strict: bool) -> Schema {
+        self.strict = strict;
+        self
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn columns(&self) -> &[(String, ColKind)] {
+        &self.columns
+    }
+
+    pub fn kind_of(&self, key: &str) -> Option<ColKind> {
+        self.columns
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, kind)| *kind)
+    }
+}
+
+// Build functions for Frame
+// frame_from_vecs validates that every key has data, every column has the same
+// length and that no key is used twice before assembling the frame
+pub fn frame_from_vecs<T: NodNum>(
+    num_keys: Vec<String>,
+    num_data: Vec<Vec<T>>,
+    str_keys: Vec<String>,
+    str_data: Vec<Vec<String>>,
+) -> Result<NodFrame<T>, NodFrameError> {
+    if num_keys.len() != num_data.len() {
+        return Err(NodFrameError::KeyCountMismatch {
+            keys: num_keys.len(),
+            columns: num_data.len(),
+        });
+    }
+    if str_keys.len() != str_data.len() {
+        return Err(NodFrameError::KeyCountMismatch {
+            keys: str_keys.len(),
+            columns: str_data.len(),
+        });
+    }
+
+    let mut builder = NodFrameBuilder::new();
+    for (k, v) in num_keys.iter().zip(num_data) {
+        builder = builder.add_numeric(k, v);
+    }
+    for (k, v) in str_keys.iter().zip(str_data) {
+        builder = builder.add_discrete(k, v);
+    }
+    builder.build()
+}
+
+// frame_from_map builds a purely numeric frame, columns are ordered by key
+pub fn frame_from_map<T: NodNum>(
+    data: HashMap<String, Vec<T>>,
+) -> Result<NodFrame<T>, NodFrameError> {
+    NodFrameBuilder::from(data).build()
+}
+
+// frame_from_ndarray builds a numeric frame with one column per array column,
+// erroring when a value cannot be represented as T
+#[cfg(feature = "ndarray")]
+pub fn frame_from_ndarray<T: NodNum>(
+    arr: &ndarray::Array2<f64>,
+    keys: &[&str],
+) -> Result<NodFrame<T>, NodFrameError> {
+    if keys.len() != arr.ncols() {
+        return Err(NodFrameError::KeyCountMismatch {
+            keys: keys.len(),
+            columns: arr.ncols(),
+        });
+    }
+    let mut builder = NodFrameBuilder::new();
+    for (key, column) in keys.iter().zip(arr.columns()) {
+        let data = column
+            .iter()
+            .enumerate()
+            .map(|(row, x)| {
+                T::from(*x).ok_or_else(|| NodFrameError::ValueConversion {
+                    key: key.to_string(),
+                    row,
+                })
+            })
+            .collect::<Result<Vec<T>, NodFrameError>>()?;
+        builder = builder.add_numeric(key, data);
+    }
+    builder.build()
+}
+
+// ExplodeOptions controls how explode treats empty values. With
+// drop_empty_pieces "a;;b" yields two rows instead of three, and
+// keep_empty_cells keeps a row with an empty string for empty cells instead of
+// dropping the row entirely
+#[derive(Clone, Debug)]
+pub struct ExplodeOptions {
+    pub drop_empty_pieces: bool,
+    pub keep_empty_cells: bool,
+}
+
+impl Default for ExplodeOptions {
+    fn default() -> Self {
+        ExplodeOptions {
+            drop_empty_pieces: true,
+            keep_empty_cells: true,
+        }
+    }
+}
+
+// ExtraPieces decides what split_column does with values that contain more
+// separators than there are new columns: Error fails, Truncate drops the extra
+// pieces and Merge leaves the remainder (separators included) in the last column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtraPieces {
+    Error,
+    Truncate,
+    Merge,
+}
+
+// SplitOptions for split_column, the new columns take the place of the
+// original column, which is kept in front of them when keep_original is set
+#[derive(Clone, Debug)]
+pub struct SplitOptions {
+    pub keep_original: bool,
+    pub extra_pieces: ExtraPieces,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        SplitOptions {
+            keep_original: false,
+            extra_pieces: ExtraPieces::Error,
+        }
+    }
+}
+
+// NodFrameBuilder collects columns one at a time and validates them on build,
+// keys following key_policy
+#[derive(Clone, Debug)]
+pub struct NodFrameBuilder<T> {
+    columns: Vec<Column<T>>,
+    key_policy: KeyPolicy,
+}
+
+impl<T> Default for NodFrameBuilder<T> {
+    fn default() -> Self {
+        NodFrameBuilder {
+            columns: Vec::new(),
+            key_policy: KeyPolicy::default(),
+        }
+    }
+}
+
+impl<T: NodNum> NodFrameBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn key_policy(mut self, policy: KeyPolicy) -> Self {
+        self.key_policy = policy;
+        self
+    }
+
+    pub fn add_numeric(mut self, key: &str, data: Vec<T>) -> Self {
+        self.columns
+            .push(Column::Numeric(NumericColumn::new(key, data)));
+        self
+    }
+
+    pub fn add_discrete(mut self, key: &str, data: Vec<String>) -> Self {
+        self.columns
+            .push(Column::Discrete(DiscreteColumn::new(key, data)));
+        self
+    }
+
+    pub fn add_column(mut self, col: Column<T>) -> Self {
+        self.columns.push(col);
+        self
+    }
+
+    // build checks that all columns have the same length as the first one and
+    // that keys are valid and unique, columns keep the order they were added in
+    pub fn build(self) -> Result<NodFrame<T>, NodFrameError> {
+        let policy = self.key_policy;
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|c| {
+                let key = policy.apply(c.get_key())?;
+                Ok(c.with_key(&key))
+            })
+            .collect::<Result<Vec<Column<T>>, NodFrameError>>()?;
+        NodFrame::from_columns(columns)
+    }
+}
+
+impl<T: NodNum> From<HashMap<String, Vec<T>>> for NodFrameBuilder<T> {
+    fn from(data: HashMap<String, Vec<T>>) -> Self {
+        let mut data: Vec<(String, Vec<T>)> = data.into_iter().collect();
+        data.sort_by(|a, b| a.0.cmp(&b.0));
+        data.into_iter()
+            .fold(NodFrameBuilder::new(), |b, (k, v)| b.add_numeric(&k, v))
+    }
+}
+
+///// TESTS /////
+
+// assert_valid fails the test when frame breaks an invariant checked by
+// validate, tests call it on the results of operations that build frames
+#[cfg(test)]
+pub(crate) fn assert_valid<T: NodNum>(frame: &NodFrame<T>) {
+    if let Err(violations) = frame.validate() {
+        panic!("frame invariants broken: {:?}", violations);
+    }
+}
+
+// test_support holds the helpers the test modules share
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::path::PathBuf;
+
+    // strings turns literals into the values of a discrete column
+    pub(crate) fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    // temp_path is name in the temp dir, prefixed with the process id so
+    // test runs side by side do not share files
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("nodframe_{}_{}", std::process::id(), name))
+    }
+
+    // temp_csv writes contents to temp_path(name)
+    pub(crate) fn temp_csv(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = temp_path(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+mod col_tests {
+    use super::*;
+
+    #[test]
+    fn binary_view_test() {
+        let col = NumericColumn {
+            key: String::from("bing"),
+            items: vec![1, 2, 3],
+            summary: OnceLock::new(),
+        };
+        let b = col.binary_view(&[true, false, true]);
+        assert_eq!(vec![1, 3], b.items)
+    }
+
+    #[test]
+    fn slice_test() {
+        let col = NumericColumn {
+            key: String::from("bing"),
+            items: vec![1, 2, 3],
+            summary: OnceLock::new(),
+        };
+        let b = col.slice(1, 3);
+        assert_eq!(vec![2, 3], b.items)
+    }
+
+    #[test]
+    fn values_test() {
+        let col = NumericColumn {
+            key: String::from("bing"),
+            items: vec![1_i64, 2, 3, 3, 2, 1, 4],
+            summary: OnceLock::new(),
+        };
+        let b = col.values();
+        let mut c = HashSet::new();
+        c.extend([1, 2, 3, 4].iter());
+        assert_eq!(b, c);
+    }
+
+    #[test]
+    fn filter_test() {
+        let col = NumericColumn {
+            key: String::from("bing"),
+            items: vec![1_i64, 2, 3, 3, 2, 1, 4],
+            summary: OnceLock::new(),
+        };
+        let b = col.filter_array(&2, Comp::Leq);
+        let c = vec![true, true, false, false, true, true, false];
+        assert_eq!(c, b);
+    }
+
+    #[test]
+    fn clip_test() {
+        let col = NumericColumn::new("v", vec![-1000, 1, 2, 3, 4, 5, 6, 7, 8, 9000]);
+        let clipped = col.clip(Some(&0), Some(&10)).unwrap();
+        assert_eq!(clipped.min(), Some(0));
+        assert_eq!(clipped.max(), Some(10));
+        let upper = col.clip(None, Some(&5)).unwrap();
+        assert_eq!(upper.min(), Some(-1000));
+        assert_eq!(upper.max(), Some(5));
+        assert!(col.clip(Some(&3), Some(&2)).is_err());
+
+        assert_eq!(col.quantile(0.1), Some(1));
+        let wins = col.winsorize(0.1, 0.1).unwrap();
+        assert_eq!(wins.min(), Some(1));
+        assert_eq!(wins.max(), Some(8));
+        assert!(col.winsorize(0.0, 0.1).is_err());
+        assert!(col.winsorize(0.1, 0.6).is_err());
+
+        // NaN takes no rank, so the upper cutoff still clips 1000
+        let mut values: Vec<f64> = (1..=10).map(f64::from).collect();
+        values.extend([f64::NAN; 4]);
+        values.push(1000.0);
+        let gaps = NumericColumn::new("v", values);
+        assert_eq!(gaps.quantile(1.0), Some(1000.0));
+        assert_eq!(gaps.quantile(0.9), Some(10.0));
+        let wins = gaps.winsorize(0.1, 0.1).unwrap();
+        assert_eq!(wins.max(), Some(10.0));
+        assert_eq!(NumericColumn::new("v", vec![f64::NAN]).quantile(0.5), None);
+    }
+
+    #[test]
+    fn histogram_test() {
+        let col = NumericColumn::new("v", (0..=100_i64).collect());
+        let hist = col.histogram(4).unwrap();
+        // the maximum lands in the last bin
+        assert_eq!(
+            hist,
+            vec![(0, 25, 25), (25, 50, 25), (50, 75, 25), (75, 100, 26)]
+        );
+
+        let single = NumericColumn::new("v", vec![7_i64; 5]);
+        assert_eq!(single.histogram(3).unwrap(), vec![(7, 7, 5)]);
+
+        let explicit = col.histogram_with_edges(&[10, 20, 30]).unwrap();
+        assert_eq!(explicit, vec![(10, 20, 10), (20, 30, 11)]);
+        // integer edges are truncated, counts follow the reported edges
+        let uneven = NumericColumn::new("v", (0..100_i64).collect())
+            .histogram(4)
+            .unwrap();
+        assert_eq!(uneven[1], (24, 49, 25));
+        assert!(col.histogram(0).is_err());
+        assert!(col.histogram_with_edges(&[3, 1]).is_err());
+        // 0..=2 in 4 bins truncates to the edges 0, 0, 1, 1, 2
+        let narrow = NumericColumn::new("v", vec![0_i64, 1, 2])
+            .histogram(4)
+            .unwrap();
+        assert_eq!(narrow, vec![(0, 1, 1), (1, 2, 2)]);
+
+        // NaN values fall in no bin and NaN edges are rejected
+        let floats = NumericColumn::new("v", vec![0.5, f64::NAN, 1.5, 2.0, f64::NAN]);
+        assert_eq!(
+            floats.histogram(2).unwrap(),
+            vec![(0.5, 1.25, 1), (1.25, 2.0, 2)]
+        );
+        assert_eq!(
+            floats.histogram_with_edges(&[0.0, 1.0, 2.0]).unwrap(),
+            vec![(0.0, 1.0, 1), (1.0, 2.0, 2)]
+        );
+        assert!(floats.histogram_with_edges(&[0.0, f64::NAN]).is_err());
+        let flat = NumericColumn::new("v", vec![3.0, f64::NAN, 3.0]);
+        assert_eq!(flat.histogram(2).unwrap(), vec![(3.0, 3.0, 2)]);
+    }
+
+    #[test]
+    fn iter_test() {
+        let data = [(1_i64, "a"), (2, "b"), (3, "c")];
+        let col: NumericColumn<i64> = data.iter().map(|x| x.0).collect_column("value");
+        assert_eq!(col.key(), "value");
+        assert_eq!(col.iter().sum::<i64>(), col.sum());
+        assert_eq!(col.sum(), 6);
+        let disc: DiscreteColumn = data.iter().map(|x| x.1.to_string()).collect_column("tag");
+        assert_eq!(
+            disc.into_iter().collect::<Vec<String>>(),
+            vec!["a", "b", "c"]
+        );
+        let sliced = col.slice(1, 3);
+        assert_eq!(sliced.as_slice(), &[2, 3]);
+        assert_eq!(col.into_vec(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn index_test() {
+        let col = NumericColumn {
+            key: String::from("bing"),
+            items: vec![1, 2, 3],
+            summary: OnceLock::new(),
+        };
+        assert_eq!(col[2], 3);
+        assert_eq!(col.get_checked(3), None);
+        let col = DiscreteColumn::new("bong", vec![String::from("a")]);
+        assert_eq!(&col[0], "a");
+        assert_eq!(col.get_checked(1), None);
+    }
+
+    #[test]
+    fn map_indexed_test() {
+        let col = NumericColumn::new("n", vec![10, 20, 30]);
+        assert_eq!(
+            col.map_indexed(|i, x| x + i as i32).as_slice(),
+            &[10, 21, 32]
+        );
+        let disc = DiscreteColumn::new("s", vec![String::from("a"), String::from("b")]);
+        let labelled =
+            disc.map_indexed(|i, s| format!("{}{}", s, if i % 2 == 0 { "-even" } else { "-odd" }));
+        assert_eq!(labelled.iter().collect::<Vec<_>>(), &["a-even", "b-odd"]);
+        assert_eq!(labelled.key(), "s");
+    }
+
+    #[test]
+    fn slice_checked_test() {
+        let col = NumericColumn::new("n", vec![1, 2, 3]);
+        assert_eq!(col.slice_checked(0, 3).unwrap().as_slice(), &[1, 2, 3]);
+        assert_eq!(col.slice_checked(1, 1).unwrap().len(), 0);
+        assert_eq!(col.slice_checked(3, 3).unwrap().len(), 0);
+        assert_eq!(
+            col.slice_checked(2, 4).unwrap_err().to_string(),
+            "range 2..4 is out of bounds for length 3"
+        );
+        assert_eq!(
+            col.slice_checked(2, 1).unwrap_err().to_string(),
+            "range 2..1 starts after it ends (length 3)"
+        );
+        assert!(col.slice_checked(4, 4).is_err());
+        let disc = DiscreteColumn::new("s", vec![String::from("a"), String::from("b")]);
+        assert_eq!(
+            disc.slice_checked(1, 2).unwrap().iter().collect::<Vec<_>>(),
+            &["b"]
+        );
+        assert_eq!(disc.slice_checked(2, 2).unwrap().len(), 0);
+        assert!(disc.slice_checked(0, 3).is_err());
+        assert!(disc.slice_checked(1, 0).is_err());
+    }
+
+    #[test]
+    fn non_finite_test() {
+        let col = NumericColumn::new(
+            "v",
+            vec![2.0, f64::NAN, f64::INFINITY, -1.0, f64::NEG_INFINITY],
+        );
+        assert!(col.has_non_finite());
+        assert_eq!(col.count_non_finite(), 3);
+        assert_eq!(
+            col.finite_mask().as_slice(),
+            &[true, false, false, true, false]
+        );
+        assert_eq!(
+            col.replace_non_finite(0.0).as_slice(),
+            &[2.0, 0.0, 0.0, -1.0, 0.0]
+        );
+        assert!(!NumericColumn::new("n", vec![1_i64, 2]).has_non_finite());
+
+        // min and max skip NaN unless asked not to, infinities still count
+        assert_eq!(col.min(), Some(f64::NEG_INFINITY));
+        assert_eq!(col.max(), Some(f64::INFINITY));
+        assert!(col.min_with_nan().unwrap().is_nan());
+        assert!(col.max_with_nan().unwrap().is_nan());
+        assert_eq!(NumericColumn::new("v", vec![f64::NAN]).min(), None);
+
+        // NaN sorts last and the order is total, so sort_by cannot panic
+        let mut values = [
+            f64::NAN,
+            3.0,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            f64::INFINITY,
+            1.0,
+        ];
+        values.sort_by(cmp_nan_last);
+        assert_eq!(&values[..4], &[f64::NEG_INFINITY, 1.0, 3.0, f64::INFINITY]);
+        assert!(values[4].is_nan() && values[5].is_nan());
+    }
+}
+
+#[cfg(test)]
+mod frame_tests {
+    use super::*;
+    use crate::test_support::{strings, temp_path};
+
+    #[test]
+    fn csv_test() {
+        let frame = frame_from_vecs(
+            vec![String::from("value"), String::from("whoop")],
+            vec![vec![1, 2, 3, 4], vec![14, 54, 7, 2]],
+            vec![String::from("kabang")],
+            vec![vec![
+                String::from("1a"),
+                String::from("2a"),
+                String::from("3a"),
+                String::from("4a"),
+            ]],
+        )
+        .unwrap();
+        let path = temp_path("hehe.csv");
+        frame.to_csv(path.display().to_string()).unwrap();
+        let frame2 = frame_from_csv::<i32>(path).unwrap();
+        assert_eq!(frame.to_string(), frame2.to_string());
+    }
+
+    #[test]
+    fn to_csv_progress_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("n", (0..5).collect())
+            .add_discrete("s", strings(&["a", "b,c", "\"q\"", "", "e"]))
+            .build()
+            .unwrap();
+        let path = temp_path("progress.csv");
+        let mut calls = Vec::new();
+        let opts = CsvWriteOptions {
+            progress_every: 2,
+            ..Default::default()
+        };
+        frame
+            .to_csv_with_progress(&path, &opts, |rows| calls.push(rows))
+            .unwrap();
+        assert_eq!(calls, vec![2, 4, 5]);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "n,s\n0,a\n1,\"b,c\"\n2,\"\"\"q\"\"\"\n3,\n4,e\n"
+        );
+
+        calls.clear();
+        let opts = CsvWriteOptions {
+            progress_every: 0,
+            ..Default::default()
+        };
+        frame
+            .to_csv_with_progress(&path, &opts, |rows| calls.push(rows))
+            .unwrap();
+        assert_eq!(calls, vec![5]);
+    }
+
+    #[test]
+    fn from_vecs_discrete_only_test() {
+        let frame: NodFrame<i64> = frame_from_vecs(
+            vec![],
+            vec![],
+            vec![String::from("city")],
+            vec![vec![String::from("Oslo"), String::from("Rome")]],
+        )
+        .unwrap();
+        assert_eq!(frame.shape(), (2, 1));
+    }
+
+    #[test]
+    fn from_vecs_length_mismatch_test() {
+        let err = frame_from_vecs(
+            vec![String::from("a"), String::from("b")],
+            vec![vec![1, 2, 3], vec![1, 2]],
+            vec![],
+            vec![],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            NodFrameError::LengthMismatch {
+                key: String::from("b"),
+                expected: 3,
+                found: 2
+            }
+        );
+        let err = frame_from_vecs(
+            vec![String::from("a")],
+            vec![vec![1, 2, 3]],
+            vec![String::from("s")],
+            vec![vec![String::from("x")]],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("'s'"));
+    }
+
+    #[test]
+    fn from_vecs_duplicate_key_test() {
+        let err = frame_from_vecs(
+            vec![String::from("a")],
+            vec![vec![1]],
+            vec![String::from("a")],
+            vec![vec![String::from("x")]],
+        )
+        .unwrap_err();
+        assert_eq!(err, NodFrameError::DuplicateKey(String::from("a")));
+        let err =
+            frame_from_vecs::<i64>(vec![String::from("a")], vec![], vec![], vec![]).unwrap_err();
+        assert_eq!(
+            err,
+            NodFrameError::KeyCountMismatch {
+                keys: 1,
+                columns: 0
+            }
+        );
+    }
+
+    #[test]
+    fn empty_frame_test() {
+        let frame: NodFrame<i64> = frame_from_vecs(vec![], vec![], vec![], vec![]).unwrap();
+        assert_eq!(frame.shape(), (0, 0));
+        assert_eq!(frame.to_string(), "nodframe:\nNum Rows: 0");
+        let path = temp_path("empty.csv");
+        frame.to_csv(path.display().to_string()).unwrap();
+        let frame2 = frame_from_csv::<i64>(path).unwrap();
+        assert_eq!(frame2.shape(), (0, 0));
+    }
+
+    #[test]
+    fn builder_order_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("name", vec![String::from("a"), String::from("b")])
+            .add_numeric("x", vec![1, 2])
+            .add_discrete("tag", vec![String::from("t"), String::from("u")])
+            .add_numeric("y", vec![3, 4])
+            .build()
+            .unwrap();
+        let keys: Vec<&String> = frame.columns().iter().map(|c| c.get_key()).collect();
+        assert_eq!(keys, vec!["name", "x", "tag", "y"]);
+        assert_eq!(frame.shape(), (2, 4));
+    }
+
+    #[test]
+    fn builder_validation_test() {
+        let err = NodFrameBuilder::new()
+            .add_numeric("x", vec![1, 2])
+            .add_numeric("x", vec![3, 4])
+            .build()
+            .unwrap_err();
+        assert_eq!(err, NodFrameError::DuplicateKey(String::from("x")));
+        let err = NodFrameBuilder::new()
+            .add_numeric("x", vec![1, 2])
+            .add_discrete("short", vec![String::from("a")])
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("'short'"));
+        let empty: NodFrame<i64> = NodFrameBuilder::new().build().unwrap();
+        assert_eq!(empty.shape(), (0, 0));
+    }
+
+    #[test]
+    fn frame_from_map_test() {
+        let mut data = HashMap::new();
+        data.insert(String::from("b"), vec![1, 2]);
+        data.insert(String::from("a"), vec![3, 4]);
+        let frame = frame_from_map(data).unwrap();
+        assert_eq!(
+            frame.to_string(),
+            "nodframe:\na: [3, 4]\nb: [1, 2]\nNum Rows: 2"
+        );
+    }
+
+    #[test]
+    fn row_ids_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("n", vec![5, 1, 8, 3, 9])
+            .build()
+            .unwrap()
+            .with_row_ids("id")
+            .unwrap();
+        let big = frame.filter("n", Comp::Geq, FilterValue::Num(5)).unwrap();
+        assert_eq!(big["id"].to_string(), "id: [0, 2, 4]");
+        let sorted = big.sort_by("n", false).unwrap();
+        assert_eq!(sorted["id"].to_string(), "id: [4, 2, 0]");
+        assert!(frame.with_row_ids("n").is_err());
+
+        let epochs = frame
+            .add_column_from_index("epoch", |i| Cell::Num(i as i32 / 2))
+            .unwrap();
+        assert_eq!(epochs["epoch"].to_string(), "epoch: [0, 0, 1, 1, 2]");
+        let sides = frame
+            .add_column_from_index("side", |i| Cell::Str(String::from(["l", "r"][i % 2])))
+            .unwrap();
+        assert_eq!(sides["side"].to_string(), "side: [l, r, l, r, l]");
+        let err = frame
+            .add_column_from_index("mixed", |i| {
+                if i < 3 {
+                    Cell::Num(0)
+                } else {
+                    Cell::Str(String::new())
+                }
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column 'mixed' row 3: cell is discrete, expected numeric"
+        );
+    }
+
+    #[test]
+    fn checked_access_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("n", vec![1, 2, 3])
+            .add_discrete("s", strings(&["a", "b", "c"]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            frame.row_checked(2),
+            Some(vec![Cell::Num(3), Cell::Str(String::from("c"))])
+        );
+        assert_eq!(frame.row_checked(3), None);
+        assert_eq!(frame.at("n", 3), None);
+        assert_eq!(frame.at("missing", 0), None);
+
+        let middle = frame.slice(1, 2).unwrap();
+        assert_eq!(middle.shape(), (1, 2));
+        assert_eq!(middle.at("s", 0), Some(Cell::Str(String::from("b"))));
+        assert_eq!(frame.slice(0, 3).unwrap().shape(), (3, 2));
+        assert_eq!(frame.slice(3, 3).unwrap().shape(), (0, 2));
+        assert_eq!(
+            frame.slice(1, 4).unwrap_err(),
+            NodFrameError::InvalidRange {
+                start: 1,
+                end: 4,
+                len: 3
+            }
+        );
+        assert!(frame.slice(2, 1).is_err());
+        assert!(frame.slice(4, 4).is_err());
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Measurement {
+        sensor: String,
+        reading: i64,
+        count: i64,
+    }
+
+    impl FrameRow<i64> for Measurement {
+        fn keys() -> Vec<(String, ColKind)> {
+            vec![
+                (String::from("sensor"), ColKind::Discrete),
+                (String::from("reading"), ColKind::Numeric),
+                (String::from("count"), ColKind::Numeric),
+            ]
+        }
+
+        fn cells(&self) -> Vec<Cell<i64>> {
+            vec![
+                Cell::Str(self.sensor.clone()),
+                Cell::Num(self.reading),
+                Cell::Num(self.count),
+            ]
+        }
+
+        fn from_cells(cells: Vec<Cell<i64>>) -> Option<Self> {
+            match &cells[..] {
+                [Cell::Str(sensor), Cell::Num(reading), Cell::Num(count)] => Some(Measurement {
+                    sensor: sensor.clone(),
+                    reading: *reading,
+                    count: *count,
+                }),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn frame_rows_round_trip_test() {
+        let rows = vec![
+            Measurement {
+                sensor: String::from("north"),
+                reading: 12,
+                count: 3,
+            },
+            Measurement {
+                sensor: String::from("south"),
+                reading: -4,
+                count: 7,
+            },
+        ];
+        let frame = frame_from_rows(rows.clone()).unwrap();
+        assert_eq!(frame.shape(), (2, 3));
+        assert_eq!(
+            frame.row(1),
+            vec![
+                Cell::Str(String::from("south")),
+                Cell::Num(-4),
+                Cell::Num(7)
+            ]
+        );
+        assert_eq!(frame.to_rows::<Measurement>().unwrap(), rows);
+    }
+
+    #[test]
+    fn frame_rows_cell_count_test() {
+        // a row that leaves out count
+        struct Partial(i64);
+        impl FrameRow<i64> for Partial {
+            fn keys() -> Vec<(String, ColKind)> {
+                Measurement::keys()
+            }
+            fn cells(&self) -> Vec<Cell<i64>> {
+                vec![Cell::Str(String::from("north")), Cell::Num(self.0)]
+            }
+            fn from_cells(_: Vec<Cell<i64>>) -> Option<Self> {
+                None
+            }
+        }
+        let err = frame_from_rows(vec![Partial(1)]).unwrap_err();
+        assert_eq!(
+            err,
+            NodFrameError::CellCountMismatch {
+                row: 0,
+                expected: 3,
+                found: 2,
+            }
+        );
+        assert_eq!(err.to_string(), "row 0 has 2 cells where 3 keys were given");
+    }
+
+    #[test]
+    fn to_rows_schema_mismatch_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("sensor", vec![1])
+            .add_numeric("reading", vec![2])
+            .add_numeric("count", vec![3])
+            .build()
+            .unwrap();
+        let err = frame.to_rows::<Measurement>().unwrap_err();
+        assert_eq!(
+            err,
+            NodFrameError::KindMismatch {
+                key: String::from("sensor"),
+                expected: ColKind::Discrete,
+                found: ColKind::Numeric
+            }
+        );
+        let frame = NodFrameBuilder::<i64>::new()
+            .add_discrete("sensor", vec![String::from("a")])
+            .build()
+            .unwrap();
+        let err = frame.to_rows::<Measurement>().unwrap_err();
+        assert_eq!(err, NodFrameError::MissingColumn(String::from("reading")));
+    }
+
+    #[test]
+    fn frame_index_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("price", vec![5, 6, 7, 8])
+            .add_discrete(
+                "city",
+                vec![
+                    String::from("a"),
+                    String::from("b"),
+                    String::from("c"),
+                    String::from("d"),
+                ],
+            )
+            .build()
+            .unwrap();
+        assert_eq!(frame["price"].as_numeric().unwrap()[3], 8);
+        assert_eq!(&frame["city"].as_discrete().unwrap()[1], "b");
+        // numeric-style reads of a discrete column yield None rather than a value
+        assert!(frame["city"].as_numeric().is_none());
+        assert_eq!(frame.at("price", 3), Some(Cell::Num(8)));
+        assert_eq!(frame.at("city", 0), Some(Cell::Str(String::from("a"))));
+        assert_eq!(frame.at("price", 4), None);
+        assert_eq!(frame.at("missing", 0), None);
+        assert!(frame.column("missing").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "no column named 'missing'")]
+    fn frame_index_missing_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("price", vec![5])
+            .build()
+            .unwrap();
+        let _ = &frame["missing"];
+    }
+
+    #[test]
+    fn to_matrix_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2, 3])
+            .add_discrete(
+                "id",
+                vec![String::from("x"), String::from("y"), String::from("z")],
+            )
+            .add_numeric("b", vec![10, 20, 30])
+            .build()
+            .unwrap();
+        let (data, rows, cols) = frame.to_matrix();
+        assert_eq!((rows, cols), (3, 2));
+        assert_eq!(data, frame.numeric_rows().concat());
+        assert_eq!(data, vec![1, 10, 2, 20, 3, 30]);
+
+        let (data, rows, cols) = frame.to_matrix_cols(&["b"]).unwrap();
+        assert_eq!((data, rows, cols), (vec![10, 20, 30], 3, 1));
+        assert!(frame.to_matrix_cols(&["id"]).is_err());
+
+        let filtered = frame.filter("a", Comp::Geq, FilterValue::Num(2)).unwrap();
+        let (data, rows, cols) = filtered.to_matrix();
+        assert_eq!((rows, cols), (2, 2));
+        assert_eq!(data, vec![2, 20, 3, 30]);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn ndarray_round_trip_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1_i64, 2, 3])
+            .add_numeric("b", vec![10, 20, 30])
+            .build()
+            .unwrap();
+        let arr = frame.to_ndarray();
+        assert_eq!(arr.shape(), &[3, 2]);
+        assert_eq!(arr[[1, 1]], 20.0);
+        let back: NodFrame<i64> = frame_from_ndarray(&arr, &["a", "b"]).unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
+        assert!(frame_from_ndarray::<i64>(&arr, &["a"]).is_err());
+    }
+
+    #[test]
+    fn iter_numeric_rows_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2, 3])
+            .add_discrete(
+                "id",
+                vec![String::from("x"), String::from("y"), String::from("z")],
+            )
+            .add_numeric("b", vec![10, 20, 30])
+            .build()
+            .unwrap();
+        let rows: Vec<Vec<i32>> = frame.iter_numeric_rows().collect();
+        assert_eq!(rows, frame.numeric_rows());
+        let mut seen = Vec::new();
+        frame.for_each_numeric_row(|row| seen.push(row.to_vec()));
+        assert_eq!(seen, frame.numeric_rows());
+    }
+
+    #[test]
+    fn explode_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("id", vec![1, 2, 3])
+            .add_discrete("tags", strings(&["red; sale ;outdoor", "blue", ""]))
+            .build()
+            .unwrap();
+        let exploded = frame.explode("tags", ";").unwrap();
+        assert_eq!(exploded.shape(), (5, 2));
+        assert_eq!(
+            exploded.to_string(),
+            "nodframe:\nid: [1, 1, 1, 2, 3]\ntags: [red, sale, outdoor, blue, ]\nNum Rows: 5"
+        );
+        let sale = exploded
+            .filter("tags", Comp::Eq, FilterValue::Str(String::from("sale")))
+            .unwrap();
+        assert_eq!(sale.at("id", 0), Some(Cell::Num(1)));
+
+        let opts = ExplodeOptions {
+            drop_empty_pieces: false,
+            keep_empty_cells: false,
+        };
+        let frame = NodFrameBuilder::<i64>::new()
+            .add_discrete("tags", strings(&["a;;b", ""]))
+            .build()
+            .unwrap();
+        let exploded = frame.explode_with("tags", ";", &opts).unwrap();
+        assert_eq!(exploded.shape(), (3, 1));
+
+        let empty = NodFrameBuilder::<i64>::new()
+            .add_discrete("tags", vec![])
+            .build()
+            .unwrap();
+        assert_eq!(empty.explode("tags", ";").unwrap().shape(), (0, 1));
+        assert!(frame.explode("missing", ";").is_err());
+    }
+
+    #[test]
+    fn split_column_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("id", vec![1, 2, 3])
+            .add_discrete("date", strings(&["2024-03-15", "2024-04", "2024-05-01-x"]))
+            .build()
+            .unwrap();
+        let keys = ["year", "month", "day"];
+        let err = frame
+            .split_column("date", "-", &keys, &SplitOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("row 2"));
+
+        let opts = SplitOptions {
+            keep_original: false,
+            extra_pieces: ExtraPieces::Truncate,
+        };
+        let split = frame.split_column("date", "-", &keys, &opts).unwrap();
+        assert_eq!(
+            split.to_string(),
+            "nodframe:\nid: [1, 2, 3]\nyear: [2024, 2024, 2024]\nmonth: [03, 04, 05]\nday: [15, , 01]\nNum Rows: 3"
+        );
+
+        let opts = SplitOptions {
+            keep_original: true,
+            extra_pieces: ExtraPieces::Merge,
+        };
+        let split = frame.split_column("date", "-", &keys, &opts).unwrap();
+        assert_eq!(split.shape(), (3, 5));
+        assert_eq!(split.at("day", 2), Some(Cell::Str(String::from("01-x"))));
+        assert!(split.column("date").is_some());
+    }
+
+    #[test]
+    fn concat_columns_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("region", strings(&["north", "south"]))
+            .add_numeric("store", vec![7, -12])
+            .build()
+            .unwrap();
+        let keyed = frame
+            .concat_columns(&["region", "store"], "|", "key")
+            .unwrap();
+        assert_eq!(keyed.shape(), (2, 3));
+        let path = temp_path("concat.csv");
+        frame.to_csv(path.display().to_string()).unwrap();
+        let written = std::fs::read_to_string(path).unwrap();
+        assert!(written.contains("south,-12"));
+        assert_eq!(
+            keyed.at("key", 1),
+            Some(Cell::Str(String::from("south|-12")))
+        );
+        let picked = keyed
+            .filter("key", Comp::Eq, FilterValue::Str(String::from("north|7")))
+            .unwrap();
+        assert_eq!(picked.shape(), (1, 3));
+        assert!(frame.concat_columns(&[], "|", "key").is_err());
+        assert!(frame.concat_columns(&["nope"], "|", "key").is_err());
+    }
+
+    #[test]
+    fn rename_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2])
+            .add_discrete("b", strings(&["x", "y"]))
+            .build()
+            .unwrap();
+        let renamed = frame.rename("a", "price").unwrap();
+        assert_valid(&renamed);
+        assert_eq!(renamed.at("price", 1), Some(Cell::Num(2)));
+        assert!(renamed.column("a").is_none());
+        let path = temp_path("rename.csv");
+        renamed.to_csv(path.display().to_string()).unwrap();
+        assert!(std::fs::read_to_string(path)
+            .unwrap()
+            .starts_with("price,b\n"));
+
+        assert_eq!(
+            frame.rename("a", "b").unwrap_err(),
+            NodFrameError::DuplicateKey(String::from("b"))
+        );
+        assert_eq!(
+            frame.rename("zzz", "c").unwrap_err(),
+            NodFrameError::MissingColumn(String::from("zzz"))
+        );
+
+        let mut mapping = HashMap::new();
+        mapping.insert(String::from("a"), String::from("b"));
+        mapping.insert(String::from("b"), String::from("a"));
+        let swapped = frame.rename_map(&mapping).unwrap();
+        assert_valid(&swapped);
+        assert_eq!(swapped.at("b", 0), Some(Cell::Num(1)));
+        assert_eq!(swapped.at("a", 0), Some(Cell::Str(String::from("x"))));
+
+        let positional = frame.set_column_names(&["c", "d"]).unwrap();
+        assert_eq!(positional.at("d", 1), Some(Cell::Str(String::from("y"))));
+        assert!(frame.set_column_names(&["c"]).is_err());
+    }
+
+    #[test]
+    fn csv_column_order_test() {
+        let path = temp_path("order.csv");
+        std::fs::write(&path, "name,x,city,y\na,1,p,2\nb,3,q,4\n").unwrap();
+        let frame = frame_from_csv::<i64>(path.clone()).unwrap();
+        let out = temp_path("order_out.csv");
+        frame.to_csv(out.display().to_string()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            std::fs::read_to_string(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn reorder_insert_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2])
+            .add_numeric("b", vec![3, 4])
+            .add_numeric("c", vec![5, 6])
+            .build()
+            .unwrap();
+        let reordered = frame.reorder(&["c", "a"]).unwrap();
+        let keys: Vec<&String> = reordered.columns().iter().map(|c| c.get_key()).collect();
+        assert_eq!(keys, vec!["c", "a", "b"]);
+        assert_eq!(reordered.at("a", 1), Some(Cell::Num(2)));
+        assert!(frame.reorder(&["a", "a"]).is_err());
+        assert!(frame.reorder(&["z"]).is_err());
+
+        frame
+            .insert_column(
+                1,
+                Column::from(build_discrete_column("tag", strings(&["x", "y"])).unwrap()),
+            )
+            .unwrap();
+        let keys: Vec<&String> = frame.columns().iter().map(|c| c.get_key()).collect();
+        assert_eq!(keys, vec!["a", "tag", "b", "c"]);
+        assert_eq!(frame.at("c", 0), Some(Cell::Num(5)));
+        assert_eq!(frame.shape(), (2, 4));
+        assert!(frame
+            .insert_column(
+                0,
+                Column::from(build_numeric_column("short", vec![1]).unwrap())
+            )
+            .is_err());
+        assert!(frame
+            .insert_column(
+                9,
+                Column::from(build_numeric_column("far", vec![1, 2]).unwrap())
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn set_column_value_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2])
+            .add_discrete("b", strings(&["x", "y"]))
+            .build()
+            .unwrap();
+        frame
+            .set_column(
+                "a",
+                Column::from(build_discrete_column("ignored", strings(&["p", "q"])).unwrap()),
+            )
+            .unwrap();
+        assert_eq!(frame.at("a", 1), Some(Cell::Str(String::from("q"))));
+        assert!(frame.column("ignored").is_none());
+        assert!(matches!(
+            frame.set_column(
+                "b",
+                Column::from(build_numeric_column("b", vec![1]).unwrap())
+            ),
+            Err(NodFrameError::LengthMismatch { .. })
+        ));
+
+        frame
+            .set_value("b", 0, Cell::Str(String::from("fixed")))
+            .unwrap();
+        assert_eq!(frame.at("b", 0), Some(Cell::Str(String::from("fixed"))));
+        assert_eq!(
+            frame.set_value("b", 0, Cell::Num(5)).unwrap_err(),
+            NodFrameError::KindMismatch {
+                key: String::from("b"),
+                expected: ColKind::Discrete,
+                found: ColKind::Numeric
+            }
+        );
+        assert_eq!(
+            frame
+                .set_value("b", 2, Cell::Str(String::new()))
+                .unwrap_err(),
+            NodFrameError::OutOfBounds { index: 2, len: 2 }
+        );
+    }
+
+    #[test]
+    fn update_where_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_numeric("qty", vec![5, 0, 7, 0, 2])
+            .add_discrete("status", strings(&["open", "open", "done", "open", "lost"]))
+            .build()
+            .unwrap()
+            .sort_by("qty", true)
+            .unwrap();
+        assert!(frame.is_known_sorted("qty"));
+        let changed = frame
+            .update_where(
+                "qty",
+                Comp::Eq,
+                Some(0),
+                None,
+                "status",
+                Cell::Str(String::from("empty")),
+            )
+            .unwrap();
+        assert_eq!(changed, 2);
+        assert_eq!(
+            frame
+                .discrete_column("status")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            &["empty", "empty", "lost", "open", "done"]
+        );
+        assert!(frame.is_known_sorted("qty"));
+
+        let raised = frame
+            .update_where_with(
+                "status",
+                Comp::Eq,
+                None,
+                Some(String::from("empty")),
+                "qty",
+                |cell| match cell {
+                    Cell::Num(n) => Cell::Num(n + 10),
+                    other => other.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(raised, 2);
+        assert_eq!(
+            frame.numeric_column("qty").unwrap().as_slice(),
+            &[10, 10, 2, 5, 7]
+        );
+        assert!(!frame.is_known_sorted("qty"));
+        assert_valid(&frame);
+
+        // nothing matches, so nothing changes and the closure never runs
+        let none = frame
+            .update_where_with("qty", Comp::Gra, Some(100), None, "qty", |_| {
+                panic!("no row matches")
+            })
+            .unwrap();
+        assert_eq!(none, 0);
+
+        // a wrong kind from any row leaves every row untouched
+        let before = frame.to_string();
+        assert_eq!(
+            frame
+                .update_where("qty", Comp::Geq, Some(0), None, "status", Cell::Num(1))
+                .unwrap_err(),
+            NodFrameError::KindMismatch {
+                key: String::from("status"),
+                expected: ColKind::Discrete,
+                found: ColKind::Numeric,
+            }
+        );
+        let mixed =
+            frame.update_where_with("qty", Comp::Geq, Some(0), None, "qty", |cell| match cell {
+                Cell::Num(n) if *n < 7 => Cell::Num(n + 1),
+                _ => Cell::Str(String::from("big")),
+            });
+        assert!(mixed.is_err());
+        assert_eq!(frame.to_string(), before);
+        assert!(frame
+            .update_where("qty", Comp::Eq, Some(0), None, "missing", Cell::Num(1))
+            .is_err());
+        assert!(frame
+            .update_where("missing", Comp::Eq, Some(0), None, "qty", Cell::Num(1))
+            .is_err());
+    }
+
+    #[test]
+    fn push_row_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_numeric("t", Vec::new())
+            .add_discrete("event", Vec::new())
+            .build()
+            .unwrap()
+            .sort_by("t", true)
+            .unwrap();
+        frame.reserve_rows(10_000);
+        for i in 0..10_000 {
+            frame
+                .push_row(&[Cell::Num(i), Cell::Str(format!("e{}", i % 7))])
+                .unwrap();
+        }
+        let bulk = NodFrameBuilder::new()
+            .add_numeric("t", (0..10_000).collect())
+            .add_discrete(
+                "event",
+                (0..10_000).map(|i| format!("e{}", i % 7)).collect(),
+            )
+            .build()
+            .unwrap();
+        assert_valid(&frame);
+        assert_eq!(frame.to_string(), bulk.to_string());
+        assert!(frame.is_known_sorted("t"));
+
+        // a bad cell anywhere in the row leaves every column untouched
+        let before = frame.to_string();
+        assert_eq!(
+            frame.push_row(&[Cell::Num(1), Cell::Num(2)]).unwrap_err(),
+            NodFrameError::KindMismatch {
+                key: String::from("event"),
+                expected: ColKind::Discrete,
+                found: ColKind::Numeric
+            }
+        );
+        assert!(frame.push_row(&[Cell::Num(1)]).is_err());
+        let mut cells = HashMap::new();
+        cells.insert("t", Cell::Num(5));
+        assert_eq!(
+            frame.push_row_map(&cells).unwrap_err(),
+            NodFrameError::MissingColumn(String::from("event"))
+        );
+        cells.insert("other", Cell::Num(5));
+        assert!(frame.push_row_map(&cells).is_err());
+        assert_eq!(frame.to_string(), before);
+        assert_eq!(frame.shape(), (10_000, 2));
+
+        cells.remove("other");
+        cells.insert("event", Cell::Str(String::from("late")));
+        frame.push_row_map(&cells).unwrap();
+        assert_eq!(
+            frame.at("event", 10_000),
+            Some(Cell::Str(String::from("late")))
+        );
+        assert!(!frame.is_known_sorted("t"));
+        assert_valid(&frame);
+    }
+
+    #[test]
+    fn assert_unique_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("id", vec![3.0, 1.0, 4.0, 1.5, 5.0, 9.0])
+            .add_numeric("score", vec![2.0, 7.0, 2.0, 0.5, 2.0, 7.0])
+            .add_discrete("name", strings(&["ann", "bo", "cy", "bo", "di", "ed"]))
+            .build()
+            .unwrap();
+        assert!(frame.assert_unique("id").is_ok());
+        assert!(frame.numeric_column("id").unwrap().is_unique());
+        assert!(frame
+            .numeric_column("id")
+            .unwrap()
+            .duplicate_values()
+            .is_empty());
+
+        let score = frame.numeric_column("score").unwrap();
+        assert!(!score.is_unique());
+        assert_eq!(score.duplicate_values(), vec![(2.0, 3), (7.0, 2)]);
+        let names = frame.discrete_column("name").unwrap();
+        assert!(!names.is_unique());
+        assert_eq!(names.duplicate_values(), vec![(String::from("bo"), 2)]);
+        assert!(DiscreteColumn::new("x", strings(&["a", "b"])).is_unique());
+
+        let err = frame.assert_unique("score").unwrap_err();
+        assert_eq!(
+            err,
+            NodFrameError::NotUnique {
+                key: String::from("score"),
+                repeated: 2,
+                examples: vec![
+                    (String::from("2"), vec![0, 2, 4]),
+                    (String::from("7"), vec![1, 5]),
+                ],
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "column 'score' is not unique, 2 values repeat: '2' at rows 0, 2, 4; '7' at rows 1, 5"
+        );
+        assert_eq!(
+            frame.assert_unique("name").unwrap_err().to_string(),
+            "column 'name' is not unique, 1 values repeat: 'bo' at rows 1, 3"
+        );
+        assert_eq!(
+            frame.assert_unique("nope").unwrap_err(),
+            NodFrameError::MissingColumn(String::from("nope"))
+        );
+
+        // floats compare by text form: NaN repeats, 0 and -0 do not
+        let floats = NumericColumn::new("f", vec![f64::NAN, 0.0, -0.0, f64::NAN]);
+        assert_eq!(floats.duplicate_values().len(), 1);
+        assert!(floats.duplicate_values()[0].0.is_nan());
+        let many = NodFrameBuilder::new()
+            .add_numeric("k", (0..20).map(|i| i % 7).collect())
+            .build()
+            .unwrap();
+        match many.assert_unique("k").unwrap_err() {
+            NodFrameError::NotUnique {
+                repeated, examples, ..
+            } => assert_eq!((repeated, examples.len()), (7, 5)),
+            other => panic!("unexpected error {}", other),
+        }
+    }
+
+    #[test]
+    fn copy_column_test() {
+        let mut data = NodFrameBuilder::new()
+            .add_numeric("id", vec![1, 2, 3])
+            .add_numeric("price", vec![10, 0, 30])
+            .build()
+            .unwrap();
+        let reference = NodFrameBuilder::new()
+            .add_numeric("id", vec![1, 2, 3])
+            .add_numeric("price", vec![10, 20, 30])
+            .add_discrete("source", strings(&["a", "b", "a"]))
+            .build()
+            .unwrap();
+        data.copy_column_checked(&reference, "price", "id").unwrap();
+        assert_eq!(data["price"].to_string(), "price: [10, 20, 30]");
+        data.copy_column_from(&reference, "source").unwrap();
+        data.copy_column_as(&reference, "price", "corrected")
+            .unwrap();
+        assert_valid(&data);
+        assert_eq!(data.shape(), (3, 4));
+        assert_eq!(data.at("source", 1), Some(Cell::Str(String::from("b"))));
+
+        let shuffled = reference.take(&[0, 2, 1]).unwrap();
+        assert_eq!(
+            data.copy_column_checked(&shuffled, "price", "id")
+                .unwrap_err()
+                .to_string(),
+            "column 'id' row 1: '2' != '3', the frames are not aligned"
+        );
+        let short = reference.slice(0, 2).unwrap();
+        assert!(matches!(
+            data.copy_column_from(&short, "price"),
+            Err(NodFrameError::LengthMismatch { .. })
+        ));
+        assert!(data.copy_column_from(&reference, "missing").is_err());
+        assert_eq!(data["price"].to_string(), "price: [10, 20, 30]");
+    }
+
+    #[test]
+    fn count_where_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 5, 3, 8, 5])
+            .add_discrete("b", strings(&["x", "y", "x", "z", "x"]))
+            .build()
+            .unwrap();
+        let count = frame.count_where("a", Comp::Geq, Some(5), None).unwrap();
+        let filtered = frame.filter("a", Comp::Geq, FilterValue::Num(5)).unwrap();
+        assert_eq!(count, filtered.shape().0);
+        assert_eq!(count, 3);
+        let count = frame
+            .count_where("b", Comp::Eq, None, Some(String::from("x")))
+            .unwrap();
+        assert_eq!(count, 3);
+        assert!(frame.exists_where("a", Comp::Gra, Some(7), None).unwrap());
+        assert!(!frame.exists_where("a", Comp::Gra, Some(8), None).unwrap());
+        assert!(frame.count_where("b", Comp::Eq, Some(1), None).is_err());
+        assert!(frame.count_where("nope", Comp::Eq, Some(1), None).is_err());
+
+        let mask = Mask::from(vec![true, false, true, true, false]);
+        assert_eq!(frame.count_where_mask(&mask).unwrap(), 3);
+        assert!(frame.count_where_mask(&Mask::new(vec![true])).is_err());
+    }
+
+    #[test]
+    fn which_take_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 5, 3, 8, 5])
+            .add_discrete("b", strings(&["x", "y", "x", "z", "x"]))
+            .build()
+            .unwrap();
+        let rows = frame.which("a", Comp::Geq, Some(5), None).unwrap();
+        assert_eq!(rows, vec![1, 3, 4]);
+        let taken = frame.take(&rows).unwrap();
+        let filtered = frame.filter("a", Comp::Geq, FilterValue::Num(5)).unwrap();
+        assert_eq!(taken.to_string(), filtered.to_string());
+        assert_eq!(
+            frame.which("a", Comp::Gra, Some(100), None).unwrap(),
+            Vec::<usize>::new()
+        );
+        assert_eq!(Mask::from(vec![false, true, true]).indices(), vec![1, 2]);
+        assert_eq!(
+            frame.take(&[0, 5]).unwrap_err(),
+            NodFrameError::OutOfBounds { index: 5, len: 5 }
+        );
+    }
+
+    #[test]
+    fn filter_kind_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("qty", vec![4, 9, 2])
+            .add_discrete("city", strings(&["a", "b", "a"]))
+            .build()
+            .unwrap();
+        let kept = frame.filter("city", Comp::Eq, FilterValue::Str(String::from("a")));
+        assert_eq!(kept.unwrap()["qty"].to_string(), "qty: [4, 2]");
+        let big = frame.filter("qty", Comp::Gra, FilterValue::Num(3)).unwrap();
+        assert_eq!(big["city"].to_string(), "city: [a, b]");
+
+        let number_on_text = frame
+            .filter("city", Comp::Eq, FilterValue::Num(5))
+            .unwrap_err();
+        assert_eq!(
+            number_on_text,
+            NodFrameError::FilterKindMismatch {
+                key: String::from("city"),
+                column: ColKind::Discrete,
+                value: ColKind::Numeric,
+            }
+        );
+        assert_eq!(
+            number_on_text.to_string(),
+            "column 'city' is discrete and cannot be filtered by a numeric value"
+        );
+        let text_on_number = frame
+            .filter("qty", Comp::Eq, FilterValue::Str(String::from("5")))
+            .unwrap_err();
+        assert_eq!(
+            text_on_number.to_string(),
+            "column 'qty' is numeric and cannot be filtered by a discrete value"
+        );
+        assert_eq!(
+            frame
+                .filter("missing", Comp::Eq, FilterValue::Num(5))
+                .unwrap_err(),
+            NodFrameError::MissingColumn(String::from("missing"))
+        );
+    }
+
+    #[test]
+    fn filter_discrete_comp_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_numeric("qty", vec![4, 9, 2])
+            .add_discrete("city", strings(&["x", "y", "x"]))
+            .build()
+            .unwrap();
+        let y = || FilterValue::Str(String::from("y"));
+        let not_y = frame.filter("city", Comp::Not, y()).unwrap();
+        assert_eq!(not_y["city"].to_string(), "city: [x, x]");
+        assert_eq!(
+            frame
+                .count_where("city", Comp::Not, None, Some(String::from("y")))
+                .unwrap(),
+            2
+        );
+        let unordered = NodFrameError::UnorderedComparison {
+            key: String::from("city"),
+            comp: Comp::Gra,
+        };
+        assert_eq!(frame.filter("city", Comp::Gra, y()).unwrap_err(), unordered);
+        assert_eq!(
+            unordered.to_string(),
+            "column 'city' has no level order for Gra, see set_order"
+        );
+        assert_eq!(
+            frame
+                .count_where("city", Comp::Les, None, Some(String::from("y")))
+                .unwrap_err(),
+            NodFrameError::UnorderedComparison {
+                key: String::from("city"),
+                comp: Comp::Les,
+            }
+        );
+
+        // an ordered column compares by level, and tests equality for a
+        // value that is not a level
+        frame.set_order("city", &["y", "x"]).unwrap();
+        let above_y = frame.filter("city", Comp::Gra, y()).unwrap();
+        assert_eq!(above_y["qty"].to_string(), "qty: [4, 2]");
+        let not_z = frame.filter("city", Comp::Not, FilterValue::Str(String::from("z")));
+        assert_eq!(not_z.unwrap().shape(), (3, 2));
+        assert!(frame
+            .filter("city", Comp::Geq, FilterValue::Str(String::from("z")))
+            .is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn filter_frame_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("qty", vec![4, 9, 2])
+            .add_discrete("city", strings(&["a", "b", "a"]))
+            .build()
+            .unwrap();
+        let kept = frame.filter_frame(
+            String::from("city"),
+            Comp::Eq,
+            None,
+            Some(String::from("a")),
+        );
+        assert_eq!(kept["qty"].to_string(), "qty: [4, 2]");
+        let wrong = std::panic::catch_unwind(|| {
+            frame.filter_frame(String::from("city"), Comp::Eq, Some(5), None)
+        });
+        let message = wrong.unwrap_err();
+        assert!(message
+            .downcast_ref::<String>()
+            .unwrap()
+            .contains("column 'city' is discrete"));
+    }
+
+    #[test]
+    fn filter_by_external_test() {
+        let features = NodFrameBuilder::new()
+            .add_discrete("id", strings(&["r1", "r2", "r3", "r4", "r5"]))
+            .add_numeric("x", vec![10, 20, 30, 40, 50])
+            .build()
+            .unwrap();
+        let labels = NodFrameBuilder::new()
+            .add_discrete("id", strings(&["r1", "r2", "r3", "r4", "r5"]))
+            .add_numeric("label", vec![1, 0, 1, 1, 0])
+            .build()
+            .unwrap();
+        let mask = labels.mask_where("label", Comp::Eq, Some(1), None).unwrap();
+        assert_eq!(mask.as_slice(), &[true, false, true, true, false]);
+        let kept = features.apply_mask(&mask).unwrap();
+        assert_eq!(kept.numeric_column("x").unwrap().as_slice(), &[10, 30, 40]);
+        assert!(features.apply_mask(&Mask::new(vec![true])).is_err());
+
+        let (f, l) = features
+            .filter_by_external(&labels, "label", Comp::Eq, Some(1), None)
+            .unwrap();
+        assert_valid(&f);
+        assert_eq!(f.shape(), (3, 2));
+        // both sides keep the same rows, so the ids line up
+        assert_eq!(
+            f.discrete_column("id").unwrap().as_slice(),
+            l.discrete_column("id").unwrap().as_slice()
+        );
+        assert_eq!(kept.to_string(), f.to_string());
+
+        let short = labels.take(&[0, 1]).unwrap();
+        assert_eq!(
+            features
+                .filter_by_external(&short, "label", Comp::Eq, Some(1), None)
+                .unwrap_err(),
+            NodFrameError::LengthMismatch {
+                key: String::from("label"),
+                expected: 5,
+                found: 2
+            }
+        );
+        assert!(features
+            .filter_by_external(&labels, "id", Comp::Eq, Some(1), None)
+            .is_err());
+    }
+
+    #[test]
+    fn ordered_levels_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_discrete(
+                "severity",
+                strings(&["low", "high", "medium", "low", "critical"]),
+            )
+            .add_discrete("month", strings(&["Mar", "Jan", "Dec", "Feb", "Jan"]))
+            .add_numeric("id", vec![1, 2, 3, 4, 5])
+            .build()
+            .unwrap();
+        frame
+            .set_order("severity", &["low", "medium", "high", "critical"])
+            .unwrap();
+        let severe = frame
+            .filter(
+                "severity",
+                Comp::Geq,
+                FilterValue::Str(String::from("medium")),
+            )
+            .unwrap();
+        assert_eq!(severe.numeric_column("id").unwrap().as_slice(), &[2, 3, 5]);
+        assert_eq!(
+            frame
+                .count_where("severity", Comp::Les, None, Some(String::from("high")))
+                .unwrap(),
+            3
+        );
+
+        let months = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        frame.set_order("month", &months).unwrap();
+        let by_month = frame.sort_by("month", true).unwrap();
+        assert_eq!(
+            by_month
+                .discrete_column("month")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["Jan", "Jan", "Feb", "Mar", "Dec"]).as_slice()
+        );
+        let latest = frame.sort_by("month", false).unwrap();
+        assert_eq!(
+            latest.numeric_column("id").unwrap().as_slice(),
+            &[3, 1, 4, 2, 5]
+        );
+
+        // the order survives row selection
+        let severity = severe.discrete_column("severity").unwrap();
+        assert_eq!(severity.ordering().unwrap().len(), 4);
+        assert_eq!(severity.min(), Some("medium"));
+        assert_eq!(severity.max(), Some("critical"));
+        assert!(severity.slice(0, 1).ordering().is_some());
+        let unordered = DiscreteColumn::new("s", strings(&["b", "a"]));
+        assert_eq!(unordered.ordering(), None);
+        assert_eq!(unordered.min(), Some("a"));
+        assert!(unordered.filter_ordered("a", Comp::Eq).is_err());
+
+        let mut col = DiscreteColumn::new("severity", strings(&["low", "urgent"]));
+        assert_eq!(
+            col.set_order(&["low", "high"]).unwrap_err().to_string(),
+            "column 'severity' row 1: 'urgent' is not one of the levels"
+        );
+        assert!(col.set_order(&["low", "urgent", "low"]).is_err());
+        assert_eq!(col.ordering(), None);
+        assert!(frame.set_order("id", &["1"]).is_err());
+        assert!(frame
+            .discrete_column("severity")
+            .unwrap()
+            .filter_ordered("urgent", Comp::Eq)
+            .is_err());
+    }
+
+    #[test]
+    fn ordered_writers_test() {
+        let mut frame: NodFrame<i64> = NodFrameBuilder::new()
+            .add_discrete("severity", strings(&["low", "", "high"]))
+            .add_numeric("id", vec![1, 2, 3])
+            .build()
+            .unwrap();
+        frame.set_order("severity", &["", "low", "high"]).unwrap();
+        let not_a_level = |row: usize| NodFrameError::InvalidValue {
+            key: String::from("severity"),
+            row,
+            message: String::from("'urgent' is not one of the levels"),
+        };
+        let urgent = || Cell::Str(String::from("urgent"));
+        assert_eq!(
+            frame.set_value("severity", 0, urgent()).unwrap_err(),
+            not_a_level(0)
+        );
+        assert_eq!(
+            frame
+                .update_where("id", Comp::Gra, Some(1), None, "severity", urgent())
+                .unwrap_err(),
+            not_a_level(1)
+        );
+        assert_eq!(
+            frame.push_row(&[urgent(), Cell::Num(4)]).unwrap_err(),
+            not_a_level(3)
+        );
+        assert_eq!(
+            frame
+                .fill_gaps("severity", None, FillDirection::Forward, Some(urgent()))
+                .unwrap_err(),
+            not_a_level(1)
+        );
+        frame
+            .set_value("severity", 0, Cell::Str(String::from("high")))
+            .unwrap();
+        assert!(frame.validate().is_ok());
+
+        // a value written behind the frame's back is reported, not ranked
+        if let Column::Discrete(d) = &mut frame.columns[0] {
+            d.items[2] = Arc::from("urgent");
+        }
+        assert_eq!(
+            frame.validate().unwrap_err(),
+            vec![InvariantViolation::UnknownLevel {
+                key: String::from("severity"),
+                row: 2,
+                value: String::from("urgent"),
+            }]
+        );
+        assert_eq!(
+            frame
+                .discrete_column("severity")
+                .unwrap()
+                .filter_ordered("high", Comp::Eq)
+                .unwrap_err(),
+            not_a_level(2)
+        );
+        assert_eq!(frame.sort_by("severity", true).unwrap_err(), not_a_level(2));
+    }
+    #[test]
+    fn frame_clip_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("v", vec![-50, 5, 50])
+            .add_discrete("b", strings(&["x", "y", "z"]))
+            .build()
+            .unwrap();
+        let replaced = frame.clip("v", Some(&0), Some(&10), None).unwrap();
+        assert_eq!(replaced.shape(), (3, 2));
+        assert_eq!(replaced.at("v", 0), Some(Cell::Num(0)));
+        let appended = frame
+            .clip("v", Some(&0), Some(&10), Some("v_clip"))
+            .unwrap();
+        assert_eq!(appended.shape(), (3, 3));
+        assert_eq!(appended.at("v", 2), Some(Cell::Num(50)));
+        assert_eq!(appended.at("v_clip", 2), Some(Cell::Num(10)));
+        assert!(frame.clip("b", None, None, None).is_err());
+    }
+
+    #[test]
+    fn frame_histogram_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("v", vec![0_i64, 1, 2, 3, 4, 5, 6, 7])
+            .build()
+            .unwrap();
+        let hist = frame.histogram("v", 2).unwrap();
+        assert_eq!(
+            hist.to_string(),
+            "nodframe:\nbin: [\"[0, 3)\", \"[3, 7]\"]\ncount: [3, 5]\nNum Rows: 2"
+        );
+    }
+
+    #[test]
+    fn bar_chart_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete(
+                "label",
+                strings(&[
+                    "apples",
+                    "kiwi",
+                    "none",
+                    "a very long label that keeps going",
+                    "debt",
+                ]),
+            )
+            .add_numeric("n", vec![10_i64, 5, 0, 2, -3])
+            .build()
+            .unwrap();
+        let chart = frame.to_bar_chart("label", "n", 10).unwrap();
+        assert_eq!(
+            chart,
+            "apples                   | ██████████ 10\n\
+             kiwi                     | █████ 5\n\
+             none                     | 0\n\
+             a very long label that … | ██ 2\n\
+             debt                     | -3"
+        );
+        let empty = frame.take(&[]).unwrap();
+        assert_eq!(empty.to_bar_chart("label", "n", 10).unwrap(), "no data");
+        assert!(frame.to_bar_chart("label", "label", 10).is_err());
+    }
+
+    #[test]
+    fn markdown_html_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("name", strings(&["a|b", "x<y", "plain"]))
+            .add_numeric("n", vec![1, 22, 333])
+            .build()
+            .unwrap();
+        assert_eq!(
+            frame.to_markdown(None),
+            "| name | n |\n\
+             | --- | ---: |\n\
+             | a\\|b | 1 |\n\
+             | x<y | 22 |\n\
+             | plain | 333 |\n\
+             \n\
+             3 rows × 2 columns\n"
+        );
+        assert_eq!(
+            frame.to_markdown(Some(2)),
+            "| name | n |\n\
+             | --- | ---: |\n\
+             | a\\|b | 1 |\n\
+             | … | … |\n\
+             | plain | 333 |\n\
+             \n\
+             3 rows × 2 columns\n"
+        );
+        assert_eq!(
+            frame.to_html(Some(1)),
+            "<table>\n\
+             <caption>3 rows × 2 columns</caption>\n\
+             <thead><tr><th>name</th><th>n</th></tr></thead>\n\
+             <tbody>\n\
+             <tr><td>a|b</td><td>1</td></tr>\n\
+             <tr><td>…</td><td>…</td></tr>\n\
+             </tbody>\n\
+             </table>\n"
+        );
+        assert!(frame.to_html(None).contains("<td>x&lt;y</td>"));
+    }
+
+    #[test]
+    fn float_format_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("id", strings(&["a", "b", "c"]))
+            .add_numeric("x", vec![0.1 + 0.2, 2.0 / 3.0, -1234.5678])
+            .add_numeric("nan", vec![f64::NAN, 0.0, f64::INFINITY])
+            .build()
+            .unwrap();
+
+        // the default writer is lossless
+        let path = temp_path("float_default.csv");
+        frame.to_csv(path.display().to_string()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "id,x,nan\na,0.30000000000000004,NaN\nb,0.6666666666666666,0\nc,-1234.5678,inf\n"
+        );
+        let back: NodFrame<f64> = frame_from_csv(&path).unwrap();
+        assert_eq!(back.numeric_column("x").unwrap().as_slice()[0], 0.1 + 0.2);
+
+        let opts = CsvWriteOptions {
+            float_format: FloatFormat::Decimals(3),
+            ..Default::default()
+        };
+        frame.to_csv_with_progress(&path, &opts, |_| {}).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "id,x,nan\na,0.300,NaN\nb,0.667,0.000\nc,-1234.568,inf\n"
+        );
+        std::fs::remove_file(path).unwrap();
+
+        let display = DisplayOptions {
+            float_format: FloatFormat::Decimals(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            frame.display_with(&display).to_string(),
+            "nodframe:\nid: [a, b, c]\nx: [0.300, 0.667, -1234.568]\nnan: [NaN, 0.000, inf]\nNum Rows: 3"
+        );
+        assert_eq!(
+            frame.display_with(&DisplayOptions::default()).to_string(),
+            frame.to_string()
+        );
+        assert!(frame
+            .to_markdown_with(&display)
+            .contains("| a | 0.300 | NaN |\n"));
+
+        let sig = FloatFormat::Significant(3);
+        let cells: Vec<String> = [0.30000000000000004, 9.996, 123456.0, -0.0012345, 0.0]
+            .iter()
+            .map(|x| sig.format(x))
+            .collect();
+        assert_eq!(cells, vec!["0.300", "10.0", "123000", "-0.00123", "0.00"]);
+        let one: Vec<String> = [1234.5, -0.0678]
+            .iter()
+            .map(|x| FloatFormat::Significant(0).format(x))
+            .collect();
+        assert_eq!(one, vec!["1000", "-0.07"]);
+        // integer frames are never rounded
+        assert_eq!(FloatFormat::Decimals(2).format(&7_i64), "7");
+    }
+
+    #[test]
+    fn display_escape_test() {
+        let long = "x".repeat(10_000);
+        let values = [
+            "San Francisco, CA",
+            "say \"hi\"",
+            "two\nlines",
+            "plain",
+            "",
+            long.as_str(),
+        ];
+        let frame: NodFrame<i64> = NodFrameBuilder::new()
+            .add_discrete("city", values.iter().map(|s| s.to_string()).collect())
+            .add_numeric("n", vec![1, 2, 3, 4, 5, 6])
+            .build()
+            .unwrap();
+        let shown_long = format!("{}…[10000 chars]", "x".repeat(80));
+        let city = format!(
+            "city: [\"San Francisco, CA\", \"say \"\"hi\"\"\", \"two\nlines\", plain, , {}]",
+            shown_long
+        );
+        assert_eq!(frame["city"].to_string(), city);
+        assert_eq!(
+            frame.to_string(),
+            format!("nodframe:\n{}\nn: [1, 2, 3, 4, 5, 6]\nNum Rows: 6", city)
+        );
+        let display = DisplayOptions {
+            max_rows: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            frame.display_with(&display).to_string(),
+            format!(
+                "nodframe:\ncity: [\"San Francisco, CA\", …, {}]\nn: [1, …, 6]\nNum Rows: 6",
+                shown_long
+            )
+        );
+
+        // a cut value that keeps a comma is still quoted
+        let cut = format!("{},{}", "a".repeat(10), "b".repeat(100));
+        assert_eq!(
+            display_value(&cut),
+            format!("\"{},{}…[111 chars]\"", "a".repeat(10), "b".repeat(69))
+        );
+        assert_eq!(display_value(&"é".repeat(80)), "é".repeat(80));
+    }
+
+    #[test]
+    fn csv_schema_test() {
+        let path = temp_path("schema.csv");
+        std::fs::write(&path, "price,zip,qty\n10,02139,1\n12,10001,3\n").unwrap();
+        let schema = Schema::new()
+            .numeric("price")
+            .discrete("zip")
+            .numeric("qty");
+        let frame = frame_from_csv_with_schema::<i64>(&path, &schema).unwrap();
+        assert_eq!(frame.at("zip", 0), Some(Cell::Str(String::from("02139"))));
+        assert_eq!(frame.schema(), schema);
+
+        // strict schema with a column the file lacks in the schema
+        let partial = Schema::new().numeric("price");
+        let err = frame_from_csv_with_schema::<i64>(&path, &partial).unwrap_err();
+        assert_eq!(err.to_string(), "column 'zip' is not part of the schema");
+        // lenient schema infers the remaining columns, zip codes stay text
+        let frame = frame_from_csv_with_schema::<i64>(&path, &partial.strict(false)).unwrap();
+        assert_eq!(
+            frame.schema(),
+            Schema::new()
+                .numeric("price")
+                .discrete("zip")
+                .numeric("qty")
+        );
+
+        // schema columns missing from the file fail under either setting
+        for strict in [true, false] {
+            let extra = schema.clone().numeric("missing").strict(strict);
+            let err = frame_from_csv_with_schema::<i64>(&path, &extra).unwrap_err();
+            assert_eq!(err.to_string(), "no column named 'missing'");
+        }
+    }
+
+    #[test]
+    fn csv_schema_violation_test() {
+        let path = temp_path("schema_bad.csv");
+        std::fs::write(&path, "id,name\n1,a\nx7,b\n").unwrap();
+        let schema = Schema::new().numeric("id").discrete("name");
+        let err = frame_from_csv_with_schema::<i64>(&path, &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: value 'x7' in column 'id' is not numeric"
+        );
+    }
+
+    #[test]
+    fn csv_options_test() {
+        let path = temp_path("options.csv");
+        let mut text = String::from("id,name,value\n");
+        for i in 0..100 {
+            text.push_str(&format!("{},n{},{}\n", i, i, i * 10));
+        }
+        std::fs::write(&path, text).unwrap();
+        let opts = CsvOptions {
+            skip_rows: 10,
+            max_rows: Some(5),
+            ..CsvOptions::default()
+        };
+        let frame = frame_from_csv_with_options::<i64>(&path, &opts).unwrap();
+        assert_eq!(frame.shape(), (5, 3));
+        assert_eq!(frame.at("id", 0), Some(Cell::Num(10)));
+        assert_eq!(frame.at("id", 4), Some(Cell::Num(14)));
+
+        let opts = CsvOptions {
+            use_columns: Some(vec![String::from("value"), String::from("id")]),
+            ..CsvOptions::default()
+        };
+        let frame = frame_from_csv_with_options::<i64>(&path, &opts).unwrap();
+        assert_eq!(frame.shape(), (100, 2));
+        assert!(frame.column("name").is_none());
+
+        let opts = CsvOptions {
+            use_columns: Some(vec![String::from("nope")]),
+            ..CsvOptions::default()
+        };
+        let err = frame_from_csv_with_options::<i64>(&path, &opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown column 'nope', available columns: id, name, value"
+        );
+    }
+
+    #[test]
+    fn csv_ragged_test() {
+        let path = temp_path("ragged.csv");
+        std::fs::write(&path, "a,b,c\n1,2,3\n4,5\n6,7,8,9\n10,11,12\n").unwrap();
+        let err = frame_from_csv::<i64>(&path).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: expected 3 fields, found 2");
+
+        let opts = CsvOptions {
+            ragged: RaggedPolicy::SkipRow,
+            ..CsvOptions::default()
+        };
+        let frame = frame_from_csv_with_options::<i64>(&path, &opts).unwrap();
+        assert_eq!(frame.shape(), (2, 3));
+        assert_eq!(frame.at("a", 1), Some(Cell::Num(10)));
+
+        let opts = CsvOptions {
+            ragged: RaggedPolicy::PadTruncate,
+            ..CsvOptions::default()
+        };
+        let frame = frame_from_csv_with_options::<i64>(&path, &opts).unwrap();
+        assert_eq!(frame.shape(), (4, 3));
+        assert_eq!(frame.at("b", 2), Some(Cell::Num(7)));
+        // the padded empty cell makes c non-numeric
+        assert_eq!(frame.at("c", 1), Some(Cell::Str(String::new())));
+        assert_eq!(frame.at("c", 2), Some(Cell::Str(String::from("8"))));
+    }
+
+    #[test]
+    fn to_discrete_test() {
+        let frame: NodFrame<i64> = NodFrameBuilder::new()
+            .add_numeric("id", vec![42, 7, 42, 1234])
+            .add_numeric("qty", vec![1, 2, 3, 4])
+            .build()
+            .unwrap();
+        let ids = frame.to_discrete("id").unwrap();
+        assert_eq!(ids.column("id").unwrap().kind(), ColKind::Discrete);
+        assert_eq!(ids.numeric_cols(), vec!["qty"]);
+        assert_eq!(ids.numeric_rows(), vec![vec![1], vec![2], vec![3], vec![4]]);
+        assert_eq!(ids.describe().len(), 1);
+        let sizes = ids.group_sizes("id").unwrap();
+        assert_eq!(sizes["id"].to_string(), "id: [1234, 42, 7]");
+
+        let padded = frame
+            .to_discrete_with("id", |x| format!("{:05}", x))
+            .unwrap();
+        assert_eq!(
+            padded
+                .discrete_column("id")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["00042", "00007", "00042", "01234"]).as_slice()
+        );
+        let path = temp_path("padded_ids.csv");
+        padded.to_csv(path.display().to_string()).unwrap();
+        let schema = Schema::new().discrete("id").numeric("qty");
+        let back: NodFrame<i64> = frame_from_csv_with_schema(&path, &schema).unwrap();
+        assert_eq!(back.to_string(), padded.to_string());
+        std::fs::remove_file(path).unwrap();
+
+        let floats: NodFrame<f64> = NodFrameBuilder::new()
+            .add_numeric("x", vec![1.5, f64::NAN])
+            .build()
+            .unwrap();
+        assert_eq!(
+            floats
+                .to_discrete("x")
+                .unwrap()
+                .discrete_column("x")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["1.5", ""]).as_slice()
+        );
+        assert!(padded.to_discrete("id").is_err());
+        assert!(frame.to_discrete("missing").is_err());
+    }
+
+    #[test]
+    fn read_csv_column_test() {
+        let path = temp_path("one_column.csv");
+        std::fs::write(
+            &path,
+            "id,name,score\n1,ann,10\n2,bob\n3,cat,NA\n4,dan,40,extra\n5,eve,x\n",
+        )
+        .unwrap();
+        let opts = CsvOptions {
+            ragged: RaggedPolicy::SkipRow,
+            na_strings: vec![String::from("NA")],
+            na_value: Some(String::from("-1")),
+            ..CsvOptions::default()
+        };
+        let ids: Vec<i64> = read_csv_column(&path, "id", &opts).unwrap();
+        assert_eq!(ids, vec![1, 3, 5]);
+        assert_eq!(
+            read_csv_column_str(&path, "name", &opts).unwrap(),
+            strings(&["ann", "cat", "eve"])
+        );
+        // the first bad score names its line
+        assert_eq!(
+            read_csv_column::<i64>(&path, "score", &opts)
+                .unwrap_err()
+                .to_string(),
+            NodFrameError::ParseError {
+                key: String::from("score"),
+                line: 6,
+                value: String::from("x")
+            }
+            .to_string()
+        );
+        let first = CsvOptions {
+            max_rows: Some(2),
+            ..opts.clone()
+        };
+        assert_eq!(
+            read_csv_column::<i64>(&path, "score", &first).unwrap(),
+            vec![10, -1]
+        );
+
+        let padded = CsvOptions {
+            ragged: RaggedPolicy::PadTruncate,
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            read_csv_column_str(&path, "score", &padded).unwrap(),
+            strings(&["10", "", "NA", "40", "x"])
+        );
+        assert!(read_csv_column_str(&path, "score", &CsvOptions::default()).is_err());
+        let err = read_csv_column::<i64>(&path, "nope", &opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown column 'nope', available columns: id, name, score"
+        );
+
+        let frame: NodFrame<i64> = read_csv_columns(&path, &["score", "id"], &first).unwrap();
+        let keys: Vec<&str> = frame
+            .columns()
+            .iter()
+            .map(|c| c.get_key().as_str())
+            .collect();
+        assert_eq!(keys, vec!["id", "score"]);
+        assert_eq!(frame.numeric_column("score").unwrap().as_slice(), &[10, -1]);
+    }
 
-impl<
-        T: Clone + Eq + std::hash::Hash + Add + Div + Mul + Sub + PartialOrd + std::string::ToString,
-    > NodFrame<T>
-{
-    // numeric_cols returns the column names of numeric columns
-    pub fn numeric_cols(&self) -> Vec<&String> {
-        let mut num_col = Vec::new();
-        for column in self.columns.iter() {
-            if let Column::Numeric(a) = column {
-                num_col.push(&a.key)
-            }
-        }
-        num_col
+    #[test]
+    fn csv_inference_test() {
+        let path = temp_path("inference.csv");
+        std::fs::write(&path, "a,b\n1,02\n3,x\n").unwrap();
+        let frame = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(frame.column("a").unwrap().kind(), ColKind::Numeric);
+        assert_eq!(frame.column("b").unwrap().kind(), ColKind::Discrete);
+        // cells read before the column turned discrete keep their text
+        assert_eq!(frame.at("b", 0), Some(Cell::Str(String::from("02"))));
     }
 
-    // numeric_index returns the column indices of number columns
-    pub fn numeric_index(&self) -> Vec<usize> {
-        let cols = self.numeric_cols();
-        let mut indices = Vec::new();
-        for key in cols {
-            indices.push(self.column_idx.get(key).unwrap().clone())
+    #[test]
+    fn csv_leading_zeros_test() {
+        let path = temp_path("leading_zeros.csv");
+        let text = "zip,amount,signed,code\n02139,10,+5,007\n10001,12,-3,abc\n00501,7,4,1\n";
+        std::fs::write(&path, text).unwrap();
+        let frame = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(
+            frame.schema(),
+            Schema::new()
+                .discrete("zip")
+                .numeric("amount")
+                .discrete("signed")
+                .discrete("code")
+        );
+        // code would be discrete anyway, so it is not reported
+        assert_eq!(frame.demoted_columns(), ["zip", "signed"]);
+        let out = temp_path("leading_zeros_out.csv");
+        frame.to_csv(out.display().to_string()).unwrap();
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), text);
+        assert!(frame.slice(0, 2).unwrap().demoted_columns().is_empty());
+
+        // a schema still loads the zip codes as numbers when asked to
+        let schema = Schema::new().numeric("zip").strict(false);
+        let numbers = frame_from_csv_with_schema::<i64>(&path, &schema).unwrap();
+        assert_eq!(numbers["zip"].to_string(), "zip: [2139, 10001, 501]");
+        assert_eq!(numbers.demoted_columns(), ["signed"]);
+        let report = inspect_csv::<i64>(&path, 10).unwrap();
+        assert_eq!(report.column("zip").unwrap().kind(), ColKind::Discrete);
+        assert_eq!(report.column("amount").unwrap().kind(), ColKind::Numeric);
+
+        for (cell, loses) in [
+            ("0", false),
+            // written back as "0" and "1" but the same number
+            ("-0", false),
+            ("1.0", false),
+            ("0.5", false),
+            ("10", false),
+            ("1e5", false),
+            ("02139", true),
+            ("-07", true),
+            ("+5", true),
+            (" 5", true),
+        ] {
+            assert_eq!(loses_text(cell), loses, "{}", cell);
         }
-        indices
     }
 
-    // numeric_rows returns the rows of numeric columns as a 2d matrix
-    pub fn numeric_rows(&self) -> Vec<Vec<T>> {
-        let numeric_index = self.numeric_index();
-        let mut data = Vec::new();
-        for i in 0..self.num_rows {
-            let mut row = Vec::new();
-            for col in numeric_index.iter() {
-                row.push(self.columns[*col].get_num(i).unwrap().clone())
-            }
-            data.push(row)
+    #[test]
+    fn zip_columns_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("math", vec![80.0, 65.0, 90.0, 72.0])
+            .add_discrete(
+                "name",
+                vec!["ada", "bo", "cy", "di"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            )
+            .add_numeric("art", vec![70.0, 95.0, 60.0, 88.0])
+            .add_numeric("music", vec![50.0, 85.0, 75.0, 90.0])
+            .add_discrete(
+                "class",
+                vec!["a", "b", "a", "b"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            )
+            .build()
+            .unwrap();
+        let keys = ["math", "art", "music"];
+        let weights = [0.5, 0.3, 0.2];
+        let scored = frame
+            .compute_from(&keys, "score", |v| {
+                v.iter().zip(weights).map(|(x, w)| *x * w).sum()
+            })
+            .unwrap();
+        assert_valid(&scored);
+        let (math, art, music) = (
+            frame.numeric_column("math").unwrap(),
+            frame.numeric_column("art").unwrap(),
+            frame.numeric_column("music").unwrap(),
+        );
+        let manual: Vec<f64> = (0..4)
+            .map(|i| math.get(i) * 0.5 + art.get(i) * 0.3 + music.get(i) * 0.2)
+            .collect();
+        assert_eq!(
+            scored.numeric_column("score").unwrap().as_slice(),
+            &manual[..]
+        );
+
+        let rows: Vec<Vec<&f64>> = frame.zip_numeric(&["music", "math"]).unwrap().collect();
+        assert_eq!(rows.len(), frame.shape().0);
+        assert_eq!(rows[1], vec![&85.0, &65.0]);
+        let labels: Vec<String> = frame
+            .zip_discrete(&["class", "name"])
+            .unwrap()
+            .map(|r| r.join("/"))
+            .collect();
+        assert_eq!(labels, ["a/ada", "b/bo", "a/cy", "b/di"]);
+
+        assert_eq!(
+            frame.zip_numeric(&["math", "nope"]).err(),
+            Some(NodFrameError::MissingColumn(String::from("nope")))
+        );
+        assert!(matches!(
+            frame.compute_from(&["math", "name"], "x", |v| *v[0]),
+            Err(NodFrameError::KindMismatch { .. })
+        ));
+        assert!(frame.zip_discrete(&["art"]).is_err());
+        assert!(frame.zip_numeric(&[]).is_err());
+        assert!(frame.compute_from(&["math"], "art", |v| *v[0]).is_err());
+    }
+
+    #[test]
+    fn key_validation_test() {
+        let invalid = |key: &str, reason: &'static str| NodFrameError::InvalidKey {
+            key: String::from(key),
+            reason,
+        };
+        for (key, reason) in [
+            ("", "it is empty"),
+            (" price", "it has leading or trailing whitespace"),
+            ("price\t", "it has leading or trailing whitespace"),
+            ("a\nb", "it contains a control character"),
+            ("a\u{7}b", "it contains a control character"),
+        ] {
+            let built = NodFrameBuilder::new().add_numeric(key, vec![1]).build();
+            assert_eq!(built.unwrap_err(), invalid(key, reason));
+            assert_eq!(check_key(key).unwrap_err(), invalid(key, reason));
         }
-        data
+        assert_eq!(
+            invalid("", "it is empty").to_string(),
+            "invalid column key \"\": it is empty"
+        );
+
+        let trimmed = NodFrameBuilder::new()
+            .key_policy(KeyPolicy::Trim)
+            .add_numeric(" price ", vec![1])
+            .add_discrete("a, b", strings(&["x"]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            trimmed.schema(),
+            Schema::new().numeric("price").discrete("a, b")
+        );
+        let blank = NodFrameBuilder::<i64>::new()
+            .key_policy(KeyPolicy::Trim)
+            .add_numeric("  ", vec![1])
+            .build();
+        assert_eq!(blank.unwrap_err(), invalid("", "it is empty"));
+
+        assert!(build_numeric_column("", vec![1]).is_err());
+        let mut frame = trimmed.clone();
+        let tag = build_discrete_column("tag", strings(&["y"])).unwrap();
+        frame.insert_column(0, tag.into()).unwrap();
+        let bad = Column::Numeric(NumericColumn::new("tag ", vec![1]));
+        assert!(frame.insert_column(0, bad).is_err());
+        assert!(frame.rename("tag", "").is_err());
     }
 
-    pub fn filter_frame(
-        &self,
-        col: String,
-        comp: Comp,
-        val: Option<T>,
-        str_val: Option<String>,
-    ) -> NodFrame<T> {
-        let col_idx = self.column_idx.get(&col).unwrap().clone();
-        let picker = self.columns[col_idx].filter_array(comp, val, str_val);
-        let mut copy = self.clone();
-        copy.columns = copy
-            .columns
+    #[test]
+    fn csv_header_quoting_test() {
+        let key = "total, \"net\"";
+        let frame = NodFrameBuilder::new()
+            .add_numeric(key, vec![1, 2])
+            .add_discrete("city", strings(&["Paris, TX", "Rome"]))
+            .build()
+            .unwrap();
+        let path = temp_path("header_quoting.csv");
+        frame.to_csv(path.display().to_string()).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("\"total, \"\"net\"\"\",city\n"));
+        let back = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(back.schema(), frame.schema());
+        assert_eq!(back.to_string(), frame.to_string());
+
+        // padding around headers is dropped on load rather than rejected
+        std::fs::write(&path, " id ,name\n1,a\n").unwrap();
+        let padded = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(
+            padded.schema(),
+            Schema::new().numeric("id").discrete("name")
+        );
+    }
+
+    #[test]
+    fn csv_duplicate_header_test() {
+        let path = temp_path("duplicate_headers.csv");
+        std::fs::write(&path, "value,value,value_1,value\n1,2,3,4\n").unwrap();
+        let err = frame_from_csv::<i64>(&path).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate column key 'value'");
+
+        let opts = CsvOptions {
+            dedup_headers: true,
+            ..CsvOptions::default()
+        };
+        let frame = frame_from_csv_with_options::<i64>(&path, &opts).unwrap();
+        let keys: Vec<&str> = frame
+            .columns()
             .iter()
-            .map(|x| x.binary_view(&picker))
+            .map(|c| c.get_key().as_str())
             .collect();
-        copy.num_rows = copy.columns[0].len();
-        copy
+        // the header already named value_1 keeps its name
+        assert_eq!(keys, vec!["value", "value_2", "value_1", "value_3"]);
+        assert_eq!(frame.at("value_2", 0), Some(Cell::Num(2)));
+        assert_eq!(frame.at("value_3", 0), Some(Cell::Num(4)));
+
+        let opts = CsvOptions {
+            use_columns: Some(vec![String::from("value_3")]),
+            ..opts
+        };
+        let frame = frame_from_csv_with_options::<i64>(&path, &opts).unwrap();
+        assert_eq!(frame.at("value_3", 0), Some(Cell::Num(4)));
     }
 
-    pub fn to_csv(&self, file_path: String) -> Result<(), Box<dyn Error>> {
-        let file = File::create(file_path)?;
-        let mut writer = Writer::from_writer(file);
-        writer.write_record(self.columns.iter().map(|x| x.get_key()))?;
-        for i in 0..self.num_rows {
-            let mut row = Vec::new();
-            for col in self.columns.iter() {
-                match col {
-                    Column::Numeric(n) => {
-                        row.push(n.get(i).to_string());
-                    }
-                    Column::Discrete(d) => {
-                        row.push(d.get(i).clone());
-                    }
-                }
+    #[test]
+    fn csv_empty_header_test() {
+        // pandas writes the index column without a header
+        let path = temp_path("empty_header.csv");
+        std::fs::write(&path, ",a,,column_2\n0,1,2,3\n").unwrap();
+        let (frame, report) =
+            frame_from_csv_with_report::<i64>(&path, &CsvOptions::default()).unwrap();
+        let keys: Vec<&str> = frame
+            .columns()
+            .iter()
+            .map(|c| c.get_key().as_str())
+            .collect();
+        assert_eq!(keys, vec!["column_0", "a", "column_2_1", "column_2"]);
+        assert_eq!(frame.at("column_0", 0), Some(Cell::Num(0)));
+        assert_eq!(
+            report.events()[1],
+            LoadEvent::HeaderRenamed {
+                field: 2,
+                from: String::new(),
+                to: String::from("column_2_1"),
             }
-            writer.write_record(&row)?;
-        }
-        writer.flush()?;
-        Ok(())
+        );
+        let report = inspect_csv::<i64>(&path, 10).unwrap();
+        assert!(report.column("column_0").is_some());
     }
 
-    pub fn to_string(&self) -> String {
-        let mut result = String::from("nodframe:\n");
-        for col in self.columns.iter() {
-            result.push_str(&col.to_string());
-            result.push_str("\n");
-        }
-        result.push_str("Num Rows: ");
-        result.push_str(&self.num_rows.to_string());
+    #[test]
+    fn csv_na_round_trip_test() {
+        // -1 marks a missing score and an empty string a missing name
+        let frame = NodFrameBuilder::new()
+            .add_numeric("score", vec![7, -1, 3])
+            .add_discrete("name", strings(&["ann", "", "cat"]))
+            .build()
+            .unwrap();
+        let path = temp_path("na.csv");
+        let write = CsvWriteOptions {
+            na_rep: String::from("NA"),
+            na_value: Some(String::from("-1")),
+            ..CsvWriteOptions::default()
+        };
+        frame.to_csv_with_progress(&path, &write, |_| {}).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "score,name\n7,ann\nNA,\n3,cat\n"
+        );
+        let read = CsvOptions {
+            na_strings: vec![String::from("NA")],
+            na_value: Some(String::from("-1")),
+            ..CsvOptions::default()
+        };
+        let back = frame_from_csv_with_options::<i64>(&path, &read).unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
 
-        result
+        // without the convention NA is ordinary text and the column turns
+        // discrete, without an na_value it is missing text
+        let plain = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(plain["score"].to_string(), "score: [7, NA, 3]");
+        let no_value = CsvOptions {
+            na_value: None,
+            ..read.clone()
+        };
+        let mangled = frame_from_csv_with_options::<i64>(&path, &no_value).unwrap();
+        assert_eq!(mangled["score"].to_string(), "score: [7, , 3]");
+        let schema = Schema::new().numeric("score").discrete("name");
+        assert!(frame_from_csv_with_schema::<i64>(&path, &schema).is_err());
+        let bad = CsvOptions {
+            na_value: Some(String::from("none")),
+            ..read
+        };
+        assert_eq!(
+            frame_from_csv_with_options::<i64>(&path, &bad)
+                .unwrap_err()
+                .to_string(),
+            "na_value 'none' is not numeric"
+        );
     }
-}
 
-// frame_from_csv reads in a csv and automatically converts it into a
-pub fn frame_from_csv<
-    T: Clone
-        + Eq
-        + std::hash::Hash
-        + Add
-        + Div
-        + Mul
-        + Sub
-        + PartialOrd
-        + std::string::ToString
-        + FromStr,
->(
-    file_path: String,
-) -> Result<NodFrame<T>, Box<dyn Error>> {
-    let file = File::open(file_path)?;
-    let mut reader = Reader::from_reader(file);
-    let mut record = StringRecord::new();
-    let head = reader.headers()?.clone();
-    let header: Vec<&str> = head.iter().collect();
-    let mut data: Vec<Vec<String>> = vec![vec![]; header.len()];
-
-    while !reader.is_done() {
-        let row: Vec<String>;
-        if reader.read_record(&mut record)? {
-            row = record.iter().map(|s| s.to_string()).collect();
-        } else {
-            break;
-        }
-        for i in 0..header.len() {
-            data[i].push(row[i].clone());
+    #[test]
+    fn csv_bom_test() {
+        let path = temp_path("bom.csv");
+        std::fs::write(&path, "\u{feff}id,name\n1,a\n").unwrap();
+        let frame = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(frame.schema().columns()[0].0, "id");
+        assert_eq!(frame.at("id", 0), Some(Cell::Num(1)));
+    }
+
+    #[test]
+    fn memory_usage_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("id", (0..1000i64).collect())
+            .add_discrete("city", (0..1000).map(|i| format!("c{}", i % 3)).collect())
+            .build()
+            .unwrap();
+        let report = frame.memory_usage();
+        let vec = std::mem::size_of::<Vec<i64>>();
+        assert_eq!(report.columns()[0], (String::from("id"), vec + 8000));
+        // each "cN" value is its own allocation of two reference counts and
+        // two bytes of text
+        let city = report.columns()[1].1;
+        let counts = 2 * std::mem::size_of::<usize>();
+        let expected = vec + 1000 * (std::mem::size_of::<Arc<str>>() + counts + 2);
+        assert_eq!(city, expected);
+        assert_eq!(report.total(), vec + 8000 + city);
+        assert!(report
+            .to_string()
+            .starts_with("column  bytes\nid      7.8 KiB\ncity    "));
+
+        let cities = frame.discrete_column("city").unwrap();
+        // three distinct values, so nearly everything but the codes is saved
+        let savings = cities.estimated_dictionary_savings();
+        assert!(savings > city - 1000 * 4 - 200 && savings < city);
+    }
+
+    #[test]
+    fn csv_interning_test() {
+        let path = temp_path("low_cardinality.csv");
+        let mut text = String::from("id,level\n");
+        for i in 0..10_000 {
+            text.push_str(&format!("{},{}\n", i, ["info", "warn", "error"][i % 3]));
         }
+        std::fs::write(&path, text).unwrap();
+        let frame: NodFrame<i64> = frame_from_csv(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let level = frame.discrete_column("level").unwrap();
+        let owned = DiscreteColumn::new("level", level.clone().into_vec());
+        // loaded rows share three buffers, rebuilding from Strings gives one per row
+        let pointer = std::mem::size_of::<Arc<str>>();
+        let counts = 2 * std::mem::size_of::<usize>();
+        let pointers = std::mem::size_of::<Vec<Arc<str>>>() + 10_000 * pointer;
+        assert_eq!(level.memory_usage(), pointers + 3 * counts + 4 + 4 + 5);
+        assert!(owned.memory_usage() > level.memory_usage() + 10_000 * counts);
+        assert_eq!(owned.intern().memory_usage(), level.memory_usage());
+        assert!(Arc::ptr_eq(&level.as_slice()[0], &level.as_slice()[3]));
+
+        // values compare and filter the same whether shared or not
+        assert!(level.iter().eq(owned.iter()));
+        assert_eq!(level.filter_array("warn"), owned.filter_array("warn"));
+        assert_eq!(level.get(2), "error");
+        assert_eq!(level.unique(), strings(&["info", "warn", "error"]));
     }
-    let mut num_keys = Vec::new();
-    let mut disc_keys = Vec::new();
-    let mut num_data = Vec::new();
-    let mut disc_data = Vec::new();
 
-    for i in 0..header.len() {
-        if let Ok(_) = data[i][0].parse::<T>() {
-            num_keys.push(header[i].to_string());
-            let mut col = Vec::new();
-            for element in data[i].iter() {
-                if let Ok(n) = element.parse::<T>() {
-                    col.push(n);
-                }
+    #[test]
+    fn sort_by_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("n", vec![3, 1, 2, 1, 3])
+            .add_discrete("s", strings(&["a", "b", "c", "d", "e"]))
+            .build()
+            .unwrap();
+        let up = frame.sort_by("n", true).unwrap();
+        assert_eq!(up.numeric_column("n").unwrap().as_slice(), &[1, 1, 2, 3, 3]);
+        assert_eq!(
+            up.discrete_column("s").unwrap().iter().collect::<Vec<_>>(),
+            strings(&["b", "d", "c", "a", "e"])
+        );
+        assert!(up.is_known_sorted("n"));
+        // ties keep their original order when sorting descending too
+        let down = frame.sort_by("n", false).unwrap();
+        assert_eq!(
+            down.discrete_column("s")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["a", "e", "c", "b", "d"])
+        );
+        assert!(!down.is_known_sorted("n"));
+        assert!(frame.sort_by("missing", true).is_err());
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Level {
+        Low,
+        Medium,
+        High,
+    }
+
+    impl FromStr for Level {
+        type Err = ();
+
+        fn from_str(s: &str) -> Result<Level, ()> {
+            match s {
+                "low" => Ok(Level::Low),
+                "medium" => Ok(Level::Medium),
+                "high" => Ok(Level::High),
+                _ => Err(()),
             }
-            num_data.push(col);
-        } else {
-            disc_keys.push(header[i].to_string());
-            disc_data.push(data[i].clone());
         }
     }
-    Ok(frame_from_vecs(num_keys, num_data, disc_keys, disc_data))
-}
 
-// Build functions for Frame
-pub fn frame_from_vecs<
-    T: Clone + Eq + std::hash::Hash + Add + Div + Mul + Sub + PartialOrd + std::string::ToString,
->(
-    num_keys: Vec<String>,
-    num_data: Vec<Vec<T>>,
-    str_keys: Vec<String>,
-    str_data: Vec<Vec<String>>,
-) -> NodFrame<T> {
-    let data_rows = num_data[0].len();
+    #[test]
+    fn unique_test() {
+        let tags = DiscreteColumn::new("tag", strings(&["red", "blue", "red", "green", "blue"]));
+        assert_eq!(tags.unique(), strings(&["red", "blue", "green"]));
+        assert_eq!(
+            tags.unique_ordered(ValueOrder::Sorted),
+            strings(&["blue", "green", "red"])
+        );
+        assert!(DiscreteColumn::new("tag", vec![]).unique().is_empty());
 
-    let num_columns: Vec<Column<T>> = num_keys
-        .iter()
-        .zip(num_data.iter())
-        .map(|(k, v)| {
-            Column::Numeric(NumericColumn {
-                key: k.to_string(),
-                items: v.to_vec(),
-            })
-        })
-        .collect();
+        let n = NumericColumn::new("n", vec![3, 1, 3, 2, 1]);
+        assert_eq!(n.unique(), vec![3, 1, 2]);
+        assert_eq!(n.unique_ordered(ValueOrder::Sorted), vec![1, 2, 3]);
+        assert!(NumericColumn::<i64>::new("n", vec![]).unique().is_empty());
+    }
 
-    let str_columns: Vec<Column<T>> = str_keys
-        .iter()
-        .zip(str_data)
-        .map(|(k, v)| {
-            Column::Discrete(DiscreteColumn {
-                key: k.to_string(),
-                items: v.to_vec(),
-            })
-        })
-        .collect();
+    #[test]
+    fn column_as_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("level", strings(&["low", "high", "medium"]))
+            .add_discrete("raw", strings(&["high", "extreme", "low"]))
+            .add_numeric("n", vec![3, -1, 250])
+            .build()
+            .unwrap();
+        assert_eq!(
+            frame.column_as::<Level>("level").unwrap(),
+            vec![Level::Low, Level::High, Level::Medium]
+        );
+        assert_eq!(
+            frame.column_as::<Level>("raw").unwrap_err().to_string(),
+            "column 'raw' row 1: 'extreme' does not parse"
+        );
+        assert_eq!(
+            frame.column_as_lenient::<Level>("raw").unwrap(),
+            vec![Some(Level::High), None, Some(Level::Low)]
+        );
+        assert_eq!(frame.column_as::<i64>("n").unwrap(), vec![3, -1, 250]);
+        assert_eq!(
+            frame.column_as::<u8>("n").unwrap_err(),
+            NodFrameError::InvalidValue {
+                key: String::from("n"),
+                row: 1,
+                message: String::from("'-1' does not parse")
+            }
+        );
+        assert_eq!(
+            frame.column_as_lenient::<u8>("n").unwrap(),
+            vec![Some(3), None, Some(250)]
+        );
+        assert!(frame.column_as::<Level>("missing").is_err());
+        let raw = frame.discrete_column("raw").unwrap();
+        assert!(raw.parse_as::<Level>().is_err());
+        assert_eq!(raw.parse_as_lenient::<Level>()[2], Some(Level::Low));
+    }
 
-    let cols: Vec<Column<T>> = num_columns
-        .into_iter()
-        .chain(str_columns.into_iter())
-        .collect();
+    #[test]
+    fn natural_sort_test() {
+        let items = DiscreteColumn::new("id", strings(&["item2", "item10", "item1"]));
+        assert_eq!(items.argsort_natural(), vec![2, 0, 1]);
 
-    let names: HashMap<String, usize> = (0..cols.len())
-        .map(|i| (cols[i].get_key().clone(), i))
-        .collect();
+        let versions = strings(&["1.10.0", "1.2.10", "1.2.9", "0.9", "1.2"]);
+        let frame = NodFrameBuilder::new()
+            .add_discrete("version", versions)
+            .add_numeric("n", vec![0, 1, 2, 3, 4])
+            .build()
+            .unwrap();
+        let sorted = frame
+            .sort_by_mode("version", true, SortMode::Natural)
+            .unwrap();
+        assert_eq!(
+            sorted["version"].to_string(),
+            "version: [0.9, 1.2, 1.2.9, 1.2.10, 1.10.0]"
+        );
+        let down = frame
+            .sort_by_mode("version", false, SortMode::Natural)
+            .unwrap();
+        assert_eq!(down["n"].to_string(), "n: [0, 1, 2, 4, 3]");
+        assert_eq!(
+            frame.sort_by("version", true).unwrap()["n"].to_string(),
+            "n: [3, 0, 4, 1, 2]"
+        );
+
+        // leading zeros only break ties, the longer run sorts first
+        assert_eq!(natural_cmp("01", "1"), std::cmp::Ordering::Less);
+        assert_eq!(natural_cmp("x007", "x7"), std::cmp::Ordering::Less);
+        assert_eq!(natural_cmp("x008", "x7"), std::cmp::Ordering::Greater);
+        assert_eq!(natural_cmp("a", "a1"), std::cmp::Ordering::Less);
+        assert_eq!(
+            natural_cmp("n99999999999999999999999", "n100000000000000000000000"),
+            std::cmp::Ordering::Less
+        );
+        assert!(compare_natural("item9", &Comp::Les, "item10"));
 
-    NodFrame {
-        num_cols: cols.len(),
-        columns: cols,
-        column_idx: names,
-        num_rows: data_rows,
+        let recent = frame
+            .cond_mask(&Cond::natural("version", Comp::Geq, "1.2.9"))
+            .unwrap();
+        assert_eq!(recent.as_slice(), &[true, true, true, false, false]);
     }
-}
 
-///// TESTS /////
+    #[test]
+    fn sorted_filter_test() {
+        let values: Vec<i64> = vec![5, 1, 3, 3, 3, 7, 1, 9, 5, 5];
+        let frame = NodFrameBuilder::new()
+            .add_numeric("n", values.clone())
+            .add_numeric("row", (0..10).collect())
+            .build()
+            .unwrap();
+        let sorted = frame.sort_by("n", true).unwrap();
+        // the same rows without the sortedness metadata take the scan path
+        let scanned = sorted.take(&(0..10).collect::<Vec<usize>>()).unwrap();
+        assert!(!scanned.is_known_sorted("n"));
+        let render = |f: &NodFrame<i64>| f.to_string();
+        for v in [0, 1, 3, 4, 5, 9, 10] {
+            for comp in [
+                Comp::Eq,
+                Comp::Geq,
+                Comp::Gra,
+                Comp::Leq,
+                Comp::Les,
+                Comp::Not,
+            ] {
+                let fast = sorted.filter("n", comp, FilterValue::Num(v)).unwrap();
+                assert_valid(&fast);
+                assert_eq!(
+                    render(&fast),
+                    render(&scanned.filter("n", comp, FilterValue::Num(v)).unwrap()),
+                );
+            }
+            for high in [0, 3, 5, 9] {
+                assert_eq!(
+                    render(&sorted.filter_between("n", v, high).unwrap()),
+                    render(&scanned.filter_between("n", v, high).unwrap()),
+                );
+            }
+        }
+        let between = sorted.filter_between("n", 3, 5).unwrap();
+        assert_eq!(
+            between.numeric_column("n").unwrap().as_slice(),
+            &[3, 3, 3, 5, 5, 5]
+        );
+        assert!(between.is_known_sorted("n"));
 
-#[cfg(test)]
-mod col_tests {
-    use super::*;
+        let mut edited = sorted.clone();
+        edited.set_value("n", 0, Cell::Num(100)).unwrap();
+        assert!(!edited.is_known_sorted("n"));
+        assert!(!edited.numeric_column("n").unwrap().is_sorted());
+    }
 
     #[test]
-    fn binary_view_test() {
-        let col = NumericColumn {
-            key: String::from("bing"),
-            items: vec![1, 2, 3],
+    fn content_hash_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("n", vec![1, 2, 3])
+            .add_discrete("s", strings(&["a", "b", "c"]))
+            .build()
+            .unwrap();
+        let same = frame.take(&[0, 1, 2]).unwrap();
+        assert_eq!(frame.content_hash(), same.content_hash());
+        // golden value, this must not change between runs or releases
+        assert_eq!(frame.content_hash(), 743581383540130688);
+
+        let reordered = frame.reorder(&["s"]).unwrap();
+        assert_ne!(frame.content_hash(), reordered.content_hash());
+        let mut edited = frame.clone();
+        edited
+            .set_value("s", 2, Cell::Str(String::from("d")))
+            .unwrap();
+        assert_ne!(frame.content_hash(), edited.content_hash());
+        assert_eq!(frame["n"].content_hash(), edited["n"].content_hash());
+        assert_ne!(frame["s"].content_hash(), edited["s"].content_hash());
+        // values are length prefixed, so "ab","c" differs from "a","bc"
+        let split = |a: &str, b: &str| {
+            Column::<i64>::from(build_discrete_column("k", strings(&[a, b])).unwrap())
         };
-        let b = col.binary_view(&vec![true, false, true]);
-        assert_eq!(vec![1, 3], b.items)
+        assert_ne!(
+            split("ab", "c").content_hash(),
+            split("a", "bc").content_hash()
+        );
     }
 
     #[test]
-    fn slice_test() {
-        let col = NumericColumn {
-            key: String::from("bing"),
-            items: vec![1, 2, 3],
+    fn compare_test() {
+        let left = NodFrameBuilder::new()
+            .add_numeric("id", vec![1, 2, 3])
+            .add_numeric("v", vec![10, 20, 30])
+            .add_discrete("s", strings(&["a", "b", "c"]))
+            .build()
+            .unwrap();
+        let opts = CompareOptions::default();
+        let same = left.compare(&left.clone(), &opts).unwrap();
+        assert!(same.is_empty());
+        assert_eq!(same.to_string(), "frames are equal");
+
+        // rows in another order only match when paired by key
+        let shuffled = left.take(&[2, 0, 1]).unwrap();
+        let by_position = left.compare(&shuffled, &opts).unwrap();
+        assert_eq!(by_position.mismatch_count(), 9);
+        let keyed = CompareOptions {
+            key: Some(String::from("id")),
+            ..CompareOptions::default()
         };
-        let b = col.slice(1, 3);
-        assert_eq!(vec![2, 3], b.items)
+        assert!(left.compare(&shuffled, &keyed).unwrap().is_empty());
+
+        // column order only matters when not ignored
+        let reordered = left.reorder(&["s"]).unwrap();
+        let diff = left.compare(&reordered, &opts).unwrap();
+        assert!(diff.column_order_differs() && diff.mismatch_count() == 0);
+        let unordered = CompareOptions {
+            ignore_column_order: true,
+            ..CompareOptions::default()
+        };
+        assert!(left.compare(&reordered, &unordered).unwrap().is_empty());
+
+        // tolerance, missing rows and columns, and the mismatch cap
+        let right = NodFrameBuilder::new()
+            .add_numeric("id", vec![1, 2, 4])
+            .add_numeric("v", vec![11, 25, 40])
+            .add_numeric("extra", vec![0, 0, 0])
+            .build()
+            .unwrap();
+        let loose = CompareOptions {
+            key: Some(String::from("id")),
+            tolerance: 1.0,
+            max_mismatches: 0,
+            ..CompareOptions::default()
+        };
+        let diff = left.compare(&right, &loose).unwrap();
+        assert_eq!(diff.columns_only_in_left(), strings(&["s"]));
+        assert_eq!(diff.columns_only_in_right(), strings(&["extra"]));
+        assert_eq!(diff.rows_only_in_left(), strings(&["3"]));
+        assert_eq!(diff.rows_only_in_right(), strings(&["4"]));
+        assert_eq!(diff.mismatch_count(), 1);
+        assert!(diff.mismatches().is_empty());
+        let shown = CompareOptions {
+            max_mismatches: 5,
+            ..loose
+        };
+        let diff = left.compare(&right, &shown).unwrap();
+        assert_eq!(
+            diff.mismatches(),
+            &[CellMismatch {
+                row: String::from("2"),
+                column: String::from("v"),
+                left: String::from("20"),
+                right: String::from("25"),
+            }]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "columns only in left: s\ncolumns only in right: extra\nrows only in left: 3\n\
+             rows only in right: 4\ncell mismatches: 1 (showing 1)\n  row 2, column 'v': 20 != 25"
+        );
+        assert!(left
+            .compare(
+                &right,
+                &CompareOptions {
+                    key: Some(String::from("s")),
+                    ..CompareOptions::default()
+                }
+            )
+            .is_err());
     }
 
     #[test]
-    fn values_test() {
-        let col = NumericColumn {
-            key: String::from("bing"),
-            items: vec![1_i64, 2, 3, 3, 2, 1, 4],
-        };
-        let b = col.values();
-        let mut c = HashSet::new();
-        c.extend(vec![1, 2, 3, 4].iter());
-        assert_eq!(b, c);
+    fn from_columns_test() {
+        let frame = NodFrame::from_columns(vec![
+            Column::from(build_numeric_column("a", vec![3, 1, 2]).unwrap()),
+            Column::from(build_discrete_column("b", strings(&["x", "y", "z"])).unwrap()),
+        ])
+        .unwrap();
+        assert_valid(&frame);
+        assert_eq!(frame.shape(), (3, 2));
+        assert_eq!(
+            NodFrame::from_columns(vec![
+                Column::from(build_numeric_column("a", vec![1, 2]).unwrap()),
+                Column::from(build_numeric_column("c", vec![1]).unwrap()),
+            ])
+            .unwrap_err(),
+            NodFrameError::LengthMismatch {
+                key: String::from("c"),
+                expected: 2,
+                found: 1
+            }
+        );
+        assert!(NodFrame::<i64>::from_columns(vec![
+            Column::from(build_numeric_column("a", vec![1]).unwrap()),
+            Column::from(build_numeric_column("a", vec![2]).unwrap()),
+        ])
+        .is_err());
+        assert_eq!(
+            NodFrame::<i64>::from_columns(Vec::new()).unwrap().shape(),
+            (0, 0)
+        );
+
+        // operations that add, drop, rename or reorder columns keep the index
+        let sorted = frame.sort_by("a", true).unwrap();
+        let steps = [
+            sorted.filter("a", Comp::Geq, FilterValue::Num(2)).unwrap(),
+            frame
+                .filter("b", Comp::Not, FilterValue::Str(String::from("y")))
+                .unwrap(),
+            frame.rename("a", "c").unwrap(),
+            frame.reorder(&["b", "a"]).unwrap(),
+            frame.with_row_ids("id").unwrap(),
+            frame.slice(1, 3).unwrap(),
+            frame_from_vecs(vec![String::from("n")], vec![vec![1]], vec![], vec![]).unwrap(),
+        ];
+        for step in &steps {
+            assert_valid(step);
+        }
+        assert!(steps[0].is_known_sorted("a"));
     }
 
     #[test]
-    fn filter_test() {
-        let col = NumericColumn {
-            key: String::from("bing"),
-            items: vec![1_i64, 2, 3, 3, 2, 1, 4],
-        };
-        let b = col.filter_array(&2, Comp::Leq);
-        let c = vec![true, true, false, false, true, true, false];
-        assert_eq!(c, b);
+    fn validate_test() {
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2, 3])
+            .add_discrete("b", strings(&["x", "y", "z"]))
+            .build()
+            .unwrap();
+        assert_eq!(frame.validate(), Ok(()));
+        assert_eq!(frame.sort_by("a", true).unwrap().validate(), Ok(()));
+
+        let mut broken = frame.clone();
+        broken.num_rows = 4;
+        broken.num_cols = 3;
+        broken.column_idx.insert(String::from("gone"), 7);
+        broken.column_idx.insert(String::from("b"), 0);
+        broken.sorted.insert(String::from("b"));
+        let violations = broken.validate().unwrap_err();
+        assert_eq!(
+            violations,
+            vec![
+                InvariantViolation::ColumnCount {
+                    recorded: 3,
+                    actual: 2
+                },
+                InvariantViolation::ColumnLength {
+                    key: String::from("a"),
+                    num_rows: 4,
+                    found: 3
+                },
+                InvariantViolation::ColumnLength {
+                    key: String::from("b"),
+                    num_rows: 4,
+                    found: 3
+                },
+                InvariantViolation::WrongIndex {
+                    key: String::from("b"),
+                    index: 0,
+                    position: 1
+                },
+                InvariantViolation::StaleIndex(String::from("gone")),
+                InvariantViolation::SortedFlag(String::from("b")),
+            ]
+        );
+        assert_eq!(
+            violations[3].to_string(),
+            "key index maps 'b' to 0 but the column is at 1"
+        );
+        broken.repair().unwrap();
+        assert_eq!(broken.validate(), Ok(()));
+        assert_eq!(broken.shape(), (3, 2));
+
+        let mut missing = frame.clone();
+        missing.column_idx.remove("a");
+        assert_eq!(
+            missing.validate(),
+            Err(vec![InvariantViolation::MissingIndex(String::from("a"))])
+        );
+
+        let mut duplicate = frame.clone();
+        duplicate.columns.push(Column::from(
+            build_numeric_column("a", vec![4, 5, 6]).unwrap(),
+        ));
+        duplicate.num_cols = 3;
+        assert_eq!(
+            duplicate.validate(),
+            Err(vec![InvariantViolation::DuplicateKey(String::from("a"))])
+        );
+        assert!(duplicate.repair().is_err());
+
+        let mut ragged = frame.clone();
+        ragged.columns[0] = Column::from(build_numeric_column("a", vec![1]).unwrap());
+        assert!(ragged.repair().is_err());
     }
-}
 
-mod frame_tests {
-    use super::*;
+    // arithmetic_for runs the same computations on a frame of any NodNum type
+    fn arithmetic_for<T: NodNum + fmt::Debug>() {
+        let of = |xs: &[u8]| -> Vec<T> { xs.iter().map(|x| T::from(*x).unwrap()).collect() };
+        let frame = NodFrameBuilder::new()
+            .add_discrete(
+                "g",
+                vec!["a", "b", "a", "b"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            )
+            .add_numeric("x", of(&[6, 2, 9, 4]))
+            .add_numeric("y", of(&[3, 2, 3, 1]))
+            .build()
+            .unwrap();
+        let (x, y) = (
+            frame.numeric_column("x").unwrap(),
+            frame.numeric_column("y").unwrap(),
+        );
+        assert_eq!(x.sum(), T::from(21).unwrap());
+        assert_eq!(x.add(y).unwrap().as_slice(), of(&[9, 4, 12, 5]).as_slice());
+        assert_eq!(x.sub(y).unwrap().as_slice(), of(&[3, 0, 6, 3]).as_slice());
+        assert_eq!(x.mul(y).unwrap().as_slice(), of(&[18, 4, 27, 4]).as_slice());
+        assert_eq!(x.div(y).unwrap().as_slice(), of(&[2, 1, 3, 4]).as_slice());
+        assert_eq!(
+            y.div(&x.sub(x).unwrap()).unwrap_err().to_string(),
+            "column 'x' row 0: division by zero"
+        );
+        assert!(x.add(&NumericColumn::new("z", of(&[1]))).is_err());
+        assert_eq!(x.stats().mean(), Some(5.25));
+        assert_eq!(x.quantile(1.0), Some(T::from(9).unwrap()));
+
+        let totals = frame.cumsum_by("g", "x", "run").unwrap();
+        assert_eq!(
+            totals.numeric_column("run").unwrap().as_slice(),
+            of(&[6, 2, 15, 6]).as_slice()
+        );
+        let doubled = frame.eval("z", "x * 2 + y").unwrap();
+        assert_eq!(
+            doubled.numeric_column("z").unwrap().as_slice(),
+            of(&[15, 6, 21, 9]).as_slice()
+        );
+        let sorted = frame.sort_by("x", false).unwrap();
+        assert_eq!(
+            sorted.numeric_column("y").unwrap().as_slice(),
+            of(&[3, 3, 1, 2]).as_slice()
+        );
+        assert_eq!(x.unique().len(), 4);
+    }
 
     #[test]
-    fn csv_test() {
-        let frame = frame_from_vecs(
-            vec![String::from("value"), String::from("whoop")],
-            vec![vec![1, 2, 3, 4], vec![14, 54, 7, 2]],
-            vec![String::from("kabang")],
-            vec![vec![
-                String::from("1a"),
-                String::from("2a"),
-                String::from("3a"),
-                String::from("4a"),
-            ]],
+    fn numeric_types_test() {
+        arithmetic_for::<i32>();
+        arithmetic_for::<i64>();
+        arithmetic_for::<u64>();
+        arithmetic_for::<f32>();
+        arithmetic_for::<f64>();
+
+        // floats keep their fractions where integers truncate
+        let halves = NumericColumn::new("h", vec![1.5_f64, 2.5]);
+        assert_eq!(halves.sum(), 4.0);
+        let ints = NumericColumn::new("i", vec![7, 9]);
+        assert_eq!(
+            ints.div(&NumericColumn::new("d", vec![2, 2]))
+                .unwrap()
+                .as_slice(),
+            &[3, 4]
+        );
+    }
+
+    #[test]
+    fn cached_bounds_test() {
+        let mut frame = NodFrameBuilder::new()
+            .add_numeric("x", vec![4.0, f64::NAN, -2.0, 9.0, 1.0])
+            .add_discrete("tag", strings(&["a", "b", "c", "d", "e"]))
+            .build()
+            .unwrap();
+        let x = frame.numeric_column("x").unwrap();
+        assert!(x.summary.get().is_none());
+        assert_eq!(frame.bounds("x").unwrap(), Some((-2.0, 9.0)));
+        // the second call reads the summary kept by the first
+        let x = frame.numeric_column("x").unwrap();
+        let kept: *const Summary<f64> = x.summary.get().unwrap();
+        assert_eq!((x.min_cached(), x.max_cached()), (Some(-2.0), Some(9.0)));
+        assert!(std::ptr::eq(kept, x.summary()));
+        assert_eq!(x.count_cached(), 4);
+        assert!(x.sum_cached().is_nan());
+
+        // slices and views start without a cache and report their own bounds
+        let head = frame.slice(0, 2).unwrap();
+        assert!(head.numeric_column("x").unwrap().summary.get().is_none());
+        assert_eq!(head.bounds("x").unwrap(), Some((4.0, 4.0)));
+        let view = x.binary_view(&[false, false, true, false, true]);
+        assert_eq!(
+            (view.min_cached(), view.max_cached()),
+            (Some(-2.0), Some(1.0))
+        );
+        assert_eq!(frame.bounds("x").unwrap(), Some((-2.0, 9.0)));
+
+        // changing a value drops the cache
+        frame.set_value("x", 3, Cell::Num(20.0)).unwrap();
+        assert!(frame.numeric_column("x").unwrap().summary.get().is_none());
+        assert_eq!(frame.bounds("x").unwrap(), Some((-2.0, 20.0)));
+        frame
+            .update_where(
+                "tag",
+                Comp::Eq,
+                None,
+                Some(String::from("c")),
+                "x",
+                Cell::Num(-5.0),
+            )
+            .unwrap();
+        assert_eq!(frame.bounds("x").unwrap(), Some((-5.0, 20.0)));
+        frame
+            .push_row(&[Cell::Num(30.0), Cell::Str(String::from("f"))])
+            .unwrap();
+        assert_eq!(frame.bounds("x").unwrap(), Some((-5.0, 30.0)));
+
+        let empty = NodFrameBuilder::new()
+            .add_numeric("x", vec![f64::NAN])
+            .build()
+            .unwrap();
+        assert_eq!(empty.bounds("x").unwrap(), None);
+        assert!(matches!(
+            frame.bounds("tag").unwrap_err(),
+            NodFrameError::KindMismatch { .. }
+        ));
+        assert_eq!(
+            frame.bounds("nope").unwrap_err(),
+            NodFrameError::MissingColumn(String::from("nope"))
         );
-        frame.to_csv(String::from("hehe.csv"));
-        let frame2 = frame_from_csv::<i32>(String::from("hehe.csv")).unwrap();
-        assert_eq!(frame.to_string(), frame2.to_string());
     }
 }
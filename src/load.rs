@@ -0,0 +1,443 @@
+// What a lenient csv load did besides failing, see frame_from_csv_with_report.
+//
+// The options of CsvOptions let a load go on where a strict one would stop:
+// ragged rows are skipped or padded, empty or repeated headers renamed and
+// missing cells filled with na_value, and inferred columns fall back to
+// discrete when a cell is not a number. LoadReport lists each of these with its line or
+// column, so a pipeline can keep the frame and still log what was changed.
+use crate::{open_csv, read_csv_with_report, CsvColumn, CsvLayout, CsvOptions, NodFrame, NodNum};
+use csv::ByteRecord;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+// LoadEvent is one thing a load did to the file's contents. Lines are those
+// of the file, as in the errors of a strict load, field is the position of a
+// header among all fields
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoadEvent {
+    HeaderRenamed {
+        field: usize,
+        from: String,
+        to: String,
+    },
+    RowSkipped {
+        line: u64,
+        expected: usize,
+        found: usize,
+    },
+    RowPadded {
+        line: u64,
+        expected: usize,
+        found: usize,
+    },
+    RowTruncated {
+        line: u64,
+        expected: usize,
+        found: usize,
+    },
+    // the inferred column key turned discrete at value, the first cell that
+    // is not a number or loses its text as one, see NodFrame::demoted_columns
+    ColumnDemoted {
+        key: String,
+        line: u64,
+        value: String,
+    },
+    // count missing cells of the numeric column key took value, the first of
+    // them on line
+    CellsFilled {
+        key: String,
+        line: u64,
+        count: usize,
+        value: String,
+    },
+}
+
+impl LoadEvent {
+    // line is where the event happened, None for the header
+    pub fn line(&self) -> Option<u64> {
+        match self {
+            LoadEvent::HeaderRenamed { .. } => None,
+            LoadEvent::RowSkipped { line, .. }
+            | LoadEvent::RowPadded { line, .. }
+            | LoadEvent::RowTruncated { line, .. }
+            | LoadEvent::ColumnDemoted { line, .. }
+            | LoadEvent::CellsFilled { line, .. } => Some(*line),
+        }
+    }
+
+    // ragged is the event of a row of found fields under a header of expected
+    pub(crate) fn ragged(line: u64, expected: usize, found: usize, skipped: bool) -> LoadEvent {
+        match (skipped, found < expected) {
+            (true, _) => LoadEvent::RowSkipped {
+                line,
+                expected,
+                found,
+            },
+            (false, true) => LoadEvent::RowPadded {
+                line,
+                expected,
+                found,
+            },
+            (false, false) => LoadEvent::RowTruncated {
+                line,
+                expected,
+                found,
+            },
+        }
+    }
+}
+
+impl fmt::Display for LoadEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadEvent::HeaderRenamed { field, from, to } => {
+                write!(f, "header: field {} '{}' renamed '{}'", field, from, to)
+            }
+            LoadEvent::RowSkipped {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: row skipped, {} fields where the header has {}",
+                line, found, expected
+            ),
+            LoadEvent::RowPadded {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: row padded, {} fields where the header has {}",
+                line, found, expected
+            ),
+            LoadEvent::RowTruncated {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: row truncated, {} fields where the header has {}",
+                line, found, expected
+            ),
+            LoadEvent::ColumnDemoted { key, line, value } => write!(
+                f,
+                "line {}: column '{}' read as discrete from value '{}'",
+                line, key, value
+            ),
+            LoadEvent::CellsFilled {
+                key,
+                line,
+                count,
+                value,
+            } => write!(
+                f,
+                "line {}: column '{}' had {} missing cells filled with {}",
+                line, key, count, value
+            ),
+        }
+    }
+}
+
+// LoadReport holds the events of one load in file order, header events
+// first, with the encoding the file was decoded from and its delimiter
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoadReport {
+    encoding: String,
+    delimiter: char,
+    events: Vec<LoadEvent>,
+}
+
+impl LoadReport {
+    pub(crate) fn new(encoding: &str) -> LoadReport {
+        LoadReport {
+            encoding: String::from(encoding),
+            delimiter: ',',
+            events: Vec::new(),
+        }
+    }
+
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    pub fn events(&self) -> &[LoadEvent] {
+        &self.events
+    }
+
+    // is_empty is whether the file loaded exactly as written
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // add_events adds events and restores file order
+    pub(crate) fn add_events(&mut self, events: impl IntoIterator<Item = LoadEvent>) {
+        self.events.extend(events);
+        self.events.sort_by_key(|e| e.line());
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "loaded as {} with delimiter '{}'",
+            self.encoding, self.delimiter
+        )?;
+        if self.events.is_empty() {
+            return write!(f, ", nothing to report");
+        }
+        for event in self.events.iter() {
+            write!(f, "\n  {}", event)?;
+        }
+        Ok(())
+    }
+}
+
+// ColumnWatch follows the columns of a load for its report: which inferred
+// columns turn discrete on a record and which missing cells get na_value
+pub(crate) struct ColumnWatch {
+    // rows of each column still inferred before the last record
+    inferred: Vec<Option<usize>>,
+    demoted: Vec<LoadEvent>,
+    filled: Vec<Option<(u64, usize)>>,
+}
+
+impl ColumnWatch {
+    pub(crate) fn new(width: usize) -> ColumnWatch {
+        ColumnWatch {
+            inferred: vec![None; width],
+            demoted: Vec::new(),
+            filled: vec![None; width],
+        }
+    }
+
+    // before notes the inferred columns ahead of pushing a record
+    pub(crate) fn before<T: NodNum>(&mut self, columns: &[CsvColumn<T>]) {
+        for (inferred, column) in self.inferred.iter_mut().zip(columns) {
+            *inferred = match column {
+                CsvColumn::Inferred { ends, .. } => Some(ends.len()),
+                _ => None,
+            };
+        }
+    }
+
+    // after compares the columns with before once record is pushed
+    pub(crate) fn after<T: NodNum>(
+        &mut self,
+        columns: &[CsvColumn<T>],
+        layout: &CsvLayout,
+        record: &ByteRecord,
+        line: u64,
+    ) {
+        for (c, column) in columns.iter().enumerate() {
+            let bytes = record.get(layout.keep[c]).unwrap_or_default();
+            let cell = String::from_utf8_lossy(bytes);
+            let discrete = matches!(column, CsvColumn::Discrete { .. });
+            // a column whose first cell is text was never numeric, unless
+            // that cell is a number losing its text
+            let numeric_before = self.inferred[c].is_some_and(|rows| rows > 0);
+            if discrete && self.inferred[c].is_some() && (numeric_before || column.is_demoted()) {
+                self.demoted.push(LoadEvent::ColumnDemoted {
+                    key: layout.header[c].clone(),
+                    line,
+                    value: cell.into_owned(),
+                });
+            } else if !discrete
+                && layout.na_value.is_some()
+                && layout.na_strings.iter().any(|na| *na == cell)
+            {
+                let filled = self.filled[c].get_or_insert((line, 0));
+                filled.1 += 1;
+            }
+        }
+    }
+
+    // finish gives the events of the load. Cells filled in a column that
+    // ended discrete were kept as empty strings and are not reported
+    pub(crate) fn finish<T: NodNum>(
+        self,
+        columns: &[CsvColumn<T>],
+        layout: &CsvLayout,
+    ) -> Vec<LoadEvent> {
+        let value = layout.na_value.clone().unwrap_or_default();
+        let filled = self
+            .filled
+            .into_iter()
+            .zip(columns)
+            .zip(layout.header.iter())
+            .filter_map(|((filled, column), key)| match (filled, column) {
+                (_, CsvColumn::Discrete { .. }) | (None, _) => None,
+                (Some((line, count)), _) => Some(LoadEvent::CellsFilled {
+                    key: key.clone(),
+                    line,
+                    count,
+                    value: value.clone(),
+                }),
+            });
+        let renamed = layout
+            .renamed
+            .iter()
+            .map(|(field, from, to)| LoadEvent::HeaderRenamed {
+                field: *field,
+                from: from.clone(),
+                to: to.clone(),
+            });
+        renamed.chain(self.demoted).chain(filled).collect()
+    }
+}
+
+// frame_from_csv_with_report is frame_from_csv_with_options that also
+// returns what the options changed while loading, see LoadReport. An error
+// is still an error: only what the options allow is reported
+pub fn frame_from_csv_with_report<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+) -> Result<(NodFrame<T>, LoadReport), Box<dyn Error>> {
+    #[cfg(feature = "encoding")]
+    let encoding = crate::encoding::detected(&file_path, opts.encoding)?;
+    #[cfg(not(feature = "encoding"))]
+    let encoding = "UTF-8";
+    let mut report = LoadReport::new(encoding);
+    let frame = read_csv_with_report(
+        open_csv(file_path, opts)?,
+        opts,
+        |header| Ok(vec![None; header.len()]),
+        Some(&mut report),
+    )?;
+    Ok((frame, report))
+}
+
+#[cfg(test)]
+mod load_tests {
+    use crate::test_support::temp_csv;
+    use crate::{frame_from_csv_with_report, Cell, CsvOptions, LoadEvent, RaggedPolicy};
+
+    #[test]
+    fn load_report_test() {
+        let path = temp_csv(
+            "messy.csv",
+            "id,price,zip,price,note\n\
+             1,9.5,10115,1,a\n\
+             2,NA,00501,2,b\n\
+             3,4.25,20095,3\n\
+             4,7,80331,4,d,extra,more\n\
+             5,NA,10115,5,e\n\
+             6,1.5,unknown,6,f\n",
+        );
+        let opts = CsvOptions {
+            ragged: RaggedPolicy::SkipRow,
+            dedup_headers: true,
+            na_strings: vec![String::from("NA")],
+            na_value: Some(String::from("0")),
+            ..CsvOptions::default()
+        };
+        let (frame, report) = frame_from_csv_with_report::<f64>(&path, &opts).unwrap();
+        assert_eq!(frame.shape(), (4, 5));
+        assert_eq!(frame.at("price", 1), Some(Cell::Num(0.0)));
+        assert_eq!(frame.at("zip", 1), Some(Cell::Str(String::from("00501"))));
+        assert_eq!(
+            report.events(),
+            &[
+                LoadEvent::HeaderRenamed {
+                    field: 3,
+                    from: String::from("price"),
+                    to: String::from("price_1"),
+                },
+                LoadEvent::ColumnDemoted {
+                    key: String::from("zip"),
+                    line: 3,
+                    value: String::from("00501"),
+                },
+                LoadEvent::CellsFilled {
+                    key: String::from("price"),
+                    line: 3,
+                    count: 2,
+                    value: String::from("0"),
+                },
+                LoadEvent::RowSkipped {
+                    line: 4,
+                    expected: 5,
+                    found: 4,
+                },
+                LoadEvent::RowSkipped {
+                    line: 5,
+                    expected: 5,
+                    found: 7,
+                },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "loaded as UTF-8 with delimiter ','\n  \
+             header: field 3 'price' renamed 'price_1'\n  \
+             line 3: column 'zip' read as discrete from value '00501'\n  \
+             line 3: column 'price' had 2 missing cells filled with 0\n  \
+             line 4: row skipped, 4 fields where the header has 5\n  \
+             line 5: row skipped, 7 fields where the header has 5"
+        );
+
+        let padded = CsvOptions {
+            ragged: RaggedPolicy::PadTruncate,
+            ..opts
+        };
+        let (frame, report) = frame_from_csv_with_report::<f64>(&path, &padded).unwrap();
+        assert_eq!(frame.shape(), (6, 5));
+        assert_eq!(
+            report.events()[3..],
+            [
+                LoadEvent::RowPadded {
+                    line: 4,
+                    expected: 5,
+                    found: 4,
+                },
+                LoadEvent::RowTruncated {
+                    line: 5,
+                    expected: 5,
+                    found: 7,
+                },
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn clean_load_report_test() {
+        let path = temp_csv("clean.csv", "id,name\n1,a\n2,b\n");
+        let (frame, report) =
+            frame_from_csv_with_report::<i64>(&path, &CsvOptions::default()).unwrap();
+        assert_eq!(frame.shape(), (2, 2));
+        assert!(report.is_empty());
+        assert_eq!(
+            report.to_string(),
+            "loaded as UTF-8 with delimiter ',', nothing to report"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_report_serde_test() {
+        let path = temp_csv("report_serde.csv", "a,a\n1,2\n");
+        let opts = CsvOptions {
+            dedup_headers: true,
+            ..CsvOptions::default()
+        };
+        let (_, report) = frame_from_csv_with_report::<i64>(&path, &opts).unwrap();
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(
+            json,
+            r#"{"encoding":"UTF-8","delimiter":",","events":[{"HeaderRenamed":{"field":1,"from":"a","to":"a_1"}}]}"#
+        );
+        let back: crate::LoadReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, report);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+// Combining the rows of frames with the same columns, see
+// NodFrame::merge_sorted and NodFrame::interleave.
+//
+// Both stack their inputs into one frame and then take its rows in a new
+// order. For merge_sorted each input's rows are checked to be in order, and
+// the runs of rows are merged pairwise until one run is left. That is
+// O(n log k) for k frames instead of the O(n log n) of stacking and sorting,
+// and merging adjacent runs taking the left row on ties keeps rows with equal
+// keys in input order. interleave alternates the rows of two frames.
+use crate::{Column, DiscreteColumn, NodFrame, NodFrameError, NodNum, NumericColumn, SortMode};
+use std::cmp::Ordering;
+
+// InterleaveOptions for interleave_with: allow_unequal appends the rows left
+// over from the longer frame instead of erroring, source_key adds a discrete
+// column holding "left" for rows of self and "right" for rows of other
+#[derive(Clone, Debug, Default)]
+pub struct InterleaveOptions {
+    pub allow_unequal: bool,
+    pub source_key: Option<String>,
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // merge_sorted combines frames each sorted on key, ascending or not as
+    // sort_by orders them, into one frame sorted on key. Every frame must
+    // have the columns of the first with the same kinds, the result has them
+    // in the order of the first frame. A frame out of order is an error
+    pub fn merge_sorted(
+        frames: &[NodFrame<T>],
+        key: &str,
+        ascending: bool,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        if frames.is_empty() {
+            return Err(NodFrameError::InvalidArgument(String::from(
+                "merge_sorted needs at least one frame",
+            )));
+        }
+        let stacked = stack(&frames.iter().collect::<Vec<_>>())?;
+        let col = stacked
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        col.check_levels()?;
+        let cmp = |a: usize, b: usize| col.cmp_rows(a, b, ascending, SortMode::Lexical);
+        let mut runs = Vec::with_capacity(frames.len());
+        let mut start = 0;
+        for (i, frame) in frames.iter().enumerate() {
+            let end = start + frame.num_rows;
+            // the sorted flag already vouches for ascending numeric columns
+            let known = ascending && frame.sorted.contains(key);
+            if !known {
+                if let Some(row) = (start + 1..end).find(|r| cmp(r - 1, *r) == Ordering::Greater) {
+                    return Err(NodFrameError::InvalidArgument(format!(
+                        "frame {} is not sorted on '{}' at row {}",
+                        i,
+                        key,
+                        row - start
+                    )));
+                }
+            }
+            runs.push((start..end).collect::<Vec<usize>>());
+            start = end;
+        }
+        while runs.len() > 1 {
+            let mut pairs = runs.into_iter();
+            let mut merged = Vec::new();
+            while let Some(left) = pairs.next() {
+                merged.push(match pairs.next() {
+                    Some(right) => merge_runs(&left, &right, cmp),
+                    None => left,
+                });
+            }
+            runs = merged;
+        }
+        let order = runs.pop().unwrap_or_default();
+        let mut out = stacked.take(&order)?;
+        if ascending && matches!(col, Column::Numeric(_)) {
+            out.sorted.insert(String::from(key));
+        }
+        Ok(out)
+    }
+
+    // interleave alternates the rows of self and other: the first row of
+    // self, the first of other, the second of self and so on. Both frames
+    // need the same columns with the same kinds and the same number of rows,
+    // the result has the columns in the order of self
+    pub fn interleave(&self, other: &NodFrame<T>) -> Result<NodFrame<T>, NodFrameError> {
+        self.interleave_with(other, &InterleaveOptions::default())
+    }
+
+    // interleave_with is interleave with options, see InterleaveOptions
+    pub fn interleave_with(
+        &self,
+        other: &NodFrame<T>,
+        opts: &InterleaveOptions,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let stacked = stack(&[self, other])?;
+        let (left, right) = (self.num_rows, other.num_rows);
+        if left != right && !opts.allow_unequal {
+            return Err(NodFrameError::InvalidArgument(format!(
+                "cannot interleave {} rows with {} rows",
+                left, right
+            )));
+        }
+        let mut order = Vec::with_capacity(left + right);
+        let mut sources = Vec::with_capacity(left + right);
+        for row in 0..left.max(right) {
+            if row < left {
+                order.push(row);
+                sources.push(String::from("left"));
+            }
+            if row < right {
+                order.push(left + row);
+                sources.push(String::from("right"));
+            }
+        }
+        let out = stacked.take(&order)?;
+        match &opts.source_key {
+            Some(key) => {
+                let mut columns = out.columns;
+                columns.push(Column::Discrete(DiscreteColumn::interned(key, sources)));
+                NodFrame::from_columns(columns)
+            }
+            None => Ok(out),
+        }
+    }
+}
+
+// merge_runs merges two ordered runs of rows, taking from left on ties
+fn merge_runs(
+    left: &[usize],
+    right: &[usize],
+    cmp: impl Fn(usize, usize) -> Ordering,
+) -> Vec<usize> {
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if cmp(left[i], right[j]) == Ordering::Greater {
+            out.push(right[j]);
+            j += 1;
+        } else {
+            out.push(left[i]);
+            i += 1;
+        }
+    }
+    out.extend_from_slice(&left[i..]);
+    out.extend_from_slice(&right[j..]);
+    out
+}
+
+// stack puts the rows of frames one after another, in the column order of
+// the first frame. A discrete column keeps the levels of the first frame, so
+// the frames must agree on them
+fn stack<T: NodNum>(frames: &[&NodFrame<T>]) -> Result<NodFrame<T>, NodFrameError> {
+    let first = frames
+        .first()
+        .ok_or_else(|| NodFrameError::InvalidArgument(String::from("no frames to stack")))?;
+    for frame in &frames[1..] {
+        for col in first.columns() {
+            let key = col.get_key();
+            let other = frame
+                .column(key)
+                .ok_or_else(|| NodFrameError::MissingColumn(key.clone()))?;
+            if other.kind() != col.kind() {
+                return Err(NodFrameError::KindMismatch {
+                    key: key.clone(),
+                    expected: col.kind(),
+                    found: other.kind(),
+                });
+            }
+            if let (Column::Discrete(a), Column::Discrete(b)) = (col, other) {
+                if a.levels != b.levels {
+                    return Err(NodFrameError::InvalidArgument(format!(
+                        "frames order column '{}' by different levels",
+                        key
+                    )));
+                }
+            }
+        }
+        if let Some(extra) = frame
+            .columns()
+            .iter()
+            .find(|c| first.column(c.get_key()).is_none())
+        {
+            return Err(NodFrameError::UnexpectedColumn(extra.get_key().clone()));
+        }
+    }
+    let columns = first
+        .columns()
+        .iter()
+        .map(|col| {
+            let parts = frames.iter().map(|f| &f[col.get_key().as_str()]);
+            match col {
+                Column::Numeric(n) => Column::Numeric(NumericColumn::new(
+                    n.key(),
+                    parts
+                        .flat_map(|p| p.as_numeric().into_iter().flat_map(|n| n.iter().cloned()))
+                        .collect(),
+                )),
+                Column::Discrete(d) => Column::Discrete(DiscreteColumn {
+                    key: d.key.clone(),
+                    items: parts
+                        .flat_map(|p| {
+                            p.as_discrete()
+                                .into_iter()
+                                .flat_map(|d| d.as_slice().iter().cloned())
+                        })
+                        .collect(),
+                    levels: d.levels.clone(),
+                }),
+            }
+        })
+        .collect();
+    NodFrame::from_columns(columns)
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use crate::test_support::strings;
+    use crate::{
+        ColKind, Comp, FilterValue, InterleaveOptions, NodFrame, NodFrameBuilder, NodFrameError,
+    };
+
+    fn day(times: Vec<i64>, source: &str) -> NodFrame<i64> {
+        let n = times.len();
+        NodFrameBuilder::new()
+            .add_numeric("t", times)
+            .add_discrete("source", vec![String::from(source); n])
+            .add_numeric("row", (0..n as i64).collect())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn merge_sorted_test() {
+        let frames = vec![
+            day(vec![1, 4, 4, 9], "a"),
+            day(vec![2, 4, 10], "b"),
+            day(vec![], "c"),
+            day(vec![0, 4], "d"),
+        ];
+        let merged = NodFrame::merge_sorted(&frames, "t", true).unwrap();
+        assert_eq!(merged["t"].to_string(), "t: [0, 1, 2, 4, 4, 4, 4, 9, 10]");
+        // equal times keep input order: a's two rows, then b, then d
+        assert_eq!(
+            merged
+                .discrete_column("source")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["d", "a", "b", "a", "a", "b", "d", "a", "b"]).as_slice()
+        );
+        assert_eq!(
+            merged["row"].to_string(),
+            "row: [0, 0, 0, 1, 2, 1, 1, 3, 2]"
+        );
+        assert!(merged.validate().is_ok());
+        // the result is flagged sorted, so filters can use binary search
+        let late = merged.filter("t", Comp::Geq, FilterValue::Num(9)).unwrap();
+        assert_eq!(late.shape().0, 2);
+
+        let single = NodFrame::merge_sorted(&frames[..1], "t", true).unwrap();
+        assert_eq!(single.to_string(), frames[0].to_string());
+
+        let down = vec![day(vec![9, 4, 1], "a"), day(vec![5, 4], "b")];
+        let merged = NodFrame::merge_sorted(&down, "t", false).unwrap();
+        assert_eq!(merged["t"].to_string(), "t: [9, 5, 4, 4, 1]");
+        assert_eq!(
+            merged
+                .discrete_column("source")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            strings(&["a", "b", "a", "b", "a"]).as_slice()
+        );
+    }
+
+    #[test]
+    fn merge_sorted_rejects_test() {
+        let unsorted = vec![day(vec![1, 2], "a"), day(vec![3, 1, 5], "b")];
+        let err = NodFrame::merge_sorted(&unsorted, "t", true).unwrap_err();
+        assert_eq!(err.to_string(), "frame 1 is not sorted on 't' at row 1");
+        assert!(NodFrame::merge_sorted(&unsorted[..1], "t", false).is_err());
+
+        let other = NodFrameBuilder::new()
+            .add_discrete("t", strings(&["x"]))
+            .add_discrete("source", strings(&["b"]))
+            .add_numeric("row", vec![0])
+            .build()
+            .unwrap();
+        let frames = vec![day(vec![1], "a"), other];
+        assert_eq!(
+            NodFrame::merge_sorted(&frames, "t", true).unwrap_err(),
+            NodFrameError::KindMismatch {
+                key: String::from("t"),
+                expected: ColKind::Numeric,
+                found: ColKind::Discrete,
+            }
+        );
+        let extra = day(vec![2], "b").rename("row", "index").unwrap();
+        let frames = vec![day(vec![1], "a"), extra];
+        assert_eq!(
+            NodFrame::merge_sorted(&frames, "t", true).unwrap_err(),
+            NodFrameError::MissingColumn(String::from("row"))
+        );
+        assert!(NodFrame::<i64>::merge_sorted(&[], "t", true).is_err());
+        assert!(NodFrame::merge_sorted(&frames[..1], "missing", true).is_err());
+    }
+
+    #[test]
+    fn interleave_test() {
+        let treatment = day(vec![1, 2, 3], "t");
+        let control = day(vec![10, 20, 30], "c");
+        let paired = treatment.interleave(&control).unwrap();
+        assert_eq!(paired["t"].to_string(), "t: [1, 10, 2, 20, 3, 30]");
+        assert_eq!(paired["row"].to_string(), "row: [0, 0, 1, 1, 2, 2]");
+        assert_eq!(paired["source"].to_string(), "source: [t, c, t, c, t, c]");
+        assert!(paired.validate().is_ok());
+
+        let opts = InterleaveOptions {
+            allow_unequal: false,
+            source_key: Some(String::from("origin")),
+        };
+        let labelled = treatment.interleave_with(&control, &opts).unwrap();
+        assert_eq!(
+            labelled
+                .discrete_column("origin")
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            &["left", "right", "left", "right", "left", "right"]
+        );
+        let taken = InterleaveOptions {
+            allow_unequal: false,
+            source_key: Some(String::from("source")),
+        };
+        assert!(treatment.interleave_with(&control, &taken).is_err());
+    }
+
+    #[test]
+    fn interleave_unequal_test() {
+        let long = day(vec![1, 2, 3, 4], "a");
+        let short = day(vec![10], "b");
+        assert_eq!(
+            long.interleave(&short).unwrap_err().to_string(),
+            "cannot interleave 4 rows with 1 rows"
+        );
+        let opts = InterleaveOptions {
+            allow_unequal: true,
+            source_key: Some(String::from("origin")),
+        };
+        let padded = long.interleave_with(&short, &opts).unwrap();
+        assert_eq!(padded["t"].to_string(), "t: [1, 10, 2, 3, 4]");
+        let flipped = short.interleave_with(&long, &opts).unwrap();
+        assert_eq!(flipped["t"].to_string(), "t: [10, 1, 2, 3, 4]");
+        assert_eq!(
+            flipped["origin"].to_string(),
+            "origin: [left, right, right, right, right]"
+        );
+        let empty = day(vec![], "c");
+        assert_eq!(empty.interleave_with(&long, &opts).unwrap().shape(), (4, 4));
+    }
+
+    #[test]
+    fn interleave_rejects_test() {
+        let frame = day(vec![1, 2], "a");
+        let retyped = NodFrameBuilder::new()
+            .add_discrete("t", strings(&["x", "y"]))
+            .add_discrete("source", strings(&["b", "b"]))
+            .add_numeric("row", vec![0, 1])
+            .build()
+            .unwrap();
+        assert_eq!(
+            frame.interleave(&retyped).unwrap_err(),
+            NodFrameError::KindMismatch {
+                key: String::from("t"),
+                expected: ColKind::Numeric,
+                found: ColKind::Discrete,
+            }
+        );
+        let renamed = day(vec![3, 4], "b").rename("row", "index").unwrap();
+        assert_eq!(
+            frame.interleave(&renamed).unwrap_err(),
+            NodFrameError::MissingColumn(String::from("row"))
+        );
+        let wider = NodFrameBuilder::new()
+            .add_numeric("t", vec![3, 4])
+            .add_discrete("source", strings(&["b", "b"]))
+            .add_numeric("row", vec![0, 1])
+            .add_numeric("extra", vec![0, 1])
+            .build()
+            .unwrap();
+        assert_eq!(
+            frame.interleave(&wider).unwrap_err(),
+            NodFrameError::UnexpectedColumn(String::from("extra"))
+        );
+    }
+}
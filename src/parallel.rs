@@ -0,0 +1,420 @@
+// Parallel csv loading, column statistics and grouping, enabled with the
+// "rayon" feature.
+//
+// The file is read into memory and cut into chunks of whole records. Chunks
+// are parsed on the rayon pool and their columns joined in file order. Each
+// chunk infers its columns on its own and the join settles on numeric only if
+// every chunk parsed, so the result matches the serial loader.
+//
+// par_group_by hash partitions the rows into shards by key value. A key lands
+// in one shard only, so the shards are grouped independently and their groups
+// concatenated, then ordered like group_by orders them.
+use crate::group::aggregate;
+use crate::{
+    csv_layout, frame_from_csv_columns, keep_record, open_csv, push_record, read_csv, AggFn,
+    Column, ColumnStats, CsvColumn, CsvLayout, CsvOptions, GroupBy, NodFrame, NodFrameError,
+    NodNum, NumericColumn, RaggedPolicy, ValueOrder,
+};
+use csv::{ByteRecord, Position, ReaderBuilder};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Read;
+use std::path::Path;
+
+// frame_from_csv_parallel is frame_from_csv_with_options spread over the
+// rayon thread pool. skip_rows and max_rows need the rows in order, so with
+// either set, with column parsers or with a single thread, the file is read
+// serially
+pub fn frame_from_csv_parallel<T: NodNum + Send>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let threads = rayon::current_num_threads();
+    if opts.skip_rows > 0 || opts.max_rows.is_some() || !opts.parsers.is_empty() || threads == 1 {
+        return read_csv(file_path, opts, |header| Ok(vec![None; header.len()]));
+    }
+    let mut bytes = Vec::new();
+    open_csv(file_path, opts)?.read_to_end(&mut bytes)?;
+    read_chunks(&bytes, opts, threads)
+}
+
+// Chunk is a byte range holding whole records, lines is the number of
+// newlines in the file before it
+struct Chunk {
+    start: usize,
+    end: usize,
+    lines: u64,
+}
+
+enum ScanState {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+// chunk_bounds cuts bytes after the header at start into about pieces chunks.
+// It follows the quoting rules of the csv reader so a newline inside a quoted
+// field never ends a chunk
+fn chunk_bounds(bytes: &[u8], start: usize, pieces: usize) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    if start >= bytes.len() {
+        return chunks;
+    }
+    let step = ((bytes.len() - start) / pieces.max(1)).max(1);
+    let mut lines = bytes[..start].iter().filter(|b| **b == b'\n').count() as u64;
+    let mut chunk = Chunk {
+        start,
+        end: start,
+        lines,
+    };
+    let mut state = ScanState::FieldStart;
+    for (i, b) in bytes.iter().enumerate().skip(start) {
+        if *b == b'\n' {
+            lines += 1;
+        }
+        state = match (state, *b) {
+            (ScanState::Quoted, b'"') => ScanState::QuoteInQuoted,
+            (ScanState::Quoted, _) => ScanState::Quoted,
+            (ScanState::QuoteInQuoted, b'"') => ScanState::Quoted,
+            (ScanState::FieldStart, b'"') => ScanState::Quoted,
+            (_, b',') => ScanState::FieldStart,
+            (_, b'\n') => {
+                if i + 1 - chunk.start >= step && i + 1 < bytes.len() {
+                    chunk.end = i + 1;
+                    chunks.push(chunk);
+                    chunk = Chunk {
+                        start: i + 1,
+                        end: i + 1,
+                        lines,
+                    };
+                }
+                ScanState::FieldStart
+            }
+            _ => ScanState::Unquoted,
+        };
+    }
+    chunk.end = bytes.len();
+    chunks.push(chunk);
+    chunks
+}
+
+fn read_chunks<T: NodNum + Send>(
+    bytes: &[u8],
+    opts: &CsvOptions,
+    pieces: usize,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(bytes);
+    let layout = csv_layout(&mut reader, opts)?;
+    let start = reader.position().byte() as usize;
+    let parts: Vec<_> = chunk_bounds(bytes, start, pieces)
+        .par_iter()
+        .map(|chunk| read_chunk::<T>(bytes, chunk, &layout, opts.ragged))
+        .collect();
+    let mut columns: Vec<CsvColumn<T>> =
+        layout.header.iter().map(|_| CsvColumn::new(None)).collect();
+    // parts are in file order, so the first error is the earliest in the file
+    for part in parts {
+        let part = part.map_err(|e| e as Box<dyn Error>)?;
+        columns = columns
+            .into_iter()
+            .zip(part)
+            .map(|(column, more)| column.append(more))
+            .collect();
+    }
+    Ok(frame_from_csv_columns(&layout.header, columns)?)
+}
+
+fn read_chunk<T: NodNum>(
+    bytes: &[u8],
+    chunk: &Chunk,
+    layout: &CsvLayout,
+    ragged: RaggedPolicy,
+) -> Result<Vec<CsvColumn<T>>, Box<dyn Error + Send + Sync>> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(&bytes[chunk.start..chunk.end]);
+    let mut columns: Vec<CsvColumn<T>> =
+        layout.header.iter().map(|_| CsvColumn::new(None)).collect();
+    let mut record = ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        let position = record.position().cloned().unwrap_or_else(Position::new);
+        let line = chunk.lines + position.line();
+        if keep_record(&record, layout.width, ragged, line)? {
+            let byte = chunk.start as u64 + position.byte();
+            push_record(&mut columns, layout, &record, line, byte)?;
+        }
+    }
+    Ok(columns)
+}
+
+impl<T: num_traits::ToPrimitive + Sync> NumericColumn<T> {
+    // par_stats is stats computed over chunks on the rayon pool and merged
+    pub fn par_stats(&self) -> ColumnStats {
+        self.as_slice()
+            .par_chunks(64 * 1024)
+            .map(ColumnStats::from_values)
+            .reduce(ColumnStats::new, |a, b| a.merge(&b))
+    }
+}
+
+impl<T: NodNum + Send + Sync> NodFrame<T> {
+    // par_group_by is group_by spread over the rayon thread pool. The groups
+    // and their order are the same as group_by's
+    pub fn par_group_by(&self, key: &str) -> Result<GroupBy<'_, T>, NodFrameError> {
+        self.par_group_by_ordered(key, ValueOrder::Sorted)
+    }
+
+    pub fn par_group_by_ordered(
+        &self,
+        key: &str,
+        order: ValueOrder,
+    ) -> Result<GroupBy<'_, T>, NodFrameError> {
+        let threads = rayon::current_num_threads();
+        if threads == 1 {
+            return self.group_by_ordered(key, order);
+        }
+        self.group_by_with(key, order, |col| split_groups(col, threads * 4))
+    }
+}
+
+impl<T: NodNum + Send + Sync> GroupBy<'_, T> {
+    // par_agg is agg with the groups reduced on the rayon pool. Each group is
+    // reduced whole, as agg does, so sums and means round the same way
+    pub fn par_agg(&self, value_col: &str, agg: AggFn) -> Result<NodFrame<T>, NodFrameError> {
+        self.agg_with(value_col, agg, |values, groups| {
+            groups
+                .par_iter()
+                .map(|rows| aggregate(values.take(rows).as_slice(), agg))
+                .collect()
+        })
+    }
+}
+
+// with_key calls f with the key of row as cell_string writes it, numbers are
+// written into buf so no string is allocated per row
+fn with_key<T: NodNum, R>(
+    col: &Column<T>,
+    row: usize,
+    buf: &mut String,
+    f: impl FnOnce(&str) -> R,
+) -> R {
+    match col {
+        Column::Numeric(n) => {
+            buf.clear();
+            let _ = write!(buf, "{}", n.get(row));
+            f(buf)
+        }
+        Column::Discrete(d) => f(d.get(row)),
+    }
+}
+
+fn shard_of(key: &str, shards: usize) -> usize {
+    // DefaultHasher::new has fixed keys, a key maps to the same shard in
+    // every chunk
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+// split_groups groups the rows of col in order of first occurrence. Chunks of
+// rows are sorted into shards, then each shard is grouped with the rows of
+// every chunk in frame order
+fn split_groups<T: NodNum + Send + Sync>(col: &Column<T>, shards: usize) -> Vec<Vec<usize>> {
+    let rows = col.len();
+    let step = rows.div_ceil(shards).max(1);
+    let chunks: Vec<Vec<Vec<usize>>> = (0..rows.div_ceil(step))
+        .into_par_iter()
+        .map(|c| {
+            let mut out = vec![Vec::new(); shards];
+            let mut buf = String::new();
+            for row in c * step..rows.min((c + 1) * step) {
+                out[with_key(col, row, &mut buf, |k| shard_of(k, shards))].push(row);
+            }
+            out
+        })
+        .collect();
+    let mut groups: Vec<Vec<usize>> = (0..shards)
+        .into_par_iter()
+        .map(|s| {
+            let mut index: HashMap<String, usize> = HashMap::new();
+            let mut groups: Vec<Vec<usize>> = Vec::new();
+            let mut buf = String::new();
+            for row in chunks.iter().flat_map(|c| c[s].iter().copied()) {
+                let g = with_key(col, row, &mut buf, |k| match index.get(k) {
+                    Some(g) => *g,
+                    None => {
+                        index.insert(String::from(k), groups.len());
+                        groups.push(Vec::new());
+                        groups.len() - 1
+                    }
+                });
+                groups[g].push(row);
+            }
+            groups
+        })
+        .flatten()
+        .collect();
+    // no two groups share a first row
+    groups.par_sort_unstable_by_key(|g| g[0]);
+    groups
+}
+
+#[cfg(test)]
+mod parallel_tests {
+    use super::read_chunks;
+    use crate::test_support::temp_csv;
+    use crate::{
+        frame_from_csv, frame_from_csv_parallel, AggFn, CsvOptions, NodFrame, NodFrameBuilder,
+        NumericColumn, RaggedPolicy, ValueOrder,
+    };
+
+    // quoted_csv has quoted fields with commas, escaped quotes and newlines
+    fn quoted_csv(rows: usize) -> String {
+        let mut text = String::from("id,note,qty\n");
+        for r in 0..rows {
+            let note = match r % 4 {
+                0 => format!("\"line one\nline two {}\"", r),
+                1 => format!("\"a, \"\"quoted\"\" {}\"", r),
+                2 => format!("plain{}", r),
+                _ => String::from("\"\n\n\""),
+            };
+            text.push_str(&format!("{},{},{}\n", r, note, r % 9));
+        }
+        text
+    }
+
+    fn render(frame: &NodFrame<i64>) -> String {
+        format!("{}", frame)
+    }
+
+    #[test]
+    fn parallel_matches_serial_test() {
+        let text = quoted_csv(200);
+        let path = temp_csv("parallel.csv", &text);
+        let serial = frame_from_csv::<i64>(&path).unwrap();
+        for pieces in [1, 2, 3, 7, 64, 1000] {
+            let parallel: NodFrame<i64> =
+                read_chunks(text.as_bytes(), &CsvOptions::default(), pieces).unwrap();
+            assert_eq!(render(&parallel), render(&serial), "pieces {}", pieces);
+        }
+        let parallel = frame_from_csv_parallel::<i64>(&path, &CsvOptions::default()).unwrap();
+        assert_eq!(render(&parallel), render(&serial));
+    }
+
+    #[test]
+    fn parallel_inference_test() {
+        // qty only stops parsing in the last rows, which land in the last chunk
+        let mut text = quoted_csv(100);
+        text.push_str("100,x,n/a\n");
+        let parallel: NodFrame<i64> =
+            read_chunks(text.as_bytes(), &CsvOptions::default(), 8).unwrap();
+        let path = temp_csv("parallel_inference.csv", &text);
+        let serial = frame_from_csv::<i64>(&path).unwrap();
+        assert_eq!(render(&parallel), render(&serial));
+        assert!(parallel.discrete_column("qty").is_ok());
+    }
+
+    #[test]
+    fn parallel_ragged_test() {
+        let mut text = quoted_csv(50);
+        text.push_str("50,short\n");
+        text.push_str(&quoted_csv(50)[12..]);
+        let path = temp_csv("parallel_ragged.csv", &text);
+        let serial = frame_from_csv::<i64>(&path).unwrap_err().to_string();
+        let parallel = read_chunks::<i64>(text.as_bytes(), &CsvOptions::default(), 5)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(parallel, serial);
+
+        let opts = CsvOptions {
+            ragged: RaggedPolicy::SkipRow,
+            ..CsvOptions::default()
+        };
+        let parallel: NodFrame<i64> = read_chunks(text.as_bytes(), &opts, 5).unwrap();
+        assert_eq!(parallel.shape(), (100, 3));
+    }
+
+    #[test]
+    fn par_stats_test() {
+        let col = NumericColumn::new("v", (0..300_000_i64).map(|i| i % 977 - 400).collect());
+        let (serial, parallel) = (col.stats(), col.par_stats());
+        assert_eq!(parallel.count(), serial.count());
+        assert_eq!(parallel.sum(), serial.sum());
+        assert_eq!(parallel.min(), serial.min());
+        let variance = serial.variance().unwrap();
+        assert!((parallel.variance().unwrap() - variance).abs() / variance < 1e-9);
+    }
+
+    #[test]
+    fn par_group_by_test() {
+        // keys repeat out of order and include NaN, -0 and 0, values are
+        // floats whose sums depend on the order they are added in
+        let rows = 20_000;
+        let num_key: Vec<f64> = (0..rows)
+            .map(|r| match r % 97 {
+                0 => f64::NAN,
+                1 => -0.0,
+                2 => 0.0,
+                _ => ((r * 7919) % 1201) as f64 / 4.0,
+            })
+            .collect();
+        let text_key: Vec<String> = (0..rows).map(|r| format!("k{}", (r * 31) % 3001)).collect();
+        let frame = NodFrameBuilder::new()
+            .add_numeric("num", num_key)
+            .add_discrete("text", text_key)
+            .add_numeric(
+                "v",
+                (0..rows).map(|r| 0.1 * (r % 13) as f64 + 1e9).collect(),
+            )
+            .build()
+            .unwrap();
+        let aggs = [
+            AggFn::Sum,
+            AggFn::Mean,
+            AggFn::Min,
+            AggFn::Max,
+            AggFn::First,
+            AggFn::Last,
+            AggFn::Count,
+            AggFn::Std,
+        ];
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            for key in ["num", "text"] {
+                for order in [ValueOrder::Sorted, ValueOrder::FirstSeen] {
+                    let serial = frame.group_by_ordered(key, order).unwrap();
+                    let parallel = frame.par_group_by_ordered(key, order).unwrap();
+                    assert_eq!(parallel.groups(), serial.groups(), "{} {:?}", key, order);
+                    for agg in aggs {
+                        let (a, b) = (serial.agg("v", agg), parallel.par_agg("v", agg));
+                        assert_eq!(
+                            b.unwrap().to_string(),
+                            a.unwrap().to_string(),
+                            "{} {:?}",
+                            key,
+                            agg
+                        );
+                    }
+                }
+            }
+            // a deviation an integer frame would truncate fails in both
+            let ints: NodFrame<i64> = NodFrameBuilder::new()
+                .add_numeric("k", vec![3, 1, 3, 2, 1])
+                .add_numeric("v", vec![1, 2, 3, 4, 5])
+                .build()
+                .unwrap();
+            let serial = ints.group_by("k").unwrap().agg("v", AggFn::Std);
+            let parallel = ints.par_group_by("k").unwrap().par_agg("v", AggFn::Std);
+            assert_eq!(parallel.unwrap_err(), serial.unwrap_err());
+            assert!(frame.par_group_by("missing").is_err());
+        });
+    }
+}
@@ -0,0 +1,227 @@
+// Per-column parsers for csv loading, see CsvOptions::parse_column.
+//
+// A parser turns the text of one cell into T or explains why it cannot. The
+// column it is registered for becomes numeric whatever inference or a schema
+// would say, and a cell it rejects fails the load with the line, the column,
+// the cell and the parser's message. Parsers are stored without their value
+// type so CsvOptions stays free of T; reading checks that the type matches.
+use crate::{is_float, to_f64, CsvOptions, NodFrameError, NodNum};
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+// ParseFn is a registered parser
+pub(crate) type ParseFn<T> = dyn Fn(&str) -> Result<T, String> + Send + Sync;
+
+// ColumnParsers holds the parsers of CsvOptions by column key
+#[derive(Clone, Default)]
+pub struct ColumnParsers {
+    parsers: Vec<(String, Arc<dyn Any + Send + Sync>)>,
+}
+
+// ColumnParsers is debugged as the keys with a parser
+impl fmt::Debug for ColumnParsers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.keys()).finish()
+    }
+}
+
+impl ColumnParsers {
+    pub fn is_empty(&self) -> bool {
+        self.parsers.is_empty()
+    }
+
+    // keys lists the columns with a parser in the order they were registered
+    pub fn keys(&self) -> Vec<&str> {
+        self.parsers.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    // get returns the parser of key, failing when it was registered for
+    // another value type than T
+    pub(crate) fn get<T: NodNum>(
+        &self,
+        key: &str,
+    ) -> Result<Option<Arc<ParseFn<T>>>, NodFrameError> {
+        match self.parsers.iter().find(|(k, _)| k == key) {
+            None => Ok(None),
+            Some((_, parser)) => parser
+                .downcast_ref::<Arc<ParseFn<T>>>()
+                .map(|p| Some(p.clone()))
+                .ok_or_else(|| {
+                    NodFrameError::InvalidArgument(format!(
+                        "the parser of column '{}' returns another value type than the frame",
+                        key
+                    ))
+                }),
+        }
+    }
+}
+
+impl CsvOptions {
+    // parse_column registers parser for the column key, replacing an earlier
+    // parser of the same column, e.g.
+    // CsvOptions::default().parse_column("price", Parsers::currency::<f64>())
+    pub fn parse_column<T: NodNum>(
+        mut self,
+        key: &str,
+        parser: impl Fn(&str) -> Result<T, String> + Send + Sync + 'static,
+    ) -> CsvOptions {
+        let parser: Arc<ParseFn<T>> = Arc::new(parser);
+        self.parsers.parsers.retain(|(k, _)| k != key);
+        self.parsers
+            .parsers
+            .push((String::from(key), Arc::new(parser)));
+        self
+    }
+}
+
+// Parsers builds parsers for common formats to pass to parse_column
+pub struct Parsers;
+
+impl Parsers {
+    // currency reads amounts like "$1,234.56", "-€5" or "(12.50)": currency
+    // symbols and thousands separators are dropped and parentheses mean a
+    // negative amount. The number left must parse as T, so integer frames
+    // reject amounts with cents
+    pub fn currency<T: NodNum>() -> impl Fn(&str) -> Result<T, String> + Send + Sync + 'static {
+        |cell: &str| {
+            let text = cell.trim();
+            let (negative, text) = match text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+                Some(inner) => (true, inner),
+                None => match text.strip_prefix('-') {
+                    Some(rest) => (true, rest),
+                    None => (false, text),
+                },
+            };
+            let digits: String = text
+                .chars()
+                .filter(|c| !matches!(c, '$' | '€' | '£' | '¥' | ',') && !c.is_whitespace())
+                .collect();
+            let signed = if negative {
+                format!("-{}", digits)
+            } else {
+                digits
+            };
+            match signed.parse::<T>() {
+                Ok(v) if !signed.is_empty() => Ok(v),
+                _ => Err(format!("'{}' is not a currency amount", cell)),
+            }
+        }
+    }
+
+    // percent reads "12%" or "12.5 %" as the fraction 0.12 or 0.125, the sign
+    // is optional. Integer frames only take whole fractions like "200%"
+    pub fn percent<T: NodNum>() -> impl Fn(&str) -> Result<T, String> + Send + Sync + 'static {
+        |cell: &str| {
+            let text = cell.trim();
+            let number = text.strip_suffix('%').unwrap_or(text).trim_end();
+            let fraction = number
+                .parse::<f64>()
+                .map_err(|_| format!("'{}' is not a percentage", cell))?
+                / 100.0;
+            T::from(fraction)
+                .filter(|v| is_float::<T>() || to_f64(v) == fraction)
+                .ok_or_else(|| format!("{} does not fit the value type", fraction))
+        }
+    }
+
+    // yes_no reads y, yes, true and 1 as one and n, no, false and 0 as zero,
+    // ignoring case
+    pub fn yes_no<T: NodNum>() -> impl Fn(&str) -> Result<T, String> + Send + Sync + 'static {
+        |cell: &str| match cell.trim().to_lowercase().as_str() {
+            "y" | "yes" | "true" | "1" => Ok(T::one()),
+            "n" | "no" | "false" | "0" => Ok(T::zero()),
+            _ => Err(format!("'{}' is not yes or no", cell)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_tests {
+    use crate::test_support::temp_path;
+    use crate::{frame_from_csv_with_options, CsvOptions, NodFrame, NodFrameError, Parsers};
+
+    #[test]
+    fn parsers_test() {
+        let currency = Parsers::currency::<f64>();
+        assert_eq!(currency("$1,234.56"), Ok(1234.56));
+        assert_eq!(currency(" -€5 "), Ok(-5.0));
+        assert_eq!(currency("(12.50)"), Ok(-12.5));
+        assert!(currency("$").is_err());
+        assert!(currency("twelve").is_err());
+        assert!(Parsers::currency::<i64>()("$1,234.56").is_err());
+        assert_eq!(Parsers::currency::<i64>()("$1,234"), Ok(1234));
+
+        let percent = Parsers::percent::<f64>();
+        assert_eq!(percent("12%"), Ok(0.12));
+        assert_eq!(percent("-12.5 %"), Ok(-0.125));
+        assert!(percent("%").is_err());
+        assert_eq!(Parsers::percent::<i64>()("200%"), Ok(2));
+        assert!(Parsers::percent::<i64>()("12%").is_err());
+
+        let yes_no = Parsers::yes_no::<u8>();
+        assert_eq!(yes_no("Y"), Ok(1));
+        assert_eq!(yes_no("no"), Ok(0));
+        assert!(yes_no("maybe").is_err());
+    }
+
+    #[test]
+    fn parse_column_test() {
+        let path = temp_path("orders.csv");
+        std::fs::write(
+            &path,
+            "id,price,discount,paid,qty\n1,\"$1,234.50\",10%,Y,2\n2,$8.00,0%,N,x\n",
+        )
+        .unwrap();
+        let opts = CsvOptions::default()
+            .parse_column("price", Parsers::currency::<f64>())
+            .parse_column("discount", Parsers::percent::<f64>())
+            .parse_column("paid", Parsers::yes_no::<f64>());
+        assert_eq!(
+            format!("{:?}", opts.parsers),
+            r#"["price", "discount", "paid"]"#
+        );
+        let frame: NodFrame<f64> = frame_from_csv_with_options(&path, &opts).unwrap();
+        assert_eq!(
+            frame.numeric_column("price").unwrap().as_slice(),
+            &[1234.5, 8.0]
+        );
+        assert_eq!(
+            frame.numeric_column("discount").unwrap().as_slice(),
+            &[0.1, 0.0]
+        );
+        assert_eq!(
+            frame.numeric_column("paid").unwrap().as_slice(),
+            &[1.0, 0.0]
+        );
+        // columns without a parser are still inferred
+        assert_eq!(frame.numeric_column("id").unwrap().as_slice(), &[1.0, 2.0]);
+        assert!(frame.discrete_column("qty").is_ok());
+
+        let custom = CsvOptions::default().parse_column("qty", |s: &str| {
+            s.parse::<f64>()
+                .map_err(|_| String::from("expected a count"))
+        });
+        let err = frame_from_csv_with_options::<f64>(&path, &custom).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            NodFrameError::ParserFailed {
+                key: String::from("qty"),
+                line: 3,
+                value: String::from("x"),
+                message: String::from("expected a count"),
+            }
+            .to_string()
+        );
+        assert_eq!(
+            err.to_string(),
+            "line 3: value 'x' in column 'qty' failed to parse: expected a count"
+        );
+
+        // the parser's value type has to be the frame's
+        assert!(frame_from_csv_with_options::<i64>(&path, &opts).is_err());
+        let unknown = CsvOptions::default().parse_column("cost", Parsers::currency::<f64>());
+        assert!(frame_from_csv_with_options::<f64>(&path, &unknown).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
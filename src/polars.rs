@@ -0,0 +1,133 @@
+// Polars support, enabled with the "polars" feature.
+//
+// Numeric columns map to the polars ChunkedArray of the frame's value type and
+// discrete columns to String columns, keeping names and column order both
+// ways. nodframe has no missing values yet, so polars columns holding nulls are
+// reported as errors, as are dtypes other than primitive numbers and strings.
+use crate::{Column, NodFrame, NodFrameBuilder, NodFrameError, NodNum};
+use ::polars::prelude::{
+    ChunkedArray, DataFrame, IntoSeries, NamedFrom, NumericNative, PolarsDataType,
+    PolarsNumericType, Series,
+};
+use std::error::Error;
+
+// frame_from_polars converts df into a frame. Numeric columns of another
+// width than T are converted with polars' strict cast, which fails on values
+// that overflow T
+pub fn frame_from_polars<T: NodNum + NumericNative>(
+    df: &DataFrame,
+) -> Result<NodFrame<T>, Box<dyn Error>>
+where
+    T::PolarsType: PolarsNumericType<Native = T>,
+{
+    let mut builder = NodFrameBuilder::new();
+    for column in df.get_columns() {
+        let key = column.name().as_str();
+        let series = column.as_materialized_series();
+        if let Some(row) = series.is_null().into_iter().position(|n| n == Some(true)) {
+            return Err(Box::new(NodFrameError::InvalidValue {
+                key: key.to_string(),
+                row,
+                message: String::from("null values are not supported"),
+            }));
+        }
+        let dtype = series.dtype();
+        builder = if dtype.is_string() {
+            let values = series
+                .str()?
+                .into_no_null_iter()
+                .map(String::from)
+                .collect();
+            builder.add_discrete(key, values)
+        } else if dtype.is_primitive_numeric() {
+            let cast = series.strict_cast(&T::PolarsType::get_static_dtype())?;
+            let values = cast
+                .unpack::<T::PolarsType>()?
+                .into_no_null_iter()
+                .collect();
+            builder.add_numeric(key, values)
+        } else {
+            return Err(Box::new(NodFrameError::UnsupportedType {
+                key: key.to_string(),
+                dtype: dtype.to_string(),
+            }));
+        };
+    }
+    Ok(builder.build()?)
+}
+
+impl<T: NodNum + NumericNative> NodFrame<T>
+where
+    T::PolarsType: PolarsNumericType<Native = T>,
+{
+    // to_polars copies the frame into a polars DataFrame
+    pub fn to_polars(&self) -> Result<DataFrame, Box<dyn Error>> {
+        let columns = self
+            .columns()
+            .iter()
+            .map(|col| match col {
+                Column::Numeric(n) => {
+                    ChunkedArray::<T::PolarsType>::from_vec(n.key().into(), n.as_slice().to_vec())
+                        .into_series()
+                        .into()
+                }
+                Column::Discrete(d) => {
+                    Series::new(d.key().into(), d.iter().collect::<Vec<_>>()).into()
+                }
+            })
+            .collect();
+        Ok(DataFrame::new(columns)?)
+    }
+}
+
+#[cfg(test)]
+mod polars_tests {
+    use crate::{frame_from_polars, NodFrame, NodFrameBuilder};
+    use ::polars::prelude::{DataFrame, NamedFrom, Series};
+
+    #[test]
+    fn round_trip_test() {
+        let frame = NodFrameBuilder::new()
+            .add_discrete("city", vec![String::from("Oslo"), String::from("Lima")])
+            .add_numeric("pop", vec![700_000_i64, 10_000_000])
+            .add_numeric("rank", vec![2, 1])
+            .build()
+            .unwrap();
+        let df = frame.to_polars().unwrap();
+        let names: Vec<&str> = df.get_column_names().iter().map(|n| n.as_str()).collect();
+        assert_eq!(names, vec!["city", "pop", "rank"]);
+        assert_eq!(df.column("pop").unwrap().dtype().to_string(), "i64");
+        let back: NodFrame<i64> = frame_from_polars(&df).unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
+
+        // narrower integer columns are cast to the frame's type
+        let df = DataFrame::new(vec![Series::new("n".into(), &[1_i32, 2]).into()]).unwrap();
+        let back: NodFrame<i64> = frame_from_polars(&df).unwrap();
+        assert_eq!(back["n"].to_string(), "n: [1, 2]");
+    }
+
+    #[test]
+    fn unsupported_test() {
+        let list = Series::new(
+            "tags".into(),
+            &[
+                Series::new("".into(), &[1_i64, 2]),
+                Series::new("".into(), &[3_i64]),
+            ],
+        );
+        let df = DataFrame::new(vec![list.into()]).unwrap();
+        let err = frame_from_polars::<i64>(&df).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column 'tags' has unsupported type list[i64]"
+        );
+
+        let nulls = Series::new("v".into(), &[Some(1_i64), None]);
+        let df = DataFrame::new(vec![nulls.into()]).unwrap();
+        let err = frame_from_polars::<i64>(&df).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column 'v' row 1: null values are not supported"
+        );
+    }
+}
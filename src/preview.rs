@@ -0,0 +1,199 @@
+// Quick looks at a single column: head, seeded sample and a short text
+// summary, see NumericColumn::preview.
+//
+// head and sample borrow the values they return instead of copying the
+// column. Samples come from a small splitmix64 generator, so the same seed
+// picks the same rows on every platform and run.
+use crate::sketch::EXACT_BELOW;
+use crate::{Column, DiscreteColumn, HyperLogLog, NodNum, NumericColumn};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+// PREVIEW_ROWS is the number of leading values preview shows
+const PREVIEW_ROWS: usize = 5;
+
+// SplitMix64 is the generator behind sample, see the module comment
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // below returns a value in 0..bound, bound must not be zero
+    pub(crate) fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+// sample_rows picks min(n, len) distinct rows of 0..len with Floyd's
+// algorithm, returned in row order
+pub(crate) fn sample_rows(len: usize, n: usize, seed: u64) -> Vec<usize> {
+    let mut rng = SplitMix64::new(seed);
+    let mut picked = HashSet::new();
+    for j in len - n.min(len)..len {
+        let t = rng.below(j + 1);
+        if !picked.insert(t) {
+            picked.insert(j);
+        }
+    }
+    let mut rows: Vec<usize> = picked.into_iter().collect();
+    rows.sort_unstable();
+    rows
+}
+
+// distinct_line counts the distinct values exactly for short columns and
+// estimates them with HyperLogLog otherwise, marking the estimate with "~".
+// values are read one at a time, the exact count keeps only distinct ones
+fn distinct_line<S: AsRef<str> + Eq + Hash>(len: usize, values: impl Iterator<Item = S>) -> String {
+    if len < EXACT_BELOW {
+        format!("distinct: {}", values.collect::<HashSet<S>>().len())
+    } else {
+        let mut sketch = HyperLogLog::new();
+        values.for_each(|v| sketch.add(v.as_ref()));
+        format!("distinct: ~{}", sketch.estimate())
+    }
+}
+
+// head_line lists the leading values, with "..." when there are more
+fn head_line(shown: Vec<String>, len: usize) -> String {
+    let more = if len > shown.len() { ", ..." } else { "" };
+    format!("head: {}{}", shown.join(", "), more)
+}
+
+impl<T: NodNum> NumericColumn<T> {
+    // head borrows the first n values, all of them when n is past the end
+    pub fn head(&self, n: usize) -> Vec<&T> {
+        self.as_slice().iter().take(n).collect()
+    }
+
+    // sample borrows n values at rows picked by seed, in row order. The same
+    // seed always picks the same rows, and n past the length gives every row
+    pub fn sample(&self, n: usize, seed: u64) -> Vec<&T> {
+        sample_rows(self.len(), n, seed)
+            .into_iter()
+            .map(|row| self.get(row))
+            .collect()
+    }
+
+    // preview summarizes the column on a few lines: key, kind and length,
+    // the leading values, the distinct count and the min and max, e.g.
+    // qty (numeric, 6 rows)
+    // head: 5, 1, 8, 3, 9, ...
+    // distinct: 5
+    // min: 1
+    // max: 9
+    pub fn preview(&self) -> String {
+        let mut out = format!("{} (numeric, {} rows)\n", self.key(), self.len());
+        let shown = self
+            .head(PREVIEW_ROWS)
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        out.push_str(&head_line(shown, self.len()));
+        out.push_str(&format!(
+            "\n{}",
+            distinct_line(self.len(), self.iter().map(|x| x.to_string()))
+        ));
+        let show = |x: Option<T>| x.map_or(String::from("-"), |x| x.to_string());
+        out.push_str(&format!(
+            "\nmin: {}\nmax: {}",
+            show(self.min()),
+            show(self.max())
+        ));
+        out
+    }
+}
+
+impl DiscreteColumn {
+    pub fn head(&self, n: usize) -> Vec<&str> {
+        self.iter().take(n).collect()
+    }
+
+    // sample is NumericColumn::sample for discrete values
+    pub fn sample(&self, n: usize, seed: u64) -> Vec<&str> {
+        sample_rows(self.len(), n, seed)
+            .into_iter()
+            .map(|row| self.get(row))
+            .collect()
+    }
+
+    // preview is NumericColumn::preview without the min and max, values are
+    // quoted so empty strings show
+    pub fn preview(&self) -> String {
+        let mut out = format!("{} (discrete, {} rows)\n", self.key(), self.len());
+        let shown = self
+            .head(PREVIEW_ROWS)
+            .iter()
+            .map(|s| format!("{:?}", s))
+            .collect();
+        out.push_str(&head_line(shown, self.len()));
+        out.push_str(&format!("\n{}", distinct_line(self.len(), self.iter())));
+        out
+    }
+}
+
+impl<T: NodNum> Column<T> {
+    // preview is the preview of the numeric or discrete column
+    pub fn preview(&self) -> String {
+        match self {
+            Column::Numeric(n) => n.preview(),
+            Column::Discrete(d) => d.preview(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod preview_tests {
+    use crate::test_support::strings;
+    use crate::{DiscreteColumn, NumericColumn};
+
+    #[test]
+    fn head_sample_test() {
+        let col = NumericColumn::new("qty", vec![5, 1, 8, 3, 9, 1]);
+        assert_eq!(col.head(2), vec![&5, &1]);
+        assert_eq!(col.head(10).len(), 6);
+        assert!(col.head(0).is_empty());
+
+        let a = col.sample(3, 7);
+        assert_eq!(a.len(), 3);
+        assert_eq!(a, col.sample(3, 7));
+        assert_eq!(col.sample(10, 7).len(), 6);
+        // different seeds pick different rows sooner or later
+        assert!((0..20).any(|seed| col.sample(3, seed) != a));
+
+        let names = DiscreteColumn::new("name", strings(&["ann", "", "bob"]));
+        assert_eq!(names.head(5).len(), 3);
+        assert_eq!(names.sample(2, 1), names.sample(2, 1));
+        assert!(names.sample(0, 1).is_empty());
+    }
+
+    #[test]
+    fn preview_test() {
+        let col = NumericColumn::new("qty", vec![5.0, 1.0, f64::NAN, 3.0, 9.0, 1.0]);
+        assert_eq!(
+            col.preview(),
+            "qty (numeric, 6 rows)\nhead: 5, 1, NaN, 3, 9, ...\ndistinct: 5\nmin: 1\nmax: 9"
+        );
+        let empty = NumericColumn::<i64>::new("n", Vec::new());
+        assert_eq!(
+            empty.preview(),
+            "n (numeric, 0 rows)\nhead: \ndistinct: 0\nmin: -\nmax: -"
+        );
+        let names = DiscreteColumn::new("name", strings(&["ann", "", "ann"]));
+        assert_eq!(
+            names.preview(),
+            "name (discrete, 3 rows)\nhead: \"ann\", \"\", \"ann\"\ndistinct: 2"
+        );
+    }
+}
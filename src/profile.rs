@@ -0,0 +1,432 @@
+// Frame profiles for spotting schema and distribution drift, see
+// NodFrame::profile and FrameProfile::compare.
+//
+// A profile is a small summary of every column, cheap to store next to the
+// data it came from. Comparing today's profile with a stored baseline flags
+// columns that appeared, disappeared or changed kind, and numeric columns
+// whose mean or distinct count moved by more than a fraction of the baseline.
+// Distinct counts of long columns are HyperLogLog estimates, see sketch.
+use crate::sketch::EXACT_BELOW;
+use crate::{is_nan, ColKind, Column, HyperLogLog, NodFrame, NodNum};
+use std::collections::HashSet;
+use std::fmt;
+
+// TOP_VALUES is the number of most frequent values kept for discrete columns
+const TOP_VALUES: usize = 3;
+
+// ColumnProfile summarizes one column. missing counts empty strings and NaN,
+// min, max and mean skip NaN and are None for discrete columns and numeric
+// columns without a value. top holds the three most frequent discrete values
+// with their counts, highest first
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnProfile {
+    key: String,
+    kind: ColKind,
+    rows: usize,
+    missing: usize,
+    distinct: usize,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: Option<f64>,
+    top: Vec<(String, usize)>,
+}
+
+impl ColumnProfile {
+    fn new<T: NodNum>(column: &Column<T>) -> ColumnProfile {
+        match column {
+            Column::Numeric(n) => {
+                let stats = n.stats();
+                ColumnProfile {
+                    key: String::from(n.key()),
+                    kind: ColKind::Numeric,
+                    rows: n.len(),
+                    missing: n.iter().filter(|x| is_nan(*x)).count(),
+                    distinct: approx_distinct(n.len(), n.iter().map(|x| x.to_string())),
+                    min: stats.min(),
+                    max: stats.max(),
+                    mean: stats.mean(),
+                    top: Vec::new(),
+                }
+            }
+            Column::Discrete(d) => ColumnProfile {
+                key: String::from(d.key()),
+                kind: ColKind::Discrete,
+                rows: d.len(),
+                missing: d.iter().filter(|s| s.is_empty()).count(),
+                distinct: d.approx_nunique(),
+                min: None,
+                max: None,
+                mean: None,
+                top: d.approx_top_k(TOP_VALUES),
+            },
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn kind(&self) -> ColKind {
+        self.kind
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn missing(&self) -> usize {
+        self.missing
+    }
+
+    pub fn distinct(&self) -> usize {
+        self.distinct
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        self.mean
+    }
+
+    pub fn top(&self) -> &[(String, usize)] {
+        &self.top
+    }
+}
+
+// approx_distinct counts distinct numbers by their text form, as
+// NumericColumn::unique does, estimating for long columns
+fn approx_distinct(len: usize, values: impl Iterator<Item = String>) -> usize {
+    if len < EXACT_BELOW {
+        return values.collect::<HashSet<String>>().len();
+    }
+    let mut sketch = HyperLogLog::new();
+    values.for_each(|v| sketch.add(&v));
+    sketch.estimate()
+}
+
+// FrameProfile is the outcome of NodFrame::profile, columns keep the order of
+// the frame
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameProfile {
+    rows: usize,
+    columns: Vec<ColumnProfile>,
+}
+
+impl FrameProfile {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> &[ColumnProfile] {
+        &self.columns
+    }
+
+    pub fn column(&self, key: &str) -> Option<&ColumnProfile> {
+        self.columns.iter().find(|c| c.key == key)
+    }
+
+    // compare lists how this profile drifted from baseline: first the removed
+    // columns, then in the order of this profile the added and retyped ones
+    // and numeric columns whose mean or distinct count moved by more than the
+    // thresholds allow
+    pub fn compare(&self, baseline: &FrameProfile, thresholds: DriftThresholds) -> DriftReport {
+        let mut drifts = Vec::new();
+        for old in baseline.columns.iter() {
+            if self.column(&old.key).is_none() {
+                drifts.push(Drift::Removed(old.key.clone()));
+            }
+        }
+        for new in self.columns.iter() {
+            let old = match baseline.column(&new.key) {
+                Some(old) => old,
+                None => {
+                    drifts.push(Drift::Added(new.key.clone()));
+                    continue;
+                }
+            };
+            if old.kind != new.kind {
+                drifts.push(Drift::Retyped {
+                    key: new.key.clone(),
+                    baseline: old.kind,
+                    current: new.kind,
+                });
+                continue;
+            }
+            if new.kind == ColKind::Discrete {
+                continue;
+            }
+            if let (Some(before), Some(after)) = (old.mean, new.mean) {
+                if shifted(before, after, thresholds.mean) {
+                    drifts.push(Drift::MeanShift {
+                        key: new.key.clone(),
+                        baseline: before,
+                        current: after,
+                    });
+                }
+            }
+            if shifted(
+                old.distinct as f64,
+                new.distinct as f64,
+                thresholds.distinct,
+            ) {
+                drifts.push(Drift::DistinctShift {
+                    key: new.key.clone(),
+                    baseline: old.distinct,
+                    current: new.distinct,
+                });
+            }
+        }
+        DriftReport { drifts }
+    }
+}
+
+// shifted is whether after moved from before by more than fraction of before,
+// any move away from a zero baseline counts
+fn shifted(before: f64, after: f64, fraction: f64) -> bool {
+    let change = (after - before).abs();
+    if before == 0.0 {
+        change > 0.0
+    } else {
+        change / before.abs() > fraction
+    }
+}
+
+// DriftThresholds are the relative changes FrameProfile::compare tolerates:
+// 0.1 lets a mean or distinct count move by up to 10% of its baseline value
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DriftThresholds {
+    pub mean: f64,
+    pub distinct: f64,
+}
+
+impl Default for DriftThresholds {
+    fn default() -> Self {
+        DriftThresholds {
+            mean: 0.1,
+            distinct: 0.1,
+        }
+    }
+}
+
+// Drift is one difference FrameProfile::compare found
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Drift {
+    Added(String),
+    Removed(String),
+    Retyped {
+        key: String,
+        baseline: ColKind,
+        current: ColKind,
+    },
+    MeanShift {
+        key: String,
+        baseline: f64,
+        current: f64,
+    },
+    DistinctShift {
+        key: String,
+        baseline: usize,
+        current: usize,
+    },
+}
+
+impl Drift {
+    // key is the column the drift is about
+    pub fn key(&self) -> &str {
+        match self {
+            Drift::Added(key) | Drift::Removed(key) => key,
+            Drift::Retyped { key, .. }
+            | Drift::MeanShift { key, .. }
+            | Drift::DistinctShift { key, .. } => key,
+        }
+    }
+}
+
+// Drift is displayed as "price: mean 10 -> 15"
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::Added(key) => write!(f, "{}: added", key),
+            Drift::Removed(key) => write!(f, "{}: removed", key),
+            Drift::Retyped {
+                key,
+                baseline,
+                current,
+            } => write!(f, "{}: {} -> {}", key, baseline, current),
+            Drift::MeanShift {
+                key,
+                baseline,
+                current,
+            } => write!(f, "{}: mean {} -> {}", key, baseline, current),
+            Drift::DistinctShift {
+                key,
+                baseline,
+                current,
+            } => write!(f, "{}: distinct {} -> {}", key, baseline, current),
+        }
+    }
+}
+
+// DriftReport is the outcome of FrameProfile::compare
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DriftReport {
+    drifts: Vec<Drift>,
+}
+
+impl DriftReport {
+    pub fn drifts(&self) -> &[Drift] {
+        &self.drifts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.drifts.is_empty()
+    }
+}
+
+// DriftReport is displayed as one line per drift
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no drift");
+        }
+        let lines: Vec<String> = self.drifts.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // profile summarizes every column for comparing against a later load,
+    // see FrameProfile::compare
+    pub fn profile(&self) -> FrameProfile {
+        FrameProfile {
+            rows: self.num_rows,
+            columns: self.columns.iter().map(ColumnProfile::new).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use crate::test_support::strings;
+    use crate::{ColKind, Drift, DriftThresholds, NodFrame, NodFrameBuilder};
+
+    fn orders(prices: Vec<f64>) -> NodFrame<f64> {
+        NodFrameBuilder::new()
+            .add_discrete("status", strings(&["open", "done", "open", "", "open"]))
+            .add_numeric("price", prices)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn profile_test() {
+        let profile = orders(vec![2.0, 4.0, 4.0, f64::NAN, 10.0]).profile();
+        assert_eq!(profile.rows(), 5);
+        let status = profile.column("status").unwrap();
+        assert_eq!(status.kind(), ColKind::Discrete);
+        assert_eq!((status.missing(), status.distinct()), (1, 3));
+        assert_eq!(
+            status.top(),
+            &[
+                (String::from("open"), 3),
+                (String::from(""), 1),
+                (String::from("done"), 1)
+            ]
+        );
+        assert_eq!(status.mean(), None);
+        let price = &profile.columns()[1];
+        assert_eq!((price.rows(), price.missing(), price.distinct()), (5, 1, 4));
+        assert_eq!((price.min(), price.max()), (Some(2.0), Some(10.0)));
+        assert_eq!(price.mean(), Some(5.0));
+        assert!(price.top().is_empty());
+    }
+
+    #[test]
+    fn compare_test() {
+        let baseline = orders(vec![2.0, 4.0, 6.0, 8.0, 10.0]).profile();
+        let same = orders(vec![2.0, 4.0, 6.0, 8.0, 10.0]).profile();
+        let report = same.compare(&baseline, DriftThresholds::default());
+        assert!(report.is_empty());
+        assert_eq!(report.to_string(), "no drift");
+
+        // the mean moves from 6 to 9, half the baseline
+        let shifted = orders(vec![5.0, 7.0, 9.0, 11.0, 13.0]).profile();
+        let report = shifted.compare(&baseline, DriftThresholds::default());
+        assert_eq!(
+            report.drifts(),
+            &[Drift::MeanShift {
+                key: String::from("price"),
+                baseline: 6.0,
+                current: 9.0
+            }]
+        );
+        let loose = DriftThresholds {
+            mean: 0.5,
+            distinct: 0.1,
+        };
+        assert!(shifted.compare(&baseline, loose).is_empty());
+
+        let retyped: NodFrame<f64> = NodFrameBuilder::new()
+            .add_discrete("price", strings(&["2", "4", "6", "8", "10"]))
+            .add_numeric("qty", vec![1.0, 1.0, 2.0, 2.0, 3.0])
+            .build()
+            .unwrap();
+        let report = retyped
+            .profile()
+            .compare(&baseline, DriftThresholds::default());
+        assert_eq!(
+            report.drifts(),
+            &[
+                Drift::Removed(String::from("status")),
+                Drift::Retyped {
+                    key: String::from("price"),
+                    baseline: ColKind::Numeric,
+                    current: ColKind::Discrete
+                },
+                Drift::Added(String::from("qty"))
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "status: removed\nprice: numeric -> discrete\nqty: added"
+        );
+    }
+
+    #[test]
+    fn distinct_threshold_test() {
+        let baseline = orders(vec![1.0, 2.0, 3.0, 4.0, 5.0]).profile();
+        // the same mean from three distinct values instead of five
+        let narrow = orders(vec![2.0, 4.0, 2.0, 4.0, 3.0]).profile();
+        let report = narrow.compare(&baseline, DriftThresholds::default());
+        assert_eq!(report.drifts()[0].key(), "price");
+        assert_eq!(report.to_string(), "price: distinct 5 -> 3");
+        let loose = DriftThresholds {
+            mean: 0.1,
+            distinct: 0.5,
+        };
+        assert!(narrow.compare(&baseline, loose).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn profile_serialize_test() {
+        let profile = orders(vec![2.0, 4.0, 6.0, 8.0, 10.0]).profile();
+        let json = serde_json::to_string(&profile).unwrap();
+        let back: crate::FrameProfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, profile);
+        assert!(profile
+            .compare(&back, DriftThresholds::default())
+            .is_empty());
+    }
+}
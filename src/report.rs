@@ -0,0 +1,152 @@
+// Multi-section csv reports, several frames in one file.
+//
+// Each section is a title line holding one csv field, then the frame as
+// to_csv writes it, then a blank line. Frames in different sections may have
+// any columns. read_csv_report splits the file on blank lines outside quoted
+// fields, so a quoted value spanning lines does not end its section, and
+// infers each frame like frame_from_csv.
+use crate::{read_csv_from, CsvOptions, CsvWriteOptions, NodFrame, NodFrameError, NodNum};
+use csv::{ReaderBuilder, StringRecord, Writer};
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+// ReportSection is a section title and its frame
+pub type ReportSection<T> = (String, NodFrame<T>);
+
+// CsvReportWriter appends titled frames to sink, see the module comment
+pub struct CsvReportWriter<W: Write> {
+    sink: W,
+}
+
+impl<W: Write> CsvReportWriter<W> {
+    pub fn new(sink: W) -> CsvReportWriter<W> {
+        CsvReportWriter { sink }
+    }
+
+    // add_section writes title, the frame and the blank line ending the
+    // section. A frame without columns has no header line to write and is
+    // rejected
+    pub fn add_section<T: NodNum>(
+        &mut self,
+        title: &str,
+        frame: &NodFrame<T>,
+    ) -> Result<(), Box<dyn Error>> {
+        if frame.columns().is_empty() {
+            return Err(Box::new(NodFrameError::InvalidArgument(format!(
+                "section '{}' has no columns",
+                title
+            ))));
+        }
+        let mut writer = Writer::from_writer(&mut self.sink);
+        writer.write_record([title])?;
+        writer.flush()?;
+        drop(writer);
+        frame.write_csv(&mut self.sink, &CsvWriteOptions::default(), |_| {})?;
+        self.sink.write_all(b"\n")?;
+        Ok(())
+    }
+
+    // into_inner flushes and returns the sink
+    pub fn into_inner(mut self) -> Result<W, Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+}
+
+// read_csv_report reads every section of a file written by CsvReportWriter,
+// in file order, as (title, frame) pairs
+pub fn read_csv_report<T: NodNum>(
+    file_path: impl AsRef<Path>,
+) -> Result<Vec<ReportSection<T>>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(file_path)?;
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut body: Option<(String, String)> = None;
+    let mut record = String::new();
+    for line in text.split_inclusive('\n') {
+        record.push_str(line);
+        // a record with an odd number of quotes continues on the next line
+        if record.matches('"').count() % 2 == 1 {
+            continue;
+        }
+        let blank = record.trim_end_matches(['\r', '\n']).is_empty();
+        match body.as_mut() {
+            None if blank => {}
+            None => body = Some((title_of(&record)?, String::new())),
+            Some(_) if blank => sections.extend(body.take()),
+            Some((_, lines)) => lines.push_str(&record),
+        }
+        record.clear();
+    }
+    sections.extend(body);
+    sections
+        .into_iter()
+        .map(|(title, lines)| {
+            let frame = read_csv_from(lines.as_bytes(), &CsvOptions::default(), |header| {
+                Ok(vec![None; header.len()])
+            })?;
+            Ok((title, frame))
+        })
+        .collect()
+}
+
+// title_of parses a title line, which holds a single csv field
+fn title_of(line: &str) -> Result<String, Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+    let mut record = StringRecord::new();
+    reader.read_record(&mut record)?;
+    Ok(record.get(0).unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod report_tests {
+    use crate::test_support::{strings, temp_path};
+    use crate::{read_csv_report, CsvReportWriter, NodFrame, NodFrameBuilder};
+    use std::fs::File;
+
+    #[test]
+    fn report_round_trip_test() {
+        let summary = NodFrameBuilder::new()
+            .add_numeric("rows", vec![1200])
+            .add_numeric("groups", vec![3])
+            .build()
+            .unwrap();
+        let groups = NodFrameBuilder::new()
+            .add_discrete("region", strings(&["north", "south", "east"]))
+            .add_numeric("total", vec![400, 350, 450])
+            .build()
+            .unwrap();
+        let exceptions = NodFrameBuilder::new()
+            .add_numeric("row", vec![17, 802])
+            .add_discrete(
+                "reason",
+                strings(&["negative, refunded", "line one\n\nline two"]),
+            )
+            .add_discrete("note", strings(&["", ""]))
+            .build()
+            .unwrap();
+
+        let path = temp_path("three.csv");
+        let mut writer = CsvReportWriter::new(File::create(&path).unwrap());
+        writer.add_section("Summary", &summary).unwrap();
+        writer.add_section("Totals, by region", &groups).unwrap();
+        writer.add_section("Exceptions", &exceptions).unwrap();
+        assert!(writer
+            .add_section("Empty", &NodFrameBuilder::<i64>::new().build().unwrap())
+            .is_err());
+        writer.into_inner().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("Summary\nrows,groups\n1200,3\n\n\"Totals, by region\"\n"));
+
+        let sections: Vec<(String, NodFrame<i64>)> = read_csv_report(&path).unwrap();
+        let titles: Vec<&str> = sections.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(titles, vec!["Summary", "Totals, by region", "Exceptions"]);
+        for ((_, back), frame) in sections.iter().zip([&summary, &groups, &exceptions]) {
+            assert_eq!(back.to_string(), frame.to_string());
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
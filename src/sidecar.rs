@@ -0,0 +1,481 @@
+// Schema sidecars for csv hand-offs, see NodFrame::write_schema_json and
+// frame_from_csv_enforcing_schema.
+//
+// A sidecar is a small JSON file next to the csv listing every column in
+// order with its kind, and for numeric columns the value type of the frame
+// that wrote it:
+//   {"columns": [{"name": "price", "kind": "numeric", "type": "f64"},
+//                {"name": "city", "kind": "discrete"}]}
+// Only this shape is read, so the parser below handles plain JSON values and
+// nothing more. Keys it does not know are ignored.
+use crate::{read_csv, ColKind, CsvOptions, NodFrame, NodFrameError, NodNum};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+// sidecar_path is where to_csv_with_progress writes the sidecar of a csv
+// file: data.csv gets data.schema.json
+pub(crate) fn sidecar_path(csv_path: &Path) -> PathBuf {
+    csv_path.with_extension("schema.json")
+}
+
+// type_name is the value type written for numeric columns, e.g. "f64"
+fn type_name<T>() -> &'static str {
+    std::any::type_name::<T>()
+}
+
+fn invalid(message: impl Into<String>) -> NodFrameError {
+    NodFrameError::InvalidSchema(message.into())
+}
+
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // schema_json is the sidecar describing this frame, one column per line
+    pub fn schema_json(&self) -> String {
+        let mut out = String::from("{\"columns\": [");
+        for (i, col) in self.columns.iter().enumerate() {
+            out.push_str(if i == 0 { "\n  " } else { ",\n  " });
+            out.push_str("{\"name\": ");
+            push_json_string(&mut out, col.get_key());
+            let _ = write!(out, ", \"kind\": \"{}\"", col.kind());
+            if col.kind() == ColKind::Numeric {
+                out.push_str(", \"type\": ");
+                push_json_string(&mut out, type_name::<T>());
+            }
+            out.push('}');
+        }
+        out.push_str("\n]}\n");
+        out
+    }
+
+    // write_schema_json writes the sidecar of this frame to path, see the
+    // module comment for the layout
+    pub fn write_schema_json(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, self.schema_json())?;
+        Ok(())
+    }
+}
+
+// frame_from_csv_enforcing_schema loads csv_path with the column kinds listed
+// in the sidecar at schema_path instead of inferring them. The header must
+// hold exactly the sidecar columns in the same order: a listed column missing
+// from the file is MissingColumn, one the sidecar does not list is
+// UnexpectedColumn and a column elsewhere is ColumnOrder. Numeric columns must
+// parse on every row, see frame_from_csv_with_schema, and must have been
+// written from a frame of the same value type
+pub fn frame_from_csv_enforcing_schema<T: NodNum>(
+    csv_path: impl AsRef<Path>,
+    schema_path: impl AsRef<Path>,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let columns = read_sidecar::<T>(&std::fs::read_to_string(schema_path)?)?;
+    read_csv(csv_path, &CsvOptions::default(), |header| {
+        if let Some((key, _)) = columns.iter().find(|(k, _)| !header.contains(k)) {
+            return Err(NodFrameError::MissingColumn(key.clone()));
+        }
+        if let Some(key) = header.iter().find(|k| columns.iter().all(|(c, _)| c != *k)) {
+            return Err(NodFrameError::UnexpectedColumn(key.clone()));
+        }
+        if let Some(found) = (0..header.len()).find(|i| header[*i] != columns[*i].0) {
+            return Err(NodFrameError::ColumnOrder {
+                key: header[found].clone(),
+                expected: columns
+                    .iter()
+                    .position(|(c, _)| *c == header[found])
+                    .unwrap(),
+                found,
+            });
+        }
+        Ok(columns.iter().map(|(_, kind)| Some(*kind)).collect())
+    })
+}
+
+// read_sidecar lists the columns of a sidecar with their kinds
+fn read_sidecar<T>(text: &str) -> Result<Vec<(String, ColKind)>, NodFrameError> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+    let root = parser.value()?;
+    parser.skip_space();
+    if parser.pos != parser.text.len() {
+        return Err(invalid(format!("unexpected text at byte {}", parser.pos)));
+    }
+    let columns = match root.get("columns") {
+        Some(Json::Array(columns)) => columns,
+        _ => return Err(invalid("expected an object with a \"columns\" array")),
+    };
+    let mut out: Vec<(String, ColKind)> = Vec::with_capacity(columns.len());
+    for (i, col) in columns.iter().enumerate() {
+        let name = match col.get("name") {
+            Some(Json::Str(name)) => name.clone(),
+            _ => return Err(invalid(format!("column {} has no \"name\"", i))),
+        };
+        let kind = match col.get("kind") {
+            Some(Json::Str(k)) if k == "numeric" => ColKind::Numeric,
+            Some(Json::Str(k)) if k == "discrete" => ColKind::Discrete,
+            _ => {
+                return Err(invalid(format!(
+                    "column '{}' needs a \"kind\" of \"numeric\" or \"discrete\"",
+                    name
+                )))
+            }
+        };
+        if kind == ColKind::Numeric {
+            match col.get("type") {
+                Some(Json::Str(t)) if t == type_name::<T>() => {}
+                Some(Json::Str(t)) => {
+                    return Err(invalid(format!(
+                        "column '{}' holds {} values, the frame holds {}",
+                        name,
+                        t,
+                        type_name::<T>()
+                    )))
+                }
+                _ => return Err(invalid(format!("column '{}' has no \"type\"", name))),
+            }
+        }
+        if out.iter().any(|(k, _)| *k == name) {
+            return Err(NodFrameError::DuplicateKey(name));
+        }
+        out.push((name, kind));
+    }
+    Ok(out)
+}
+
+// Json is a parsed value. Numbers, booleans and null are checked but their
+// values are not kept, the sidecar has no use for them
+enum Json {
+    Scalar,
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // get is the value of key in an object, None for other values
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, expected: &str) -> NodFrameError {
+        invalid(format!("expected {} at byte {}", expected, self.pos))
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.text[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, NodFrameError> {
+        self.skip_space();
+        match self.text.get(self.pos) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(Json::Str(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ if self.eat("true") || self.eat("false") || self.eat("null") => Ok(Json::Scalar),
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    // items parses the comma separated items of an array or object up to
+    // close, the opening bracket already consumed
+    fn items(
+        &mut self,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Result<(), NodFrameError>,
+    ) -> Result<(), NodFrameError> {
+        self.pos += 1;
+        self.skip_space();
+        if self.text.get(self.pos) == Some(&close) {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_space();
+            match self.text.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(c) if *c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error(&format!("',' or '{}'", close as char))),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, NodFrameError> {
+        let mut entries = Vec::new();
+        self.items(b'}', |p| {
+            p.skip_space();
+            if p.text.get(p.pos) != Some(&b'"') {
+                return Err(p.error("a key"));
+            }
+            let key = p.string()?;
+            p.skip_space();
+            if !p.eat(":") {
+                return Err(p.error("':'"));
+            }
+            entries.push((key, p.value()?));
+            Ok(())
+        })?;
+        Ok(Json::Object(entries))
+    }
+
+    fn array(&mut self) -> Result<Json, NodFrameError> {
+        let mut values = Vec::new();
+        self.items(b']', |p| {
+            values.push(p.value()?);
+            Ok(())
+        })?;
+        Ok(Json::Array(values))
+    }
+
+    fn number(&mut self) -> Result<Json, NodFrameError> {
+        let start = self.pos;
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|c| matches!(c, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(|_| Json::Scalar)
+            .ok_or_else(|| invalid(format!("bad number at byte {}", start)))
+    }
+
+    // string parses a quoted string, the parser standing on the opening quote
+    fn string(&mut self) -> Result<String, NodFrameError> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let c = *self.text.get(self.pos).ok_or_else(|| self.error("'\"'"))?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self
+                        .text
+                        .get(self.pos)
+                        .ok_or_else(|| self.error("an escape"))?;
+                    self.pos += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("an escape")),
+                    };
+                    out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                c => out.push(c),
+            }
+        }
+        String::from_utf8(out).map_err(|_| invalid("string is not valid UTF-8"))
+    }
+
+    // unicode_escape reads the four hex digits after \u, and a second escape
+    // when the first is the high half of a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, NodFrameError> {
+        let hex = |p: &mut Self| {
+            let digits = p
+                .text
+                .get(p.pos..p.pos + 4)
+                .and_then(|d| std::str::from_utf8(d).ok())
+                .and_then(|d| u32::from_str_radix(d, 16).ok())
+                .ok_or_else(|| p.error("four hex digits"))?;
+            p.pos += 4;
+            Ok(digits)
+        };
+        let first = hex(self)?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if !self.eat("\\u") {
+                return Err(self.error("a low surrogate"));
+            }
+            let second = hex(self)?;
+            0x10000 + ((first - 0xd800) << 10) + (second.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("a unicode scalar value"))
+    }
+}
+
+#[cfg(test)]
+mod sidecar_tests {
+    use super::read_sidecar;
+    use crate::test_support::{strings, temp_path};
+    use crate::{
+        frame_from_csv_enforcing_schema, ColKind, CsvWriteOptions, NodFrame, NodFrameBuilder,
+        NodFrameError,
+    };
+
+    fn orders() -> NodFrame<f64> {
+        NodFrameBuilder::new()
+            .add_discrete("zip", strings(&["02134", "10001", "94105"]))
+            .add_numeric("price", vec![9.5, 12.0, 7.25])
+            .add_discrete("note", strings(&["a \"quoted\" note", "", "ünïcode"]))
+            .build()
+            .unwrap()
+    }
+
+    // enforce writes csv next to a sidecar for orders and loads it back
+    fn enforce(name: &str, csv: &str) -> Result<NodFrame<f64>, NodFrameError> {
+        let (csv_path, schema_path) = (temp_path(name), temp_path(&format!("{}.json", name)));
+        std::fs::write(&csv_path, csv).unwrap();
+        orders().write_schema_json(&schema_path).unwrap();
+        let loaded = frame_from_csv_enforcing_schema(&csv_path, &schema_path);
+        std::fs::remove_file(csv_path).unwrap();
+        std::fs::remove_file(schema_path).unwrap();
+        loaded.map_err(|e| e.downcast_ref::<NodFrameError>().unwrap().clone())
+    }
+
+    #[test]
+    fn schema_json_test() {
+        assert_eq!(
+            orders().schema_json(),
+            "{\"columns\": [\n  \
+             {\"name\": \"zip\", \"kind\": \"discrete\"},\n  \
+             {\"name\": \"price\", \"kind\": \"numeric\", \"type\": \"f64\"},\n  \
+             {\"name\": \"note\", \"kind\": \"discrete\"}\n]}\n"
+        );
+        let columns = read_sidecar::<f64>(&orders().schema_json()).unwrap();
+        assert_eq!(columns[1], (String::from("price"), ColKind::Numeric));
+        let escaped = r#"{"columns": [{"name": "a\"b\\cé😀", "kind": "discrete",
+            "extra": [1, -2.5e3, true, null, {}]}], "version": 1}"#;
+        assert_eq!(
+            read_sidecar::<f64>(escaped).unwrap()[0].0,
+            "a\"b\\cé\u{1f600}"
+        );
+        // a discrete column keeps its text, whatever inference would make of it
+        let loaded = enforce("zips.csv", "zip,price,note\n02134,9.5,x\n").unwrap();
+        assert_eq!(loaded["zip"].to_string(), "zip: [02134]");
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let csv_path = temp_path("sidecar_round_trip.csv");
+        let opts = CsvWriteOptions {
+            write_schema: true,
+            ..CsvWriteOptions::default()
+        };
+        orders()
+            .to_csv_with_progress(&csv_path, &opts, |_| {})
+            .unwrap();
+        let schema_path = temp_path("sidecar_round_trip.schema.json");
+        let back: NodFrame<f64> = frame_from_csv_enforcing_schema(&csv_path, &schema_path).unwrap();
+        assert_eq!(back.to_string(), orders().to_string());
+        // an integer frame does not load a float sidecar
+        let wrong = frame_from_csv_enforcing_schema::<i64>(&csv_path, &schema_path).unwrap_err();
+        assert_eq!(
+            wrong.to_string(),
+            "invalid schema: column 'price' holds f64 values, the frame holds i64"
+        );
+        std::fs::remove_file(csv_path).unwrap();
+        std::fs::remove_file(schema_path).unwrap();
+    }
+
+    #[test]
+    fn enforcing_schema_rejects_test() {
+        assert_eq!(
+            enforce("renamed.csv", "zip,cost,note\n02134,9.5,x\n").unwrap_err(),
+            NodFrameError::MissingColumn(String::from("price"))
+        );
+        assert_eq!(
+            enforce("extra.csv", "zip,price,note,qty\n02134,9.5,x,1\n").unwrap_err(),
+            NodFrameError::UnexpectedColumn(String::from("qty"))
+        );
+        let reordered = enforce("reordered.csv", "price,zip,note\n9.5,02134,x\n").unwrap_err();
+        assert_eq!(
+            reordered,
+            NodFrameError::ColumnOrder {
+                key: String::from("price"),
+                expected: 1,
+                found: 0,
+            }
+        );
+        assert_eq!(
+            reordered.to_string(),
+            "column 'price' is at position 0, expected 1"
+        );
+        assert_eq!(
+            enforce("text.csv", "zip,price,note\n02134,9.5,x\n10001,n/a,y\n").unwrap_err(),
+            NodFrameError::ParseError {
+                key: String::from("price"),
+                line: 3,
+                value: String::from("n/a"),
+            }
+        );
+
+        for bad in [
+            "",
+            "[]",
+            "{\"columns\": [{\"name\": \"x\"}]}",
+            "{\"columns\": [{\"name\": \"x\", \"kind\": \"numeric\"}]}",
+            "{\"columns\": [{\"name\": \"x\", \"kind\": \"discrete\"}]} trailing",
+            "{\"columns\": [{\"name\": \"x\", \"kind\": \"discrete\"},]}",
+            "{\"columns\": [{\"name\": \"x\\q\", \"kind\": \"discrete\"}]}",
+        ] {
+            assert!(
+                matches!(
+                    read_sidecar::<f64>(bad),
+                    Err(NodFrameError::InvalidSchema(_))
+                ),
+                "{}",
+                bad
+            );
+        }
+        let twice = "{\"columns\": [{\"name\": \"x\", \"kind\": \"discrete\"}, \
+                     {\"name\": \"x\", \"kind\": \"discrete\"}]}";
+        assert_eq!(
+            read_sidecar::<f64>(twice).unwrap_err(),
+            NodFrameError::DuplicateKey(String::from("x"))
+        );
+    }
+}
@@ -0,0 +1,296 @@
+// Approximate distinct counts and heavy hitters for large discrete columns.
+//
+// HyperLogLog keeps 4096 one-byte registers whatever the input size, and its
+// estimate has a standard error of about 1.6%, so it lands within 5% of the
+// true count in practice. TopKSketch is the space-saving algorithm: it tracks
+// a fixed number of candidates and over-counts each by at most the number of
+// rows seen divided by its capacity. Both are single pass and merge, so chunks
+// of a CSV read one at a time can be sketched separately and combined.
+use crate::DiscreteColumn;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+// columns shorter than this are counted exactly by approx_nunique and approx_top_k
+pub(crate) const EXACT_BELOW: usize = 100_000;
+
+const PRECISION: u32 = 12;
+const REGISTERS: usize = 1 << PRECISION;
+
+// DefaultHasher::new uses fixed keys, so sketches built in different places
+// agree and can be merged
+fn hash_str(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
+}
+
+// HyperLogLog estimates how many distinct strings it has seen
+#[derive(Clone, Debug, PartialEq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> HyperLogLog {
+        HyperLogLog {
+            registers: vec![0; REGISTERS],
+        }
+    }
+
+    pub fn add(&mut self, value: &str) {
+        let hash = hash_str(value);
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // rank of the first set bit in the remaining bits, capped when all are zero
+        let rest = hash << PRECISION;
+        let rank = (rest.leading_zeros() + 1).min(64 - PRECISION + 1) as u8;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn add_column(&mut self, col: &DiscreteColumn) {
+        col.iter().for_each(|s| self.add(s));
+    }
+
+    // merge folds other in, the result is the sketch of both inputs together
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (a, b) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *a = (*a).max(*b);
+        }
+    }
+
+    pub fn estimate(&self) -> usize {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|r| 2_f64.powi(-(*r as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        // linear counting is more accurate while many registers are still empty
+        if raw <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            raw.round() as usize
+        }
+    }
+}
+
+// TopKSketch tracks the most frequent strings in a fixed number of slots
+#[derive(Clone, Debug)]
+pub struct TopKSketch {
+    capacity: usize,
+    slots: HashMap<String, usize>,
+    // (count, value) ordered so the smallest count is evicted first
+    order: BTreeSet<(usize, String)>,
+}
+
+impl TopKSketch {
+    // new makes a sketch with room for capacity candidates, which should be
+    // well above the k asked for later
+    pub fn new(capacity: usize) -> TopKSketch {
+        TopKSketch {
+            capacity: capacity.max(1),
+            slots: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn bump(&mut self, value: &str, by: usize) {
+        if let Some(count) = self.slots.get_mut(value) {
+            self.order.remove(&(*count, String::from(value)));
+            *count += by;
+            self.order.insert((*count, String::from(value)));
+            return;
+        }
+        let mut count = by;
+        if self.slots.len() == self.capacity {
+            // the newcomer inherits the evicted count, which bounds its error
+            let (min, evicted) = self.order.pop_first().unwrap();
+            self.slots.remove(&evicted);
+            count += min;
+        }
+        self.slots.insert(String::from(value), count);
+        self.order.insert((count, String::from(value)));
+    }
+
+    pub fn add(&mut self, value: &str) {
+        self.bump(value, 1);
+    }
+
+    pub fn add_column(&mut self, col: &DiscreteColumn) {
+        col.iter().for_each(|s| self.add(s));
+    }
+
+    // merge folds other in, keeping the largest combined counts
+    pub fn merge(&mut self, other: &TopKSketch) {
+        let mut counts = self.slots.clone();
+        for (value, count) in &other.slots {
+            *counts.entry(value.clone()).or_insert(0) += count;
+        }
+        let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(self.capacity);
+        self.order = ranked.iter().map(|(v, c)| (*c, v.clone())).collect();
+        self.slots = ranked.into_iter().collect();
+    }
+
+    // top returns up to k strings with their estimated counts, highest first
+    pub fn top(&self, k: usize) -> Vec<(String, usize)> {
+        self.order
+            .iter()
+            .rev()
+            .map(|(c, v)| (v.clone(), *c))
+            .collect::<Vec<_>>()
+            .chunk_by(|a, b| a.1 == b.1)
+            .flat_map(|tied| {
+                let mut tied = tied.to_vec();
+                tied.sort();
+                tied
+            })
+            .take(k)
+            .collect()
+    }
+}
+
+impl DiscreteColumn {
+    // approx_nunique estimates the number of distinct values with a
+    // HyperLogLog sketch, see HyperLogLog for the error bound. Short columns
+    // are counted exactly
+    pub fn approx_nunique(&self) -> usize {
+        if self.len() < EXACT_BELOW {
+            return self.iter().collect::<HashSet<&str>>().len();
+        }
+        let mut sketch = HyperLogLog::new();
+        sketch.add_column(self);
+        sketch.estimate()
+    }
+
+    // approx_top_k estimates the k most frequent values and their counts,
+    // highest first with ties by value. Short columns are counted exactly
+    pub fn approx_top_k(&self, k: usize) -> Vec<(String, usize)> {
+        if self.len() < EXACT_BELOW {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            self.iter().for_each(|s| *counts.entry(s).or_insert(0) += 1);
+            let mut ranked: Vec<(String, usize)> = counts
+                .into_iter()
+                .map(|(s, c)| (String::from(s), c))
+                .collect();
+            ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ranked.truncate(k);
+            return ranked;
+        }
+        let mut sketch = TopKSketch::new((k * 50).max(1000));
+        sketch.add_column(self);
+        sketch.top(k)
+    }
+}
+
+#[cfg(test)]
+mod sketch_tests {
+    use crate::{DiscreteColumn, HyperLogLog, TopKSketch};
+    use std::collections::{HashMap, HashSet};
+
+    // zipf draws rows with frequencies falling off roughly as 1 / rank
+    fn zipf(rows: usize, distinct: usize) -> DiscreteColumn {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let values = (0..rows)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let u = (state >> 11) as f64 / (1_u64 << 53) as f64;
+                let rank = (distinct as f64).powf(u) as usize;
+                format!("user{}", rank)
+            })
+            .collect();
+        DiscreteColumn::new("user", values)
+    }
+
+    fn exact_counts(col: &DiscreteColumn) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        col.iter()
+            .for_each(|s| *counts.entry(String::from(s)).or_insert(0) += 1);
+        counts
+    }
+
+    #[test]
+    fn approx_nunique_test() {
+        let col = zipf(400_000, 150_000);
+        let exact = col.iter().collect::<HashSet<_>>().len();
+        let approx = col.approx_nunique();
+        let error = (approx as f64 - exact as f64).abs() / exact as f64;
+        assert!(error < 0.05, "{} vs {}", approx, exact);
+
+        let small = DiscreteColumn::new("s", ["a", "b", "a"].map(String::from).to_vec());
+        assert_eq!(small.approx_nunique(), 2);
+        assert_eq!(HyperLogLog::new().estimate(), 0);
+    }
+
+    #[test]
+    fn approx_top_k_test() {
+        let col = zipf(400_000, 150_000);
+        let counts = exact_counts(&col);
+        let top = col.approx_top_k(5);
+        assert_eq!(top.len(), 5);
+        for (value, count) in &top {
+            // space-saving never under-counts and over-counts by at most rows / capacity
+            let exact = counts[value];
+            assert!(*count >= exact && *count - exact <= 400_000 / 1000);
+        }
+        let mut ranked: Vec<_> = counts.iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(a.1));
+        assert_eq!(&top[0].0, ranked[0].0);
+
+        let small = DiscreteColumn::new("s", ["b", "a", "b", "c", "a"].map(String::from).to_vec());
+        assert_eq!(
+            small.approx_top_k(2),
+            vec![(String::from("a"), 2), (String::from("b"), 2)]
+        );
+    }
+
+    #[test]
+    fn merge_test() {
+        let col = zipf(300_000, 100_000);
+        let mut whole_hll = HyperLogLog::new();
+        whole_hll.add_column(&col);
+        let mut whole_top = TopKSketch::new(1000);
+        whole_top.add_column(&col);
+
+        let mut merged_hll = HyperLogLog::new();
+        let mut merged_top = TopKSketch::new(1000);
+        for chunk in col.as_slice().chunks(70_000) {
+            let chunk =
+                DiscreteColumn::new("user", chunk.iter().map(|s| String::from(&**s)).collect());
+            let mut hll = HyperLogLog::new();
+            hll.add_column(&chunk);
+            merged_hll.merge(&hll);
+            let mut top = TopKSketch::new(1000);
+            top.add_column(&chunk);
+            merged_top.merge(&top);
+        }
+        assert_eq!(merged_hll.estimate(), whole_hll.estimate());
+
+        let counts = exact_counts(&col);
+        let merged = merged_top.top(5);
+        let whole = whole_top.top(5);
+        assert_eq!(merged[0].0, whole[0].0);
+        for (value, count) in merged {
+            let exact = counts[&value] as f64;
+            assert!((count as f64 - exact).abs() / exact < 0.05);
+        }
+    }
+}
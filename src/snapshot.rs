@@ -0,0 +1,325 @@
+// Binary snapshots of frames, see NodFrame::save.
+//
+// Layout, all integers little-endian:
+//   magic "NODFRAME", version u32
+//   value type: class u8 (b'i', b'u' or b'f') and width u8 in bytes
+//   column count u64, row count u64
+//   per column: key length u32 and UTF-8 key, kind u8 (0 numeric,
+//   1 discrete, 2 ordered discrete), row count u64, then the values: raw
+//   little-endian numbers, or a u32 length and UTF-8 bytes per string. An
+//   ordered discrete column follows with a u32 level count and its levels,
+//   lowest first, stored like the values
+// The layout only changes together with VERSION, and load rejects versions
+// it does not know. Version 1 had no ordered discrete columns.
+use crate::{is_float, Column, DiscreteColumn, NodFrame, NodFrameBuilder, NodFrameError, NodNum};
+use num_traits::{FromBytes, ToBytes};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"NODFRAME";
+const VERSION: u32 = 2;
+const BLOCK_ROWS: usize = 8192;
+
+// value_type describes T so a snapshot is not loaded into another type
+fn value_type<T: NodNum>() -> [u8; 2] {
+    let class = if is_float::<T>() {
+        b'f'
+    } else if T::from(-1).is_some() {
+        b'i'
+    } else {
+        b'u'
+    };
+    [class, std::mem::size_of::<T>() as u8]
+}
+
+fn invalid(message: impl Into<String>) -> Box<dyn Error> {
+    Box::new(NodFrameError::InvalidSnapshot(message.into()))
+}
+
+struct SnapshotReader<R> {
+    inner: R,
+}
+
+impl<R: BufRead> SnapshotReader<R> {
+    fn bytes(&mut self, buf: &mut [u8]) -> Result<(), Box<dyn Error>> {
+        self.inner.read_exact(buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => invalid("file ends early"),
+            _ => Box::new(e),
+        })
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error>> {
+        let mut buf = [0; 1];
+        self.bytes(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        let mut buf = [0; 4];
+        self.bytes(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64, Box<dyn Error>> {
+        let mut buf = [0; 8];
+        self.bytes(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let len = self.u32()? as usize;
+        // most strings sit whole in the read buffer and are copied out directly
+        let buffered = self.inner.fill_buf()?;
+        if buffered.len() >= len {
+            let s = std::str::from_utf8(&buffered[..len])
+                .map_err(|_| invalid("string is not valid UTF-8"))?
+                .to_string();
+            self.inner.consume(len);
+            return Ok(s);
+        }
+        let mut buf = vec![0; len];
+        self.bytes(&mut buf)?;
+        String::from_utf8(buf).map_err(|_| invalid("string is not valid UTF-8"))
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // save writes the frame to path in the snapshot layout described above
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>>
+    where
+        T: ToBytes,
+    {
+        let mut out = BufWriter::new(File::create(path)?);
+        let (rows, cols) = self.shape();
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&value_type::<T>())?;
+        out.write_all(&(cols as u64).to_le_bytes())?;
+        out.write_all(&(rows as u64).to_le_bytes())?;
+        for col in self.columns() {
+            let key = col.get_key();
+            out.write_all(&(key.len() as u32).to_le_bytes())?;
+            out.write_all(key.as_bytes())?;
+            match col {
+                Column::Numeric(n) => {
+                    out.write_all(&[0])?;
+                    out.write_all(&(n.len() as u64).to_le_bytes())?;
+                    for x in n.iter() {
+                        out.write_all(x.to_le_bytes().as_ref())?;
+                    }
+                }
+                Column::Discrete(d) => {
+                    out.write_all(&[if d.ordering().is_some() { 2 } else { 1 }])?;
+                    out.write_all(&(d.len() as u64).to_le_bytes())?;
+                    for s in d.iter() {
+                        out.write_all(&(s.len() as u32).to_le_bytes())?;
+                        out.write_all(s.as_bytes())?;
+                    }
+                    if let Some(levels) = d.ordering() {
+                        out.write_all(&(levels.len() as u32).to_le_bytes())?;
+                        for l in levels {
+                            out.write_all(&(l.len() as u32).to_le_bytes())?;
+                            out.write_all(l.as_bytes())?;
+                        }
+                    }
+                }
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    // load reads a frame written by save, failing on a wrong magic, an
+    // unknown version, another value type or a truncated file
+    pub fn load(path: impl AsRef<Path>) -> Result<NodFrame<T>, Box<dyn Error>>
+    where
+        T: ToBytes + FromBytes<Bytes = <T as ToBytes>::Bytes>,
+    {
+        let mut reader = SnapshotReader {
+            inner: BufReader::with_capacity(1 << 20, File::open(path)?),
+        };
+        let mut magic = [0; 8];
+        reader.bytes(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a nodframe snapshot"));
+        }
+        let version = reader.u32()?;
+        if version == 0 || version > VERSION {
+            return Err(invalid(format!(
+                "version {} is not supported, expected at most {}",
+                version, VERSION
+            )));
+        }
+        let stored = [reader.u8()?, reader.u8()?];
+        let expected = value_type::<T>();
+        if stored != expected {
+            return Err(invalid(format!(
+                "values are {}{}, expected {}{}",
+                stored[0] as char,
+                stored[1] as u32 * 8,
+                expected[0] as char,
+                expected[1] as u32 * 8
+            )));
+        }
+        let cols = reader.u64()?;
+        let rows = reader.u64()? as usize;
+        // the lengths are untrusted, so capacity is capped until data arrives
+        let capacity = rows.min(1 << 16);
+        let mut buf = T::from(0).unwrap().to_le_bytes();
+        let mut builder = NodFrameBuilder::new();
+        for _ in 0..cols {
+            let key = reader.string()?;
+            let kind = reader.u8()?;
+            let len = reader.u64()? as usize;
+            if len != rows {
+                return Err(invalid(format!(
+                    "column '{}' has {} rows, expected {}",
+                    key, len, rows
+                )));
+            }
+            builder = match kind {
+                0 => {
+                    let width = buf.as_ref().len();
+                    let mut values = Vec::with_capacity(capacity);
+                    let mut block = Vec::new();
+                    // reading blocks of values is much faster than one at a time
+                    for start in (0..len).step_by(BLOCK_ROWS) {
+                        block.resize((len - start).min(BLOCK_ROWS) * width, 0);
+                        reader.bytes(&mut block)?;
+                        for raw in block.chunks_exact(width) {
+                            buf.as_mut().copy_from_slice(raw);
+                            values.push(T::from_le_bytes(&buf));
+                        }
+                    }
+                    builder.add_numeric(&key, values)
+                }
+                1 | 2 => {
+                    let mut values = Vec::with_capacity(capacity);
+                    for _ in 0..len {
+                        values.push(reader.string()?);
+                    }
+                    let mut column = DiscreteColumn::new(&key, values);
+                    if kind == 2 {
+                        let count = reader.u32()?;
+                        let mut levels = Vec::with_capacity((count as usize).min(1 << 16));
+                        for _ in 0..count {
+                            levels.push(reader.string()?);
+                        }
+                        let levels: Vec<&str> = levels.iter().map(|l| l.as_str()).collect();
+                        column
+                            .set_order(&levels)
+                            .map_err(|e| invalid(format!("column '{}': {}", key, e)))?;
+                    }
+                    builder.add_column(Column::Discrete(column))
+                }
+                k => return Err(invalid(format!("column '{}' has unknown kind {}", key, k))),
+            };
+        }
+        let mut rest = [0; 1];
+        if reader.inner.read(&mut rest)? != 0 {
+            return Err(invalid("unexpected data after the last column"));
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use crate::test_support::temp_path;
+    use crate::{NodFrame, NodFrameBuilder};
+
+    fn readings() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_numeric("t", vec![0, 60, 120, i64::MIN])
+            .add_discrete(
+                "station",
+                ["north", "süd", "", "east"]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            )
+            .add_numeric("temp", vec![-4, 2, 7, i64::MAX])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let path = temp_path("round_trip.bin");
+        let frame = readings();
+        frame.save(&path).unwrap();
+        let back = NodFrame::<i64>::load(&path).unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
+
+        let err = NodFrame::<i32>::load(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid snapshot: values are i64, expected i32"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn ordered_round_trip_test() {
+        let path = temp_path("ordered.bin");
+        let mut frame = readings();
+        frame
+            .set_order("station", &["", "north", "east", "süd", "west"])
+            .unwrap();
+        frame.save(&path).unwrap();
+        let back = NodFrame::<i64>::load(&path).unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
+        assert_eq!(
+            back["station"].as_discrete().unwrap().ordering(),
+            frame["station"].as_discrete().unwrap().ordering()
+        );
+        assert_eq!(
+            back.sort_by("station", true).unwrap()["station"].to_string(),
+            "station: [, north, east, süd]"
+        );
+        // a version 1 snapshot of an unordered frame still loads
+        readings().save(&path).unwrap();
+        let mut old = std::fs::read(&path).unwrap();
+        old[8..12].copy_from_slice(&1_u32.to_le_bytes());
+        std::fs::write(&path, &old).unwrap();
+        let back = NodFrame::<i64>::load(&path).unwrap();
+        assert_eq!(back.to_string(), readings().to_string());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_snapshot_test() {
+        let path = temp_path("corrupt.bin");
+        readings().save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        // every truncation fails cleanly instead of panicking or misparsing
+        for end in 0..bytes.len() {
+            std::fs::write(&path, &bytes[..end]).unwrap();
+            assert!(NodFrame::<i64>::load(&path).is_err(), "prefix {}", end);
+        }
+
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&3_u32.to_le_bytes());
+        std::fs::write(&path, &newer).unwrap();
+        let err = NodFrame::<i64>::load(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid snapshot: version 3 is not supported, expected at most 2"
+        );
+
+        let mut wrong = bytes.clone();
+        wrong[0] = b'X';
+        std::fs::write(&path, &wrong).unwrap();
+        let err = NodFrame::<i64>::load(&path).unwrap_err();
+        assert_eq!(err.to_string(), "invalid snapshot: not a nodframe snapshot");
+
+        let mut longer = bytes;
+        longer.push(0);
+        std::fs::write(&path, &longer).unwrap();
+        assert!(NodFrame::<i64>::load(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
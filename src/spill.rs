@@ -0,0 +1,838 @@
+// Discrete columns kept on disk, see frame_from_csv_spilled.
+//
+// A spilled column writes its cells one after another to a temporary file and
+// keeps only the offset where each cell ends in memory. get reads the block
+// of rows holding a cell through a small LRU cache of blocks, while filters,
+// value_counts and binary_view read the file once from start to end. The file
+// is removed when the last clone of the column is dropped. Numeric columns
+// and discrete columns below the threshold stay in memory as usual.
+use crate::{
+    check_key, csv_columns, csv_layout, for_each_record, open_csv, push_record, ranked_counts,
+    Cell, ColKind, Column, Comp, CsvColumn, CsvOptions, DiscreteColumn, FilterValue, Interner,
+    Mask, MemoryReport, NodFrame, NodFrameError, NodNum,
+};
+use csv::ReaderBuilder;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+const BLOCK_ROWS: usize = 4096;
+const CACHED_BLOCKS: usize = 8;
+
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+impl CsvOptions {
+    // spill_discrete_over makes frame_from_csv_spilled move a discrete column
+    // to a temporary file once its cells take more than bytes in memory
+    pub fn spill_discrete_over(mut self, bytes: usize) -> CsvOptions {
+        self.spill_discrete_over = Some(bytes);
+        self
+    }
+}
+
+// held_bytes is DiscreteColumn::memory_usage of cells interned by interner,
+// without the Vec itself: a pointer per row and each distinct text once
+fn held_bytes(cells: &[Arc<str>], interner: &Interner) -> usize {
+    size_of_val(cells) + interner.bytes()
+}
+
+fn invalid_text(e: impl Into<Box<dyn Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+// SpillFile is the file of a spilled column, ends[i] is the offset where
+// cell i ends and the cache holds the most recently used block first
+struct SpillFile {
+    path: PathBuf,
+    ends: Vec<u64>,
+    reader: Mutex<File>,
+    cache: Mutex<VecDeque<(usize, Arc<Vec<String>>)>>,
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// SpillWriter writes the cells of a column to a new spill file. A writer
+// dropped before finish removes its file
+pub(crate) struct SpillWriter {
+    path: PathBuf,
+    out: BufWriter<File>,
+    ends: Vec<u64>,
+}
+
+impl SpillWriter {
+    // create only opens a file that did not exist, so it never writes
+    // through a file or link someone else placed at the predictable path.
+    // A taken name moves on to the next counter
+    pub(crate) fn create() -> io::Result<SpillWriter> {
+        loop {
+            let path = std::env::temp_dir().join(format!(
+                "nodframe_spill_{}_{}",
+                std::process::id(),
+                SPILL_FILES.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    return Ok(SpillWriter {
+                        path,
+                        out: BufWriter::new(file),
+                        ends: Vec::new(),
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub(crate) fn push(&mut self, cell: &str) -> io::Result<()> {
+        self.out.write_all(cell.as_bytes())?;
+        let start = self.ends.last().copied().unwrap_or(0);
+        self.ends.push(start + cell.len() as u64);
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self, key: &str) -> io::Result<SpilledColumn> {
+        self.out.flush()?;
+        let reader = File::open(&self.path)?;
+        Ok(SpilledColumn {
+            key: String::from(key),
+            file: Arc::new(SpillFile {
+                path: std::mem::take(&mut self.path),
+                ends: std::mem::take(&mut self.ends),
+                reader: Mutex::new(reader),
+                cache: Mutex::new(VecDeque::new()),
+            }),
+        })
+    }
+}
+
+impl Drop for SpillWriter {
+    fn drop(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Spiller gathers the cells of a column in memory until they take more than
+// spill_over bytes, then moves them to a spill file and writes the rest there
+struct Spiller {
+    key: String,
+    spill_over: Option<usize>,
+    cells: Vec<Arc<str>>,
+    interner: Interner,
+    writer: Option<SpillWriter>,
+}
+
+impl Spiller {
+    fn new(key: &str, spill_over: Option<usize>) -> Spiller {
+        Spiller {
+            key: String::from(key),
+            spill_over,
+            cells: Vec::new(),
+            interner: Interner::default(),
+            writer: None,
+        }
+    }
+
+    fn push(&mut self, cell: &str) -> io::Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            return writer.push(cell);
+        }
+        self.cells.push(self.interner.intern(cell));
+        let bytes = held_bytes(&self.cells, &self.interner);
+        if self.spill_over.is_some_and(|limit| bytes > limit) {
+            let mut writer = SpillWriter::create()?;
+            for cell in self.cells.drain(..) {
+                writer.push(&cell)?;
+            }
+            self.interner = Interner::default();
+            self.writer = Some(writer);
+        }
+        Ok(())
+    }
+
+    fn finish<T: NodNum>(self) -> io::Result<SpillColumn<T>> {
+        Ok(match self.writer {
+            Some(writer) => SpillColumn::Spilled(writer.finish(&self.key)?),
+            None => SpillColumn::Resident(Column::Discrete(DiscreteColumn::from_shared(
+                &self.key, self.cells,
+            ))),
+        })
+    }
+}
+
+// SpilledColumn is a discrete column whose cells are in a spill file, see
+// the module comment. Clones share the file and its cache
+#[derive(Clone)]
+pub struct SpilledColumn {
+    key: String,
+    file: Arc<SpillFile>,
+}
+
+impl fmt::Debug for SpilledColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpilledColumn")
+            .field("key", &self.key)
+            .field("rows", &self.len())
+            .field("path", &self.file.path)
+            .finish()
+    }
+}
+
+impl SpilledColumn {
+    // spill writes column to a new spill file
+    pub fn spill(column: &DiscreteColumn) -> io::Result<SpilledColumn> {
+        let mut writer = SpillWriter::create()?;
+        for cell in column.iter() {
+            writer.push(cell)?;
+        }
+        writer.finish(column.key())
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn len(&self) -> usize {
+        self.file.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file.ends.is_empty()
+    }
+
+    // path is the spill file, removed once the last clone is dropped
+    pub fn path(&self) -> &Path {
+        &self.file.path
+    }
+
+    // memory_usage estimates the bytes kept in memory: the offsets and the
+    // cached blocks
+    pub fn memory_usage(&self) -> usize {
+        let cache = self
+            .file
+            .cache
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let cached: usize = cache
+            .iter()
+            .flat_map(|(_, cells)| cells.iter())
+            .map(|s| size_of::<String>() + s.len())
+            .sum();
+        size_of::<Vec<u64>>() + self.len() * size_of::<u64>() + cached
+    }
+
+    fn start(&self, row: usize) -> u64 {
+        if row == 0 {
+            0
+        } else {
+            self.file.ends[row - 1]
+        }
+    }
+
+    // get reads the value at index through the block cache, an index out of
+    // range panics like DiscreteColumn::get
+    pub fn get(&self, index: usize) -> io::Result<String> {
+        assert!(
+            index < self.len(),
+            "index {} out of range for column '{}' of {} rows",
+            index,
+            self.key,
+            self.len()
+        );
+        let block = self.block(index / BLOCK_ROWS)?;
+        Ok(block[index % BLOCK_ROWS].clone())
+    }
+
+    pub fn get_checked(&self, index: usize) -> io::Result<Option<String>> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        self.get(index).map(Some)
+    }
+
+    // block returns the cells of block b, from the cache or read from the
+    // file and then cached in place of the least recently used block
+    fn block(&self, b: usize) -> io::Result<Arc<Vec<String>>> {
+        let cache = || {
+            self.file
+                .cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+        };
+        {
+            let mut cache = cache();
+            if let Some(i) = cache.iter().position(|(n, _)| *n == b) {
+                let hit = cache.remove(i).unwrap();
+                let cells = hit.1.clone();
+                cache.push_front(hit);
+                return Ok(cells);
+            }
+        }
+        let first = b * BLOCK_ROWS;
+        let last = (first + BLOCK_ROWS).min(self.len());
+        let start = self.start(first);
+        let mut bytes = vec![0; (self.file.ends[last - 1] - start) as usize];
+        {
+            let mut reader = self
+                .file
+                .reader
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            reader.seek(SeekFrom::Start(start))?;
+            reader.read_exact(&mut bytes)?;
+        }
+        let text = String::from_utf8(bytes).map_err(invalid_text)?;
+        let mut at = 0;
+        let cells: Vec<String> = self.file.ends[first..last]
+            .iter()
+            .map(|end| {
+                let end = (end - start) as usize;
+                let cell = text[at..end].to_string();
+                at = end;
+                cell
+            })
+            .collect();
+        let cells = Arc::new(cells);
+        let mut cache = cache();
+        cache.push_front((b, cells.clone()));
+        cache.truncate(CACHED_BLOCKS);
+        Ok(cells)
+    }
+
+    // scan calls f with every row and its value in order, reading the file
+    // once from the start without the cache
+    pub fn scan(&self, mut f: impl FnMut(usize, &str) -> io::Result<()>) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(&self.file.path)?);
+        let mut buf = Vec::new();
+        let mut at = 0;
+        for (row, end) in self.file.ends.iter().enumerate() {
+            buf.resize((end - at) as usize, 0);
+            reader.read_exact(&mut buf)?;
+            at = *end;
+            f(row, std::str::from_utf8(&buf).map_err(invalid_text)?)?;
+        }
+        Ok(())
+    }
+
+    // filter_array marks the rows equal to val, like DiscreteColumn::filter_array
+    pub fn filter_array(&self, val: &str) -> io::Result<Vec<bool>> {
+        let mut filter = Vec::with_capacity(self.len());
+        self.scan(|_, cell| {
+            filter.push(cell == val);
+            Ok(())
+        })?;
+        Ok(filter)
+    }
+
+    // filter_comp is DiscreteColumn::filter_comp for a column without levels:
+    // Eq and Not test equality, range comparisons are an error
+    pub fn filter_comp(&self, val: &str, comp: Comp) -> Result<Vec<bool>, Box<dyn Error>> {
+        match comp {
+            Comp::Eq => Ok(self.filter_array(val)?),
+            Comp::Not => {
+                let mut filter = self.filter_array(val)?;
+                filter.iter_mut().for_each(|b| *b = !*b);
+                Ok(filter)
+            }
+            _ => Err(Box::new(NodFrameError::UnorderedComparison {
+                key: self.key.clone(),
+                comp,
+            })),
+        }
+    }
+
+    // value_counts is DiscreteColumn::value_counts read in one pass
+    pub fn value_counts(&self) -> io::Result<Vec<(String, usize)>> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        self.scan(|_, cell| {
+            match counts.get_mut(cell) {
+                Some(n) => *n += 1,
+                None => {
+                    counts.insert(String::from(cell), 1);
+                }
+            }
+            Ok(())
+        })?;
+        Ok(ranked_counts(counts))
+    }
+
+    // binary_view keeps the rows where picker is true. The kept cells stay in
+    // memory unless they take more than spill_over bytes, then they are
+    // spilled to a new file
+    pub fn binary_view<T: NodNum>(
+        &self,
+        picker: &[bool],
+        spill_over: Option<usize>,
+    ) -> io::Result<SpillColumn<T>> {
+        let mut kept = Spiller::new(&self.key, spill_over);
+        self.scan(|row, cell| match picker.get(row) {
+            Some(true) => kept.push(cell),
+            _ => Ok(()),
+        })?;
+        kept.finish()
+    }
+
+    // load reads the whole column into memory
+    pub fn load(&self) -> io::Result<DiscreteColumn> {
+        let mut interner = Interner::default();
+        let mut cells = Vec::with_capacity(self.len());
+        self.scan(|_, cell| {
+            cells.push(interner.intern(cell));
+            Ok(())
+        })?;
+        Ok(DiscreteColumn::from_shared(&self.key, cells))
+    }
+}
+
+// SpillColumn is a column of a SpilledFrame, in memory or spilled
+#[derive(Clone, Debug)]
+pub enum SpillColumn<T> {
+    Resident(Column<T>),
+    Spilled(SpilledColumn),
+}
+
+impl<T: NodNum> SpillColumn<T> {
+    pub fn key(&self) -> &str {
+        match self {
+            SpillColumn::Resident(c) => c.get_key(),
+            SpillColumn::Spilled(s) => s.key(),
+        }
+    }
+
+    pub fn kind(&self) -> ColKind {
+        match self {
+            SpillColumn::Resident(c) => c.kind(),
+            SpillColumn::Spilled(_) => ColKind::Discrete,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            SpillColumn::Resident(c) => c.len(),
+            SpillColumn::Spilled(s) => s.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        matches!(self, SpillColumn::Spilled(_))
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        match self {
+            SpillColumn::Resident(c) => c.memory_usage(),
+            SpillColumn::Spilled(s) => s.memory_usage(),
+        }
+    }
+
+    pub fn get_cell(&self, index: usize) -> io::Result<Cell<T>> {
+        match self {
+            SpillColumn::Resident(c) => Ok(c.get_cell(index)),
+            SpillColumn::Spilled(s) => s.get(index).map(Cell::Str),
+        }
+    }
+
+    // load returns the column in memory, reading a spilled one
+    pub fn load(&self) -> io::Result<Column<T>> {
+        match self {
+            SpillColumn::Resident(c) => Ok(c.clone()),
+            SpillColumn::Spilled(s) => s.load().map(Column::Discrete),
+        }
+    }
+
+    fn binary_view(&self, picker: &[bool], spill_over: Option<usize>) -> io::Result<Self> {
+        match self {
+            SpillColumn::Resident(c) => Ok(SpillColumn::Resident(c.binary_view(picker))),
+            SpillColumn::Spilled(s) => s.binary_view(picker, spill_over),
+        }
+    }
+}
+
+// SpilledFrame is a frame whose large discrete columns are spilled, made by
+// frame_from_csv_spilled. filter and load give frames in memory or spilled
+// again as the threshold of the load decides
+#[derive(Clone, Debug)]
+pub struct SpilledFrame<T> {
+    columns: Vec<SpillColumn<T>>,
+    column_idx: HashMap<String, usize>,
+    num_rows: usize,
+    spill_over: Option<usize>,
+}
+
+impl<T: NodNum> SpilledFrame<T> {
+    fn new(
+        columns: Vec<SpillColumn<T>>,
+        spill_over: Option<usize>,
+    ) -> Result<SpilledFrame<T>, NodFrameError> {
+        let num_rows = columns.first().map_or(0, |c| c.len());
+        let mut column_idx = HashMap::new();
+        for (i, col) in columns.iter().enumerate() {
+            check_key(col.key())?;
+            if col.len() != num_rows {
+                return Err(NodFrameError::LengthMismatch {
+                    key: String::from(col.key()),
+                    expected: num_rows,
+                    found: col.len(),
+                });
+            }
+            if column_idx.insert(String::from(col.key()), i).is_some() {
+                return Err(NodFrameError::DuplicateKey(String::from(col.key())));
+            }
+        }
+        Ok(SpilledFrame {
+            columns,
+            column_idx,
+            num_rows,
+            spill_over,
+        })
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.num_rows, self.columns.len())
+    }
+
+    pub fn columns(&self) -> &[SpillColumn<T>] {
+        &self.columns
+    }
+
+    pub fn column(&self, key: &str) -> Option<&SpillColumn<T>> {
+        self.column_idx.get(key).map(|i| &self.columns[*i])
+    }
+
+    // spilled_keys lists the spilled columns in frame order
+    pub fn spilled_keys(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .filter(|c| c.is_spilled())
+            .map(|c| c.key())
+            .collect()
+    }
+
+    pub fn at(&self, key: &str, row: usize) -> io::Result<Option<Cell<T>>> {
+        match self.column(key) {
+            Some(col) if row < self.num_rows => col.get_cell(row).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // memory_usage estimates the bytes kept in memory per column
+    pub fn memory_usage(&self) -> MemoryReport {
+        MemoryReport {
+            columns: self
+                .columns
+                .iter()
+                .map(|c| (c.key().to_string(), c.memory_usage()))
+                .collect(),
+        }
+    }
+
+    // filter_mask marks the rows NodFrame::filter would keep. A spilled
+    // column has no level order, so it only takes Eq and Not
+    pub fn filter_mask(
+        &self,
+        key: &str,
+        comp: Comp,
+        value: &FilterValue<T>,
+    ) -> Result<Mask, Box<dyn Error>> {
+        let col = self
+            .column(key)
+            .ok_or_else(|| NodFrameError::MissingColumn(String::from(key)))?;
+        let bits = match (col, value) {
+            (SpillColumn::Resident(Column::Numeric(n)), FilterValue::Num(v)) => {
+                n.filter_array(v, comp)
+            }
+            (SpillColumn::Resident(Column::Discrete(d)), FilterValue::Str(s)) => {
+                d.filter_comp(s, comp)?
+            }
+            (SpillColumn::Spilled(d), FilterValue::Str(s)) => d.filter_comp(s, comp)?,
+            (col, value) => {
+                return Err(Box::new(NodFrameError::FilterKindMismatch {
+                    key: String::from(key),
+                    column: col.kind(),
+                    value: value.kind(),
+                }))
+            }
+        };
+        Ok(Mask::new(bits))
+    }
+
+    // filter keeps the rows where the filter holds, reading each spilled
+    // column once. Kept cells of a spilled column are spilled again when
+    // they pass the threshold the frame was loaded with
+    pub fn filter(
+        &self,
+        key: &str,
+        comp: Comp,
+        value: &FilterValue<T>,
+    ) -> Result<SpilledFrame<T>, Box<dyn Error>> {
+        let mask = self.filter_mask(key, comp, value)?;
+        let columns = self
+            .columns
+            .iter()
+            .map(|c| c.binary_view(mask.as_slice(), self.spill_over))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(SpilledFrame::new(columns, self.spill_over)?)
+    }
+
+    // load reads every spilled column back into an ordinary frame
+    pub fn load(&self) -> Result<NodFrame<T>, Box<dyn Error>> {
+        let columns = self
+            .columns
+            .iter()
+            .map(|c| c.load())
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(NodFrame::from_columns(columns)?)
+    }
+}
+
+// frame_from_csv_spilled is frame_from_csv_with_options that spills discrete
+// columns once their cells take more than opts.spill_discrete_over bytes, so
+// a high-cardinality text column never has to fit in memory. Without a
+// threshold nothing is spilled
+pub fn frame_from_csv_spilled<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+) -> Result<SpilledFrame<T>, Box<dyn Error>> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(open_csv(file_path, opts)?);
+    let layout = csv_layout(&mut reader, opts)?;
+    let mut columns = csv_columns::<T>(&layout, opts, |header| Ok(vec![None; header.len()]))?;
+    let mut writers: Vec<Option<SpillWriter>> = columns.iter().map(|_| None).collect();
+    for_each_record(&mut reader, layout.width, opts, None, |record, position| {
+        push_record(
+            &mut columns,
+            &layout,
+            record,
+            position.line(),
+            position.byte(),
+        )?;
+        let Some(limit) = opts.spill_discrete_over else {
+            return Ok(());
+        };
+        let failed = |e: io::Error| NodFrameError::WriteFailed {
+            row: position.line() as usize,
+            message: format!("spilling failed: {}", e),
+        };
+        for (c, column) in columns.iter_mut().enumerate() {
+            let CsvColumn::Discrete {
+                values, interner, ..
+            } = column
+            else {
+                continue;
+            };
+            if held_bytes(values, interner) > limit {
+                let writer = match &mut writers[c] {
+                    Some(writer) => writer,
+                    empty => empty.insert(SpillWriter::create().map_err(failed)?),
+                };
+                for cell in values.drain(..) {
+                    writer.push(&cell).map_err(failed)?;
+                }
+                *interner = Interner::default();
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut frame_columns = Vec::with_capacity(columns.len());
+    for ((column, writer), key) in columns.into_iter().zip(writers).zip(layout.header.iter()) {
+        frame_columns.push(match (column, writer) {
+            (CsvColumn::Discrete { values, .. }, Some(mut writer)) => {
+                for cell in values.iter() {
+                    writer.push(cell)?;
+                }
+                SpillColumn::Spilled(writer.finish(key)?)
+            }
+            (column, _) => SpillColumn::Resident(column.into_column(key)),
+        });
+    }
+    Ok(SpilledFrame::new(frame_columns, opts.spill_discrete_over)?)
+}
+
+#[cfg(test)]
+mod spill_tests {
+    use crate::test_support::temp_csv;
+    use crate::{
+        frame_from_csv, frame_from_csv_spilled, Comp, CsvOptions, FilterValue, NodFrame,
+        SpillColumn,
+    };
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    // tokens_csv writes rows of a unique token, one of five cities and a score
+    fn tokens_csv(name: &str, rows: usize) -> PathBuf {
+        let cities = ["Oslo", "Bergen", "Tromsø", "Stavanger", "Bodø"];
+        let mut text = String::from("id,token,city,score\n");
+        for i in 0..rows {
+            let token = format!(
+                "tok-{:016x}",
+                (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            );
+            // every thousandth token repeats so counts are not all one
+            let token = if i % 1000 == 999 {
+                String::from("tok-repeat")
+            } else {
+                token
+            };
+            let city = cities[(i * 7) % cities.len()];
+            text.push_str(&format!(
+                "{},{},{},{}\n",
+                i,
+                token,
+                city,
+                (i % 97) as f64 / 4.0
+            ));
+        }
+        temp_csv(name, text)
+    }
+
+    #[test]
+    fn spill_matches_memory_test() {
+        let path = tokens_csv("spill.csv", 20_000);
+        let plain: NodFrame<f64> = frame_from_csv(&path).unwrap();
+        // token cells take about 52 bytes each, city rows 16 bytes as they
+        // share five names
+        let opts = CsvOptions::default().spill_discrete_over(900_000);
+        let spilled = frame_from_csv_spilled::<f64>(&path, &opts).unwrap();
+        assert_eq!(spilled.shape(), plain.shape());
+        assert_eq!(spilled.spilled_keys(), vec!["token"]);
+
+        for row in [0, 1, 4095, 4096, 12_345, 19_999, 0, 4096] {
+            for key in ["id", "token", "city"] {
+                assert_eq!(spilled.at(key, row).unwrap(), plain.at(key, row));
+            }
+        }
+        assert_eq!(spilled.at("token", 20_000).unwrap(), None);
+
+        let token = match spilled.column("token").unwrap() {
+            SpillColumn::Spilled(s) => s,
+            _ => unreachable!(),
+        };
+        let plain_token = plain.discrete_column("token").unwrap();
+        assert_eq!(token.value_counts().unwrap(), plain_token.value_counts());
+        assert_eq!(
+            token.value_counts().unwrap()[0],
+            (String::from("tok-repeat"), 20)
+        );
+
+        for (key, value) in [("token", "tok-repeat"), ("city", "Bodø")] {
+            let value = FilterValue::Str(String::from(value));
+            let filtered = spilled.filter(key, Comp::Eq, &value).unwrap();
+            assert!(filtered.spilled_keys().is_empty());
+            let expected = plain.filter(key, Comp::Eq, value).unwrap();
+            assert_eq!(filtered.load().unwrap().to_string(), expected.to_string());
+        }
+        for key in ["token", "city"] {
+            let value = FilterValue::Str(String::from("tok-repeat"));
+            let others = spilled.filter_mask(key, Comp::Not, &value).unwrap();
+            let expected = plain.mask_where(key, Comp::Not, None, Some(String::from("tok-repeat")));
+            assert_eq!(others.as_slice(), expected.unwrap().as_slice());
+            let err = spilled.filter_mask(key, Comp::Gra, &value).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("column '{}' has no level order for Gra, see set_order", key)
+            );
+        }
+        // keeping most rows spills the kept tokens again
+        let most = spilled
+            .filter("score", Comp::Geq, &FilterValue::Num(1.0))
+            .unwrap();
+        assert_eq!(most.spilled_keys(), vec!["token"]);
+        let expected = plain
+            .filter("score", Comp::Geq, FilterValue::Num(1.0))
+            .unwrap();
+        assert_eq!(most.load().unwrap().to_string(), expected.to_string());
+        assert!(matches!(
+            token.binary_view::<f64>(&[true, false, true], Some(900_000)),
+            Ok(SpillColumn::Resident(_))
+        ));
+
+        assert_eq!(spilled.load().unwrap().to_string(), plain.to_string());
+        let unspilled = frame_from_csv_spilled::<f64>(&path, &CsvOptions::default()).unwrap();
+        assert!(unspilled.spilled_keys().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn spill_memory_bounded_test() {
+        let rows = 200_000;
+        let path = tokens_csv("spill_bounded.csv", rows);
+        // city repeats five values, its rows cost a pointer each and stay
+        // under the threshold, while every token is a new string
+        let limit = 4 * 1024 * 1024;
+        let opts = CsvOptions::default().spill_discrete_over(limit);
+        let spilled = frame_from_csv_spilled::<f64>(&path, &opts).unwrap();
+        let plain: NodFrame<f64> = frame_from_csv(&path).unwrap();
+        assert_eq!(spilled.spilled_keys(), vec!["token"]);
+        let bytes = |report: crate::MemoryReport, key: &str| -> usize {
+            report
+                .columns()
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, bytes)| *bytes)
+                .unwrap()
+        };
+        assert_eq!(
+            bytes(spilled.memory_usage(), "city"),
+            bytes(plain.memory_usage(), "city")
+        );
+        assert!(bytes(plain.memory_usage(), "city") < limit);
+        // a spilled column keeps one offset per row and at most a few blocks
+        let token = spilled.column("token").unwrap();
+        assert!(token.memory_usage() <= 24 + rows * 8);
+        assert!(bytes(spilled.memory_usage(), "token") * 4 < bytes(plain.memory_usage(), "token"));
+
+        let spill_file = match token {
+            SpillColumn::Spilled(s) => s.path().to_path_buf(),
+            _ => unreachable!(),
+        };
+        assert!(spill_file.exists());
+        drop(spilled);
+        assert!(!spill_file.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn spill_file_taken_test() {
+        // squat on the next few spill paths, create must not open them
+        let next = super::SPILL_FILES.load(std::sync::atomic::Ordering::Relaxed);
+        // another test may already hold one of them, so only new files count
+        let squatted: Vec<PathBuf> = (next..next + 4)
+            .map(|n| {
+                std::env::temp_dir().join(format!("nodframe_spill_{}_{}", std::process::id(), n))
+            })
+            .filter(|path| {
+                let file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path);
+                file.and_then(|mut f| f.write_all(b"taken")).is_ok()
+            })
+            .collect();
+        let mut writer = super::SpillWriter::create().unwrap();
+        writer.push("mine").unwrap();
+        let column = writer.finish("v").unwrap();
+        assert!(!squatted.iter().any(|p| p == column.path()));
+        assert_eq!(column.get(0).unwrap(), "mine");
+        for path in squatted {
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "taken");
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// SQLite support, enabled with the "sqlite" feature.
+//
+// Query results map INTEGER and REAL columns to numeric columns and TEXT
+// columns to discrete ones. nodframe has no missing values yet, so NULL cells
+// and BLOB columns are reported as errors.
+use crate::{Column, NodFrame, NodFrameBuilder, NodFrameError, NodNum};
+use num_traits::ToPrimitive;
+use rusqlite::types::ValueRef;
+use rusqlite::{params_from_iter, Connection};
+use std::error::Error;
+
+// IfExists decides what to_sql does when the table is already present
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IfExists {
+    Fail,
+    Replace,
+    Append,
+}
+
+// quote_ident quotes a table or column name for use in SQL
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+enum SqlColumn<T> {
+    Numeric(Vec<T>),
+    Text(Vec<String>),
+}
+
+// frame_from_sql runs query and builds a frame from its result set
+pub fn frame_from_sql<T: NodNum>(
+    conn: &Connection,
+    query: &str,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    let mut stmt = conn.prepare(query)?;
+    let keys: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+    let mut data: Vec<Option<SqlColumn<T>>> = keys.iter().map(|_| None).collect();
+    let mut rows = stmt.query([])?;
+    let mut row_idx = 0;
+    while let Some(row) = rows.next()? {
+        for (c, key) in keys.iter().enumerate() {
+            let invalid = |message: &str| NodFrameError::InvalidValue {
+                key: key.clone(),
+                row: row_idx,
+                message: message.to_string(),
+            };
+            let value = row.get_ref(c)?;
+            let column = data[c].get_or_insert_with(|| match value {
+                ValueRef::Text(_) => SqlColumn::Text(Vec::new()),
+                _ => SqlColumn::Numeric(Vec::new()),
+            });
+            match (column, value) {
+                (SqlColumn::Numeric(v), ValueRef::Integer(i)) => {
+                    v.push(T::from(i).ok_or_else(|| NodFrameError::ValueConversion {
+                        key: key.clone(),
+                        row: row_idx,
+                    })?)
+                }
+                (SqlColumn::Numeric(v), ValueRef::Real(f)) => {
+                    let n = T::from(f)
+                        .filter(|n| n.to_f64() == Some(f))
+                        .ok_or_else(|| NodFrameError::ValueConversion {
+                            key: key.clone(),
+                            row: row_idx,
+                        })?;
+                    v.push(n)
+                }
+                (SqlColumn::Text(v), ValueRef::Text(t)) => {
+                    v.push(String::from_utf8_lossy(t).into_owned())
+                }
+                (_, ValueRef::Null) => {
+                    return Err(Box::new(invalid("NULL values are not supported")))
+                }
+                (_, ValueRef::Blob(_)) => {
+                    return Err(Box::new(invalid("BLOB values are not supported")))
+                }
+                (_, _) => return Err(Box::new(invalid("column mixes text and numbers"))),
+            }
+        }
+        row_idx += 1;
+    }
+
+    let mut builder = NodFrameBuilder::new();
+    for (key, column) in keys.iter().zip(data) {
+        builder = match column {
+            Some(SqlColumn::Numeric(v)) => builder.add_numeric(key, v),
+            Some(SqlColumn::Text(v)) => builder.add_discrete(key, v),
+            // an empty result has no values to take a kind from
+            None => builder.add_discrete(key, Vec::new()),
+        };
+    }
+    Ok(builder.build()?)
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // to_sql writes the frame to table inside a single transaction. Numeric
+    // columns are created as INTEGER when every value is integral and REAL
+    // otherwise, discrete columns as TEXT
+    pub fn to_sql(
+        &self,
+        conn: &Connection,
+        table: &str,
+        if_exists: IfExists,
+    ) -> Result<(), Box<dyn Error>> {
+        let tx = conn.unchecked_transaction()?;
+        let exists: i64 = tx.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |r| r.get(0),
+        )?;
+        let table_sql = quote_ident(table);
+        if exists > 0 {
+            match if_exists {
+                IfExists::Fail => {
+                    return Err(Box::new(NodFrameError::InvalidArgument(format!(
+                        "table '{}' already exists",
+                        table
+                    ))))
+                }
+                IfExists::Replace => {
+                    tx.execute(&format!("DROP TABLE {}", table_sql), [])?;
+                }
+                IfExists::Append => {}
+            }
+        }
+        if exists == 0 || if_exists == IfExists::Replace {
+            let defs: Vec<String> = self
+                .columns()
+                .iter()
+                .map(|c| {
+                    let kind = match c {
+                        Column::Numeric(n) if n.iter().all(|v| integral(v).is_some()) => "INTEGER",
+                        Column::Numeric(_) => "REAL",
+                        Column::Discrete(_) => "TEXT",
+                    };
+                    format!("{} {}", quote_ident(c.get_key()), kind)
+                })
+                .collect();
+            tx.execute(
+                &format!("CREATE TABLE {} ({})", table_sql, defs.join(", ")),
+                [],
+            )?;
+        }
+
+        let names: Vec<String> = self
+            .columns()
+            .iter()
+            .map(|c| quote_ident(c.get_key()))
+            .collect();
+        let marks: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table_sql,
+                names.join(", "),
+                marks.join(", ")
+            ))?;
+            for i in 0..self.shape().0 {
+                let values = self.columns().iter().map(|c| match c {
+                    Column::Numeric(n) => match integral(n.get(i)) {
+                        Some(v) => rusqlite::types::Value::Integer(v),
+                        None => rusqlite::types::Value::Real(n.get(i).to_f64().unwrap_or(f64::NAN)),
+                    },
+                    Column::Discrete(d) => rusqlite::types::Value::Text(String::from(d.get(i))),
+                });
+                insert.execute(params_from_iter(values))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+// integral returns the value as i64 when it has no fractional part
+fn integral<T: ToPrimitive>(v: &T) -> Option<i64> {
+    match v.to_i64() {
+        Some(i) if v.to_f64() == Some(i as f64) => Some(i),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod sqlite_tests {
+    use super::*;
+    use crate::Cell;
+
+    fn sample() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_discrete(
+                "name \"quoted\"",
+                vec![String::from("a"), String::from("b")],
+            )
+            .add_numeric("value", vec![1, -2])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn sql_round_trip_test() {
+        let conn = Connection::open_in_memory().unwrap();
+        let frame = sample();
+        frame.to_sql(&conn, "my table", IfExists::Fail).unwrap();
+        let back: NodFrame<i64> = frame_from_sql(&conn, "SELECT * FROM \"my table\"").unwrap();
+        assert_eq!(back.to_string(), frame.to_string());
+    }
+
+    #[test]
+    fn sql_if_exists_test() {
+        let conn = Connection::open_in_memory().unwrap();
+        let frame = sample();
+        frame.to_sql(&conn, "t", IfExists::Fail).unwrap();
+        assert!(frame.to_sql(&conn, "t", IfExists::Fail).is_err());
+        frame.to_sql(&conn, "t", IfExists::Append).unwrap();
+        let back: NodFrame<i64> = frame_from_sql(&conn, "SELECT * FROM t").unwrap();
+        assert_eq!(back.shape(), (4, 2));
+        frame.to_sql(&conn, "t", IfExists::Replace).unwrap();
+        let back: NodFrame<i64> = frame_from_sql(&conn, "SELECT * FROM t").unwrap();
+        assert_eq!(back.shape(), (2, 2));
+    }
+
+    #[test]
+    fn sql_types_test() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE t (i INTEGER, r REAL, s TEXT);
+             INSERT INTO t VALUES (1, 2.0, 'x'), (3, 4.0, 'y');
+             CREATE TABLE n (i INTEGER); INSERT INTO n VALUES (NULL);
+             CREATE TABLE f (r REAL); INSERT INTO f VALUES (1.5);",
+        )
+        .unwrap();
+        let frame: NodFrame<i64> = frame_from_sql(&conn, "SELECT * FROM t").unwrap();
+        assert_eq!(frame.at("r", 1), Some(Cell::Num(4)));
+        assert_eq!(frame.at("s", 0), Some(Cell::Str(String::from("x"))));
+        let err = frame_from_sql::<i64>(&conn, "SELECT * FROM n").unwrap_err();
+        assert!(err.to_string().contains("NULL"));
+        assert!(frame_from_sql::<i64>(&conn, "SELECT * FROM f").is_err());
+    }
+}
@@ -0,0 +1,477 @@
+// Summary statistics of numeric columns.
+//
+// ColumnStats holds count, sum, min, max, mean and the second to fourth
+// central moments, working in f64. A column is summarized by from_values in
+// blocks of BLOCK values: each block takes two passes, one for the sum and
+// mean and one for the moments about that mean, and the blocks merge with
+// Chan's formula and its higher-moment counterparts. push adds single values
+// with Welford's update as extended by Pébay, for values that arrive one at a
+// time. Either way the result matches one exact pass up to rounding.
+use crate::{to_f64, Column, NodFrame, NodFrameBuilder, NodFrameError, NodNum, NumericColumn};
+use std::fmt;
+
+const BLOCK: usize = 1024;
+
+// ColumnStats summarizes the values pushed into it, NaN values are skipped
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ColumnStats {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: f64,
+    // sums of the second, third and fourth powers of differences from the mean
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl ColumnStats {
+    pub fn new() -> ColumnStats {
+        ColumnStats::default()
+    }
+
+    pub fn push(&mut self, x: f64) {
+        if x.is_nan() {
+            return;
+        }
+        self.count += 1;
+        self.sum += x;
+        self.min = Some(self.min.map_or(x, |m| m.min(x)));
+        self.max = Some(self.max.map_or(x, |m| m.max(x)));
+        let n = self.count as f64;
+        let delta = x - self.mean;
+        let delta_n = delta / n;
+        let term = delta * delta_n * (n - 1.0);
+        self.mean += delta_n;
+        // m4 and m3 are updated from the previous m3 and m2
+        self.m4 += term * delta_n * delta_n * (n * n - 3.0 * n + 3.0)
+            + 6.0 * delta_n * delta_n * self.m2
+            - 4.0 * delta_n * self.m3;
+        self.m3 += term * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term;
+    }
+
+    // from_values summarizes values in blocks small enough to stay in cache:
+    // each block's stats are exact from two quick passes over the block and
+    // the blocks merge, which is much faster than pushing value by value
+    pub fn from_values<T: num_traits::ToPrimitive>(values: &[T]) -> ColumnStats {
+        let mut block = Vec::with_capacity(BLOCK);
+        values
+            .chunks(BLOCK)
+            .fold(ColumnStats::new(), |stats, chunk| {
+                block.clear();
+                block.extend(chunk.iter().map(to_f64).filter(|x| !x.is_nan()));
+                stats.merge(&ColumnStats::from_block(&block))
+            })
+    }
+
+    fn from_block(block: &[f64]) -> ColumnStats {
+        if block.is_empty() {
+            return ColumnStats::new();
+        }
+        let (mut sum, mut min, mut max) = (0.0, block[0], block[0]);
+        for x in block {
+            sum += x;
+            if *x < min {
+                min = *x;
+            }
+            if *x > max {
+                max = *x;
+            }
+        }
+        let mean = sum / block.len() as f64;
+        let (mut m2, mut m3, mut m4) = (0.0, 0.0, 0.0);
+        for x in block {
+            let d = x - mean;
+            let d2 = d * d;
+            m2 += d2;
+            m3 += d2 * d;
+            m4 += d2 * d2;
+        }
+        ColumnStats {
+            count: block.len(),
+            sum,
+            min: Some(min),
+            max: Some(max),
+            mean,
+            m2,
+            m3,
+            m4,
+        }
+    }
+
+    // merge combines the stats of two disjoint parts into the stats of both
+    pub fn merge(&self, other: &ColumnStats) -> ColumnStats {
+        if self.count == 0 {
+            return *other;
+        }
+        if other.count == 0 {
+            return *self;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let (n, m) = (self.count as f64, other.count as f64);
+        let total = count as f64;
+        let delta2 = delta * delta;
+        ColumnStats {
+            count,
+            sum: self.sum + other.sum,
+            min: self.min.zip(other.min).map(|(a, b)| a.min(b)),
+            max: self.max.zip(other.max).map(|(a, b)| a.max(b)),
+            mean: self.mean + delta * m / total,
+            m2: self.m2 + other.m2 + delta2 * n * m / total,
+            m3: self.m3
+                + other.m3
+                + delta2 * delta * n * m * (n - m) / (total * total)
+                + 3.0 * delta * (n * other.m2 - m * self.m2) / total,
+            m4: self.m4
+                + other.m4
+                + delta2 * delta2 * n * m * (n * n - n * m + m * m) / (total * total * total)
+                + 6.0 * delta2 * (n * n * other.m2 + m * m * self.m2) / (total * total)
+                + 4.0 * delta * (n * other.m3 - m * self.m3) / total,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    // variance is the sample variance, None below two values
+    pub fn variance(&self) -> Option<f64> {
+        (self.count > 1).then(|| self.m2 / (self.count - 1) as f64)
+    }
+
+    pub fn std(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+
+    // skewness is the biased sample skewness m3 / m2^1.5 of the central
+    // moments, as scipy.stats.skew computes by default. None below three
+    // values or without spread
+    pub fn skewness(&self) -> Option<f64> {
+        let n = self.count as f64;
+        (self.count > 2 && self.m2 > 0.0).then(|| n.sqrt() * self.m3 / self.m2.powf(1.5))
+    }
+
+    // kurtosis is the biased excess kurtosis m4 / m2^2 - 3 (Fisher), as
+    // scipy.stats.kurtosis computes by default, so a normal sample gives about
+    // 0. None below four values or without spread
+    pub fn kurtosis(&self) -> Option<f64> {
+        let n = self.count as f64;
+        (self.count > 3 && self.m2 > 0.0).then(|| n * self.m4 / (self.m2 * self.m2) - 3.0)
+    }
+}
+
+impl fmt::Display for ColumnStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "count: {}, sum: {}", self.count, self.sum)?;
+        let fields = [
+            ("mean", self.mean()),
+            ("std", self.std()),
+            ("min", self.min),
+            ("max", self.max),
+            ("skewness", self.skewness()),
+            ("kurtosis", self.kurtosis()),
+        ];
+        for (name, value) in fields {
+            if let Some(v) = value {
+                write!(f, ", {}: {}", name, v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// OutlierMethod decides which values flag_outliers marks. Every bound is
+// strict, so a value exactly at a threshold is not an outlier:
+// ZScore(t) marks |x - mean| / std > t with the sample std, and marks nothing
+// in a column without spread. Iqr(k) marks values outside
+// [q1 - k * iqr, q3 + k * iqr] with the quartiles from NumericColumn::quantile,
+// which ranks the values other than NaN.
+// Absolute marks values outside [low, high]. NaN is never an outlier
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutlierMethod {
+    ZScore(f64),
+    Iqr(f64),
+    Absolute { low: f64, high: f64 },
+}
+
+impl<T: num_traits::ToPrimitive> NumericColumn<T> {
+    // stats summarizes the column in one pass, see ColumnStats
+    pub fn stats(&self) -> ColumnStats {
+        ColumnStats::from_values(self.as_slice())
+    }
+}
+
+impl<T: NodNum> NumericColumn<T> {
+    // outliers marks the values method counts as outliers, see OutlierMethod
+    pub fn outliers(&self, method: OutlierMethod) -> Vec<bool> {
+        let (low, high) = match method {
+            OutlierMethod::ZScore(t) => {
+                let stats = self.stats();
+                match (stats.mean(), stats.std()) {
+                    (Some(mean), Some(std)) if std > 0.0 => (mean - t * std, mean + t * std),
+                    _ => return vec![false; self.as_slice().len()],
+                }
+            }
+            OutlierMethod::Iqr(k) => match (self.quantile(0.25), self.quantile(0.75)) {
+                (Some(q1), Some(q3)) => {
+                    let (q1, q3) = (to_f64(&q1), to_f64(&q3));
+                    (q1 - k * (q3 - q1), q3 + k * (q3 - q1))
+                }
+                _ => return vec![false; self.as_slice().len()],
+            },
+            OutlierMethod::Absolute { low, high } => (low, high),
+        };
+        self.iter()
+            .map(|x| {
+                let x = to_f64(x);
+                x < low || x > high
+            })
+            .collect()
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // flag_outliers appends new_key holding 1 where the value of key is an
+    // outlier by method and 0 elsewhere, see OutlierMethod
+    pub fn flag_outliers(
+        &self,
+        key: &str,
+        method: OutlierMethod,
+        new_key: &str,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let flags = self.numeric_column(key)?.outliers(method);
+        let (zero, one) = (T::from(0).unwrap(), T::from(1).unwrap());
+        let out = self
+            .columns()
+            .iter()
+            .fold(NodFrameBuilder::new(), |b, c| b.add_column(c.clone()))
+            .add_numeric(
+                new_key,
+                flags
+                    .iter()
+                    .map(|f| if *f { one.clone() } else { zero.clone() })
+                    .collect(),
+            )
+            .build()?;
+        Ok(self.traced(out, "flag_outliers", || {
+            vec![
+                ("key", String::from(key)),
+                ("method", format!("{:?}", method)),
+                ("new_key", String::from(new_key)),
+            ]
+        }))
+    }
+
+    // drop_outliers keeps the rows flag_outliers would not flag
+    pub fn drop_outliers(
+        &self,
+        key: &str,
+        method: OutlierMethod,
+    ) -> Result<NodFrame<T>, NodFrameError> {
+        let flags = self.numeric_column(key)?.outliers(method);
+        let kept = self.pick_rows(&flags.iter().map(|f| !f).collect::<Vec<bool>>());
+        Ok(self.traced(kept, "drop_outliers", || {
+            vec![
+                ("key", String::from(key)),
+                ("method", format!("{:?}", method)),
+            ]
+        }))
+    }
+
+    // skewness is the skewness of the numeric column key, see
+    // ColumnStats::skewness
+    pub fn skewness(&self, key: &str) -> Result<Option<f64>, NodFrameError> {
+        Ok(self.numeric_column(key)?.stats().skewness())
+    }
+
+    // kurtosis is the excess kurtosis of the numeric column key, see
+    // ColumnStats::kurtosis
+    pub fn kurtosis(&self, key: &str) -> Result<Option<f64>, NodFrameError> {
+        Ok(self.numeric_column(key)?.stats().kurtosis())
+    }
+
+    // describe returns the stats of every numeric column in frame order,
+    // skewness and kurtosis included where the column has enough values
+    pub fn describe(&self) -> Vec<(String, ColumnStats)> {
+        self.columns()
+            .iter()
+            .filter_map(|c| match c {
+                Column::Numeric(n) => Some((n.key().to_string(), n.stats())),
+                Column::Discrete(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::{assert_valid, ColumnStats, NodFrameBuilder, NumericColumn, OutlierMethod};
+
+    #[test]
+    fn stats_test() {
+        let stats = NumericColumn::new("v", vec![2, 4, 4, 4, 5, 5, 7, 9]).stats();
+        assert_eq!(stats.count(), 8);
+        assert_eq!(stats.sum(), 40.0);
+        assert_eq!(stats.mean(), Some(5.0));
+        assert_eq!((stats.min(), stats.max()), (Some(2.0), Some(9.0)));
+        assert!((stats.variance().unwrap() - 32.0 / 7.0).abs() < 1e-12);
+        assert_eq!(ColumnStats::new().mean(), None);
+        assert_eq!(ColumnStats::new().to_string(), "count: 0, sum: 0");
+        let one = NumericColumn::new("v", vec![3]).stats();
+        assert_eq!(one.variance(), None);
+        assert_eq!(one.to_string(), "count: 1, sum: 3, mean: 3, min: 3, max: 3");
+
+        let floats = NumericColumn::new("f", vec![1.5, f64::NAN, 2.5]).stats();
+        assert_eq!(floats.count(), 2);
+        assert_eq!(floats.mean(), Some(2.0));
+
+        let frame = NodFrameBuilder::new()
+            .add_numeric("a", vec![1, 2, 3])
+            .add_discrete("s", vec![String::new(); 3])
+            .add_numeric("b", vec![10, 10, 10])
+            .build()
+            .unwrap();
+        let described = frame.describe();
+        assert_eq!(described.len(), 2);
+        assert_eq!(described[1].0, "b");
+        assert_eq!(
+            described[1].1.to_string(),
+            "count: 3, sum: 30, mean: 10, std: 0, min: 10, max: 10"
+        );
+    }
+
+    #[test]
+    fn merge_test() {
+        let values: Vec<i64> = (0..10_007).map(|i| (i * 7919) % 1_000 - 300).collect();
+        let whole = NumericColumn::new("v", values.clone()).stats();
+        let merged = values
+            .chunks(999)
+            .map(|c| NumericColumn::new("v", c.to_vec()).stats())
+            .fold(ColumnStats::new(), |acc, s| acc.merge(&s));
+        assert_eq!(merged.count(), whole.count());
+        assert_eq!(merged.sum(), whole.sum());
+        assert_eq!((merged.min(), merged.max()), (whole.min(), whole.max()));
+        assert!((merged.mean().unwrap() - whole.mean().unwrap()).abs() < 1e-9);
+        assert!((merged.variance().unwrap() - whole.variance().unwrap()).abs() < 1e-9);
+        assert_eq!(whole.merge(&ColumnStats::new()), whole);
+        let (s, k) = (whole.skewness().unwrap(), whole.kurtosis().unwrap());
+        assert!((merged.skewness().unwrap() - s).abs() < 1e-9);
+        assert!((merged.kurtosis().unwrap() - k).abs() < 1e-9);
+        // pushing value by value takes the online update instead of blocks
+        let mut pushed = ColumnStats::new();
+        values.iter().for_each(|x| pushed.push(*x as f64));
+        assert!((pushed.skewness().unwrap() - s).abs() < 1e-9);
+        assert!((pushed.kurtosis().unwrap() - k).abs() < 1e-9);
+    }
+
+    #[test]
+    fn moments_test() {
+        // reference values from scipy.stats.skew and scipy.stats.kurtosis
+        let data = vec![1.0, 2.0, 2.0, 3.0, 3.0, 3.0, 4.0, 10.0, 15.5, -2.0];
+        let stats = NumericColumn::new("v", data.clone()).stats();
+        assert!((stats.skewness().unwrap() - 1.2958403115564199).abs() < 1e-9);
+        assert!((stats.kurtosis().unwrap() - 0.8542488566027684).abs() < 1e-9);
+        let even = NumericColumn::new("v", vec![2, 4, 4, 4, 5, 5, 7, 9]).stats();
+        assert!((even.skewness().unwrap() - 0.65625).abs() < 1e-9);
+        assert!((even.kurtosis().unwrap() + 0.21875).abs() < 1e-9);
+        let merged = [&data[..3], &data[3..4], &data[4..]]
+            .iter()
+            .map(|c| NumericColumn::new("v", c.to_vec()).stats())
+            .fold(ColumnStats::new(), |acc, s| acc.merge(&s));
+        assert!((merged.skewness().unwrap() - 1.2958403115564199).abs() < 1e-9);
+        assert!((merged.kurtosis().unwrap() - 0.8542488566027684).abs() < 1e-9);
+
+        let three = NumericColumn::new("v", vec![1, 2, 6]).stats();
+        assert!(three.skewness().is_some());
+        assert_eq!(three.kurtosis(), None);
+        assert_eq!(NumericColumn::new("v", vec![1, 2]).stats().skewness(), None);
+        let flat = NumericColumn::new("v", vec![3, 3, 3, 3, 3]).stats();
+        assert_eq!((flat.skewness(), flat.kurtosis()), (None, None));
+
+        let frame = NodFrameBuilder::new()
+            .add_numeric("v", vec![2, 4, 4, 4, 5, 5, 7, 9])
+            .add_discrete("s", vec![String::new(); 8])
+            .build()
+            .unwrap();
+        assert_eq!(frame.skewness("v").unwrap(), even.skewness());
+        assert_eq!(frame.kurtosis("v").unwrap(), even.kurtosis());
+        assert!(frame.skewness("s").is_err());
+        assert!(frame.describe()[0]
+            .1
+            .to_string()
+            .ends_with("skewness: 0.65625, kurtosis: -0.21875"));
+    }
+
+    #[test]
+    fn outliers_test() {
+        // mean 5 and sample std 2: 1 and 9 sit exactly at |z| = 2
+        let col = NumericColumn::new("v", vec![5, 5, 1, 5, 5, 9, 5, 5, 5]);
+        let stats = col.stats();
+        assert_eq!((stats.mean(), stats.std()), (Some(5.0), Some(2.0)));
+        assert!(!col.outliers(OutlierMethod::ZScore(2.0)).contains(&true));
+        assert_eq!(
+            col.outliers(OutlierMethod::ZScore(1.5)),
+            vec![false, false, true, false, false, true, false, false, false]
+        );
+        let flat = NumericColumn::new("v", vec![4, 4, 4]);
+        assert_eq!(flat.outliers(OutlierMethod::ZScore(0.0)), vec![false; 3]);
+
+        // nearest-rank quartiles 3 and 7, so the fences are -3 and 13 for k = 1.5
+        let skewed = NumericColumn::new("v", vec![3, 13, 5, 14, -3, 7, 4, -4, 6]);
+        assert_eq!(
+            skewed.outliers(OutlierMethod::Iqr(1.5)),
+            vec![false, false, false, true, false, false, false, true, false]
+        );
+        // a NaN does not move the quartiles, so 1000 is still flagged
+        let mut gaps: Vec<f64> = (1..=10).map(f64::from).collect();
+        gaps.extend([f64::NAN, 1000.0]);
+        let flags = NumericColumn::new("v", gaps).outliers(OutlierMethod::Iqr(1.5));
+        assert_eq!(flags.iter().filter(|f| **f).count(), 1);
+        assert!(flags[11]);
+        let empty = NumericColumn::new("v", vec![f64::NAN, f64::NAN]);
+        assert_eq!(empty.outliers(OutlierMethod::Iqr(1.5)), vec![false; 2]);
+        let band = OutlierMethod::Absolute {
+            low: 0.0,
+            high: 10.0,
+        };
+        assert_eq!(
+            NumericColumn::new("v", vec![0, 10, 11, -1]).outliers(band),
+            vec![false, false, true, true]
+        );
+
+        let frame = NodFrameBuilder::new()
+            .add_numeric("latency", vec![12, 15, 11, 90, 14])
+            .add_discrete("host", vec![String::from("a"); 5])
+            .build()
+            .unwrap();
+        let method = OutlierMethod::Absolute {
+            low: 0.0,
+            high: 50.0,
+        };
+        let flagged = frame.flag_outliers("latency", method, "outlier").unwrap();
+        assert_eq!(flagged["outlier"].to_string(), "outlier: [0, 0, 0, 1, 0]");
+        let kept = frame.drop_outliers("latency", method).unwrap();
+        assert_valid(&kept);
+        assert_eq!(kept["latency"].to_string(), "latency: [12, 15, 11, 14]");
+        assert!(frame.flag_outliers("host", method, "o").is_err());
+    }
+}
@@ -0,0 +1,439 @@
+// Aggregating csv files without loading them.
+//
+// aggregate_csv and aggregate_csv_total read a file record by record and keep
+// one accumulator per group, so memory grows with the number of groups and
+// not with the number of rows. Rows are chosen and cells parsed as
+// frame_from_csv_with_options would, and the result matches loading the file
+// and aggregating it with group_by.
+use crate::parse::ParseFn;
+use crate::{
+    cmp_nan_last, for_each_csv_cells, is_nan, loses_text, to_f64, AggFn, ColumnStats, CsvOptions,
+    NodFrame, NodFrameBuilder, NodFrameError, NodNum,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+// Group accumulates the values of one group. First and Last keep the row
+// they came from so they survive merging. NaN values count as rows and carry
+// into the sum, as in aggregate, while stats, min and max skip them
+struct Group<T> {
+    stats: ColumnStats,
+    rows: usize,
+    sum: T,
+    min: Option<T>,
+    max: Option<T>,
+    first: Option<(usize, T)>,
+    last: Option<(usize, T)>,
+}
+
+impl<T: NodNum> Group<T> {
+    fn new() -> Group<T> {
+        Group {
+            stats: ColumnStats::new(),
+            rows: 0,
+            sum: T::zero(),
+            min: None,
+            max: None,
+            first: None,
+            last: None,
+        }
+    }
+
+    fn push(&mut self, row: usize, x: T) {
+        self.rows += 1;
+        self.stats.push(to_f64(&x));
+        self.sum = self.sum.clone() + x.clone();
+        if !is_nan(&x) {
+            if self.min.as_ref().is_none_or(|m| x < *m) {
+                self.min = Some(x.clone());
+            }
+            if self.max.as_ref().is_none_or(|m| x > *m) {
+                self.max = Some(x.clone());
+            }
+        }
+        self.first.get_or_insert_with(|| (row, x.clone()));
+        self.last = Some((row, x));
+    }
+
+    // merge combines the groups of two key texts that load as the same value,
+    // such as "1" and "1.0" in a numeric column
+    fn merge(self, other: Group<T>) -> Group<T> {
+        let pick = |a: Option<T>, b: Option<T>, keep_a: fn(&T, &T) -> bool| match (a, b) {
+            (Some(a), Some(b)) if !keep_a(&a, &b) => Some(b),
+            (a, b) => a.or(b),
+        };
+        let by_row = |a: Option<(usize, T)>,
+                      b: Option<(usize, T)>,
+                      keep_a: fn(usize, usize) -> bool| match (a, b) {
+            (Some(a), Some(b)) if !keep_a(a.0, b.0) => Some(b),
+            (a, b) => a.or(b),
+        };
+        Group {
+            stats: self.stats.merge(&other.stats),
+            rows: self.rows + other.rows,
+            sum: self.sum + other.sum,
+            min: pick(self.min, other.min, |a, b| a <= b),
+            max: pick(self.max, other.max, |a, b| a >= b),
+            first: by_row(self.first, other.first, |a, b| a < b),
+            last: by_row(self.last, other.last, |a, b| a > b),
+        }
+    }
+
+    // value reduces the group with agg, like aggregate does for a loaded
+    // column: a NaN makes the mean NaN, and the minimum and maximum are NaN
+    // when the first value is
+    fn value(&self, agg: AggFn) -> Option<T> {
+        let nan = || T::from(f64::NAN);
+        let first_nan = self.first.as_ref().is_none_or(|f| is_nan(&f.1));
+        match agg {
+            AggFn::Sum => Some(self.sum.clone()),
+            AggFn::Mean if self.stats.count() < self.rows => nan(),
+            AggFn::Mean => T::from(self.stats.sum() / self.rows as f64),
+            AggFn::Min if first_nan => nan(),
+            AggFn::Min => self.min.clone(),
+            AggFn::Max if first_nan => nan(),
+            AggFn::Max => self.max.clone(),
+            AggFn::First => self.first.as_ref().map(|f| f.1.clone()),
+            AggFn::Last => self.last.as_ref().map(|l| l.1.clone()),
+            AggFn::Count => T::from(self.rows),
+            AggFn::Std => T::from(self.stats.std().unwrap_or(f64::NAN)),
+        }
+    }
+}
+
+// ValueParser parses the cells of the value column: a registered parser or
+// FromStr for text and na_value for cells in na_strings
+struct ValueParser<T> {
+    key: String,
+    parser: Option<Arc<ParseFn<T>>>,
+    na_value: Option<T>,
+}
+
+impl<T: NodNum> ValueParser<T> {
+    fn new(key: &str, opts: &CsvOptions) -> Result<ValueParser<T>, NodFrameError> {
+        Ok(ValueParser {
+            key: String::from(key),
+            parser: opts.parsers.get::<T>(key)?,
+            na_value: parse_na_value(opts)?,
+        })
+    }
+
+    fn parse(&self, cell: &str, missing: bool, line: u64) -> Result<T, NodFrameError> {
+        let bad = || NodFrameError::ParseError {
+            key: self.key.clone(),
+            line,
+            value: String::from(cell),
+        };
+        match (missing, &self.parser) {
+            (true, _) => self.na_value.clone().ok_or_else(bad),
+            (false, Some(parser)) => parser(cell).map_err(|message| NodFrameError::ParserFailed {
+                key: self.key.clone(),
+                line,
+                value: String::from(cell),
+                message,
+            }),
+            (false, None) => cell.parse::<T>().map_err(|_| bad()),
+        }
+    }
+}
+
+fn parse_na_value<T: NodNum>(opts: &CsvOptions) -> Result<Option<T>, NodFrameError> {
+    match &opts.na_value {
+        Some(text) => Ok(Some(text.parse::<T>().map_err(|_| {
+            NodFrameError::InvalidArgument(format!("na_value '{}' is not numeric", text))
+        })?)),
+        None => Ok(None),
+    }
+}
+
+// aggregate_csv reduces value_col within each group of group_col with agg,
+// reading the file once without building a frame. The result has group_col
+// and value_col with one row per group in the order of group_by, and equals
+// loading the two columns and calling group_by(group_col)?.agg(value_col, agg).
+// group_col is numeric when every key would load as a number, a parser
+// registered for it is not used. value_col must parse on every row read
+pub fn aggregate_csv<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+    group_col: &str,
+    value_col: &str,
+    agg: AggFn,
+) -> Result<NodFrame<T>, Box<dyn Error>> {
+    agg.check::<T>(value_col)?;
+    let values = ValueParser::<T>::new(value_col, opts)?;
+    // keys by their text, cells in na_strings kept apart
+    let mut groups: HashMap<String, Group<T>> = HashMap::new();
+    let mut missing: Option<Group<T>> = None;
+    let mut row = 0;
+    for_each_csv_cells(
+        file_path,
+        [group_col, value_col],
+        opts,
+        |[(key, key_missing), (cell, cell_missing)], line| {
+            let value = values.parse(cell, cell_missing, line)?;
+            let group = if key_missing {
+                missing.get_or_insert_with(Group::new)
+            } else if let Some(group) = groups.get_mut(key) {
+                group
+            } else {
+                groups.entry(String::from(key)).or_insert_with(Group::new)
+            };
+            group.push(row, value);
+            row += 1;
+            Ok(())
+        },
+    )?;
+
+    // a loaded key column is numeric when every cell parses and keeps its
+    // text, see loses_text, cells in na_strings taking na_value. Keys that
+    // load the same, such as "1" and "1.0", then form one group
+    let keyed: Vec<(Option<String>, Group<T>)> = groups
+        .into_iter()
+        .map(|(k, g)| (Some(k), g))
+        .chain(missing.map(|g| (None, g)))
+        .collect();
+    let numbers: Option<Vec<T>> = keyed
+        .iter()
+        .map(|(k, _)| match k {
+            Some(k) if loses_text(k) => None,
+            Some(k) => k.parse::<T>().ok(),
+            None => values.na_value.clone(),
+        })
+        .collect();
+    let builder = NodFrameBuilder::new();
+    let (builder, reduced) = match numbers {
+        Some(numbers) => {
+            let mut merged: HashMap<String, (T, Group<T>)> = HashMap::new();
+            for (n, (_, group)) in numbers.into_iter().zip(keyed) {
+                merge_into(&mut merged, n.to_string(), n, group);
+            }
+            let mut merged: Vec<(T, Group<T>)> = merged.into_values().collect();
+            merged.sort_by(|a, b| cmp_nan_last(&a.0, &b.0));
+            let (keys, groups): (Vec<T>, Vec<Group<T>>) = merged.into_iter().unzip();
+            (builder.add_numeric(group_col, keys), groups)
+        }
+        None => {
+            let mut merged: HashMap<String, (String, Group<T>)> = HashMap::new();
+            for (key, group) in keyed {
+                let key = key.unwrap_or_default();
+                merge_into(&mut merged, key.clone(), key, group);
+            }
+            let mut merged: Vec<(String, Group<T>)> = merged.into_values().collect();
+            merged.sort_by(|a, b| a.0.cmp(&b.0));
+            let (keys, groups): (Vec<String>, Vec<Group<T>>) = merged.into_iter().unzip();
+            (builder.add_discrete(group_col, keys), groups)
+        }
+    };
+    let out = reduced
+        .iter()
+        .enumerate()
+        .map(|(g, group)| {
+            group
+                .value(agg)
+                .ok_or_else(|| NodFrameError::ValueConversion {
+                    key: String::from(value_col),
+                    row: g,
+                })
+        })
+        .collect::<Result<Vec<T>, NodFrameError>>()?;
+    Ok(builder.add_numeric(value_col, out).build()?)
+}
+
+// merge_into adds group under text, merging it with a group already there
+fn merge_into<K, T: NodNum>(
+    merged: &mut HashMap<String, (K, Group<T>)>,
+    text: String,
+    key: K,
+    group: Group<T>,
+) {
+    let group = match merged.remove(&text) {
+        Some((_, earlier)) => earlier.merge(group),
+        None => group,
+    };
+    merged.insert(text, (key, group));
+}
+
+// aggregate_csv_total summarizes value_col over the whole file, reading it
+// once without building a frame. Cells parse as for aggregate_csv
+pub fn aggregate_csv_total<T: NodNum>(
+    file_path: impl AsRef<Path>,
+    opts: &CsvOptions,
+    value_col: &str,
+) -> Result<ColumnStats, Box<dyn Error>> {
+    let values = ValueParser::<T>::new(value_col, opts)?;
+    let mut stats = ColumnStats::new();
+    for_each_csv_cells(file_path, [value_col], opts, |[(cell, missing)], line| {
+        stats.push(to_f64(&values.parse(cell, missing, line)?));
+        Ok(())
+    })?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use crate::test_support::temp_csv;
+    use crate::{
+        aggregate_csv, aggregate_csv_total, frame_from_csv_with_options, AggFn, CsvOptions,
+        NodFrame, NodFrameError, RaggedPolicy,
+    };
+
+    const AGGS: [AggFn; 8] = [
+        AggFn::Sum,
+        AggFn::Mean,
+        AggFn::Min,
+        AggFn::Max,
+        AggFn::First,
+        AggFn::Last,
+        AggFn::Count,
+        AggFn::Std,
+    ];
+
+    #[test]
+    fn aggregate_csv_test() {
+        let mut text = String::from("id,region,amount,store\n");
+        for i in 0..2_000 {
+            let region = ["north", "south", "east", "west", "north-east"][(i * 7) % 5];
+            let amount = ((i * 7919) % 1_000) as f64 / 8.0 - 20.0;
+            text.push_str(&format!("{},{},{},{}\n", i, region, amount, i % 13));
+        }
+        let path = temp_csv("aggregate.csv", &text);
+        let loaded: NodFrame<f64> =
+            frame_from_csv_with_options(&path, &CsvOptions::default()).unwrap();
+        for group_col in ["region", "store"] {
+            let groups = loaded.group_by(group_col).unwrap();
+            for agg in AGGS {
+                let expected = groups.agg("amount", agg).unwrap();
+                let streamed: NodFrame<f64> =
+                    aggregate_csv(&path, &CsvOptions::default(), group_col, "amount", agg).unwrap();
+                assert_eq!(
+                    streamed[group_col].to_string(),
+                    expected[group_col].to_string()
+                );
+                let (a, b) = (
+                    streamed.numeric_column("amount").unwrap(),
+                    expected.numeric_column("amount").unwrap(),
+                );
+                for (x, y) in a.iter().zip(b.iter()) {
+                    assert!(
+                        (x - y).abs() < 1e-9,
+                        "{:?} {}: {} != {}",
+                        agg,
+                        group_col,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+
+        let total = aggregate_csv_total::<f64>(&path, &CsvOptions::default(), "amount").unwrap();
+        let whole = loaded.numeric_column("amount").unwrap().stats();
+        assert_eq!(
+            (total.count(), total.min(), total.max()),
+            (2_000, whole.min(), whole.max())
+        );
+        assert!((total.sum() - whole.sum()).abs() < 1e-9);
+        assert!((total.std().unwrap() - whole.std().unwrap()).abs() < 1e-9);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn aggregate_csv_options_test() {
+        // "1" and "1.0" are the same key once loaded as numbers
+        let path = temp_csv(
+            "aggregate_options.csv",
+            "k,v\n1,10\n2,20\n1.0,5\nNA,7\n3,4,extra\n2,NA\n",
+        );
+        let ragged = aggregate_csv::<i64>(&path, &CsvOptions::default(), "k", "v", AggFn::Sum);
+        assert_eq!(
+            ragged.unwrap_err().downcast_ref::<NodFrameError>(),
+            Some(&NodFrameError::RaggedRow {
+                line: 6,
+                expected: 2,
+                found: 3,
+            })
+        );
+        let skip = CsvOptions {
+            ragged: RaggedPolicy::SkipRow,
+            ..CsvOptions::default()
+        };
+        let unparsed = aggregate_csv::<f64>(&path, &skip, "k", "v", AggFn::Sum).unwrap_err();
+        assert_eq!(
+            unparsed.to_string(),
+            "line 7: value 'NA' in column 'v' is not numeric"
+        );
+
+        let opts = CsvOptions {
+            ragged: RaggedPolicy::SkipRow,
+            na_strings: vec![String::from("NA")],
+            na_value: Some(String::from("0")),
+            ..CsvOptions::default()
+        };
+        let summed: NodFrame<f64> = aggregate_csv(&path, &opts, "k", "v", AggFn::Sum).unwrap();
+        assert_eq!(summed["k"].to_string(), "k: [0, 1, 2]");
+        assert_eq!(summed["v"].to_string(), "v: [7, 15, 20]");
+        let loaded: NodFrame<f64> = frame_from_csv_with_options(&path, &opts).unwrap();
+        let expected = loaded
+            .group_by("k")
+            .unwrap()
+            .agg("v", AggFn::First)
+            .unwrap();
+        let first: NodFrame<f64> = aggregate_csv(&path, &opts, "k", "v", AggFn::First).unwrap();
+        assert_eq!(first.to_string(), expected.to_string());
+
+        let skipped = CsvOptions {
+            skip_rows: 1,
+            max_rows: Some(3),
+            ..opts.clone()
+        };
+        let counted: NodFrame<f64> =
+            aggregate_csv(&path, &skipped, "k", "v", AggFn::Count).unwrap();
+        assert_eq!(counted["k"].to_string(), "k: [0, 1, 2]");
+        assert_eq!(counted["v"].to_string(), "v: [1, 1, 1]");
+        std::fs::remove_file(path).unwrap();
+
+        // without na_value a missing key stays text and the keys discrete
+        let path = temp_csv("aggregate_text_keys.csv", "k,v\n1,1\nNA,2\n1,3\n");
+        let text_keys = CsvOptions {
+            na_strings: vec![String::from("NA")],
+            ..CsvOptions::default()
+        };
+        let loaded: NodFrame<i64> = frame_from_csv_with_options(&path, &text_keys).unwrap();
+        let expected = loaded.group_by("k").unwrap().agg("v", AggFn::Max).unwrap();
+        let max: NodFrame<i64> = aggregate_csv(&path, &text_keys, "k", "v", AggFn::Max).unwrap();
+        assert_eq!(max.to_string(), expected.to_string());
+        assert_eq!(max["k"].to_string(), "k: [, 1]");
+        std::fs::remove_file(path).unwrap();
+
+        // "02139" stays apart from "2139" as the key column loads as text
+        let path = temp_csv("aggregate_zip.csv", "k,v\n02139,1\n10001,2\n2139,3\n");
+        let loaded: NodFrame<i64> =
+            frame_from_csv_with_options(&path, &CsvOptions::default()).unwrap();
+        let expected = loaded.group_by("k").unwrap().agg("v", AggFn::Sum).unwrap();
+        let zips: NodFrame<i64> =
+            aggregate_csv(&path, &CsvOptions::default(), "k", "v", AggFn::Sum).unwrap();
+        assert_eq!(zips.to_string(), expected.to_string());
+        assert_eq!(zips["k"].to_string(), "k: [02139, 10001, 2139]");
+        std::fs::remove_file(path).unwrap();
+
+        // NaN values count and carry into the sum as when loaded
+        let path = temp_csv("aggregate_nan.csv", "k,v\na,1\nb,NA\na,NA\nb,4\n");
+        let nan = CsvOptions {
+            na_strings: vec![String::from("NA")],
+            na_value: Some(String::from("NaN")),
+            ..CsvOptions::default()
+        };
+        let loaded: NodFrame<f64> = frame_from_csv_with_options(&path, &nan).unwrap();
+        let groups = loaded.group_by("k").unwrap();
+        for agg in AGGS {
+            let expected = groups.agg("v", agg).unwrap();
+            let streamed: NodFrame<f64> = aggregate_csv(&path, &nan, "k", "v", agg).unwrap();
+            assert_eq!(streamed.to_string(), expected.to_string(), "{:?}", agg);
+        }
+        let counted: NodFrame<f64> = aggregate_csv(&path, &nan, "k", "v", AggFn::Count).unwrap();
+        assert_eq!(counted["v"].to_string(), "v: [2, 2]");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+// Operation log of a frame, see NodFrame::enable_tracing.
+//
+// A frame with tracing on carries a history of OpRecord entries. Traced
+// operations copy the history of their input into their output and append a
+// record of themselves, so the log follows the data through a pipeline.
+// Frames made from scratch, by the builder, a loader or from several frames
+// as in merge_sorted, start without a history. With tracing off the history
+// is None and nothing is recorded or formatted.
+use crate::{NodFrame, NodNum};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// OpRecord is one traced operation: its name, its parameters as text, the
+// row counts going in and coming out and when it ran
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpRecord {
+    operation: String,
+    params: Vec<(String, String)>,
+    rows_in: usize,
+    rows_out: usize,
+    timestamp: SystemTime,
+}
+
+impl OpRecord {
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    // param returns the value of the named parameter
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn rows_in(&self) -> usize {
+        self.rows_in
+    }
+
+    pub fn rows_out(&self) -> usize {
+        self.rows_out
+    }
+
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+// OpRecord is displayed as the unix time in seconds, the call and the row
+// counts, e.g. "1700000000.123 filter(col=a, comp=Geq, val=5) 5 -> 3 rows"
+impl fmt::Display for OpRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let since = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|(n, v)| format!("{}={}", n, v))
+            .collect();
+        write!(
+            f,
+            "{}.{:03} {}({}) {} -> {} rows",
+            since.as_secs(),
+            since.subsec_millis(),
+            self.operation,
+            params.join(", "),
+            self.rows_in,
+            self.rows_out
+        )
+    }
+}
+
+impl<T: NodNum> NodFrame<T> {
+    // enable_tracing starts recording traced operations on this frame and
+    // the frames derived from it, an existing history is kept
+    pub fn enable_tracing(&mut self) {
+        self.history.get_or_insert_with(Vec::new);
+    }
+
+    // disable_tracing stops recording and drops the history
+    pub fn disable_tracing(&mut self) {
+        self.history = None;
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.history.is_some()
+    }
+
+    // history lists the traced operations that produced this frame, oldest
+    // first, empty when tracing is off
+    pub fn history(&self) -> &[OpRecord] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    // traced gives out the history of self followed by a record of operation.
+    // params is only called when tracing is on. Whatever history out picked
+    // up from operations used inside operation is replaced, so composed
+    // operations record only themselves
+    pub(crate) fn traced(
+        &self,
+        mut out: NodFrame<T>,
+        operation: &str,
+        params: impl FnOnce() -> Vec<(&'static str, String)>,
+    ) -> NodFrame<T> {
+        out.history = self.history.as_ref().map(|history| {
+            let mut history = history.clone();
+            history.push(OpRecord {
+                operation: String::from(operation),
+                params: params()
+                    .into_iter()
+                    .map(|(n, v)| (String::from(n), v))
+                    .collect(),
+                rows_in: self.num_rows,
+                rows_out: out.num_rows,
+                timestamp: SystemTime::now(),
+            });
+            history
+        });
+        out
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use crate::test_support::strings;
+    use crate::{Comp, FilterValue, JoinKind, NodFrame, NodFrameBuilder};
+
+    fn orders() -> NodFrame<i64> {
+        NodFrameBuilder::new()
+            .add_discrete("id", strings(&["a", "b", "c", "d", "e"]))
+            .add_numeric("qty", vec![5, 1, 8, 3, 9])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn tracing_test() {
+        let mut frame = orders();
+        // nothing is recorded until tracing is enabled
+        let untraced = frame.sort_by("qty", true).unwrap();
+        assert!(!untraced.is_tracing());
+        assert!(untraced.history().is_empty());
+
+        frame.enable_tracing();
+        let prices = NodFrameBuilder::new()
+            .add_discrete("id", strings(&["a", "c", "e"]))
+            .add_numeric("price", vec![10, 20, 30])
+            .build()
+            .unwrap();
+        let out = frame
+            .filter("qty", Comp::Geq, FilterValue::Num(3))
+            .unwrap()
+            .sort_by("qty", false)
+            .unwrap()
+            .join(&prices, "id", JoinKind::Inner)
+            .unwrap();
+        let history = out.history();
+        assert_eq!(history.len(), 3);
+        let ops: Vec<&str> = history.iter().map(|r| r.operation()).collect();
+        assert_eq!(ops, vec!["filter", "sort_by", "join"]);
+        let counts: Vec<(usize, usize)> = history
+            .iter()
+            .map(|r| (r.rows_in(), r.rows_out()))
+            .collect();
+        assert_eq!(counts, vec![(5, 4), (4, 4), (4, 3)]);
+        assert_eq!(history[0].param("comp"), Some("Geq"));
+        assert_eq!(history[0].param("val"), Some("3"));
+        assert!(history[0].timestamp() <= history[2].timestamp());
+        assert!(history[0]
+            .to_string()
+            .ends_with(" filter(col=qty, comp=Geq, val=3) 5 -> 4 rows"));
+
+        // a clone carries the history, and each copy grows on its own
+        let copy = out.clone();
+        assert_eq!(copy.history(), out.history());
+        let more = copy.take(&[0]).unwrap();
+        assert_eq!(more.history().len(), 4);
+        assert_eq!(out.history().len(), 3);
+
+        let mut off = more;
+        off.disable_tracing();
+        assert!(off.take(&[0]).unwrap().history().is_empty());
+    }
+
+    #[test]
+    fn tracing_builder_ops_test() {
+        // operations that assemble their output with the builder keep the log
+        let mut frame = orders();
+        frame.enable_tracing();
+        let out = frame
+            .sort_by("qty", true)
+            .unwrap()
+            .eval("double", "qty * 2")
+            .unwrap()
+            .rename("double", "twice")
+            .unwrap()
+            .with_row_ids("row")
+            .unwrap()
+            .compute_from(&["qty", "twice"], "total", |v| v[0] + v[1])
+            .unwrap();
+        assert!(out.is_tracing());
+        let ops: Vec<&str> = out.history().iter().map(|r| r.operation()).collect();
+        assert_eq!(
+            ops,
+            vec!["sort_by", "eval", "rename", "with_row_ids", "compute_from"]
+        );
+        assert_eq!(out.history()[1].param("expr"), Some("qty * 2"));
+        assert_eq!(out.history()[2].param("mapping"), Some("double->twice"));
+        // composed operations record only themselves
+        let text = frame.to_discrete("qty").unwrap();
+        assert_eq!(text.history().len(), 1);
+        assert_eq!(text.history()[0].operation(), "to_discrete");
+    }
+}